@@ -0,0 +1,67 @@
+#![deny(warnings)]
+
+use nextshell::Filter;
+
+#[tokio::main]
+async fn main() {
+    pretty_env_logger::init();
+
+    // GET /terminal -> websocket upgrade, backed by the user's shell
+    let terminal = nextshell::path("terminal")
+        .and(nextshell::ws())
+        .map(|ws: nextshell::ws::Ws| {
+            ws.on_upgrade(|socket| async move {
+                match nextshell::terminal::shell().spawn() {
+                    Ok(terminal) => nextshell::terminal::serve(socket, terminal).await,
+                    Err(e) => eprintln!("failed to spawn shell: {}", e),
+                }
+            })
+        });
+
+    // GET / -> index html
+    let index = nextshell::path::end().map(|| nextshell::reply::html(INDEX_HTML));
+
+    let routes = index.or(terminal);
+
+    nextshell::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+}
+
+static INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+    <head>
+        <title>nextshell terminal</title>
+        <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/xterm@5.3.0/css/xterm.css" />
+        <script src="https://cdn.jsdelivr.net/npm/xterm@5.3.0/lib/xterm.js"></script>
+    </head>
+    <body>
+        <div id="terminal"></div>
+        <script type="text/javascript">
+        const term = new Terminal();
+        term.open(document.getElementById('terminal'));
+
+        const ws = new WebSocket('ws://' + location.host + '/terminal');
+        ws.binaryType = 'arraybuffer';
+        const encoder = new TextEncoder();
+
+        ws.onopen = function() {
+            ws.send(JSON.stringify({ type: 'resize', cols: term.cols, rows: term.rows }));
+        };
+
+        ws.onmessage = function(msg) {
+            if (msg.data instanceof ArrayBuffer) {
+                term.write(new Uint8Array(msg.data));
+                return;
+            }
+            const frame = JSON.parse(msg.data);
+            if (frame.type === 'exit') {
+                term.write('\r\n[process exited with code ' + frame.code + ']\r\n');
+            }
+        };
+
+        term.onData(function(data) {
+            ws.send(encoder.encode(data));
+        });
+        </script>
+    </body>
+</html>
+"#;
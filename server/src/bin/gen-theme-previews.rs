@@ -0,0 +1,132 @@
+//! Draws the preview of every theme in a collection.
+//!
+//! ```notrust
+//! gen-theme-previews [--readme] [--check] DIR...
+//! ```
+//!
+//! Each `DIR` is a collection directory, like `static/themes/standard`. The
+//! preview of `name.yaml` is written to `previews/name.yaml.svg` in it. With
+//! `--readme`, the collection's `README.md` is replaced by a table of the
+//! themes and their previews, to edit from there.
+//!
+//! With `--check`, nothing is written; instead, files that are out of date
+//! are listed, and the exit status is 1 if there are any.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use nextshell::themes::{preview, validate};
+
+const USAGE: &str = "usage: gen-theme-previews [--readme] [--check] DIR...";
+
+fn main() {
+    let mut check = false;
+    let mut with_readme = false;
+    let mut dirs = Vec::new();
+    for arg in env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--check") => check = true,
+            Some("--readme") => with_readme = true,
+            Some("-h") | Some("--help") => {
+                println!("{}", USAGE);
+                return;
+            }
+            Some(flag) if flag.starts_with('-') => usage(&format!("unknown option {}", flag)),
+            _ => dirs.push(PathBuf::from(arg)),
+        }
+    }
+    if dirs.is_empty() {
+        usage("expected a collection directory");
+    }
+
+    let mut stale = false;
+    for dir in &dirs {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).unwrap_or_else(|err| fail(dir, err)) {
+            let name = entry.unwrap_or_else(|err| fail(dir, err)).file_name();
+            let name = name.to_string_lossy();
+            if name.ends_with(".yaml") || name.ends_with(".yml") {
+                files.push(name.into_owned());
+            }
+        }
+        files.sort();
+
+        let mut outputs = Vec::new();
+        let mut readme = vec![
+            "|Theme name | Preview|".to_owned(),
+            "| --- | --- |".to_owned(),
+        ];
+        for file in &files {
+            let path = dir.join(file);
+            let text = fs::read_to_string(&path).unwrap_or_else(|err| fail(&path, err));
+            let theme = validate::check(&text).theme.unwrap_or_else(|| {
+                eprintln!(
+                    "{}: invalid theme; run validate-themes for details",
+                    path.display()
+                );
+                process::exit(2);
+            });
+            let svg = format!("previews/{}.svg", file);
+            outputs.push((dir.join(&svg), preview::render(&theme)));
+            readme.push(format!(
+                "|**[{}]({})**:|<img src='{}' width='300'>|",
+                display_name(file),
+                file,
+                svg
+            ));
+        }
+        if with_readme {
+            outputs.push((dir.join("README.md"), readme.join("\n")));
+        }
+
+        if !check {
+            let previews = dir.join("previews");
+            fs::create_dir_all(&previews).unwrap_or_else(|err| fail(&previews, err));
+        }
+        for (path, contents) in outputs {
+            if check {
+                if fs::read_to_string(&path).ok().as_deref() != Some(&*contents) {
+                    println!("{}: out of date", path.display());
+                    stale = true;
+                }
+            } else {
+                fs::write(&path, contents).unwrap_or_else(|err| fail(&path, err));
+            }
+        }
+    }
+
+    if stale {
+        process::exit(1);
+    }
+}
+
+/// The name of a theme in the README: its file name, with each word
+/// capitalized, like `Solarized Dark`.
+fn display_name(file: &str) -> String {
+    let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+    stem.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn fail(path: &Path, err: std::io::Error) -> ! {
+    eprintln!("{}: {}", path.display(), err);
+    process::exit(2);
+}
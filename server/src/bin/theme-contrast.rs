@@ -0,0 +1,169 @@
+//! Reports the WCAG contrast of theme colors against their backgrounds.
+//!
+//! ```notrust
+//! theme-contrast [--min RATIO] [--min-ansi RATIO] [--all] [--markdown] [PATH]...
+//! ```
+//!
+//! Each `PATH` is a themes directory or a single theme file, defaulting to
+//! `static/themes`. For every theme, prints the contrast of the foreground
+//! and of the least readable ANSI color. With `--all`, also lists the
+//! contrast of every ANSI color against the background. With `--markdown`,
+//! prints them as a table instead, to paste into a README, with a column for
+//! each ANSI color if `--all` is given too.
+//!
+//! With `--min`, exits with status 1 if a foreground falls below the ratio;
+//! with `--min-ansi`, likewise for the ANSI colors.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use nextshell::themes::contrast::{self, Report};
+use nextshell::themes::{validate, Catalog};
+
+const USAGE: &str =
+    "usage: theme-contrast [--min RATIO] [--min-ansi RATIO] [--all] [--markdown] [PATH]...";
+
+struct Theme {
+    name: String,
+    file: String,
+    report: Report,
+}
+
+fn main() {
+    let mut min = 0.0;
+    let mut min_ansi = 0.0;
+    let mut all = false;
+    let mut markdown = false;
+    let mut paths = Vec::new();
+
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--min") => min = ratio(args.next()),
+            Some("--min-ansi") => min_ansi = ratio(args.next()),
+            Some("--all") => all = true,
+            Some("--markdown") => markdown = true,
+            Some("-h") | Some("--help") => {
+                println!("{}", USAGE);
+                return;
+            }
+            Some(flag) if flag.starts_with('-') => usage(&format!("unknown option {}", flag)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("static/themes"));
+    }
+
+    let mut themes = Vec::new();
+    for path in &paths {
+        if path.is_dir() {
+            let catalog = Catalog::load(path).unwrap_or_else(|err| fail(&err.to_string()));
+            themes.extend(catalog.iter().map(|entry| Theme {
+                name: entry.name.clone(),
+                file: entry.file.clone(),
+                report: contrast::check(&entry.theme),
+            }));
+        } else {
+            let text = fs::read_to_string(path)
+                .unwrap_or_else(|err| fail(&format!("{}: {}", path.display(), err)));
+            let theme = validate::check(&text).theme.unwrap_or_else(|| {
+                fail(&format!(
+                    "{}: invalid theme; run validate-themes for details",
+                    path.display()
+                ))
+            });
+            themes.push(Theme {
+                name: path.display().to_string(),
+                file: path.display().to_string(),
+                report: contrast::check(&theme),
+            });
+        }
+    }
+
+    if markdown {
+        let mut header = "| Theme | Foreground | Least readable ANSI color |".to_owned();
+        let mut rule = "| --- | --- | --- |".to_owned();
+        if all {
+            // Every theme has the same ANSI colors, in the same order.
+            for contrast in themes.first().map_or(&[][..], |theme| &theme.report.ansi) {
+                header.push_str(&format!(" `{}` |", contrast.name));
+                rule.push_str(" --- |");
+            }
+        }
+        println!("{}", header);
+        println!("{}", rule);
+    }
+    let mut failed = false;
+    for theme in &themes {
+        let fg = &theme.report.foreground;
+        let ansi = theme
+            .report
+            .worst_ansi()
+            .map(|worst| format!("{:.2} {} (`{}`)", worst.ratio, worst.level(), worst.name))
+            .unwrap_or_default();
+        if markdown {
+            let mut row = format!(
+                "| [{}]({}) | {:.2} {} | {} |",
+                theme.name,
+                theme.file,
+                fg.ratio,
+                fg.level(),
+                ansi
+            );
+            if all {
+                for contrast in &theme.report.ansi {
+                    row.push_str(&format!(" {:.2} {} |", contrast.ratio, contrast.level()));
+                }
+            }
+            println!("{}", row);
+        } else {
+            println!(
+                "{}: foreground {:.2} {}, ANSI {}",
+                theme.file,
+                fg.ratio,
+                fg.level(),
+                ansi
+            );
+            if all {
+                for contrast in &theme.report.ansi {
+                    println!("  {}", contrast);
+                }
+            }
+        }
+
+        for contrast in theme.report.below(min, min_ansi) {
+            let threshold = if contrast.name == "foreground" {
+                min
+            } else {
+                min_ansi
+            };
+            eprintln!("{}: {} is below {}", theme.file, contrast, threshold);
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn ratio(arg: Option<std::ffi::OsString>) -> f64 {
+    arg.as_ref()
+        .and_then(|arg| arg.to_str())
+        .and_then(|arg| arg.parse().ok())
+        .filter(|ratio| (1.0..=21.0).contains(ratio))
+        .unwrap_or_else(|| usage("expected a contrast ratio from 1 to 21"))
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
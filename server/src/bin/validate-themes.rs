@@ -0,0 +1,89 @@
+//! Checks theme files, printing `file:line: severity: message` for every
+//! problem found.
+//!
+//! ```notrust
+//! validate-themes [--strict] [PATH]...
+//! ```
+//!
+//! Each `PATH` is a themes directory, checked whole, or a single theme file
+//! in one. It defaults to `static/themes`. Exits with status 1 if there were
+//! any errors, or, with `--strict`, any warnings.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use nextshell::themes::validate::{self, Report, Severity};
+
+const USAGE: &str = "usage: validate-themes [--strict] [PATH]...";
+
+fn main() {
+    let mut strict = false;
+    let mut paths = Vec::new();
+    for arg in env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("--strict") => strict = true,
+            Some("-h") | Some("--help") => {
+                println!("{}", USAGE);
+                return;
+            }
+            Some(flag) if flag.starts_with('-') => {
+                eprintln!("unknown option {}\n{}", flag, USAGE);
+                process::exit(2);
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(PathBuf::from("static/themes"));
+    }
+
+    let mut reports = Vec::new();
+    for path in &paths {
+        let checked = if path.is_dir() {
+            validate::check_dir(path)
+        } else {
+            validate::check_file(&themes_dir(path), path).map(|diagnostics| {
+                vec![Report {
+                    file: path.clone(),
+                    diagnostics,
+                }]
+            })
+        };
+        match checked {
+            Ok(checked) => reports.extend(checked),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(2);
+            }
+        }
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for report in &reports {
+        print!("{}", report);
+        for diagnostic in &report.diagnostics {
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+    }
+    println!(
+        "checked {} themes: {} errors, {} warnings",
+        reports.len(),
+        errors,
+        warnings
+    );
+
+    if errors > 0 || (strict && warnings > 0) {
+        process::exit(1);
+    }
+}
+
+/// The themes directory a theme file is in: the parent of its collection.
+fn themes_dir(file: &Path) -> PathBuf {
+    file.parent()
+        .and_then(Path::parent)
+        .map_or_else(|| PathBuf::from("."), Path::to_owned)
+}
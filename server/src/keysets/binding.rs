@@ -0,0 +1,334 @@
+//! Key bindings, like `ctrl-shift-up`.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+/// A chord: modifiers held down while a key is pressed.
+///
+/// Written as hyphen-separated modifiers ending with the key, like
+/// `cmd-shift-A` or `alt-cmd-[`. A binding is displayed with its modifiers
+/// in a fixed order, `ctrl`, `alt`, `shift`, `meta`, `cmd`, so two ways of
+/// writing the same chord display the same.
+///
+/// # Example
+///
+/// ```
+/// use nextshell::keysets::{Key, KeyBinding};
+///
+/// let binding: KeyBinding = "shift-cmd-}".parse().unwrap();
+/// assert!(binding.modifiers.cmd && binding.modifiers.shift);
+/// assert_eq!(binding.key, Key::Char('}'));
+/// assert_eq!(binding.to_string(), "shift-cmd-}");
+///
+/// let minus: KeyBinding = "cmd--".parse().unwrap();
+/// assert_eq!(minus.key, Key::Char('-'));
+///
+/// assert!("hyper-a".parse::<KeyBinding>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyBinding {
+    /// The modifiers held down.
+    pub modifiers: Modifiers,
+    /// The key pressed.
+    pub key: Key,
+}
+
+/// The modifier keys of a [`KeyBinding`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Modifiers {
+    /// `ctrl`: Control.
+    pub ctrl: bool,
+    /// `alt`: Alt, or Option on a Mac.
+    pub alt: bool,
+    /// `shift`: Shift.
+    pub shift: bool,
+    /// `meta`: Meta.
+    pub meta: bool,
+    /// `cmd`: Command, which only Macs have.
+    pub cmd: bool,
+}
+
+/// A key, as named in a [`KeyBinding`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    /// A printable key, by the character it types.
+    ///
+    /// With `shift`, this is the shifted character, like `A` or `}`.
+    Char(char),
+    /// `up`
+    Up,
+    /// `down`
+    Down,
+    /// `left`
+    Left,
+    /// `right`
+    Right,
+    /// `home`
+    Home,
+    /// `end`
+    End,
+    /// `pageup`
+    PageUp,
+    /// `pagedown`
+    PageDown,
+    /// `backspace`
+    Backspace,
+    /// `enter`
+    Enter,
+    /// `insert`
+    Insert,
+    /// `delete`
+    Delete,
+    /// `escape`
+    Escape,
+    /// `tab`
+    Tab,
+    /// `numpadenter`
+    NumpadEnter,
+    /// A function key, `f1` to `f20`.
+    F(u8),
+}
+
+/// An operating system, which decides the modifiers there are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// macOS.
+    Mac,
+    /// Linux.
+    Linux,
+    /// Windows.
+    Windows,
+}
+
+/// A string that isn't a valid [`KeyBinding`].
+#[derive(Debug)]
+pub struct InvalidKeyBinding {
+    binding: String,
+    message: String,
+}
+
+/// A name that isn't a [`Platform`].
+#[derive(Debug)]
+pub struct UnknownPlatform(String);
+
+const MODIFIERS: [&str; 5] = ["ctrl", "alt", "shift", "meta", "cmd"];
+
+const NAMED_KEYS: [(&str, Key); 15] = [
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("backspace", Key::Backspace),
+    ("enter", Key::Enter),
+    ("insert", Key::Insert),
+    ("delete", Key::Delete),
+    ("escape", Key::Escape),
+    ("tab", Key::Tab),
+    ("numpadenter", Key::NumpadEnter),
+];
+
+impl KeyBinding {
+    /// Returns the modifiers this binding uses that `platform` doesn't have.
+    ///
+    /// Only Macs have `cmd`.
+    pub fn missing_on(&self, platform: Platform) -> Vec<&'static str> {
+        if self.modifiers.cmd && platform != Platform::Mac {
+            vec!["cmd"]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl Modifiers {
+    fn set(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "ctrl" => Some(&mut self.ctrl),
+            "alt" => Some(&mut self.alt),
+            "shift" => Some(&mut self.shift),
+            "meta" => Some(&mut self.meta),
+            "cmd" => Some(&mut self.cmd),
+            _ => None,
+        }
+    }
+
+    fn names(&self) -> impl Iterator<Item = &'static str> {
+        let held = [self.ctrl, self.alt, self.shift, self.meta, self.cmd];
+        MODIFIERS
+            .iter()
+            .zip(held)
+            .filter(|&(_, held)| held)
+            .map(|(&name, _)| name)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = InvalidKeyBinding;
+
+    fn from_str(s: &str) -> Result<KeyBinding, InvalidKeyBinding> {
+        let invalid = |message: String| InvalidKeyBinding {
+            binding: s.to_owned(),
+            message,
+        };
+
+        // The key may itself be `-`, as in `cmd--`.
+        let (modifiers, key) = if s == "-" {
+            ("", "-")
+        } else if let Some(modifiers) = s.strip_suffix("--") {
+            if modifiers.is_empty() {
+                return Err(invalid("missing a modifier before `-`".to_owned()));
+            }
+            (modifiers, "-")
+        } else {
+            match s.rsplit_once('-') {
+                Some((modifiers, key)) if !modifiers.is_empty() => (modifiers, key),
+                Some(_) => return Err(invalid("missing a modifier before `-`".to_owned())),
+                None => ("", s),
+            }
+        };
+
+        let key = match Key::parse(key) {
+            Some(key) => key,
+            None if key.is_empty() => return Err(invalid("missing a key".to_owned())),
+            None => return Err(invalid(format!("unknown key `{}`", key))),
+        };
+
+        let mut held = Modifiers::default();
+        if !modifiers.is_empty() {
+            for name in modifiers.split('-') {
+                match held.set(name) {
+                    Some(true) => return Err(invalid(format!("`{}` appears twice", name))),
+                    Some(modifier) => *modifier = true,
+                    None if name.is_empty() => {
+                        return Err(invalid("missing a modifier before `-`".to_owned()))
+                    }
+                    None => return Err(invalid(format!("unknown modifier `{}`", name))),
+                }
+            }
+        }
+
+        Ok(KeyBinding {
+            modifiers: held,
+            key,
+        })
+    }
+}
+
+impl Key {
+    fn parse(s: &str) -> Option<Key> {
+        if let Some(&(_, key)) = NAMED_KEYS.iter().find(|&&(name, _)| name == s) {
+            return Some(key);
+        }
+        let digits = s
+            .strip_prefix('f')
+            .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if let Some(digits) = digits {
+            return match digits.parse() {
+                Ok(n @ 1..=20) if !digits.starts_with('0') => Some(Key::F(n)),
+                _ => None,
+            };
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_whitespace() && !c.is_control() => Some(Key::Char(c)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.modifiers.names() {
+            write!(f, "{}-", name)?;
+        }
+        self.key.fmt(f)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::F(n) => write!(f, "f{}", n),
+            key => {
+                let &(name, _) = NAMED_KEYS
+                    .iter()
+                    .find(|&&(_, named)| named == key)
+                    .expect("every other key is named");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl Serialize for KeyBinding {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<KeyBinding, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Platform {
+    /// The name of the platform, like `linux`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Platform::Mac => "mac",
+            Platform::Linux => "linux",
+            Platform::Windows => "windows",
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = UnknownPlatform;
+
+    fn from_str(s: &str) -> Result<Platform, UnknownPlatform> {
+        match s {
+            "mac" | "macos" => Ok(Platform::Mac),
+            "linux" => Ok(Platform::Linux),
+            "windows" => Ok(Platform::Windows),
+            _ => Err(UnknownPlatform(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for InvalidKeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid key binding {:?}: {}",
+            self.binding, self.message
+        )
+    }
+}
+
+impl StdError for InvalidKeyBinding {}
+
+impl fmt::Display for UnknownPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown platform {:?}", self.0)
+    }
+}
+
+impl StdError for UnknownPlatform {}
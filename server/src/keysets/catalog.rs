@@ -0,0 +1,158 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::Keyset;
+
+/// The keysets in a keysets directory.
+///
+/// Each `.yaml` or `.yml` file in the directory is a keyset, named after the
+/// file, like `emacs` for `emacs.yaml`.
+#[derive(Debug)]
+pub struct Catalog {
+    entries: Vec<Entry>,
+}
+
+/// A keyset in a [`Catalog`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry {
+    /// The file name, without its extension.
+    pub name: String,
+    /// The keyset file, relative to the keysets directory.
+    pub file: String,
+    /// The keyset itself.
+    pub keyset: Keyset,
+}
+
+impl Catalog {
+    /// Loads every keyset in `dir`.
+    ///
+    /// Fails on the first keyset file that can't be read or parsed.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Catalog, LoadError> {
+        let dir = dir.as_ref();
+        let mut paths = fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .map_err(|err| LoadError::io(dir, err))?;
+        paths.sort();
+
+        let mut entries = Vec::new();
+        for path in paths {
+            let file = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let name = match file
+                .strip_suffix(".yaml")
+                .or_else(|| file.strip_suffix(".yml"))
+            {
+                Some(name) if path.is_file() => name.to_owned(),
+                _ => continue,
+            };
+            let text = fs::read_to_string(&path).map_err(|err| LoadError::io(&path, err))?;
+            let keyset = serde_yaml::from_str::<Option<Keyset>>(&text)
+                .map_err(|err| LoadError::yaml(&path, err))?
+                .unwrap_or_default();
+            entries.push(Entry { name, file, keyset });
+        }
+
+        tracing::debug!("keysets: loaded {} keysets from {:?}", entries.len(), dir);
+        Ok(Catalog::new(entries))
+    }
+
+    /// Creates a catalog of the given keysets.
+    pub fn new(entries: Vec<Entry>) -> Catalog {
+        Catalog { entries }
+    }
+
+    /// Looks a keyset up by its name.
+    pub fn get(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Iterates over every keyset, ordered by file name.
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Returns the number of keysets.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no keysets.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// An error loading a keyset catalog.
+pub struct LoadError {
+    path: PathBuf,
+    kind: LoadErrorKind,
+}
+
+enum LoadErrorKind {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+}
+
+impl LoadError {
+    fn io(path: &Path, err: io::Error) -> LoadError {
+        LoadError {
+            path: path.to_owned(),
+            kind: LoadErrorKind::Io(err),
+        }
+    }
+
+    fn yaml(path: &Path, err: serde_yaml::Error) -> LoadError {
+        LoadError {
+            path: path.to_owned(),
+            kind: LoadErrorKind::Yaml(err),
+        }
+    }
+
+    /// The file or directory that failed to load.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("LoadError");
+        f.field("path", &self.path);
+        match self.kind {
+            LoadErrorKind::Io(ref err) => f.field("io", err),
+            LoadErrorKind::Yaml(ref err) => f.field("yaml", err),
+        };
+        f.finish()
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LoadErrorKind::Io(ref err) => write!(f, "{}: {}", self.path.display(), err),
+            LoadErrorKind::Yaml(ref err) => {
+                write!(f, "{}: invalid keyset: {}", self.path.display(), err)
+            }
+        }
+    }
+}
+
+impl StdError for LoadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.kind {
+            LoadErrorKind::Io(ref err) => Some(err),
+            LoadErrorKind::Yaml(ref err) => Some(err),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! GNU readline `inputrc` files: `keyseq: function-name` lines, where the
+//! key sequence is quoted with escapes, like `"\C-a"`, or a key name, like
+//! `Meta-b`.
+
+use super::{action, is_shifted, ConvertError, Format, Imported, Importer};
+use crate::keysets::KeyBinding;
+
+/// Readline functions, and the actions that do the same.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("beginning-of-line", "editor_view:move_to_line_start"),
+    ("end-of-line", "editor_view:move_to_line_end"),
+    ("forward-char", "editor_view:right"),
+    ("backward-char", "editor_view:left"),
+    ("forward-word", "editor_view:move_forward_one_word"),
+    ("backward-word", "editor_view:move_backward_one_word"),
+    ("previous-history", "editor_view:up"),
+    ("next-history", "editor_view:down"),
+    ("beginning-of-history", "editor_view:move_to_buffer_start"),
+    ("end-of-history", "editor_view:move_to_buffer_end"),
+    ("delete-char", "editor_view:delete"),
+    ("backward-delete-char", "editor_view:backspace"),
+    ("kill-line", "editor_view:cut_all_right"),
+    ("backward-kill-line", "editor_view:delete_all_left"),
+    ("unix-line-discard", "editor_view:clear_and_copy_lines"),
+    ("kill-whole-line", "editor_view:clear_lines"),
+    ("kill-word", "editor_view:cut_word_right"),
+    ("backward-kill-word", "editor:delete_word_left"),
+    ("unix-word-rubout", "editor_view:cut_word_left"),
+    ("yank-last-arg", "editor:insert_last_word_previous_command"),
+    (
+        "insert-last-argument",
+        "editor:insert_last_word_previous_command",
+    ),
+    ("clear-screen", "input:clear_screen"),
+    ("reverse-search-history", "workspace:show_command_search"),
+];
+
+/// A key typed, with the modifiers readline knows.
+#[derive(Clone, Copy)]
+struct Event {
+    ctrl: bool,
+    meta: bool,
+    c: char,
+}
+
+const ESCAPE: char = '\x1b';
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let mut importer = Importer::new();
+    // For each `$if`, whether it holds vi mode bindings.
+    let mut conditions = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("set ") {
+            continue;
+        }
+        if let Some(directive) = line.strip_prefix('$') {
+            let (name, test) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive, ""), |(name, test)| (name, test.trim()));
+            match name {
+                "if" => conditions.push(test.strip_prefix("mode=").map(|mode| mode.trim() == "vi")),
+                "else" => {
+                    if let Some(Some(vi)) = conditions.last_mut() {
+                        *vi = !*vi;
+                    }
+                }
+                "endif" => {
+                    if conditions.pop().is_none() {
+                        return Err(ConvertError::at(
+                            Format::Inputrc,
+                            line_number,
+                            "`$endif` without `$if`",
+                        ));
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let (keyseq, rest) = split_binding(line).ok_or_else(|| {
+            ConvertError::at(
+                Format::Inputrc,
+                line_number,
+                format!("expected `keyseq: function-name`, not `{}`", line),
+            )
+        })?;
+        let entry = format!("{}: {}", keyseq, rest);
+        if rest.starts_with('"') || rest.starts_with('\'') {
+            importer.unmapped(line_number, entry, "macros aren't supported");
+            continue;
+        }
+        if conditions.contains(&Some(true)) {
+            importer.unmapped(line_number, entry, "vi mode bindings aren't supported");
+            continue;
+        }
+        let function = rest.split_whitespace().next().unwrap_or_default();
+        let action = match action(FUNCTIONS, function) {
+            Some(action) => action,
+            None => {
+                importer.unmapped(
+                    line_number,
+                    entry,
+                    format!("no nextshell action for `{}`", function),
+                );
+                continue;
+            }
+        };
+        let events = match keyseq.strip_prefix('"') {
+            Some(quoted) => unescape(quoted.strip_suffix('"').unwrap_or(quoted)),
+            None => key_name(keyseq).map(|event| vec![event]),
+        };
+        match events.and_then(|events| binding(&events)) {
+            Ok(binding) => importer.bind(line_number, entry, action, binding),
+            Err(reason) => importer.unmapped(line_number, entry, reason),
+        }
+    }
+    Ok(importer.finish())
+}
+
+/// Splits a binding line at the `:` after its key sequence.
+fn split_binding(line: &str) -> Option<(&str, &str)> {
+    let end = if let Some(quoted) = line.strip_prefix('"') {
+        let mut escaped = false;
+        let close = quoted.find(|c| {
+            let found = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            found
+        })?;
+        let end = close + 2;
+        if !line[end..].trim_start().starts_with(':') {
+            return None;
+        }
+        end + line[end..].find(':')?
+    } else {
+        line.find(':')?
+    };
+    Some((line[..end].trim_end(), line[end + 1..].trim()))
+}
+
+/// Reads the escapes of a quoted key sequence.
+fn unescape(quoted: &str) -> Result<Vec<Event>, String> {
+    let mut chars = quoted.chars().peekable();
+    let mut events = Vec::new();
+    while chars.peek().is_some() {
+        events.push(next_event(&mut chars)?);
+    }
+    Ok(events)
+}
+
+fn next_event(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Result<Event, String> {
+    let plain = |c| Event {
+        ctrl: false,
+        meta: false,
+        c,
+    };
+    let c = chars.next().ok_or("missing a key")?;
+    if c != '\\' {
+        return Ok(plain(c));
+    }
+    let c = chars.next().ok_or("missing a key after `\\`")?;
+    let event = match c {
+        'C' | 'M' if chars.peek() == Some(&'-') => {
+            chars.next();
+            let mut event = next_event(chars)?;
+            if c == 'C' {
+                event.ctrl = true;
+            } else {
+                event.meta = true;
+            }
+            event
+        }
+        'e' => plain(ESCAPE),
+        'a' => plain('\x07'),
+        'b' => plain('\x08'),
+        'd' => plain('\x7f'),
+        'f' => plain('\x0c'),
+        'n' => plain('\n'),
+        'r' => plain('\r'),
+        't' => plain('\t'),
+        'v' => plain('\x0b'),
+        '0'..='7' => {
+            let mut code = c.to_digit(8).unwrap_or_default();
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(8)) {
+                    Some(digit) => {
+                        code = code * 8 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            plain(char::from_u32(code).ok_or("invalid octal escape")?)
+        }
+        'x' => {
+            let mut code = 0;
+            for _ in 0..2 {
+                match chars.peek().and_then(|c| c.to_digit(16)) {
+                    Some(digit) => {
+                        code = code * 16 + digit;
+                        chars.next();
+                    }
+                    None => break,
+                }
+            }
+            plain(char::from_u32(code).ok_or("invalid hex escape")?)
+        }
+        c => plain(c),
+    };
+    Ok(event)
+}
+
+/// Reads a key name, like `Control-a` or `Meta-Rubout`.
+fn key_name(name: &str) -> Result<Event, String> {
+    let (modifiers, key) = match name.strip_suffix("--") {
+        Some(modifiers) => (modifiers, "-"),
+        None => name.rsplit_once('-').unwrap_or(("", name)),
+    };
+    let mut event = Event {
+        ctrl: false,
+        meta: false,
+        c: match key.to_lowercase().as_str() {
+            "del" | "rubout" => '\x7f',
+            "esc" | "escape" => ESCAPE,
+            "lfd" | "newline" => '\n',
+            "ret" | "return" => '\r',
+            "tab" => '\t',
+            "spc" | "space" => ' ',
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("unknown key `{}`", key)),
+                }
+            }
+        },
+    };
+    for modifier in modifiers.split('-').filter(|m| !m.is_empty()) {
+        match modifier.to_lowercase().as_str() {
+            "c" | "control" => event.ctrl = true,
+            "m" | "meta" => event.meta = true,
+            _ => return Err(format!("unknown modifier `{}`", modifier)),
+        }
+    }
+    Ok(event)
+}
+
+/// Translates the keys of a key sequence to one binding.
+///
+/// `Escape` before a key is `meta`, and the sequences terminals send for
+/// arrows, function keys and the like are read as those keys.
+fn binding(events: &[Event]) -> Result<KeyBinding, String> {
+    let several = || "sequences of several keys aren't supported".to_owned();
+    let mut event = match *events {
+        [event] => event,
+        [escape, event] if escape.c == ESCAPE && !escape.ctrl && !escape.meta => Event {
+            meta: true,
+            ..event
+        },
+        [escape, introducer, ref rest @ ..]
+            if escape.c == ESCAPE && (introducer.c == '[' || introducer.c == 'O') =>
+        {
+            let sequence = rest.iter().map(|event| event.c).collect::<String>();
+            return terminal_key(&sequence).ok_or_else(several);
+        }
+        _ => return Err(several()),
+    };
+
+    let mut binding = String::new();
+    let key = match event.c {
+        '\x7f' => "backspace".to_owned(),
+        '\x08' if !event.ctrl => "backspace".to_owned(),
+        '\t' => "tab".to_owned(),
+        '\r' => "enter".to_owned(),
+        ESCAPE => "escape".to_owned(),
+        ' ' => return Err("nextshell can't bind `space`".to_owned()),
+        // Control characters are `ctrl` and a letter, as `\n` is `C-j`.
+        c @ '\x01'..='\x1a' => {
+            event.ctrl = true;
+            char::from(c as u8 + b'a' - 1).to_string()
+        }
+        c if c.is_control() => return Err(format!("unknown key {:?}", c)),
+        // Readline doesn't tell `C-a` from `C-A`.
+        c if event.ctrl => c.to_lowercase().to_string(),
+        c => c.to_string(),
+    };
+    if event.ctrl {
+        binding.push_str("ctrl-");
+    }
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if is_shifted(c) {
+            binding.push_str("shift-");
+        }
+    }
+    if event.meta {
+        binding.push_str("meta-");
+    }
+    binding.push_str(&key);
+    binding
+        .parse()
+        .map_err(|_| format!("unknown key `{}`", key))
+}
+
+/// Reads what follows `Escape [` or `Escape O` in the sequence a terminal
+/// sends for a key, like `1;5C` for `ctrl-right`.
+fn terminal_key(sequence: &str) -> Option<KeyBinding> {
+    let last = sequence.chars().last()?;
+    let params = sequence[..sequence.len() - last.len_utf8()]
+        .split(';')
+        .collect::<Vec<_>>();
+    let key = match last {
+        'A' => "up",
+        'B' => "down",
+        'C' => "right",
+        'D' => "left",
+        'H' => "home",
+        'F' => "end",
+        'P' => "f1",
+        'Q' => "f2",
+        'R' => "f3",
+        'S' => "f4",
+        '~' => match params[0] {
+            "1" | "7" => "home",
+            "2" => "insert",
+            "3" => "delete",
+            "4" | "8" => "end",
+            "5" => "pageup",
+            "6" => "pagedown",
+            "11" => "f1",
+            "12" => "f2",
+            "13" => "f3",
+            "14" => "f4",
+            "15" => "f5",
+            "17" => "f6",
+            "18" => "f7",
+            "19" => "f8",
+            "20" => "f9",
+            "21" => "f10",
+            "23" => "f11",
+            "24" => "f12",
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    // Modifiers are sent as one more than a bit mask.
+    let mask = match params.get(1) {
+        Some(param) => param.parse::<u8>().ok()?.checked_sub(1)?,
+        None => 0,
+    };
+    let mut binding = String::new();
+    for (bit, name) in [(4, "ctrl-"), (2, "alt-"), (1, "shift-"), (8, "meta-")] {
+        if mask & bit != 0 {
+            binding.push_str(name);
+        }
+    }
+    binding.push_str(key);
+    binding.parse().ok()
+}
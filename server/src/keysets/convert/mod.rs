@@ -0,0 +1,324 @@
+//! Converting keybindings from other programs to keysets.
+//!
+//! Other programs name their commands differently, and can bind things
+//! nextshell can't, like sequences of several chords. Bindings of commands
+//! that nextshell has are imported; everything else is reported as
+//! [`Unmapped`], with the reason, rather than dropped.
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{KeyBinding, Keyset};
+
+mod inputrc;
+mod vscode;
+
+/// A keybindings format of another program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A VS Code `keybindings.json`.
+    ///
+    /// Comments and trailing commas are allowed, as VS Code allows them.
+    /// `when` clauses are ignored, and removing a command's binding, as
+    /// `-command` does, unbinds its action.
+    VsCode,
+    /// A GNU readline `inputrc`.
+    ///
+    /// `Meta` and `\e` are the `meta` modifier, and the escape sequences
+    /// terminals send for arrows and the like are read as those keys.
+    /// Variables and conditionals are skipped, except that the bindings of
+    /// `$if mode=vi` are reported, since nextshell's editor isn't modal.
+    Inputrc,
+}
+
+/// A keyset read from another format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Imported {
+    /// The bindings that were understood.
+    pub keyset: Keyset,
+    /// The bindings that weren't, in the order found.
+    pub unmapped: Vec<Unmapped>,
+}
+
+/// A binding that couldn't be imported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unmapped {
+    /// The line of the binding, counting from 1.
+    pub line: usize,
+    /// The binding, in its own format's words.
+    pub entry: String,
+    /// Why it wasn't imported.
+    pub reason: String,
+}
+
+/// An error reading keybindings in another format.
+#[derive(Debug)]
+pub struct ConvertError {
+    format: Format,
+    line: Option<usize>,
+    message: String,
+}
+
+/// A name that isn't a [`Format`].
+#[derive(Debug)]
+pub struct UnknownFormat(String);
+
+/// The formats, in the order of [`Format`]'s variants.
+pub const FORMATS: [Format; 2] = [Format::VsCode, Format::Inputrc];
+
+/// Reads keybindings in another format.
+///
+/// Fails only if the text isn't in the format at all; bindings that can't be
+/// imported are listed in [`Imported::unmapped`].
+///
+/// # Example
+///
+/// ```
+/// use nextshell::keysets::convert::{self, Format};
+///
+/// let inputrc = r#"
+/// "\C-a": beginning-of-line
+/// "\e[1;5C": forward-word
+/// Meta-b: backward-word
+/// "\C-x\C-e": kill-whole-line
+/// "#;
+/// let imported = convert::import(Format::Inputrc, inputrc).unwrap();
+/// let keyset = &imported.keyset;
+/// assert_eq!(keyset.get("editor_view:move_to_line_start").unwrap().to_string(), "ctrl-a");
+/// assert_eq!(keyset.get("editor_view:move_forward_one_word").unwrap().to_string(), "ctrl-right");
+/// assert_eq!(keyset.get("editor_view:move_backward_one_word").unwrap().to_string(), "meta-b");
+///
+/// assert_eq!(imported.unmapped[0].line, 5);
+/// assert_eq!(
+///     imported.unmapped[0].to_string(),
+///     r#"line 5: "\C-x\C-e": kill-whole-line: sequences of several keys aren't supported"#
+/// );
+/// ```
+pub fn import(format: Format, text: &str) -> Result<Imported, ConvertError> {
+    match format {
+        Format::VsCode => vscode::import(text),
+        Format::Inputrc => inputrc::import(text),
+    }
+}
+
+/// Writes an imported keyset as a keyset file.
+///
+/// The unmapped bindings are listed in comments at the top, so they can be
+/// bound by hand.
+pub fn to_yaml(format: Format, imported: &Imported) -> String {
+    let mut yaml = format!("# Imported from {}.\n", format.description());
+    if !imported.unmapped.is_empty() {
+        yaml.push_str("#\n# Not imported:\n");
+        for unmapped in &imported.unmapped {
+            yaml.push_str(&format!("#   {}\n", unmapped));
+        }
+    }
+    yaml.push_str("---\n");
+    for action in imported.keyset.unbound() {
+        yaml.push_str(&format!("{}: {}\n", quote(action), super::UNBOUND));
+    }
+    for (action, binding) in imported.keyset.iter() {
+        yaml.push_str(&format!("{}: {}\n", quote(action), plain(binding)));
+    }
+    yaml
+}
+
+impl Format {
+    /// The name of the format in URLs, like `vscode`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Format::VsCode => "vscode",
+            Format::Inputrc => "inputrc",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match *self {
+            Format::VsCode => "VS Code keybindings.json",
+            Format::Inputrc => "readline inputrc",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Format, UnknownFormat> {
+        FORMATS
+            .iter()
+            .copied()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| UnknownFormat(s.to_owned()))
+    }
+}
+
+/// Collects the bindings of an import.
+///
+/// Actions have one binding each, so when an action is bound twice the last
+/// binding wins, and the earlier one is reported.
+struct Importer {
+    imported: Imported,
+    bound_on: HashMap<&'static str, (usize, String)>,
+}
+
+impl Importer {
+    fn new() -> Importer {
+        Importer {
+            imported: Imported {
+                keyset: Keyset::new(),
+                unmapped: Vec::new(),
+            },
+            bound_on: HashMap::new(),
+        }
+    }
+
+    fn bind(&mut self, line: usize, entry: String, action: &'static str, binding: KeyBinding) {
+        if let Some((earlier, earlier_entry)) = self.bound_on.insert(action, (line, entry)) {
+            self.unmapped(
+                earlier,
+                earlier_entry,
+                format!("`{}` is bound again on line {}", action, line),
+            );
+        }
+        self.imported.keyset.insert(action, binding);
+    }
+
+    fn unbind(&mut self, action: &'static str) {
+        self.bound_on.remove(action);
+        self.imported.keyset.unbind(action);
+    }
+
+    fn unmapped(&mut self, line: usize, entry: String, reason: impl Into<String>) {
+        self.imported.unmapped.push(Unmapped {
+            line,
+            entry,
+            reason: reason.into(),
+        });
+    }
+
+    fn finish(mut self) -> Imported {
+        self.imported.unmapped.sort_by_key(|unmapped| unmapped.line);
+        self.imported
+    }
+}
+
+impl fmt::Display for Unmapped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.entry, self.reason)
+    }
+}
+
+impl ConvertError {
+    fn at(format: Format, line: usize, message: impl Into<String>) -> ConvertError {
+        ConvertError {
+            format,
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+
+    /// The line of the error, if known, counting from 1.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(
+                f,
+                "invalid {} keybindings: line {}: {}",
+                self.format, line, self.message
+            ),
+            None => write!(f, "invalid {} keybindings: {}", self.format, self.message),
+        }
+    }
+}
+
+impl StdError for ConvertError {}
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown keybindings format {:?}", self.0)
+    }
+}
+
+impl StdError for UnknownFormat {}
+
+/// Looks an action up in a table of another program's commands.
+fn action(table: &[(&str, &'static str)], command: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|&&(name, _)| name == command)
+        .map(|&(_, action)| action)
+}
+
+/// Characters and the characters typed with `shift`, on a US keyboard.
+const SHIFTED: [(char, char); 21] = [
+    ('`', '~'),
+    ('1', '!'),
+    ('2', '@'),
+    ('3', '#'),
+    ('4', '$'),
+    ('5', '%'),
+    ('6', '^'),
+    ('7', '&'),
+    ('8', '*'),
+    ('9', '('),
+    ('0', ')'),
+    ('-', '_'),
+    ('=', '+'),
+    ('[', '{'),
+    (']', '}'),
+    ('\\', '|'),
+    (';', ':'),
+    ('\'', '"'),
+    (',', '<'),
+    ('.', '>'),
+    ('/', '?'),
+];
+
+/// The character typed by a key with `shift` held.
+fn shifted(c: char) -> char {
+    match SHIFTED.iter().find(|&&(plain, _)| plain == c) {
+        Some(&(_, shifted)) => shifted,
+        None => c.to_uppercase().next().unwrap_or(c),
+    }
+}
+
+/// Returns true if typing `c` takes `shift`.
+fn is_shifted(c: char) -> bool {
+    c.is_uppercase() || SHIFTED.iter().any(|&(_, shifted)| shifted == c)
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes a binding as the bundled keysets do, unquoted unless YAML would
+/// read it as something else, like `[` or `cmd-:`.
+fn plain(binding: KeyBinding) -> String {
+    let s = binding.to_string();
+    match s.chars().next() {
+        Some(c) if c.is_alphanumeric() && !s.ends_with(':') => s,
+        _ => quote(&s),
+    }
+}
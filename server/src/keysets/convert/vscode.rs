@@ -0,0 +1,302 @@
+//! VS Code `keybindings.json`: a list of `{ "key", "command", "when" }`
+//! objects, in JSON with comments.
+
+use serde_json::Value;
+
+use super::{action, shifted, ConvertError, Format, Imported, Importer};
+use crate::keysets::KeyBinding;
+
+/// VS Code commands, and the actions that do the same.
+const COMMANDS: &[(&str, &str)] = &[
+    ("cursorLeft", "editor_view:left"),
+    ("cursorRight", "editor_view:right"),
+    ("cursorUp", "editor_view:up"),
+    ("cursorDown", "editor_view:down"),
+    ("cursorWordLeft", "editor_view:move_backward_one_word"),
+    ("cursorWordStartLeft", "editor_view:move_backward_one_word"),
+    ("cursorWordRight", "editor_view:move_forward_one_word"),
+    ("cursorWordEndRight", "editor_view:move_forward_one_word"),
+    ("cursorHome", "editor_view:home"),
+    ("cursorEnd", "editor_view:end"),
+    ("cursorLineStart", "editor_view:move_to_line_start"),
+    ("cursorLineEnd", "editor_view:move_to_line_end"),
+    ("cursorTop", "editor_view:move_to_buffer_start"),
+    ("cursorBottom", "editor_view:move_to_buffer_end"),
+    ("cursorLeftSelect", "editor_view:select_left"),
+    ("cursorRightSelect", "editor_view:select_right"),
+    ("cursorUpSelect", "editor_view:select_up"),
+    ("cursorDownSelect", "editor_view:select_down"),
+    ("cursorWordLeftSelect", "editor_view:select_left_by_word"),
+    (
+        "cursorWordStartLeftSelect",
+        "editor_view:select_left_by_word",
+    ),
+    ("cursorWordRightSelect", "editor_view:select_right_by_word"),
+    (
+        "cursorWordEndRightSelect",
+        "editor_view:select_right_by_word",
+    ),
+    ("cursorHomeSelect", "editor:select_to_line_start"),
+    ("cursorEndSelect", "editor:select_to_line_end"),
+    ("deleteLeft", "editor_view:backspace"),
+    ("deleteRight", "editor_view:delete"),
+    ("deleteWordLeft", "editor:delete_word_left"),
+    ("deleteWordRight", "editor:delete_word_right"),
+    ("deleteAllLeft", "editor_view:delete_all_left"),
+    ("deleteAllRight", "editor_view:delete_all_right"),
+    ("editor.action.selectAll", "editor_view:select_all"),
+    (
+        "editor.action.insertCursorAbove",
+        "editor_view:add_cursor_above",
+    ),
+    (
+        "editor.action.insertCursorBelow",
+        "editor_view:add_cursor_below",
+    ),
+    (
+        "editor.action.addSelectionToNextFindMatch",
+        "editor_view:add_next_occurrence",
+    ),
+    ("editor.action.deleteLines", "editor_view:clear_lines"),
+    (
+        "editor.action.inlineSuggest.commit",
+        "editor_view:insert_autosuggestion_text_and_update_selection",
+    ),
+    ("editor.fold", "editor_view:fold"),
+    ("editor.unfold", "editor_view:unfold"),
+    (
+        "editor.createFoldingRangeFromSelection",
+        "editor_view:fold_selected_ranges",
+    ),
+    (
+        "editor.action.nextMatchFindAction",
+        "find:find_next_occurrence",
+    ),
+    (
+        "editor.action.previousMatchFindAction",
+        "find:find_prev_occurrence",
+    ),
+    ("workbench.action.terminal.clear", "input:clear_screen"),
+    ("workbench.action.terminal.copySelection", "terminal:copy"),
+    ("workbench.action.terminal.paste", "terminal:paste"),
+    ("workbench.action.terminal.focusFind", "terminal:find"),
+    (
+        "workbench.action.terminal.selectAll",
+        "terminal:select_all_blocks",
+    ),
+    ("workbench.action.terminal.focus", "terminal:focus_input"),
+    ("workbench.action.terminal.split", "pane_group:add_right"),
+    (
+        "workbench.action.terminal.focusNextPane",
+        "pane_group:navigate_next",
+    ),
+    (
+        "workbench.action.terminal.focusPreviousPane",
+        "pane_group:navigate_prev",
+    ),
+    (
+        "workbench.action.terminal.resizePaneLeft",
+        "pane_group:resize_left",
+    ),
+    (
+        "workbench.action.terminal.resizePaneRight",
+        "pane_group:resize_right",
+    ),
+    (
+        "workbench.action.terminal.resizePaneUp",
+        "pane_group:resize_up",
+    ),
+    (
+        "workbench.action.terminal.resizePaneDown",
+        "pane_group:resize_down",
+    ),
+    (
+        "workbench.action.showCommands",
+        "workspace:toggle_command_palette",
+    ),
+    (
+        "workbench.action.openSettings",
+        "workspace:show_settings_modal",
+    ),
+    (
+        "workbench.action.openGlobalKeybindings",
+        "workspace:show_keybinding_settings",
+    ),
+    (
+        "workbench.action.selectTheme",
+        "workspace:show_theme_chooser",
+    ),
+    ("workbench.action.zoomIn", "workspace:increase_font_size"),
+    ("workbench.action.zoomOut", "workspace:decrease_font_size"),
+    ("workbench.action.zoomReset", "workspace:reset_font_size"),
+    ("workbench.action.nextEditor", "workspace:activate_next_tab"),
+    (
+        "workbench.action.previousEditor",
+        "workspace:activate_prev_tab",
+    ),
+];
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let (json, lines) = strip_comments(text);
+    let value = serde_json::from_str::<Value>(&json)
+        .map_err(|err| ConvertError::at(Format::VsCode, err.line(), err.to_string()))?;
+    let entries = match value {
+        Value::Array(entries) => entries,
+        _ => {
+            return Err(ConvertError::at(
+                Format::VsCode,
+                1,
+                "expected a list of keybindings",
+            ))
+        }
+    };
+
+    let mut importer = Importer::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let line = lines.get(i).copied().unwrap_or(1);
+        let (key, command) = match (
+            entry.get("key").and_then(Value::as_str),
+            entry.get("command").and_then(Value::as_str),
+        ) {
+            (Some(key), Some(command)) => (key, command),
+            _ => {
+                importer.unmapped(line, entry.to_string(), "expected a `key` and a `command`");
+                continue;
+            }
+        };
+        let text = format!("{}: {}", key, command);
+
+        let (removed, name) = match command.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, command),
+        };
+        let action = match action(COMMANDS, name) {
+            Some(action) => action,
+            None => {
+                importer.unmapped(line, text, format!("no nextshell action for `{}`", name));
+                continue;
+            }
+        };
+        if removed {
+            importer.unbind(action);
+            continue;
+        }
+        match binding(key) {
+            Ok(binding) => importer.bind(line, text, action, binding),
+            Err(reason) => importer.unmapped(line, text, reason),
+        }
+    }
+    Ok(importer.finish())
+}
+
+/// Translates a VS Code key, like `ctrl+shift+k`, to a binding.
+fn binding(key: &str) -> Result<KeyBinding, String> {
+    let key = key.trim().to_lowercase();
+    if key.contains(char::is_whitespace) {
+        return Err("sequences of several keys aren't supported".to_owned());
+    }
+    let (modifiers, key) = if key == "+" {
+        ("", "+")
+    } else if let Some(modifiers) = key.strip_suffix("++") {
+        (modifiers, "+")
+    } else {
+        key.rsplit_once('+').unwrap_or(("", &key))
+    };
+
+    let mut binding = String::new();
+    let mut shift = false;
+    for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+        let name = match modifier {
+            "ctrl" | "alt" | "cmd" => modifier,
+            "shift" => {
+                shift = true;
+                modifier
+            }
+            "meta" | "win" => "meta",
+            _ => return Err(format!("unknown modifier `{}`", modifier)),
+        };
+        binding.push_str(name);
+        binding.push('-');
+    }
+
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if shift => binding.push(shifted(c)),
+        _ if key == "space" => return Err("nextshell can't bind `space`".to_owned()),
+        _ => binding.push_str(key),
+    }
+    binding
+        .parse()
+        .map_err(|_| format!("unknown key `{}`", key))
+}
+
+/// Blanks out the comments and trailing commas VS Code allows, keeping
+/// every line where it was, and finds the line each binding starts on.
+fn strip_comments(text: &str) -> (String, Vec<usize>) {
+    let mut json = String::with_capacity(text.len());
+    let mut starts = Vec::new();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                json.push(c);
+                while let Some(c) = chars.next() {
+                    json.push(c);
+                    match c {
+                        '\\' => json.extend(chars.next()),
+                        '"' => break,
+                        '\n' => line += 1,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+                json.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        json.push('\n');
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                json.push(' ');
+            }
+            '[' | '{' => {
+                if c == '{' && depth == 1 {
+                    starts.push(line);
+                }
+                depth += 1;
+                json.push(c);
+            }
+            ']' | '}' => {
+                let end = json.trim_end().len();
+                if json[..end].ends_with(',') {
+                    json.replace_range(end - 1..end, " ");
+                }
+                depth -= 1;
+                json.push(c);
+            }
+            '\n' => {
+                line += 1;
+                json.push(c);
+            }
+            _ => json.push(c),
+        }
+    }
+    (json, starts)
+}
@@ -0,0 +1,373 @@
+//! Keybinding sets
+//!
+//! A keyset maps action names, like `terminal:copy`, to the [`KeyBinding`]
+//! that triggers them, as in the files under `static/keysets`. Their format
+//! is described in `static/keysets/FORMAT.md`. The [`validate`] module
+//! checks keyset files, line by line, the [`resolve`](mod@resolve) module layers them,
+//! one on top of another, and the [`convert`] module imports other programs'
+//! keybindings. A [`Catalog`] loads a keysets directory, and
+//! [`api`] serves it as JSON:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use nextshell::Filter;
+//!
+//! let catalog = nextshell::keysets::Catalog::load("static/keysets").expect("keysets");
+//!
+//! // GET /keysets         -> every keyset, in brief
+//! // GET /keysets/emacs   -> one keyset, in full
+//! // GET /keysets/resolve?layers=default-nextshell-keybindings,emacs
+//! //                      -> the bindings in effect, and where they come from
+//! // POST /keysets/resolve?layers=default-nextshell-keybindings
+//! //                      -> the same, with the YAML keyset posted on top
+//! // POST /keysets/import/vscode -> a posted keybindings.json, as a keyset
+//! let routes = nextshell::path("keysets").and(nextshell::keysets::api(Arc::new(catalog)));
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use nextshell::keysets::{Key, Keyset};
+//!
+//! let keyset: Keyset = serde_yaml::from_str(r#"
+//! "editor_view:add_next_occurrence": ctrl-g
+//! "terminal:copy": cmd-c
+//! "terminal:find": cmd-f
+//! "#).unwrap();
+//!
+//! let copy = keyset.get("terminal:copy").unwrap();
+//! assert!(copy.modifiers.cmd);
+//! assert_eq!(copy.key, Key::Char('c'));
+//! assert!(keyset.conflicts().is_empty());
+//! ```
+
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use headers::{ContentType, HeaderMapExt};
+use http::StatusCode;
+use hyper::Body;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
+
+use crate::filter::{Filter, FilterClone, One};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+
+mod binding;
+mod catalog;
+pub mod convert;
+pub mod resolve;
+pub mod validate;
+
+pub use self::binding::{InvalidKeyBinding, Key, KeyBinding, Modifiers, Platform, UnknownPlatform};
+pub use self::catalog::{Catalog, Entry, LoadError};
+
+/// The largest keyset the resolve and import routes accept.
+const MAX_KEYSET: u64 = 256 * 1024;
+
+/// The layer name of a keyset posted to the resolve route.
+const USER_LAYER: &str = "user";
+
+/// Actions and the bindings that trigger them.
+///
+/// A keyset may also unbind actions, written `none` or left empty in YAML,
+/// to take away the bindings of the keysets beneath it; see [`resolve`](mod@resolve).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Keyset {
+    bindings: BTreeMap<String, Option<KeyBinding>>,
+}
+
+/// Actions of one context bound to the same chord, so that pressing it
+/// can only trigger one of them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Conflict {
+    /// The chord.
+    pub binding: KeyBinding,
+    /// The actions bound to it, in order.
+    pub actions: Vec<String>,
+}
+
+impl Keyset {
+    /// Creates an empty keyset.
+    pub fn new() -> Keyset {
+        Keyset::default()
+    }
+
+    /// Returns the binding of an action, if it's bound.
+    pub fn get(&self, action: &str) -> Option<KeyBinding> {
+        self.bindings.get(action).copied().flatten()
+    }
+
+    /// Returns true if the keyset unbinds an action.
+    pub fn unbinds(&self, action: &str) -> bool {
+        matches!(self.bindings.get(action), Some(None))
+    }
+
+    /// Binds an action, returning its previous binding.
+    pub fn insert(&mut self, action: impl Into<String>, binding: KeyBinding) -> Option<KeyBinding> {
+        self.bindings.insert(action.into(), Some(binding)).flatten()
+    }
+
+    /// Unbinds an action, returning its previous binding.
+    pub fn unbind(&mut self, action: impl Into<String>) -> Option<KeyBinding> {
+        self.bindings.insert(action.into(), None).flatten()
+    }
+
+    /// Iterates over the bound actions and their bindings, ordered by
+    /// action.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, KeyBinding)> + '_ {
+        self.bindings
+            .iter()
+            .filter_map(|(action, &binding)| Some((&**action, binding?)))
+    }
+
+    /// Iterates over the actions the keyset unbinds, in order.
+    pub fn unbound(&self) -> impl Iterator<Item = &str> + '_ {
+        self.bindings
+            .iter()
+            .filter(|(_, binding)| binding.is_none())
+            .map(|(action, _)| &**action)
+    }
+
+    /// Returns the number of actions bound or unbound.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Returns true if no actions are bound or unbound.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Finds chords bound to several actions of the same [`context`].
+    ///
+    /// Actions of different contexts may share a chord: `cmd-a` can select
+    /// all the text of the editor, and all the blocks of the terminal.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut chords = BTreeMap::<(&str, KeyBinding), Vec<String>>::new();
+        for (action, binding) in self.iter() {
+            chords
+                .entry((context(action), binding))
+                .or_default()
+                .push(action.to_owned());
+        }
+        chords
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|((_, binding), actions)| Conflict { binding, actions })
+            .collect()
+    }
+}
+
+impl FromIterator<(String, KeyBinding)> for Keyset {
+    fn from_iter<I: IntoIterator<Item = (String, KeyBinding)>>(iter: I) -> Keyset {
+        Keyset {
+            bindings: iter
+                .into_iter()
+                .map(|(action, binding)| (action, Some(binding)))
+                .collect(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Keyset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Keyset, D::Error> {
+        let bindings = BTreeMap::<String, Option<String>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(action, binding)| {
+                let binding = match binding.as_deref().map(str::trim) {
+                    None | Some(UNBOUND) => None,
+                    Some(binding) => Some(binding.parse().map_err(de::Error::custom)?),
+                };
+                Ok((action, binding))
+            })
+            .collect::<Result<_, D::Error>>()?;
+        Ok(Keyset { bindings })
+    }
+}
+
+/// How a keyset file unbinds an action, besides leaving it empty.
+pub(crate) const UNBOUND: &str = "none";
+
+/// The context of an action: the part of its name before the `:`, like
+/// `terminal` for `terminal:copy`.
+pub fn context(action: &str) -> &str {
+    action.split(':').next().unwrap_or_default()
+}
+
+/// Creates a `Filter` serving every keyset API route.
+///
+/// Combines [`resolve()`], [`import`], [`list`] and [`get`].
+pub fn api(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    self::resolve(catalog.clone())
+        .or(import())
+        .unify()
+        .or(list(catalog.clone()))
+        .unify()
+        .or(get(catalog))
+        .unify()
+}
+
+/// Creates a `Filter` that lists keysets in brief, at `GET /`.
+pub fn list(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get().and(crate::path::end()).map(move || {
+        let summaries = catalog
+            .iter()
+            .map(|entry| Summary {
+                name: &entry.name,
+                file: &entry.file,
+                actions: entry.keyset.len(),
+            })
+            .collect::<Vec<_>>();
+        crate::reply::json(&summaries).into_response()
+    })
+}
+
+/// Creates a `Filter` that fetches one keyset in full, at `GET /<name>`.
+///
+/// Unbound actions are `null`. Rejects with "not found" if there's no
+/// keyset with that name.
+pub fn get(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path::param::<String>())
+        .and(crate::path::end())
+        .and_then(move |name: String| {
+            let entry = catalog.get(&name).cloned();
+            async move {
+                match entry {
+                    Some(entry) => Ok(crate::reply::json(&entry).into_response()),
+                    None => {
+                        tracing::debug!("keysets: no keyset {:?}", name);
+                        Err(reject::not_found())
+                    }
+                }
+            }
+        })
+}
+
+/// Creates a `Filter` that layers keysets, at `GET /resolve?layers=<names>`.
+///
+/// `layers` names keysets of the catalog, bottom first, separated by commas.
+/// The reply is their [`resolve::Resolved`] bindings. With `POST`, the body
+/// is a YAML keyset to put on top of them, as the `user` layer; it's checked
+/// as by [`validate::check`], and if it has errors, they are the reply, with
+/// `400 Bad Request`. Unknown keysets are also a `400 Bad Request`.
+pub fn resolve(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    let user = crate::get()
+        .map(|| None)
+        .or(crate::post()
+            .and(crate::body::content_length_limit(MAX_KEYSET))
+            .and(crate::body::bytes())
+            .map(Some))
+        .unify();
+    crate::path("resolve")
+        .and(crate::path::end())
+        .and(crate::query::<ResolveQuery>())
+        .and(user)
+        .map(move |query: ResolveQuery, user: Option<Bytes>| {
+            let mut layers = Vec::new();
+            for name in query.layers.split(',').map(str::trim) {
+                if name.is_empty() {
+                    continue;
+                }
+                match catalog.get(name) {
+                    Some(entry) => layers.push((&*entry.name, &entry.keyset)),
+                    None => return bad_request(format!("unknown keyset `{}`", name)),
+                }
+            }
+
+            let user = match user.as_deref().map(std::str::from_utf8) {
+                None => None,
+                Some(Ok(text)) => {
+                    let checked = validate::check(text, None);
+                    match checked.keyset {
+                        Some(keyset) => Some(keyset),
+                        None => {
+                            let errors = checked
+                                .diagnostics
+                                .iter()
+                                .filter(|diagnostic| {
+                                    diagnostic.severity == validate::Severity::Error
+                                })
+                                .map(|diagnostic| {
+                                    format!("line {}: {}", diagnostic.line, diagnostic.message)
+                                })
+                                .collect::<Vec<_>>();
+                            return bad_request(errors.join("\n"));
+                        }
+                    }
+                }
+                Some(Err(_)) => return bad_request("the keyset isn't UTF-8 text".to_owned()),
+            };
+            if let Some(ref user) = user {
+                layers.push((USER_LAYER, user));
+            }
+
+            crate::reply::json(&resolve::resolve(layers)).into_response()
+        })
+}
+
+/// Creates a `Filter` that converts keybindings from another program, at
+/// `POST /import/<format>`, where `format` is a [`convert::Format`], like
+/// `vscode` or `inputrc`.
+///
+/// The body is the keybindings file. The reply is a keyset file, as written
+/// by [`convert::to_yaml`], listing the bindings it couldn't import in
+/// comments; or `400 Bad Request` saying why the file couldn't be read, or
+/// that the format is unknown.
+pub fn import() -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::path("import")
+        .and(crate::post())
+        .and(crate::path::param::<String>())
+        .and(crate::path::end())
+        .and(crate::body::content_length_limit(MAX_KEYSET))
+        .and(crate::body::bytes())
+        .map(|format: String, body: Bytes| {
+            let format = match format.parse::<convert::Format>() {
+                Ok(format) => format,
+                Err(err) => return bad_request(err.to_string()),
+            };
+            let text = match std::str::from_utf8(&body) {
+                Ok(text) => text,
+                Err(_) => return bad_request("the keybindings aren't UTF-8 text".to_owned()),
+            };
+            match convert::import(format, text) {
+                Ok(imported) => {
+                    let mut res = Response::new(Body::from(convert::to_yaml(format, &imported)));
+                    res.headers_mut().insert(
+                        http::header::CONTENT_TYPE,
+                        http::HeaderValue::from_static("application/yaml"),
+                    );
+                    res
+                }
+                Err(err) => bad_request(err.to_string()),
+            }
+        })
+}
+
+#[derive(Deserialize)]
+struct ResolveQuery {
+    #[serde(default)]
+    layers: String,
+}
+
+/// A keyset in brief.
+#[derive(Serialize)]
+struct Summary<'a> {
+    name: &'a str,
+    file: &'a str,
+    actions: usize,
+}
+
+fn bad_request(message: String) -> Response {
+    let mut res = Response::new(Body::from(message + "\n"));
+    *res.status_mut() = StatusCode::BAD_REQUEST;
+    res.headers_mut().typed_insert(ContentType::text_utf8());
+    res
+}
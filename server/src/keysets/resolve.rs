@@ -0,0 +1,127 @@
+//! Layering keysets.
+//!
+//! Keysets are stacked, each one on top of those before it: `emacs.yaml`
+//! goes on top of `default-nextshell-keybindings.yaml`, and a user's own
+//! bindings on top of both. [`resolve`] works out the bindings in effect,
+//! and the layer each one comes from.
+//!
+//! # Example
+//!
+//! ```
+//! use nextshell::keysets::{resolve, Keyset};
+//!
+//! let default: Keyset = serde_yaml::from_str(r#"
+//! "input:clear_screen": ctrl-l
+//! "terminal:copy": cmd-c
+//! "terminal:find": cmd-f
+//! "#).unwrap();
+//! let user: Keyset = serde_yaml::from_str(r#"
+//! "terminal:copy": ctrl-shift-C
+//! "terminal:find": none
+//! "#).unwrap();
+//!
+//! let resolved = resolve::resolve(vec![("default", &default), ("user", &user)]);
+//! let copy = &resolved.bindings["terminal:copy"];
+//! assert_eq!(copy.binding.unwrap().to_string(), "ctrl-shift-C");
+//! assert_eq!(copy.layer, "user");
+//! assert_eq!(copy.overrides[0].layer, "default");
+//!
+//! let keyset = resolved.keyset();
+//! assert!(keyset.get("input:clear_screen").is_some());
+//! assert!(keyset.get("terminal:find").is_none());
+//! ```
+
+use std::collections::{btree_map, BTreeMap};
+
+use serde::Serialize;
+
+use super::{Conflict, KeyBinding, Keyset};
+
+/// The bindings in effect after layering keysets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Resolved {
+    /// The names of the layers, bottom first.
+    pub layers: Vec<String>,
+    /// Every action bound or unbound by some layer, and how it ended up.
+    pub bindings: BTreeMap<String, Resolution>,
+    /// Chords bound to several actions of one context, once layered.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// How an action ended up, after layering keysets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Resolution {
+    /// The binding in effect, or `None` if the action was unbound.
+    pub binding: Option<KeyBinding>,
+    /// The topmost layer that binds or unbinds the action.
+    pub layer: String,
+    /// What the layers beneath said about the action, topmost first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Overridden>,
+}
+
+/// A binding, or unbinding, hidden by a layer above it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Overridden {
+    /// The layer it was in.
+    pub layer: String,
+    /// The binding, or `None` if that layer unbound the action.
+    pub binding: Option<KeyBinding>,
+}
+
+/// Stacks keysets, bottom first, each named for provenance.
+///
+/// A layer that binds an action replaces its binding in the layers beneath,
+/// and a layer that unbinds an action removes it. Actions a layer says
+/// nothing about are left as they were.
+pub fn resolve<'a, I>(layers: I) -> Resolved
+where
+    I: IntoIterator<Item = (&'a str, &'a Keyset)>,
+{
+    let mut names = Vec::new();
+    let mut bindings = BTreeMap::<String, Resolution>::new();
+    for (name, keyset) in layers {
+        names.push(name.to_owned());
+        for (action, binding) in &keyset.bindings {
+            let resolution = Resolution {
+                binding: *binding,
+                layer: name.to_owned(),
+                overrides: Vec::new(),
+            };
+            match bindings.entry(action.clone()) {
+                btree_map::Entry::Occupied(mut entry) => {
+                    let previous = entry.insert(resolution);
+                    let overrides = &mut entry.get_mut().overrides;
+                    overrides.push(Overridden {
+                        layer: previous.layer,
+                        binding: previous.binding,
+                    });
+                    overrides.extend(previous.overrides);
+                }
+                btree_map::Entry::Vacant(entry) => {
+                    entry.insert(resolution);
+                }
+            }
+        }
+    }
+
+    let mut resolved = Resolved {
+        layers: names,
+        bindings,
+        conflicts: Vec::new(),
+    };
+    resolved.conflicts = resolved.keyset().conflicts();
+    resolved
+}
+
+impl Resolved {
+    /// The bindings in effect, as a keyset of their own.
+    ///
+    /// Actions that were unbound are left out.
+    pub fn keyset(&self) -> Keyset {
+        self.bindings
+            .iter()
+            .filter_map(|(action, resolution)| Some((action.clone(), resolution.binding?)))
+            .collect()
+    }
+}
@@ -0,0 +1,300 @@
+//! Strict checking of keyset files.
+//!
+//! [`check`] reads a keyset, reporting every problem it finds with the line
+//! it is on: bindings that don't parse, chords bound to two actions, and,
+//! for a given platform, modifiers it doesn't have. Actions left empty, or
+//! bound to `none`, are unbound.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_yaml::Value;
+
+use super::{Key, KeyBinding, Keyset, Platform, UNBOUND};
+
+/// A problem found in a keyset file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The line the problem is on, counting from 1.
+    pub line: usize,
+    /// Whether the keyset is unusable, or merely suspect.
+    pub severity: Severity,
+    /// What's wrong.
+    pub message: String,
+}
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The keyset works, but likely not as meant.
+    Warning,
+    /// The file isn't a valid keyset.
+    Error,
+}
+
+/// The result of checking a keyset file.
+#[derive(Debug)]
+pub struct Checked {
+    /// The keyset, if the file had no errors.
+    pub keyset: Option<Keyset>,
+    /// Every problem found, in line order.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Checked {
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.line, self.severity, self.message)
+    }
+}
+
+/// Checks the text of a keyset file.
+///
+/// Conflicts, where one chord is bound to two actions of the same context,
+/// are warnings: the keyset loads, but one of the actions can't be used.
+/// With a `platform`, bindings using modifiers it doesn't have, like `cmd`
+/// on Linux, are errors.
+///
+/// # Example
+///
+/// ```
+/// use nextshell::keysets::validate::{self, Severity};
+/// use nextshell::keysets::Platform;
+///
+/// let text = "\"terminal:copy\": cmd-c\n\"terminal:paste\": hyper-v\n";
+/// let checked = validate::check(text, Some(Platform::Linux));
+/// assert!(checked.keyset.is_none());
+///
+/// for diagnostic in &checked.diagnostics {
+///     println!("keyset.yaml:{}", diagnostic);
+/// }
+/// assert_eq!(checked.diagnostics[0].line, 1);
+/// assert_eq!(
+///     checked.diagnostics[0].message,
+///     "`terminal:copy`: `cmd-c` uses `cmd`, which linux doesn't have"
+/// );
+/// assert_eq!(checked.diagnostics[1].severity, Severity::Error);
+/// ```
+pub fn check(text: &str, platform: Option<Platform>) -> Checked {
+    let mut checker = Checker {
+        lines: action_lines(text),
+        diagnostics: Vec::new(),
+    };
+
+    let value = match serde_yaml::from_str::<Value>(text) {
+        Ok(value) => value,
+        Err(err) => {
+            let line = err.location().map_or(1, |location| location.line());
+            checker.error(line, format!("invalid YAML: {}", err));
+            return checker.finish(None);
+        }
+    };
+    let map = match value {
+        // A file of comments only.
+        Value::Null => return checker.finish(Some(Keyset::new())),
+        Value::Mapping(map) => map,
+        _ => {
+            checker.error(
+                1,
+                "expected a map of action names to key bindings".to_owned(),
+            );
+            return checker.finish(None);
+        }
+    };
+
+    let mut keyset = Keyset::new();
+    for (action, binding) in &map {
+        let action = match action.as_str() {
+            Some(action) => action,
+            None => {
+                checker.error(1, format!("action names are strings, not {:?}", action));
+                continue;
+            }
+        };
+        let line = checker.line(action);
+        if !is_action_name(action) {
+            checker.warning(
+                line,
+                format!("`{}`: action names look like `context:action_name`", action),
+            );
+        }
+        let binding = match binding {
+            Value::Null => {
+                keyset.unbind(action);
+                continue;
+            }
+            Value::String(binding) if binding.trim() == UNBOUND => {
+                keyset.unbind(action);
+                continue;
+            }
+            Value::String(binding) => binding,
+            _ => {
+                checker.error(line, format!("`{}`: expected a key binding", action));
+                continue;
+            }
+        };
+        let binding = match binding.parse::<KeyBinding>() {
+            Ok(binding) => binding,
+            Err(err) => {
+                checker.error(line, format!("`{}`: {}", action, err));
+                continue;
+            }
+        };
+        checker.shift(line, action, binding);
+        if let Some(platform) = platform {
+            for modifier in binding.missing_on(platform) {
+                checker.error(
+                    line,
+                    format!(
+                        "`{}`: `{}` uses `{}`, which {} doesn't have",
+                        action, binding, modifier, platform
+                    ),
+                );
+            }
+        }
+        keyset.insert(action, binding);
+    }
+
+    for mut conflict in keyset.conflicts() {
+        // The first binding in the file stands; the others are warned about.
+        conflict.actions.sort_by_key(|action| checker.line(action));
+        let (first, rest) = conflict
+            .actions
+            .split_first()
+            .expect("conflicts have actions");
+        for action in rest {
+            let line = checker.line(action);
+            checker.warning(
+                line,
+                format!(
+                    "`{}` is bound to `{}`, as is `{}` on line {}; only one of them can be used",
+                    action,
+                    conflict.binding,
+                    first,
+                    checker.line(first)
+                ),
+            );
+        }
+    }
+
+    if checker
+        .diagnostics
+        .iter()
+        .any(|d| d.severity == Severity::Error)
+    {
+        checker.finish(None)
+    } else {
+        checker.finish(Some(keyset))
+    }
+}
+
+struct Checker {
+    lines: HashMap<String, usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn finish(mut self, keyset: Option<Keyset>) -> Checked {
+        // Sorting is stable, so problems on one line stay in the order found.
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        Checked {
+            keyset,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    fn line(&self, action: &str) -> usize {
+        self.lines.get(action).copied().unwrap_or(1)
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    /// Printable keys are written as typed, so `shift` goes with an
+    /// upper-case letter.
+    fn shift(&mut self, line: usize, action: &str, binding: KeyBinding) {
+        let c = match binding.key {
+            Key::Char(c) if c.is_alphabetic() => c,
+            _ => return,
+        };
+        let mut fixed = binding;
+        if binding.modifiers.shift && c.is_lowercase() {
+            fixed.key = Key::Char(c.to_uppercase().next().unwrap_or(c));
+        } else if !binding.modifiers.shift && c.is_uppercase() {
+            fixed.modifiers.shift = true;
+        } else {
+            return;
+        }
+        self.warning(
+            line,
+            format!(
+                "`{}`: keys are written as typed, so `{}` should be `{}`",
+                action, binding, fixed
+            ),
+        );
+    }
+}
+
+/// Action names are `context:action_name`, in snake case.
+fn is_action_name(action: &str) -> bool {
+    let snake = |s: &str| {
+        !s.is_empty()
+            && s.bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+    };
+    match action.split_once(':') {
+        Some((context, name)) => snake(context) && snake(name),
+        None => false,
+    }
+}
+
+/// Finds the line each action is on.
+///
+/// Keysets are flat maps, so every line that isn't a comment starts with an
+/// action name, quoted or not.
+fn action_lines(text: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        let action = match line.chars().next() {
+            Some(quote @ '"') | Some(quote @ '\'') => line[1..].split(quote).next(),
+            Some('#') | None => None,
+            // Action names have a `:` of their own; the key ends at `: `.
+            Some(_) => line.split(": ").next().map(str::trim_end),
+        };
+        if let Some(action) = action {
+            lines.entry(action.to_owned()).or_insert(i + 1);
+        }
+    }
+    lines
+}
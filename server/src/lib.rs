@@ -16,6 +16,9 @@
 //! - Multipart form data
 //! - Static Files and Directories
 //! - Websockets
+//! - Terminal sessions over Websockets
+//! - Terminal themes as JSON
+//! - Keybinding sets
 //! - Access logging
 //! - Etc
 //!
@@ -90,16 +93,24 @@ mod error;
 mod filter;
 pub mod filters;
 mod generic;
+#[cfg(feature = "keysets")]
+pub mod keysets;
 pub mod redirect;
 pub mod reject;
 pub mod reply;
 mod route;
 mod server;
 mod service;
+#[cfg(all(feature = "terminal", unix))]
+pub mod terminal;
 pub mod test;
+#[cfg(feature = "themes")]
+pub mod themes;
 #[cfg(feature = "tls")]
 mod tls;
 mod transport;
+#[cfg(feature = "workflows")]
+pub mod workflows;
 
 pub use self::error::Error;
 pub use self::filter::Filter;
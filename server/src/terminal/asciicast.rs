@@ -0,0 +1,367 @@
+//! Terminal recordings in the [asciicast v2] format.
+//!
+//! A recording is a file of newline-delimited JSON: a header object, followed
+//! by one `[time, code, data]` array per event.
+//!
+//! ```notrust
+//! {"version":2,"width":80,"height":24,"timestamp":1504467315}
+//! [0.248848,"o","\u001b[1;31mHello \u001b[32mWorld!\u001b[0m\n"]
+//! [1.001376,"r","100x40"]
+//! ```
+//!
+//! [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures_util::{stream, Stream};
+use serde::{Deserialize, Serialize};
+
+use super::Size;
+
+const VERSION: u8 = 2;
+
+/// The slowest and fastest a recording plays back.
+const MIN_SPEED: f64 = 0.1;
+const MAX_SPEED: f64 = 100.0;
+
+/// The header line of a recording.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Header {
+    /// Initial terminal width, in columns.
+    pub width: u16,
+    /// Initial terminal height, in rows.
+    pub height: u16,
+    /// When the recording started, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// Pauses longer than this many seconds are shortened during playback.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+    /// A title for the recording.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Environment variables of the recorded program, like `SHELL` and `TERM`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Header {
+    /// Creates a header for a terminal of the given size, starting now.
+    pub fn new(size: Size) -> Header {
+        Header {
+            width: size.cols,
+            height: size.rows,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since| since.as_secs()),
+            ..Header::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct VersionedHeader {
+    version: u8,
+    #[serde(flatten)]
+    header: Header,
+}
+
+/// A single timed event of a recording.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    /// Seconds since the start of the recording.
+    pub time: f64,
+    /// What kind of event this is.
+    pub kind: EventKind,
+    /// The event data: terminal bytes for output and input, `COLSxROWS` for
+    /// resizes, or the label of a marker.
+    pub data: String,
+}
+
+/// The kind of a recorded [`Event`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// Data written by the program to the terminal.
+    Output,
+    /// Data typed into the terminal.
+    Input,
+    /// The terminal was resized.
+    Resize,
+    /// A named point in the recording.
+    Marker,
+}
+
+impl EventKind {
+    /// The one-letter code used for this kind in asciicast files.
+    pub fn code(&self) -> &'static str {
+        match self {
+            EventKind::Output => "o",
+            EventKind::Input => "i",
+            EventKind::Resize => "r",
+            EventKind::Marker => "m",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<EventKind> {
+        match code {
+            "o" => Some(EventKind::Output),
+            "i" => Some(EventKind::Input),
+            "r" => Some(EventKind::Resize),
+            "m" => Some(EventKind::Marker),
+            _ => None,
+        }
+    }
+}
+
+impl Event {
+    /// The new terminal size, if this is a resize event.
+    pub fn size(&self) -> Option<Size> {
+        if self.kind != EventKind::Resize {
+            return None;
+        }
+        let (cols, rows) = self.data.split_once('x')?;
+        Some(Size {
+            cols: cols.trim().parse().ok()?,
+            rows: rows.trim().parse().ok()?,
+        })
+    }
+}
+
+/// Writes a recording as a terminal session happens.
+///
+/// Output and resizes are recorded by default. Input is not, since it may
+/// contain passwords typed at prompts that don't echo; enable it with
+/// [`Recorder::record_input`].
+pub struct Recorder {
+    out: BufWriter<Box<dyn Write + Send>>,
+    start: Instant,
+    record_input: bool,
+    record_resize: bool,
+    output_utf8: Utf8Decoder,
+    input_utf8: Utf8Decoder,
+}
+
+impl Recorder {
+    /// Starts a recording into `out`, writing the header immediately.
+    pub fn new(out: impl Write + Send + 'static, header: &Header) -> io::Result<Recorder> {
+        let mut out = BufWriter::new(Box::new(out) as Box<dyn Write + Send>);
+        serde_json::to_writer(
+            &mut out,
+            &VersionedHeader {
+                version: VERSION,
+                header: header.clone(),
+            },
+        )?;
+        out.write_all(b"\n")?;
+
+        Ok(Recorder {
+            out,
+            start: Instant::now(),
+            record_input: false,
+            record_resize: true,
+            output_utf8: Utf8Decoder::default(),
+            input_utf8: Utf8Decoder::default(),
+        })
+    }
+
+    /// Starts a recording into a new file at `path`.
+    pub fn create(path: impl AsRef<Path>, header: &Header) -> io::Result<Recorder> {
+        Recorder::new(File::create(path)?, header)
+    }
+
+    /// Sets whether input events are recorded (defaults to false).
+    pub fn record_input(mut self, enabled: bool) -> Self {
+        self.record_input = enabled;
+        self
+    }
+
+    /// Sets whether resize events are recorded (defaults to true).
+    pub fn record_resize(mut self, enabled: bool) -> Self {
+        self.record_resize = enabled;
+        self
+    }
+
+    /// Records output written by the program.
+    ///
+    /// A UTF-8 sequence split across calls is held back until it completes.
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let data = self.output_utf8.decode(bytes);
+        self.event(EventKind::Output, &data)
+    }
+
+    /// Records input typed into the terminal, if enabled.
+    pub fn input(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if !self.record_input {
+            return Ok(());
+        }
+        let data = self.input_utf8.decode(bytes);
+        self.event(EventKind::Input, &data)
+    }
+
+    /// Records a resize of the terminal, if enabled.
+    pub fn resize(&mut self, size: Size) -> io::Result<()> {
+        if !self.record_resize {
+            return Ok(());
+        }
+        self.event(EventKind::Resize, &format!("{}x{}", size.cols, size.rows))
+    }
+
+    /// Records a marker, to make a point of the recording easy to find.
+    pub fn marker(&mut self, label: &str) -> io::Result<()> {
+        self.event(EventKind::Marker, label)
+    }
+
+    /// Flushes buffered events to the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn event(&mut self, kind: EventKind, data: &str) -> io::Result<()> {
+        if data.is_empty() && kind != EventKind::Marker {
+            return Ok(());
+        }
+        let time = self.start.elapsed().as_micros() as f64 / 1_000_000.0;
+        serde_json::to_writer(&mut self.out, &(time, kind.code(), data))?;
+        self.out.write_all(b"\n")
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("record_input", &self.record_input)
+            .field("record_resize", &self.record_resize)
+            .finish()
+    }
+}
+
+/// A recording read back from an asciicast file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// The header of the recording.
+    pub header: Header,
+    /// The events of the recording, in order.
+    pub events: Vec<Event>,
+}
+
+impl Recording {
+    /// Reads a recording.
+    ///
+    /// Events of kinds this version doesn't know about are skipped.
+    pub fn from_reader(reader: impl BufRead) -> io::Result<Recording> {
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(line) => serde_json::from_str::<VersionedHeader>(&line?)?,
+            None => return Err(invalid("empty recording")),
+        };
+        if header.version != VERSION {
+            return Err(invalid("unsupported asciicast version"));
+        }
+
+        let mut events = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (time, code, data): (f64, String, String) = serde_json::from_str(&line)?;
+            if let Some(kind) = EventKind::from_code(&code) {
+                events.push(Event { time, kind, data });
+            }
+        }
+
+        Ok(Recording {
+            header: header.header,
+            events,
+        })
+    }
+
+    /// Reads the recording stored at `path`.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Recording> {
+        let bytes = tokio::fs::read(path).await?;
+        Recording::from_reader(&bytes[..])
+    }
+
+    /// Plays the recording back, yielding each event when it is due.
+    ///
+    /// A `speed` of 2.0 plays twice as fast as recorded. Speeds are clamped
+    /// to between 0.1 and 100, and one that isn't a positive number plays in
+    /// real time. Pauses are capped by the header's `idle_time_limit`, if it
+    /// has one.
+    pub fn play(self, speed: f64) -> impl Stream<Item = Event> + Send {
+        let idle_limit = self.header.idle_time_limit;
+        let speed = if speed > 0.0 {
+            speed.clamp(MIN_SPEED, MAX_SPEED)
+        } else {
+            1.0
+        };
+        stream::unfold(
+            (self.events.into_iter(), 0.0),
+            move |(mut events, last)| async move {
+                let event = events.next()?;
+                let mut pause = (event.time - last).max(0.0);
+                if let Some(limit) = idle_limit {
+                    pause = pause.min(limit);
+                }
+                // A negative or NaN pause, from a bad limit, is none at all.
+                let pause =
+                    Duration::try_from_secs_f64((pause / speed).max(0.0)).unwrap_or(Duration::MAX);
+                tokio::time::sleep(pause).await;
+                let time = event.time;
+                Some((event, (events, time)))
+            },
+        )
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Decodes a stream of bytes as UTF-8, replacing invalid sequences and
+/// holding back a sequence that is split across chunks.
+#[derive(Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+
+        let mut out = String::new();
+        let mut rest = &self.pending[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    out.push_str(valid);
+                    rest = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    out.push_str(std::str::from_utf8(valid).expect("checked utf8"));
+                    match err.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        None => {
+                            rest = invalid;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.pending = rest.to_vec();
+        out
+    }
+}
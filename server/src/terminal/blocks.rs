@@ -0,0 +1,229 @@
+//! Command blocks, from shell integration marks.
+//!
+//! Shells set up with an [init script](init_script) mark their output with
+//! the OSC 133 "semantic prompt" sequences first used by FinalTerm:
+//!
+//! ```notrust
+//! ESC ] 133 ; A BEL        a prompt starts
+//! ESC ] 133 ; B BEL        the prompt ends, and the command line starts
+//! ESC ] 133 ; C BEL        the command line ends, and the output starts
+//! ESC ] 133 ; D ; 0 BEL    the command finished, with exit code 0
+//! ```
+//!
+//! A [`Parser`] finds these marks in a terminal's output, and turns them
+//! into [`BlockEvent`]s, so that a client can show each command, its output
+//! and its exit code as a unit. The marks themselves are left in the output,
+//! where terminals ignore them.
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+
+pub use nextshell_workflows_types::Shell;
+
+/// The longest OSC sequence the parser looks into. Longer ones are skipped.
+const MAX_OSC: usize = 4 * 1024;
+/// The longest prompt or command line the parser keeps.
+const MAX_TEXT: usize = 4 * 1024;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+const BS: u8 = 0x08;
+
+/// Returns the script that sets up `shell` to mark its prompts and commands.
+///
+/// The script is meant to be sourced from the shell's startup file, and does
+/// nothing when sourced twice or by a non-interactive shell.
+pub fn init_script(shell: &Shell) -> &'static str {
+    match shell {
+        Shell::Bash => include_str!("integration/bash.sh"),
+        Shell::Zsh => include_str!("integration/zsh.zsh"),
+        Shell::Fish => include_str!("integration/fish.fish"),
+    }
+}
+
+/// A step in the life of a command block.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BlockEvent {
+    /// The shell started printing a prompt, which starts a new block.
+    PromptStart,
+    /// The prompt is complete, and the user is typing a command.
+    Prompt {
+        /// The prompt, without escape sequences.
+        text: String,
+    },
+    /// A command was entered. Its output follows.
+    Command {
+        /// The command line.
+        ///
+        /// Shells that don't report it get the text echoed while it was
+        /// typed, which is a close guess for simple line editing.
+        text: String,
+    },
+    /// The command finished.
+    Finished {
+        /// The exit code, if the shell reported one.
+        exit_code: Option<i32>,
+    },
+}
+
+/// Finds shell integration marks in a stream of terminal output.
+///
+/// Marks may be split across reads; the parser picks up where it left off.
+#[derive(Debug, Default)]
+pub struct Parser {
+    escape: Escape,
+    osc: Vec<u8>,
+    phase: Phase,
+    text: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Escape {
+    #[default]
+    Ground,
+    Esc,
+    Csi,
+    Osc,
+    OscEsc,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Phase {
+    #[default]
+    Idle,
+    Prompt,
+    Input,
+    Running,
+}
+
+impl Parser {
+    /// Creates a parser, expecting the start of a terminal's output.
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Scans the next chunk of output.
+    ///
+    /// Returns the events completed in this chunk, each with the offset just
+    /// past the mark that caused it, so the output can be split around them.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<(usize, BlockEvent)> {
+        let mut events = Vec::new();
+        for (i, &byte) in bytes.iter().enumerate() {
+            match self.escape {
+                Escape::Ground => match byte {
+                    ESC => self.escape = Escape::Esc,
+                    _ => self.text(byte),
+                },
+                Escape::Esc => {
+                    self.escape = match byte {
+                        b'[' => Escape::Csi,
+                        b']' => {
+                            self.osc.clear();
+                            Escape::Osc
+                        }
+                        // Intermediate bytes, as in `ESC ( B`.
+                        0x20..=0x2f => Escape::Esc,
+                        _ => Escape::Ground,
+                    }
+                }
+                Escape::Csi => {
+                    if (0x40..=0x7e).contains(&byte) {
+                        self.escape = Escape::Ground;
+                    }
+                }
+                Escape::Osc => match byte {
+                    BEL => {
+                        self.escape = Escape::Ground;
+                        self.osc(i + 1, &mut events);
+                    }
+                    ESC => self.escape = Escape::OscEsc,
+                    _ if self.osc.len() < MAX_OSC => self.osc.push(byte),
+                    _ => {}
+                },
+                Escape::OscEsc => {
+                    self.escape = Escape::Ground;
+                    if byte == b'\\' {
+                        self.osc(i + 1, &mut events);
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Returns true if a command has started, and not yet finished.
+    pub fn is_running(&self) -> bool {
+        self.phase == Phase::Running
+    }
+
+    fn text(&mut self, byte: u8) {
+        if !matches!(self.phase, Phase::Prompt | Phase::Input) {
+            return;
+        }
+        match byte {
+            BS => {
+                // Drop a whole UTF-8 character.
+                while let Some(b) = self.text.pop() {
+                    if b & 0xc0 != 0x80 {
+                        break;
+                    }
+                }
+            }
+            b'\n' | b'\t' => self.text.push(byte),
+            _ if byte < 0x20 || byte == 0x7f => {}
+            _ if self.text.len() < MAX_TEXT => self.text.push(byte),
+            _ => {}
+        }
+    }
+
+    fn osc(&mut self, offset: usize, events: &mut Vec<(usize, BlockEvent)>) {
+        let osc = String::from_utf8_lossy(&self.osc).into_owned();
+        let mut params = match osc.strip_prefix("133;") {
+            Some(mark) => mark.split(';'),
+            None => return,
+        };
+
+        match params.next() {
+            Some("A") => {
+                if self.phase == Phase::Running {
+                    // The shell didn't report how the last command ended.
+                    events.push((offset, BlockEvent::Finished { exit_code: None }));
+                }
+                self.phase = Phase::Prompt;
+                self.text.clear();
+                events.push((offset, BlockEvent::PromptStart));
+            }
+            Some("B") => {
+                let text = self.take_text();
+                self.phase = Phase::Input;
+                events.push((offset, BlockEvent::Prompt { text }));
+            }
+            Some("C") => {
+                let typed = self.take_text();
+                let reported = params.find_map(|param| {
+                    if let Some(url) = param.strip_prefix("cmdline_url=") {
+                        Some(percent_decode_str(url).decode_utf8_lossy().into_owned())
+                    } else {
+                        param.strip_prefix("cmdline=").map(str::to_owned)
+                    }
+                });
+                let text = reported.unwrap_or_else(|| typed.trim().to_owned());
+                self.phase = Phase::Running;
+                events.push((offset, BlockEvent::Command { text }));
+            }
+            Some("D") if self.phase == Phase::Running => {
+                let exit_code = params.next().and_then(|code| code.trim().parse().ok());
+                self.phase = Phase::Idle;
+                events.push((offset, BlockEvent::Finished { exit_code }));
+            }
+            _ => {}
+        }
+    }
+
+    fn take_text(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.text).into_owned();
+        self.text.clear();
+        text
+    }
+}
@@ -0,0 +1,310 @@
+//! Fanning a terminal out to several viewers.
+//!
+//! A [`Hub`] gives each viewer its own queue of [`ServerFrame`]s, so a viewer
+//! on a slow connection only holds up itself. Once a viewer has more output
+//! queued than the hub's limit, its queued output is dropped and the viewer is
+//! marked as lagging; it should then be sent a fresh replay of the screen with
+//! [`Hub::resync`], rather than the output it missed. Only output is dropped:
+//! other frames, like a command's end or the program's exit, are still queued
+//! for a lagging viewer, so that it never misses them.
+//!
+//! The hub also tracks which viewer holds control of the terminal. Exactly
+//! one viewer does, as long as there are any: the first to join, until it
+//! hands control to another or leaves. Viewers added with [`Hub::watch`]
+//! never hold control.
+//!
+//! A `Hub` does no locking of its own. Keep it behind the same lock as
+//! whatever must change atomically with it, like a session's scrollback, so
+//! that a replay and the live output after it line up.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use super::protocol::ServerFrame;
+
+/// A set of viewers of one terminal.
+pub struct Hub {
+    viewers: BTreeMap<u64, Arc<Queue>>,
+    watchers: BTreeSet<u64>,
+    next_id: u64,
+    controller: Option<u64>,
+    limit: usize,
+}
+
+/// The receiving end of a viewer's queue.
+///
+/// Dropping a `Viewer` doesn't remove it from the hub; call [`Hub::leave`].
+pub struct Viewer {
+    id: u64,
+    queue: Arc<Queue>,
+}
+
+/// What a [`Viewer`] received.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Recv {
+    /// The next frame for this viewer.
+    Frame(ServerFrame),
+    /// The viewer fell behind, and output was dropped. No more output is
+    /// queued for it until it is resynced.
+    Lagged,
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    frames: VecDeque<ServerFrame>,
+    bytes: usize,
+    lagged: bool,
+    closed: bool,
+}
+
+impl Hub {
+    /// Creates a hub that lets up to `limit` bytes of output queue up for
+    /// each viewer.
+    ///
+    /// A single frame larger than the limit is still delivered, if the
+    /// viewer has nothing else queued.
+    pub fn new(limit: usize) -> Hub {
+        Hub {
+            viewers: BTreeMap::new(),
+            watchers: BTreeSet::new(),
+            next_id: 1,
+            controller: None,
+            limit,
+        }
+    }
+
+    /// Adds a viewer. If nobody holds control, the new viewer takes it.
+    pub fn join(&mut self) -> Viewer {
+        let viewer = self.add();
+        if self.controller.is_none() {
+            self.controller = Some(viewer.id);
+        }
+        viewer
+    }
+
+    /// Adds a viewer that only watches, and is never given control, like a
+    /// task on the server following the output.
+    pub fn watch(&mut self) -> Viewer {
+        let viewer = self.add();
+        self.watchers.insert(viewer.id);
+        viewer
+    }
+
+    fn add(&mut self) -> Viewer {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let queue = Arc::new(Queue {
+            state: Mutex::default(),
+            notify: Notify::new(),
+        });
+        self.viewers.insert(id, queue.clone());
+        Viewer { id, queue }
+    }
+
+    /// Removes a viewer, closing its queue once drained.
+    ///
+    /// If it held control, control passes to the longest-joined viewer left.
+    /// Returns false if there was no such viewer.
+    pub fn leave(&mut self, id: u64) -> bool {
+        let queue = match self.viewers.remove(&id) {
+            Some(queue) => queue,
+            None => return false,
+        };
+        queue.close();
+        self.watchers.remove(&id);
+
+        if self.controller == Some(id) {
+            let watchers = &self.watchers;
+            self.controller = self
+                .viewers
+                .keys()
+                .find(|viewer| !watchers.contains(viewer))
+                .copied();
+            if let Some(controller) = self.controller {
+                self.broadcast(ServerFrame::Control { controller });
+            }
+        }
+        true
+    }
+
+    /// Removes every viewer.
+    pub fn close(&mut self) {
+        for queue in self.viewers.values() {
+            queue.close();
+        }
+        self.viewers.clear();
+        self.watchers.clear();
+        self.controller = None;
+    }
+
+    /// The viewer holding control, if there are any viewers.
+    pub fn controller(&self) -> Option<u64> {
+        self.controller
+    }
+
+    /// Returns true if this viewer holds control.
+    pub fn is_controller(&self, id: u64) -> bool {
+        self.controller == Some(id)
+    }
+
+    /// Hands control from one viewer to another, telling every viewer.
+    ///
+    /// Returns false, and does nothing, unless `from` holds control and `to`
+    /// is a viewer that isn't only watching.
+    pub fn hand_off(&mut self, from: u64, to: u64) -> bool {
+        if !self.is_controller(from)
+            || !self.viewers.contains_key(&to)
+            || self.watchers.contains(&to)
+        {
+            return false;
+        }
+        self.controller = Some(to);
+        self.broadcast(ServerFrame::Control { controller: to });
+        true
+    }
+
+    /// Passes a viewer's request for control on to the viewer holding it.
+    pub fn request_control(&mut self, id: u64) {
+        match self.controller {
+            Some(controller) if controller != id => {
+                self.send(controller, ServerFrame::ControlRequested { viewer: id });
+            }
+            _ => {}
+        }
+    }
+
+    /// Queues a frame for one viewer.
+    pub fn send(&self, id: u64, frame: ServerFrame) {
+        if let Some(queue) = self.viewers.get(&id) {
+            queue.push(frame, self.limit);
+        }
+    }
+
+    /// Queues a frame for every viewer.
+    pub fn broadcast(&self, frame: ServerFrame) {
+        for queue in self.viewers.values() {
+            queue.push(frame.clone(), self.limit);
+        }
+    }
+
+    /// Queues a frame for every viewer but one.
+    pub fn broadcast_except(&self, id: u64, frame: ServerFrame) {
+        for (_, queue) in self.viewers.iter().filter(|(&viewer, _)| viewer != id) {
+            queue.push(frame.clone(), self.limit);
+        }
+    }
+
+    /// Replaces everything queued for a viewer with `frames`, and clears its
+    /// lagging mark.
+    ///
+    /// The frames are queued regardless of the limit.
+    pub fn resync(&self, id: u64, frames: Vec<ServerFrame>) {
+        if let Some(queue) = self.viewers.get(&id) {
+            let mut state = queue.state.lock().unwrap();
+            state.bytes = frames.iter().map(frame_size).sum();
+            state.frames = frames.into();
+            state.lagged = false;
+            drop(state);
+            queue.notify.notify_one();
+        }
+    }
+
+    /// Returns the number of viewers.
+    pub fn len(&self) -> usize {
+        self.viewers.len()
+    }
+
+    /// Returns true if there are no viewers.
+    pub fn is_empty(&self) -> bool {
+        self.viewers.is_empty()
+    }
+}
+
+impl fmt::Debug for Hub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hub")
+            .field("viewers", &self.viewers.keys().collect::<Vec<_>>())
+            .field("watchers", &self.watchers)
+            .field("controller", &self.controller)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl Viewer {
+    /// The id of this viewer, unique within its hub.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Waits for the next frame.
+    ///
+    /// Returns `None` once the viewer has left the hub, and everything queued
+    /// before has been received.
+    pub async fn recv(&self) -> Option<Recv> {
+        loop {
+            {
+                let mut state = self.queue.state.lock().unwrap();
+                if let Some(frame) = state.frames.pop_front() {
+                    state.bytes -= frame_size(&frame);
+                    return Some(Recv::Frame(frame));
+                }
+                if state.closed {
+                    return None;
+                }
+                if state.lagged {
+                    return Some(Recv::Lagged);
+                }
+            }
+            self.queue.notify.notified().await;
+        }
+    }
+}
+
+impl fmt::Debug for Viewer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Viewer").field("id", &self.id).finish()
+    }
+}
+
+impl Queue {
+    fn push(&self, frame: ServerFrame, limit: usize) {
+        let mut state = self.state.lock().unwrap();
+        let size = frame_size(&frame);
+        if state.closed || (state.lagged && size > 0) {
+            return;
+        }
+        if size > 0 && state.bytes > 0 && state.bytes + size > limit {
+            state.frames.retain(|frame| frame_size(frame) == 0);
+            state.bytes = 0;
+            state.lagged = true;
+        } else {
+            state.frames.push_back(frame);
+            state.bytes += size;
+        }
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+}
+
+/// How much a frame counts against a viewer's limit: only output does.
+fn frame_size(frame: &ServerFrame) -> usize {
+    match frame {
+        ServerFrame::Output(bytes) => bytes.len(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,24 @@
+# nextshell shell integration for bash.
+#
+# Marks the prompt, the command line and the command's output with OSC 133
+# sequences, so the terminal can tell them apart. Source it from ~/.bashrc.
+
+if [[ $- == *i* && -z $__nextshell_integrated ]]; then
+    __nextshell_integrated=1
+    __nextshell_first_prompt=1
+
+    __nextshell_prompt_command() {
+        local ret=$?
+        if [[ -z $__nextshell_first_prompt ]]; then
+            printf '\e]133;D;%s\a' "$ret"
+        fi
+        __nextshell_first_prompt=
+        printf '\e]133;A\a'
+        # Prompt themes may rebuild PS1 before every prompt.
+        [[ $PS1 == *'\e]133;B\a'* ]] || PS1+='\[\e]133;B\a\]'
+        return $ret
+    }
+
+    PROMPT_COMMAND="__nextshell_prompt_command${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+    PS0+='\e]133;C\a'
+fi
@@ -0,0 +1,27 @@
+# nextshell shell integration for fish.
+#
+# Marks the prompt, the command line and the command's output with OSC 133
+# sequences, so the terminal can tell them apart. Source it from
+# ~/.config/fish/config.fish.
+
+if status is-interactive; and not set -q __nextshell_integrated
+    set -g __nextshell_integrated 1
+
+    function __nextshell_prompt_start --on-event fish_prompt
+        printf '\e]133;A\a'
+    end
+
+    function __nextshell_preexec --on-event fish_preexec
+        printf '\e]133;C;cmdline_url=%s\a' (string escape --style=url -- "$argv")
+    end
+
+    function __nextshell_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    functions -c fish_prompt __nextshell_original_prompt
+    function fish_prompt
+        __nextshell_original_prompt
+        printf '\e]133;B\a'
+    end
+end
@@ -0,0 +1,357 @@
+//! Terminal sessions
+//!
+//! Spawns a shell behind a pseudo-terminal and bridges it to a browser over a
+//! [`WebSocket`](crate::ws::WebSocket), using the frames described in
+//! [`protocol`].
+//!
+//! [`serve`] ties the life of the shell to a single WebSocket. To keep shells
+//! running across page reloads, spawn them in a [`Registry`] instead.
+//!
+//! Sessions can be recorded in the [`asciicast`] format, and the recordings
+//! streamed back with the filters in [`playback`]. Shells set up with the
+//! scripts in [`blocks`] can also report where each command starts and ends.
+//!
+//! # Example
+//!
+//! ```
+//! use nextshell::Filter;
+//!
+//! let route = nextshell::path("terminal")
+//!     .and(nextshell::ws())
+//!     .map(|ws: nextshell::ws::Ws| {
+//!         ws.on_upgrade(|socket| async move {
+//!             match nextshell::terminal::shell().spawn() {
+//!                 Ok(terminal) => nextshell::terminal::serve(socket, terminal).await,
+//!                 Err(err) => eprintln!("failed to spawn shell: {}", err),
+//!             }
+//!         })
+//!     });
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use futures_util::stream::SplitStream;
+use futures_util::{future, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::process::Child;
+
+use self::protocol::{ClientFrame, ServerFrame};
+use self::pty::Pty;
+use crate::filters::ws::WebSocket;
+
+pub mod asciicast;
+pub mod blocks;
+pub mod hub;
+pub mod playback;
+pub mod protocol;
+mod pty;
+mod session;
+
+pub use self::session::{Registry, Run, RunEvent};
+
+const READ_BUF_SIZE: usize = 8 * 1024;
+
+/// Creates a [`Command`] for the user's login shell.
+///
+/// Uses the `SHELL` environment variable, falling back to `/bin/sh`.
+pub fn shell() -> Command {
+    let program = std::env::var_os("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| OsString::from("/bin/sh"));
+    command(program)
+}
+
+/// Creates a [`Command`] that runs `program` inside a terminal.
+pub fn command(program: impl Into<OsString>) -> Command {
+    Command {
+        program: program.into(),
+        args: Vec::new(),
+        envs: Vec::new(),
+        current_dir: None,
+        size: Size::default(),
+    }
+}
+
+/// A builder describing the process to spawn inside a terminal.
+#[derive(Clone, Debug)]
+pub struct Command {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    current_dir: Option<PathBuf>,
+    size: Size,
+}
+
+impl Command {
+    /// Adds an argument to pass to the program.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable for the program.
+    ///
+    /// `TERM` defaults to `xterm-256color` unless set here.
+    pub fn env(mut self, key: impl Into<OsString>, val: impl Into<OsString>) -> Self {
+        self.envs.push((key.into(), val.into()));
+        self
+    }
+
+    /// Sets the working directory of the program.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the initial window size of the terminal (defaults to 80x24).
+    pub fn size(mut self, cols: u16, rows: u16) -> Self {
+        self.size = Size { cols, rows };
+        self
+    }
+
+    /// Allocates a pseudo-terminal and spawns the program inside it.
+    pub fn spawn(&self) -> io::Result<Terminal> {
+        let (pty, slave) = Pty::open(self.size)?;
+
+        let mut cmd = tokio::process::Command::new(&self.program);
+        cmd.args(&self.args)
+            .env("TERM", "xterm-256color")
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave))
+            .kill_on_drop(true);
+        if let Some(ref dir) = self.current_dir {
+            cmd.current_dir(dir);
+        }
+        unsafe {
+            cmd.pre_exec(pty::make_controlling_terminal);
+        }
+
+        let child = cmd.spawn()?;
+        tracing::debug!(
+            "terminal: spawned {:?} (pid {:?})",
+            self.program,
+            child.id()
+        );
+        // The command still holds our copies of the slave. Close them, so
+        // that reads on the master report EOF once the child is gone.
+        drop(cmd);
+
+        Ok(Terminal {
+            pty: Arc::new(pty),
+            child,
+        })
+    }
+
+    /// The program this command runs.
+    pub fn program(&self) -> &OsStr {
+        &self.program
+    }
+}
+
+/// The size of a terminal window, in character cells.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Size {
+    /// Number of columns.
+    pub cols: u16,
+    /// Number of rows.
+    pub rows: u16,
+}
+
+impl Default for Size {
+    fn default() -> Size {
+        Size { cols: 80, rows: 24 }
+    }
+}
+
+/// How the program running in a terminal exited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ExitStatus {
+    /// The exit code, if the program exited normally.
+    pub code: Option<i32>,
+    /// The signal that terminated the program, if any.
+    pub signal: Option<i32>,
+}
+
+impl ExitStatus {
+    /// Returns true if the program exited normally with a code of zero.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl From<std::process::ExitStatus> for ExitStatus {
+    fn from(status: std::process::ExitStatus) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+
+        ExitStatus {
+            code: status.code(),
+            signal: status.signal(),
+        }
+    }
+}
+
+/// A program running inside a pseudo-terminal.
+///
+/// Dropping a `Terminal` closes the terminal and kills the program.
+pub struct Terminal {
+    pty: Arc<Pty>,
+    child: Child,
+}
+
+impl Terminal {
+    /// The OS process id of the program, if it hasn't been reaped yet.
+    pub fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Reads output written by the program.
+    ///
+    /// Returns `Ok(0)` once the program and all of its children have closed
+    /// the terminal.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.pty.read(buf).await
+    }
+
+    /// Writes input to the program, as if it were typed.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<()> {
+        self.pty.write_all(buf).await
+    }
+
+    /// Resizes the terminal window.
+    pub fn resize(&self, size: Size) -> io::Result<()> {
+        self.pty.resize(size)
+    }
+
+    /// Waits for the program to exit.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.child.wait().await.map(ExitStatus::from)
+    }
+
+    /// Forcibly kills the program.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.start_kill()
+    }
+}
+
+impl fmt::Debug for Terminal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Terminal")
+            .field("pid", &self.child.id())
+            .finish()
+    }
+}
+
+/// Bridges a terminal to a WebSocket until either side goes away.
+///
+/// Output from the program is forwarded as [`ServerFrame::Output`], followed
+/// by a single [`ServerFrame::Exit`] once it exits. If the client disconnects
+/// first, the program is killed.
+pub async fn serve(ws: WebSocket, terminal: Terminal) {
+    let Terminal { pty, mut child } = terminal;
+    let (mut ws_tx, ws_rx) = ws.split();
+
+    let reader = pty.clone();
+    let output = tokio::task::spawn(async move {
+        let mut buf = vec![0; READ_BUF_SIZE];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    let frame = ServerFrame::Output(buf[..n].to_vec());
+                    if let Err(err) = ws_tx.send(frame.into_message()).await {
+                        tracing::debug!("terminal: websocket send error: {}", err);
+                        return;
+                    }
+                }
+                Err(err) => {
+                    tracing::debug!("terminal: pty read error: {}", err);
+                    break;
+                }
+            }
+        }
+
+        match child.wait().await {
+            Ok(status) => {
+                tracing::debug!("terminal: program exited: {:?}", status);
+                let frame = ServerFrame::Exit(status.into());
+                let _ = ws_tx.send(frame.into_message()).await;
+            }
+            Err(err) => tracing::debug!("terminal: wait error: {}", err),
+        }
+        let _ = ws_tx.close().await;
+    });
+
+    let input = forward_input(ws_rx, &pty, |_| true);
+    let finished = future::select(Box::pin(input), output).await;
+
+    if let future::Either::Left((_, output)) = finished {
+        // The client went away first, so hang up on the program.
+        // Dropping the child (and with it the terminal) kills it.
+        tracing::debug!("terminal: client disconnected");
+        output.abort();
+    }
+}
+
+/// Feeds client frames into the terminal until the client goes away.
+///
+/// Each frame is passed to `on_frame` first, and only applied to the terminal
+/// if it returns true.
+async fn forward_input(
+    mut ws_rx: SplitStream<WebSocket>,
+    pty: &Pty,
+    mut on_frame: impl FnMut(&ClientFrame) -> bool,
+) {
+    while let Some(result) = ws_rx.next().await {
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(err) => {
+                tracing::debug!("terminal: websocket error: {}", err);
+                break;
+            }
+        };
+        if msg.is_close() {
+            break;
+        }
+        if msg.is_ping() || msg.is_pong() {
+            continue;
+        }
+
+        let frame = match ClientFrame::from_message(&msg) {
+            Ok(frame) => frame,
+            Err(err) => {
+                tracing::debug!("terminal: ignoring frame: {}", err);
+                continue;
+            }
+        };
+        if !on_frame(&frame) {
+            continue;
+        }
+        let result = match frame {
+            ClientFrame::Input(bytes) => pty.write_all(&bytes).await,
+            ClientFrame::Resize(size) => pty.resize(size),
+            // There's nobody to share control with.
+            ClientFrame::RequestControl | ClientFrame::HandOff { .. } => Ok(()),
+        };
+        if let Err(err) = result {
+            tracing::debug!("terminal: pty write error: {}", err);
+            break;
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Filters that play back recorded terminal sessions.
+//!
+//! Both filters serve the recordings in a directory, such as the one given to
+//! [`Registry::record`](super::Registry::record), matching the request path
+//! `/<id>` to the file `<id>.cast`. The `speed` query parameter speeds up
+//! playback (`?speed=2` plays twice as fast); it defaults to real time.
+//!
+//! # Example
+//!
+//! ```
+//! use nextshell::Filter;
+//!
+//! // GET /recordings/<id>?speed=2 -> server-sent events
+//! let sse = nextshell::path("recordings").and(nextshell::terminal::playback::sse("casts"));
+//!
+//! // GET /play/<id> -> websocket upgrade
+//! let ws = nextshell::path("play").and(nextshell::terminal::playback::ws("casts"));
+//!
+//! let routes = sse.or(ws);
+//! ```
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures_util::{future, stream, SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+
+use super::asciicast::{Event, EventKind, Recording};
+use super::protocol::ServerFrame;
+use super::Size;
+use crate::filter::{Filter, FilterClone, One};
+use crate::filters::sse;
+use crate::filters::ws::{WebSocket, Ws};
+use crate::reject::{self, Rejection};
+use crate::reply::Reply;
+
+#[derive(Deserialize)]
+struct Options {
+    #[serde(default = "real_time")]
+    speed: f64,
+}
+
+fn real_time() -> f64 {
+    1.0
+}
+
+/// Creates a `Filter` that streams a recording as server-sent events.
+///
+/// The stream starts with a `header` event holding the recording's
+/// [`Header`](super::asciicast::Header) as JSON. Then, as they fall due, come
+/// `output`, `input` and `marker` events whose data is a JSON string, and
+/// `resize` events whose data is `{"cols":..,"rows":..}`. A final `end` event
+/// marks the end of the recording.
+pub fn sse(
+    dir: impl Into<PathBuf>,
+) -> impl FilterClone<Extract = One<impl Reply>, Error = Rejection> {
+    crate::get()
+        .and(recording(dir))
+        .and(crate::query::<Options>())
+        .map(|recording: Recording, options: Options| {
+            sse::reply(sse_events(recording, options.speed))
+        })
+}
+
+/// Creates a `Filter` that plays a recording to a terminal WebSocket.
+///
+/// The client is sent [`ServerFrame`]s, as if it were attached to the
+/// session while it was recorded: a [`ServerFrame::Resize`] with the initial
+/// size, followed by output and resizes as they fall due. The socket is
+/// closed at the end of the recording.
+pub fn ws(
+    dir: impl Into<PathBuf>,
+) -> impl FilterClone<Extract = One<impl Reply>, Error = Rejection> {
+    crate::filters::ws::ws()
+        .and(recording(dir))
+        .and(crate::query::<Options>())
+        .map(|ws: Ws, recording: Recording, options: Options| {
+            ws.on_upgrade(move |socket| play(socket, recording, options.speed))
+        })
+}
+
+fn recording(
+    dir: impl Into<PathBuf>,
+) -> impl FilterClone<Extract = One<Recording>, Error = Rejection> {
+    let dir = Arc::new(dir.into());
+    crate::path::param::<String>()
+        .and(crate::path::end())
+        .and_then(move |id: String| {
+            let dir = dir.clone();
+            async move {
+                if !is_valid_id(&id) {
+                    tracing::debug!("playback: rejecting recording id {:?}", id);
+                    return Err(reject::not_found());
+                }
+                let path = dir.join(format!("{}.cast", id));
+                Recording::open(&path).await.map_err(|err| {
+                    tracing::debug!("playback: error opening {:?}: {}", path, err);
+                    reject::not_found()
+                })
+            }
+        })
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn sse_events(
+    recording: Recording,
+    speed: f64,
+) -> impl Stream<Item = Result<sse::Event, serde_json::Error>> + Send {
+    let header = sse::Event::default()
+        .event("header")
+        .json_data(&recording.header);
+    let end = sse::Event::default().event("end").data("");
+
+    stream::once(future::ready(header))
+        .chain(recording.play(speed).map(sse_event))
+        .chain(stream::once(future::ready(Ok(end))))
+}
+
+fn sse_event(event: Event) -> Result<sse::Event, serde_json::Error> {
+    let sse = sse::Event::default();
+    match event.kind {
+        EventKind::Output => sse.event("output").json_data(&event.data),
+        EventKind::Input => sse.event("input").json_data(&event.data),
+        EventKind::Marker => sse.event("marker").json_data(&event.data),
+        EventKind::Resize => match event.size() {
+            Some(size) => sse.event("resize").json_data(size),
+            None => sse.event("resize").json_data(&event.data),
+        },
+    }
+}
+
+async fn play(ws: WebSocket, recording: Recording, speed: f64) {
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    let size = Size {
+        cols: recording.header.width,
+        rows: recording.header.height,
+    };
+
+    let frames = stream::once(future::ready(ServerFrame::Resize(size))).chain(
+        recording
+            .play(speed)
+            .filter_map(|event| future::ready(frame(event))),
+    );
+    let send = async move {
+        futures_util::pin_mut!(frames);
+        while let Some(frame) = frames.next().await {
+            if let Err(err) = ws_tx.send(frame.into_message()).await {
+                tracing::debug!("playback: websocket send error: {}", err);
+                return;
+            }
+        }
+        let _ = ws_tx.close().await;
+    };
+    // Stop early if the client goes away.
+    let closed = async move {
+        while let Some(Ok(msg)) = ws_rx.next().await {
+            if msg.is_close() {
+                break;
+            }
+        }
+    };
+
+    future::select(Box::pin(send), Box::pin(closed)).await;
+}
+
+fn frame(event: Event) -> Option<ServerFrame> {
+    match event.kind {
+        EventKind::Output => Some(ServerFrame::Output(event.data.into_bytes())),
+        EventKind::Resize => event.size().map(ServerFrame::Resize),
+        EventKind::Input | EventKind::Marker => None,
+    }
+}
@@ -0,0 +1,264 @@
+//! The framed protocol spoken over a terminal WebSocket.
+//!
+//! Terminal bytes travel as binary messages in both directions: keystrokes
+//! from the client, and shell output from the server. Everything else is a
+//! text message holding a JSON object tagged with `type`:
+//!
+//! ```notrust
+//! client -> server  {"type":"resize","cols":120,"rows":40}
+//! client -> server  {"type":"request_control"}
+//! client -> server  {"type":"hand_off","viewer":2}
+//! server -> client  {"type":"attached","session":"9f86d081884c7d65","viewer":2,"controller":1,"cols":80,"rows":24}
+//! server -> client  {"type":"resize","cols":100,"rows":40}
+//! server -> client  {"type":"control","controller":2}
+//! server -> client  {"type":"control_requested","viewer":3}
+//! server -> client  {"type":"block","kind":"command","text":"ls -l"}
+//! server -> client  {"type":"exit","code":0,"signal":null}
+//! ```
+//!
+//! Several clients may view a shared session, but only the one holding
+//! control may type into it or resize it. Frames from the others are ignored.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::blocks::BlockEvent;
+use super::{ExitStatus, Size};
+use crate::filters::ws::Message;
+
+/// A frame sent by the browser client to the terminal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientFrame {
+    /// Bytes to write to the terminal, as if typed.
+    Input(Vec<u8>),
+    /// The client's viewport changed size.
+    Resize(Size),
+    /// Asks the viewer holding control of a shared session to hand it over.
+    RequestControl,
+    /// Hands control of a shared session to another viewer.
+    ///
+    /// Only honored when sent by the viewer holding control.
+    HandOff {
+        /// The viewer to hand control to.
+        viewer: u64,
+    },
+}
+
+/// A frame sent by the terminal to the browser client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerFrame {
+    /// The client was attached to a detachable session.
+    ///
+    /// Sent before any output. The client should reset its screen and resize
+    /// it to `size`, since the session's scrollback is replayed as output
+    /// immediately afterwards, and remember the id in order to reattach
+    /// later.
+    ///
+    /// A client that falls too far behind on a shared session is sent this
+    /// frame again, and the replay, instead of the output it missed.
+    Attached {
+        /// The id of the session, for reattaching.
+        session: String,
+        /// The id of this client among the session's viewers.
+        viewer: u64,
+        /// The viewer holding control of the session.
+        controller: u64,
+        /// The size of the session's terminal.
+        size: Size,
+    },
+    /// Bytes written by the shell to the terminal.
+    Output(Vec<u8>),
+    /// The terminal was resized, and the client should resize its view to
+    /// match, such as when playing back a recording.
+    Resize(Size),
+    /// Another viewer took control of the session.
+    Control {
+        /// The viewer now holding control.
+        controller: u64,
+    },
+    /// Another viewer asked for control; sent to the viewer holding it.
+    ControlRequested {
+        /// The viewer asking for control.
+        viewer: u64,
+    },
+    /// The shell marked a step of a command block, between the output before
+    /// the mark and the output after it.
+    Block(BlockEvent),
+    /// The shell exited; no more frames follow.
+    Exit(ExitStatus),
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientControl {
+    Resize { cols: u16, rows: u16 },
+    RequestControl,
+    HandOff { viewer: u64 },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerControl {
+    Attached {
+        session: String,
+        viewer: u64,
+        controller: u64,
+        cols: u16,
+        rows: u16,
+    },
+    Control {
+        controller: u64,
+    },
+    ControlRequested {
+        viewer: u64,
+    },
+    Block {
+        #[serde(flatten)]
+        event: BlockEvent,
+    },
+    Resize {
+        cols: u16,
+        rows: u16,
+    },
+    Exit {
+        code: Option<i32>,
+        signal: Option<i32>,
+    },
+}
+
+impl ClientFrame {
+    /// Decode a frame from a WebSocket message.
+    ///
+    /// Ping, pong and close messages are not frames, and should be handled
+    /// before calling this.
+    pub fn from_message(msg: &Message) -> Result<ClientFrame, ProtocolError> {
+        if msg.is_binary() {
+            return Ok(ClientFrame::Input(msg.as_bytes().to_vec()));
+        }
+        let text = msg.to_str().map_err(|()| ProtocolError::unexpected())?;
+        match serde_json::from_str(text).map_err(ProtocolError::json)? {
+            ClientControl::Resize { cols, rows } => Ok(ClientFrame::Resize(Size { cols, rows })),
+            ClientControl::RequestControl => Ok(ClientFrame::RequestControl),
+            ClientControl::HandOff { viewer } => Ok(ClientFrame::HandOff { viewer }),
+        }
+    }
+
+    /// Encode this frame into a WebSocket message.
+    pub fn into_message(self) -> Message {
+        match self {
+            ClientFrame::Input(bytes) => Message::binary(bytes),
+            ClientFrame::Resize(Size { cols, rows }) => {
+                control_message(&ClientControl::Resize { cols, rows })
+            }
+            ClientFrame::RequestControl => control_message(&ClientControl::RequestControl),
+            ClientFrame::HandOff { viewer } => control_message(&ClientControl::HandOff { viewer }),
+        }
+    }
+}
+
+impl ServerFrame {
+    /// Decode a frame from a WebSocket message.
+    pub fn from_message(msg: &Message) -> Result<ServerFrame, ProtocolError> {
+        if msg.is_binary() {
+            return Ok(ServerFrame::Output(msg.as_bytes().to_vec()));
+        }
+        let text = msg.to_str().map_err(|()| ProtocolError::unexpected())?;
+        match serde_json::from_str(text).map_err(ProtocolError::json)? {
+            ServerControl::Attached {
+                session,
+                viewer,
+                controller,
+                cols,
+                rows,
+            } => Ok(ServerFrame::Attached {
+                session,
+                viewer,
+                controller,
+                size: Size { cols, rows },
+            }),
+            ServerControl::Control { controller } => Ok(ServerFrame::Control { controller }),
+            ServerControl::ControlRequested { viewer } => {
+                Ok(ServerFrame::ControlRequested { viewer })
+            }
+            ServerControl::Block { event } => Ok(ServerFrame::Block(event)),
+            ServerControl::Resize { cols, rows } => Ok(ServerFrame::Resize(Size { cols, rows })),
+            ServerControl::Exit { code, signal } => {
+                Ok(ServerFrame::Exit(ExitStatus { code, signal }))
+            }
+        }
+    }
+
+    /// Encode this frame into a WebSocket message.
+    pub fn into_message(self) -> Message {
+        match self {
+            ServerFrame::Attached {
+                session,
+                viewer,
+                controller,
+                size: Size { cols, rows },
+            } => control_message(&ServerControl::Attached {
+                session,
+                viewer,
+                controller,
+                cols,
+                rows,
+            }),
+            ServerFrame::Control { controller } => {
+                control_message(&ServerControl::Control { controller })
+            }
+            ServerFrame::ControlRequested { viewer } => {
+                control_message(&ServerControl::ControlRequested { viewer })
+            }
+            ServerFrame::Block(event) => control_message(&ServerControl::Block { event }),
+            ServerFrame::Output(bytes) => Message::binary(bytes),
+            ServerFrame::Resize(Size { cols, rows }) => {
+                control_message(&ServerControl::Resize { cols, rows })
+            }
+            ServerFrame::Exit(ExitStatus { code, signal }) => {
+                control_message(&ServerControl::Exit { code, signal })
+            }
+        }
+    }
+}
+
+fn control_message<T: Serialize>(control: &T) -> Message {
+    Message::text(serde_json::to_string(control).expect("control frames serialize to json"))
+}
+
+/// A WebSocket message that isn't a valid terminal frame.
+pub struct ProtocolError {
+    json: Option<serde_json::Error>,
+}
+
+impl ProtocolError {
+    fn unexpected() -> ProtocolError {
+        ProtocolError { json: None }
+    }
+
+    fn json(err: serde_json::Error) -> ProtocolError {
+        ProtocolError { json: Some(err) }
+    }
+}
+
+impl fmt::Debug for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ProtocolError").field(&self.json).finish()
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.json {
+            Some(ref err) => write!(f, "invalid terminal control frame: {}", err),
+            None => f.write_str("unexpected terminal message type"),
+        }
+    }
+}
+
+impl StdError for ProtocolError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.json.as_ref().map(|err| err as _)
+    }
+}
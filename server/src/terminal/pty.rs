@@ -0,0 +1,171 @@
+//! Pseudo-terminal allocation.
+
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+
+use tokio::io::unix::AsyncFd;
+
+use super::Size;
+
+/// The master side of a pseudo-terminal, registered with the tokio reactor.
+pub(crate) struct Pty {
+    master: AsyncFd<OwnedFd>,
+}
+
+impl Pty {
+    /// Opens a new pseudo-terminal pair with the given window size.
+    ///
+    /// Returns the non-blocking master and the slave, which should be handed
+    /// to the child process as its controlling terminal.
+    pub(crate) fn open(size: Size) -> io::Result<(Pty, OwnedFd)> {
+        let mut master: libc::c_int = -1;
+        let mut slave: libc::c_int = -1;
+        let winsize = size.to_winsize();
+
+        let ret = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                ptr::null_mut(),
+                ptr::null(),
+                &winsize,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let master = unsafe { OwnedFd::from_raw_fd(master) };
+        let slave = unsafe { OwnedFd::from_raw_fd(slave) };
+
+        set_cloexec(master.as_raw_fd())?;
+        set_cloexec(slave.as_raw_fd())?;
+        set_nonblocking(master.as_raw_fd())?;
+
+        let master = AsyncFd::new(master)?;
+        Ok((Pty { master }, slave))
+    }
+
+    /// Reads output produced by the child.
+    ///
+    /// Returns `Ok(0)` once every slave descriptor has been closed, which is
+    /// how Linux reports that the child side of the terminal went away.
+    pub(crate) async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let mut guard = self.master.readable().await?;
+            let result = guard.try_io(|fd| {
+                let n = unsafe {
+                    libc::read(
+                        fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                    )
+                };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+
+            match result {
+                Ok(Err(ref err)) if err.raw_os_error() == Some(libc::EIO) => return Ok(0),
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Writes all of `buf` to the terminal, as if it were typed.
+    pub(crate) async fn write_all(&self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let mut guard = self.master.writable().await?;
+            let result = guard.try_io(|fd| {
+                let n = unsafe {
+                    libc::write(
+                        fd.as_raw_fd(),
+                        buf.as_ptr() as *const libc::c_void,
+                        buf.len(),
+                    )
+                };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+
+            match result {
+                Ok(Ok(0)) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(Ok(n)) => buf = &buf[n..],
+                Ok(Err(err)) => return Err(err),
+                Err(_would_block) => continue,
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the window size, delivering `SIGWINCH` to the foreground job.
+    pub(crate) fn resize(&self, size: Size) -> io::Result<()> {
+        let winsize = size.to_winsize();
+        let ret = unsafe {
+            libc::ioctl(
+                self.master.get_ref().as_raw_fd(),
+                libc::TIOCSWINSZ,
+                &winsize,
+            )
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Size {
+    fn to_winsize(self) -> libc::winsize {
+        libc::winsize {
+            ws_row: self.rows,
+            ws_col: self.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// Makes the child the leader of a new session, with the terminal on its
+/// standard input as the controlling terminal.
+///
+/// Must only be called between `fork` and `exec`.
+pub(crate) fn make_controlling_terminal() -> io::Result<()> {
+    unsafe {
+        if libc::setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_cloexec(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags == -1 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
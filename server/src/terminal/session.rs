@@ -0,0 +1,815 @@
+//! Detachable terminal sessions.
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{future, SinkExt, StreamExt};
+use tokio::process::Child;
+use tokio::task::AbortHandle;
+
+use super::asciicast::{Header, Recorder};
+use super::blocks::{self, BlockEvent};
+use super::hub::{Hub, Recv, Viewer};
+use super::protocol::{ClientFrame, ServerFrame};
+use super::pty::Pty;
+use super::{forward_input, Command, ExitStatus, Size, Terminal, READ_BUF_SIZE};
+use crate::filters::ws::WebSocket;
+
+const DEFAULT_SCROLLBACK: usize = 256 * 1024;
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_VIEWER_BUFFER: usize = 1024 * 1024;
+
+/// A registry of terminal sessions that outlive their WebSocket.
+///
+/// When a client disconnects, its session keeps running and keeps recording
+/// output into a bounded scrollback buffer. A client that reconnects with the
+/// session id is sent the scrollback, and then continues live. Sessions that
+/// stay detached for longer than the idle timeout are killed.
+///
+/// Any number of clients can be attached to a session at once, and all of
+/// them see its output. Only one of them, the first to attach, holds control
+/// and may type into the terminal, until it hands control to another with a
+/// [`ClientFrame::HandOff`] frame. Output is queued separately for each
+/// client, so a slow one can't hold up the rest; a client that falls too far
+/// behind skips ahead to a fresh replay of the scrollback.
+///
+/// Sessions can also be recorded, from start to exit, by pointing the
+/// registry at a directory with [`Registry::record`].
+///
+/// Cloning a `Registry` is cheap, and clones share the same sessions.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::time::Duration;
+/// use nextshell::Filter;
+///
+/// let registry = nextshell::terminal::Registry::new()
+///     .idle_timeout(Duration::from_secs(60));
+///
+/// // GET /terminal?session=<id> reattaches, or starts a new shell.
+/// let route = nextshell::path("terminal")
+///     .and(nextshell::ws())
+///     .and(nextshell::query::<HashMap<String, String>>())
+///     .map(move |ws: nextshell::ws::Ws, query: HashMap<String, String>| {
+///         let registry = registry.clone();
+///         ws.on_upgrade(move |socket| async move {
+///             let shell = nextshell::terminal::shell();
+///             registry.serve(socket, query.get("session").map(String::as_str), &shell).await
+///         })
+///     });
+/// ```
+#[derive(Clone)]
+pub struct Registry {
+    sessions: Arc<Mutex<HashMap<String, Arc<Session>>>>,
+    scrollback: usize,
+    idle_timeout: Duration,
+    viewer_buffer: usize,
+    blocks: bool,
+    record: Option<Arc<PathBuf>>,
+    record_input: bool,
+}
+
+struct Session {
+    id: String,
+    program: OsString,
+    pty: Arc<Pty>,
+    state: Mutex<State>,
+    pump: Mutex<Option<AbortHandle>>,
+}
+
+struct State {
+    scrollback: Scrollback,
+    hub: Hub,
+    size: Size,
+    blocks: Option<blocks::Parser>,
+    exit: Option<ExitStatus>,
+    recorder: Option<Recorder>,
+    // Bumped on every attach and detach, so that an idle timer knows whether
+    // the session has been touched since it was started.
+    generation: u64,
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Registry {
+        Registry {
+            sessions: Arc::default(),
+            scrollback: DEFAULT_SCROLLBACK,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            viewer_buffer: DEFAULT_VIEWER_BUFFER,
+            blocks: false,
+            record: None,
+            record_input: false,
+        }
+    }
+
+    /// Sets how many bytes of output each session keeps for replay
+    /// (defaults to 256 kilobytes).
+    ///
+    /// The oldest output is discarded first, so a replay may start in the
+    /// middle of a line or escape sequence.
+    pub fn scrollback(mut self, bytes: usize) -> Self {
+        self.scrollback = bytes;
+        self
+    }
+
+    /// Sets how long a session may stay detached before it is killed
+    /// (defaults to 10 minutes).
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Sets how many bytes of output may queue up for a client before it is
+    /// considered too slow to keep up (defaults to 1 megabyte).
+    ///
+    /// A client that falls behind by more is sent the scrollback again, in
+    /// place of the output it missed.
+    pub fn viewer_buffer(mut self, bytes: usize) -> Self {
+        self.viewer_buffer = bytes;
+        self
+    }
+
+    /// Sets whether output is scanned for shell integration marks, and
+    /// clients sent a [`ServerFrame::Block`] for each (defaults to false).
+    ///
+    /// Only shells set up with an [init script](blocks::init_script) make
+    /// these marks.
+    pub fn blocks(mut self, enabled: bool) -> Self {
+        self.blocks = enabled;
+        self
+    }
+
+    /// Records every new session into `dir`, as `<session id>.cast`.
+    ///
+    /// Recordings are in the [asciicast v2](super::asciicast) format, and
+    /// include output and resizes. The directory must already exist.
+    pub fn record(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.record = Some(Arc::new(dir.into()));
+        self
+    }
+
+    /// Sets whether recordings include input typed by clients
+    /// (defaults to false).
+    ///
+    /// Input is left out by default, since it includes anything typed at a
+    /// prompt that doesn't echo, like a password. Command lines typed in by
+    /// [`Registry::run`] are always left out, since they may have secrets
+    /// filled in.
+    pub fn record_input(mut self, enabled: bool) -> Self {
+        self.record_input = enabled;
+        self
+    }
+
+    /// Spawns `cmd` in a new, detached session, and returns its id.
+    ///
+    /// The session is reaped if nobody attaches within the idle timeout.
+    pub fn spawn(&self, cmd: &Command) -> io::Result<String> {
+        self.spawn_session(cmd).map(|session| session.id.clone())
+    }
+
+    /// Returns true if a session with this id is still alive.
+    pub fn contains(&self, id: &str) -> bool {
+        self.lock().contains_key(id)
+    }
+
+    /// The program a live session runs, like its shell.
+    pub fn program(&self, id: &str) -> Option<OsString> {
+        self.lock().get(id).map(|session| session.program.clone())
+    }
+
+    /// Returns the number of live sessions.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if there are no live sessions.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Kills a session, disconnecting any attached clients.
+    ///
+    /// Returns false if there was no such session.
+    pub fn kill(&self, id: &str) -> bool {
+        let session = self.lock().remove(id);
+        match session {
+            Some(session) => {
+                session.kill();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Serves a client over `ws`, until it disconnects.
+    ///
+    /// If `id` names a live session, the client is attached to it, alongside
+    /// any clients already attached. Otherwise, `cmd` is spawned in a new
+    /// session. Either way, the client is first sent a
+    /// [`ServerFrame::Attached`] frame with the session id, followed by the
+    /// session's scrollback.
+    pub async fn serve(&self, ws: WebSocket, id: Option<&str>, cmd: &Command) {
+        let existing = id.and_then(|id| self.lock().get(id).cloned());
+        let session = match existing {
+            Some(session) => session,
+            None => match self.spawn_session(cmd) {
+                Ok(session) => session,
+                Err(err) => {
+                    tracing::debug!("terminal session: spawn error: {}", err);
+                    let _ = ws.close().await;
+                    return;
+                }
+            },
+        };
+        self.attach(session, ws).await;
+    }
+
+    /// Runs a command line, and follows what it does.
+    ///
+    /// If `id` names a live session, the command line is typed into it, as if
+    /// by the client holding control, and every attached client sees it run.
+    /// The run ends when the shell marks the command finished, which only
+    /// shells set up with an [init script](blocks::init_script) do, in a
+    /// registry with [`blocks`](Registry::blocks) enabled; otherwise, when the
+    /// shell exits. Fails with [`io::ErrorKind::NotFound`] if the session
+    /// isn't live, or has exited, and with [`io::ErrorKind::WouldBlock`] if
+    /// it's marked as still running another command.
+    ///
+    /// Otherwise, `cmd`, a shell, is spawned in a new session with `-c` and
+    /// the command line as its arguments, and the run ends when it exits.
+    /// Clients can attach to the session to watch it, as to any other.
+    pub async fn run(&self, id: Option<&str>, cmd: &Command, line: &str) -> io::Result<Run> {
+        let (session, viewer, fresh) = match id {
+            Some(id) => {
+                let session =
+                    self.lock().get(id).cloned().ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::NotFound, "no such session")
+                    })?;
+                let mut input = line.as_bytes().to_vec();
+                input.push(b'\r');
+                let viewer = session.watch_input()?;
+                if let Err(err) = session.pty.write_all(&input).await {
+                    self.detach(&session, viewer.id());
+                    return Err(err);
+                }
+                (session, viewer, false)
+            }
+            None => {
+                let session = self.spawn_session(&cmd.clone().arg("-c").arg(line))?;
+                let viewer = session.watch(true);
+                (session, viewer, true)
+            }
+        };
+        tracing::debug!(
+            "terminal session {}: viewer {} running a command",
+            session.id,
+            viewer.id()
+        );
+        Ok(Run {
+            registry: self.clone(),
+            session,
+            viewer,
+            fresh,
+            started: false,
+            done: false,
+        })
+    }
+
+    fn spawn_session(&self, cmd: &Command) -> io::Result<Arc<Session>> {
+        let id = session_id()?;
+        let recorder = match self.record {
+            Some(ref dir) => Some(self.recorder(dir, &id, cmd)?),
+            None => None,
+        };
+        let Terminal { pty, child } = cmd.spawn()?;
+        let session = Arc::new(Session {
+            id,
+            program: cmd.program.clone(),
+            pty,
+            state: Mutex::new(State {
+                scrollback: Scrollback::new(self.scrollback),
+                hub: Hub::new(self.viewer_buffer),
+                size: cmd.size,
+                blocks: if self.blocks {
+                    Some(blocks::Parser::new())
+                } else {
+                    None
+                },
+                exit: None,
+                recorder,
+                generation: 0,
+            }),
+            pump: Mutex::new(None),
+        });
+
+        self.lock().insert(session.id.clone(), session.clone());
+        let pump = tokio::task::spawn(pump(session.clone(), child));
+        *session.pump.lock().unwrap() = Some(pump.abort_handle());
+        self.reap_when_idle(&session, 0);
+
+        tracing::debug!("terminal session {}: spawned", session.id);
+        Ok(session)
+    }
+
+    fn recorder(&self, dir: &Path, id: &str, cmd: &Command) -> io::Result<Recorder> {
+        let mut header = Header::new(cmd.size);
+        let term = cmd
+            .envs
+            .iter()
+            .rev()
+            .find(|(key, _)| key == "TERM")
+            .map(|(_, val)| val.to_string_lossy().into_owned());
+        header.env.insert(
+            "TERM".to_owned(),
+            term.unwrap_or_else(|| "xterm-256color".to_owned()),
+        );
+        header.env.insert(
+            "SHELL".to_owned(),
+            cmd.program.to_string_lossy().into_owned(),
+        );
+
+        let path = dir.join(format!("{}.cast", id));
+        tracing::debug!("terminal session {}: recording to {:?}", id, path);
+        Ok(Recorder::create(path, &header)?.record_input(self.record_input))
+    }
+
+    async fn attach(&self, session: Arc<Session>, ws: WebSocket) {
+        let (mut ws_tx, ws_rx) = ws.split();
+        let viewer = session.attach();
+        let viewer_id = viewer.id();
+        tracing::debug!(
+            "terminal session {}: viewer {} attached",
+            session.id,
+            viewer_id
+        );
+
+        let writer = session.clone();
+        let output = tokio::task::spawn(async move {
+            let mut exited = false;
+            while let Some(recv) = viewer.recv().await {
+                let frame = match recv {
+                    Recv::Frame(frame) => frame,
+                    Recv::Lagged => {
+                        tracing::debug!(
+                            "terminal session {}: viewer {} lagging, resyncing",
+                            writer.id,
+                            viewer_id
+                        );
+                        writer.resync(viewer_id);
+                        continue;
+                    }
+                };
+                exited = matches!(frame, ServerFrame::Exit(_));
+                if let Err(err) = ws_tx.send(frame.into_message()).await {
+                    tracing::debug!("terminal session: websocket send error: {}", err);
+                    return false;
+                }
+                if exited {
+                    break;
+                }
+            }
+            // Either the program exited, or the session was killed.
+            let _ = ws_tx.close().await;
+            exited
+        });
+
+        let input = forward_input(ws_rx, &session.pty, |frame| session.input(viewer_id, frame));
+        let finished = future::select(Box::pin(input), output).await;
+
+        match finished {
+            future::Either::Left((_, output)) => {
+                output.abort();
+                self.detach(&session, viewer_id);
+            }
+            future::Either::Right((Ok(true), _)) => {
+                // A client has seen the exit status; nothing is left to
+                // replay, so forget the session.
+                self.lock().remove(&session.id);
+            }
+            future::Either::Right(_) => self.detach(&session, viewer_id),
+        }
+    }
+
+    fn detach(&self, session: &Arc<Session>, viewer: u64) {
+        tracing::debug!(
+            "terminal session {}: viewer {} detached",
+            session.id,
+            viewer
+        );
+        if let Some(generation) = session.detach(viewer) {
+            self.reap_when_idle(session, generation);
+        }
+    }
+
+    fn reap_when_idle(&self, session: &Arc<Session>, generation: u64) {
+        let registry = self.clone();
+        let id = session.id.clone();
+        tokio::task::spawn(async move {
+            tokio::time::sleep(registry.idle_timeout).await;
+
+            let mut sessions = registry.lock();
+            let idle = sessions
+                .get(&id)
+                .is_some_and(|session| session.is_idle_since(generation));
+            if idle {
+                tracing::debug!("terminal session {}: idle, reaping", id);
+                if let Some(session) = sessions.remove(&id) {
+                    session.kill();
+                }
+            }
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Session>>> {
+        self.sessions.lock().unwrap()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("sessions", &self.len())
+            .field("scrollback", &self.scrollback)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("viewer_buffer", &self.viewer_buffer)
+            .field("blocks", &self.blocks)
+            .field("record", &self.record)
+            .field("record_input", &self.record_input)
+            .finish()
+    }
+}
+
+impl Session {
+    /// Attaches a new viewer.
+    ///
+    /// The viewer is queued the replay as it joins, so it sees the scrollback
+    /// followed by live output, with nothing lost or repeated.
+    fn attach(&self) -> Viewer {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        let viewer = state.hub.join();
+        let greeting = self.greeting(&state, viewer.id());
+        state.hub.resync(viewer.id(), greeting);
+        viewer
+    }
+
+    /// Adds a viewer that only watches, queued the replay if `replay` is
+    /// true, or else only the output from now on.
+    fn watch(&self, replay: bool) -> Viewer {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        let viewer = state.hub.watch();
+        if replay {
+            let mut frames = self.greeting(&state, viewer.id());
+            // A watcher isn't a client, and doesn't need to be told where it is.
+            frames.remove(0);
+            state.hub.resync(viewer.id(), frames);
+        }
+        viewer
+    }
+
+    /// Adds a viewer that only watches, for input about to be typed into
+    /// the terminal, if the shell is ready for it.
+    ///
+    /// The input isn't recorded, even with `record_input`: it's a command
+    /// line the server typed in, not a client, and may have secrets in it.
+    fn watch_input(&self) -> io::Result<Viewer> {
+        let state = self.state.lock().unwrap();
+        if state.exit.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the session has exited",
+            ));
+        }
+        if state
+            .blocks
+            .as_ref()
+            .is_some_and(blocks::Parser::is_running)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "the session is running another command",
+            ));
+        }
+        drop(state);
+        Ok(self.watch(false))
+    }
+
+    /// Drops what's queued for a viewer that fell behind, without a replay.
+    fn skip(&self, viewer: u64) {
+        self.state.lock().unwrap().hub.resync(viewer, Vec::new());
+    }
+
+    /// Queues a fresh replay for a viewer that fell behind.
+    fn resync(&self, viewer: u64) {
+        let state = self.state.lock().unwrap();
+        state.hub.resync(viewer, self.greeting(&state, viewer));
+    }
+
+    /// The frames that bring a viewer up to date.
+    fn greeting(&self, state: &State, viewer: u64) -> Vec<ServerFrame> {
+        let mut frames = vec![ServerFrame::Attached {
+            session: self.id.clone(),
+            viewer,
+            controller: state.hub.controller().unwrap_or(viewer),
+            size: state.size,
+        }];
+        let replay = state.scrollback.to_vec();
+        if state.blocks.is_some() {
+            // The replay starts from scratch, so give it a fresh parser.
+            let marks = blocks::Parser::new().feed(&replay);
+            frames.extend(output_frames(&replay, marks));
+        } else if !replay.is_empty() {
+            frames.push(ServerFrame::Output(replay));
+        }
+        if let Some(status) = state.exit {
+            frames.push(ServerFrame::Exit(status));
+        }
+        frames
+    }
+
+    /// Detaches a viewer. If it was the last, returns the generation an idle
+    /// timer should check against.
+    fn detach(&self, viewer: u64) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        if !state.hub.leave(viewer) {
+            return None;
+        }
+        state.generation += 1;
+        if state.hub.is_empty() {
+            Some(state.generation)
+        } else {
+            None
+        }
+    }
+
+    fn is_idle_since(&self, generation: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        state.hub.is_empty() && state.generation == generation
+    }
+
+    /// Handles a frame from a viewer, returning true if it should be applied
+    /// to the terminal.
+    fn input(&self, viewer: u64, frame: &ClientFrame) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *frame {
+            ClientFrame::Input(ref bytes) if state.hub.is_controller(viewer) => {
+                state.record(|recorder| recorder.input(bytes));
+                true
+            }
+            ClientFrame::Resize(size) if state.hub.is_controller(viewer) => {
+                state.size = size;
+                state.record(|recorder| recorder.resize(size));
+                state
+                    .hub
+                    .broadcast_except(viewer, ServerFrame::Resize(size));
+                true
+            }
+            ClientFrame::Input(_) | ClientFrame::Resize(_) => false,
+            ClientFrame::RequestControl => {
+                state.hub.request_control(viewer);
+                false
+            }
+            ClientFrame::HandOff { viewer: to } => {
+                if state.hub.hand_off(viewer, to) {
+                    tracing::debug!(
+                        "terminal session {}: viewer {} handed control to {}",
+                        self.id,
+                        viewer,
+                        to
+                    );
+                }
+                false
+            }
+        }
+    }
+
+    fn output(&self, bytes: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.record(|recorder| recorder.output(bytes));
+        state.scrollback.push(bytes);
+        match state.blocks {
+            Some(ref mut parser) => {
+                let marks = parser.feed(bytes);
+                for frame in output_frames(bytes, marks) {
+                    state.hub.broadcast(frame);
+                }
+            }
+            None => state.hub.broadcast(ServerFrame::Output(bytes.to_vec())),
+        }
+    }
+
+    fn exited(&self, status: ExitStatus) {
+        let mut state = self.state.lock().unwrap();
+        state.record(|recorder| recorder.flush());
+        state.recorder = None;
+        state.exit = Some(status);
+        state.hub.broadcast(ServerFrame::Exit(status));
+    }
+
+    fn kill(&self) {
+        // Aborting the pump drops the child, which kills it.
+        if let Some(pump) = self.pump.lock().unwrap().take() {
+            pump.abort();
+        }
+        self.state.lock().unwrap().hub.close();
+    }
+}
+
+impl State {
+    /// Records into the session's recording, if it has one.
+    ///
+    /// A failed write stops the recording, rather than the session.
+    fn record(&mut self, f: impl FnOnce(&mut Recorder) -> io::Result<()>) {
+        if let Some(ref mut recorder) = self.recorder {
+            if let Err(err) = f(recorder) {
+                tracing::debug!("terminal session: recording stopped: {}", err);
+                self.recorder = None;
+            }
+        }
+    }
+}
+
+/// A command line run with [`Registry::run`].
+///
+/// Dropping a `Run` stops following it, but leaves the command running.
+pub struct Run {
+    registry: Registry,
+    session: Arc<Session>,
+    viewer: Viewer,
+    fresh: bool,
+    started: bool,
+    done: bool,
+}
+
+/// Something a [`Run`] did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunEvent {
+    /// The terminal's output, which in a live session may include the echo
+    /// of the command line, and prompts.
+    Output(Vec<u8>),
+    /// The command finished, and the run is over.
+    ///
+    /// For a command typed into a live session, only the exit code the shell
+    /// marked is known, and the signal is always `None`.
+    Exit(ExitStatus),
+}
+
+impl Run {
+    /// The id of the session the command runs in.
+    pub fn session(&self) -> &str {
+        &self.session.id
+    }
+
+    /// Waits for the next thing the run does.
+    ///
+    /// Returns `None` after [`RunEvent::Exit`], or if the session is killed.
+    /// Output the run falls too far behind on is skipped.
+    pub async fn next(&mut self) -> Option<RunEvent> {
+        if self.done {
+            return None;
+        }
+        while let Some(recv) = self.viewer.recv().await {
+            let frame = match recv {
+                Recv::Frame(frame) => frame,
+                Recv::Lagged => {
+                    self.session.skip(self.viewer.id());
+                    continue;
+                }
+            };
+            match frame {
+                ServerFrame::Output(bytes) => return Some(RunEvent::Output(bytes)),
+                ServerFrame::Block(BlockEvent::Command { .. }) => self.started = true,
+                ServerFrame::Block(BlockEvent::Finished { exit_code }) if self.started => {
+                    self.done = true;
+                    return Some(RunEvent::Exit(ExitStatus {
+                        code: exit_code,
+                        signal: None,
+                    }));
+                }
+                ServerFrame::Exit(status) => {
+                    self.done = true;
+                    if self.fresh {
+                        // Like a client seeing the exit status, the run was
+                        // the session's reason to be.
+                        self.registry.lock().remove(&self.session.id);
+                    }
+                    return Some(RunEvent::Exit(status));
+                }
+                _ => {}
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.registry.detach(&self.session, self.viewer.id());
+    }
+}
+
+impl fmt::Debug for Run {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Run")
+            .field("session", &self.session.id)
+            .field("viewer", &self.viewer.id())
+            .field("fresh", &self.fresh)
+            .finish()
+    }
+}
+
+/// Reads the program's output into the session until it exits.
+async fn pump(session: Arc<Session>, mut child: Child) {
+    let mut buf = vec![0; READ_BUF_SIZE];
+    loop {
+        match session.pty.read(&mut buf).await {
+            Ok(0) => break,
+            Ok(n) => session.output(&buf[..n]),
+            Err(err) => {
+                tracing::debug!("terminal session {}: pty read error: {}", session.id, err);
+                break;
+            }
+        }
+    }
+
+    let status = match child.wait().await {
+        Ok(status) => ExitStatus::from(status),
+        Err(err) => {
+            tracing::debug!("terminal session {}: wait error: {}", session.id, err);
+            ExitStatus {
+                code: None,
+                signal: None,
+            }
+        }
+    };
+    tracing::debug!("terminal session {}: exited: {:?}", session.id, status);
+    // The session stays registered until a client has seen the exit status,
+    // or it is reaped for being idle.
+    session.exited(status);
+}
+
+/// Splits output around the block events found in it.
+fn output_frames(bytes: &[u8], marks: Vec<(usize, BlockEvent)>) -> Vec<ServerFrame> {
+    let mut frames = Vec::with_capacity(marks.len() * 2 + 1);
+    let mut start = 0;
+    for (offset, event) in marks {
+        if offset > start {
+            frames.push(ServerFrame::Output(bytes[start..offset].to_vec()));
+            start = offset;
+        }
+        frames.push(ServerFrame::Block(event));
+    }
+    if start < bytes.len() {
+        frames.push(ServerFrame::Output(bytes[start..].to_vec()));
+    }
+    frames
+}
+
+/// A bounded buffer holding the most recent output of a session.
+struct Scrollback {
+    buf: VecDeque<u8>,
+    limit: usize,
+}
+
+impl Scrollback {
+    fn new(limit: usize) -> Scrollback {
+        Scrollback {
+            buf: VecDeque::new(),
+            limit,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let bytes = &bytes[bytes.len().saturating_sub(self.limit)..];
+        let overflow = (self.buf.len() + bytes.len()).saturating_sub(self.limit);
+        self.buf.drain(..overflow);
+        self.buf.extend(bytes);
+    }
+
+    fn to_vec(&self) -> Vec<u8> {
+        self.buf.iter().copied().collect()
+    }
+}
+
+/// Generates an unguessable session id.
+fn session_id() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
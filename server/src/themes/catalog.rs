@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::Theme;
+
+/// A collection of themes, loaded from a themes directory.
+///
+/// The directory holds one subdirectory per collection, such as `standard`
+/// or `base16`, each with a YAML file per theme. A collection's `README.md`
+/// may name its themes in a table of links, `[Solarized Dark](solarized_dark.yaml)`;
+/// other themes are named after their file.
+#[derive(Debug)]
+pub struct Catalog {
+    entries: Vec<Entry>,
+    by_slug: HashMap<String, usize>,
+}
+
+/// A theme in a [`Catalog`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry {
+    /// Identifies the theme in URLs.
+    ///
+    /// This is the file name, in kebab case, like `solarized-dark`. When
+    /// several collections have a file of the same name, their slugs are
+    /// prefixed with the collection, like `standard-solarized-dark`.
+    pub slug: String,
+    /// The display name.
+    pub name: String,
+    /// The collection the theme belongs to.
+    pub collection: String,
+    /// The theme file, relative to the themes directory.
+    pub file: String,
+    /// The theme itself.
+    pub theme: Theme,
+}
+
+impl Catalog {
+    /// Loads every theme under `dir`, as found by [`theme_files`].
+    ///
+    /// Fails on the first theme file that can't be read or parsed.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Catalog, LoadError> {
+        let dir = dir.as_ref();
+        let mut entries = Vec::new();
+        let mut names = HashMap::new();
+
+        for path in theme_files(dir)? {
+            let collection = path.parent().expect("theme in a collection");
+            let collection_name = file_name(collection);
+            let file = file_name(&path);
+            let stem = file.rsplit_once('.').map_or(&*file, |(stem, _)| stem);
+
+            let readme = names
+                .entry(collection_name.clone())
+                .or_insert_with(|| readme_names(&collection.join("README.md")));
+            let text = fs::read_to_string(&path).map_err(|err| LoadError::io(&path, err))?;
+            let theme = serde_yaml::from_str(&text).map_err(|err| LoadError::yaml(&path, err))?;
+
+            entries.push(Entry {
+                slug: stem.replace('_', "-").to_lowercase(),
+                name: readme
+                    .get(&file)
+                    .cloned()
+                    .unwrap_or_else(|| title_case(stem)),
+                file: format!("{}/{}", collection_name, file),
+                collection: collection_name,
+                theme,
+            });
+        }
+
+        tracing::debug!("themes: loaded {} themes from {:?}", entries.len(), dir);
+        Ok(Catalog::new(entries))
+    }
+
+    /// Creates a catalog of the given themes.
+    ///
+    /// Slugs that clash are prefixed with their collection.
+    pub fn new(mut entries: Vec<Entry>) -> Catalog {
+        let mut counts = HashMap::new();
+        for entry in &entries {
+            *counts.entry(entry.slug.clone()).or_insert(0) += 1;
+        }
+        for entry in &mut entries {
+            if counts[&entry.slug] > 1 {
+                entry.slug = format!(
+                    "{}-{}",
+                    entry.collection.replace('_', "-").to_lowercase(),
+                    entry.slug
+                );
+            }
+        }
+
+        let by_slug = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.slug.clone(), i))
+            .collect();
+        Catalog { entries, by_slug }
+    }
+
+    /// Looks a theme up by its slug.
+    pub fn get(&self, slug: &str) -> Option<&Entry> {
+        self.by_slug.get(slug).map(|&i| &self.entries[i])
+    }
+
+    /// Iterates over every theme, ordered by collection and file name.
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> + '_ {
+        self.entries.iter()
+    }
+
+    /// Returns the number of themes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no themes.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the themes matching a search query, best matches first.
+    ///
+    /// Every word of the query must appear in a theme's name, slug or
+    /// collection, ignoring case. Themes whose name or slug starts with the
+    /// query rank above the rest.
+    pub fn search(&self, query: &str) -> Vec<&Entry> {
+        let query = query.trim().to_lowercase();
+        let words = query.split_whitespace().collect::<Vec<_>>();
+
+        let mut found = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.name.to_lowercase();
+                let haystack = format!("{} {} {}", name, entry.slug, entry.collection);
+                if !words.iter().all(|word| haystack.contains(word)) {
+                    return None;
+                }
+                let rank = if name == query || entry.slug == query {
+                    0
+                } else if name.starts_with(&query) || entry.slug.starts_with(&query) {
+                    1
+                } else {
+                    2
+                };
+                Some((rank, entry))
+            })
+            .collect::<Vec<_>>();
+        found.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| x.name.cmp(&y.name)));
+        found.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+/// Lists the theme files in a themes directory, ordered by collection and
+/// file name.
+///
+/// These are the `.yaml` and `.yml` files in each collection directory.
+/// Directories that start with a `.` are skipped.
+pub fn theme_files(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, LoadError> {
+    let mut files = Vec::new();
+    for collection in sorted_dir(dir.as_ref())? {
+        let name = file_name(&collection);
+        if !collection.is_dir() || name.starts_with('.') {
+            continue;
+        }
+        files.extend(sorted_dir(&collection)?.into_iter().filter(|path| {
+            let file = file_name(path);
+            path.is_file() && (file.ends_with(".yaml") || file.ends_with(".yml"))
+        }));
+    }
+    Ok(files)
+}
+
+/// Reads the theme names from the links in a collection's README.
+fn readme_names(path: &Path) -> HashMap<String, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return HashMap::new(),
+    };
+    let mut names = HashMap::new();
+    for line in text.lines() {
+        let mut rest = line;
+        while let Some(open) = rest.find('[') {
+            rest = &rest[open + 1..];
+            let link = rest
+                .find("](")
+                .and_then(|mid| Some((mid, mid + rest[mid..].find(')')?)));
+            if let Some((mid, close)) = link {
+                let name = rest[..mid].trim();
+                let file = rest[mid + 2..close].trim();
+                if !name.is_empty() && (file.ends_with(".yaml") || file.ends_with(".yml")) {
+                    names.insert(file.to_owned(), name.to_owned());
+                }
+                rest = &rest[close..];
+            }
+        }
+    }
+    names
+}
+
+pub(super) fn title_case(stem: &str) -> String {
+    stem.split(['_', '-'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn sorted_dir(dir: &Path) -> Result<Vec<PathBuf>, LoadError> {
+    let mut paths = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(|err| LoadError::io(dir, err))?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// An error loading a theme catalog.
+pub struct LoadError {
+    path: PathBuf,
+    kind: LoadErrorKind,
+}
+
+enum LoadErrorKind {
+    Io(io::Error),
+    Yaml(serde_yaml::Error),
+}
+
+impl LoadError {
+    pub(super) fn io(path: &Path, err: io::Error) -> LoadError {
+        LoadError {
+            path: path.to_owned(),
+            kind: LoadErrorKind::Io(err),
+        }
+    }
+
+    fn yaml(path: &Path, err: serde_yaml::Error) -> LoadError {
+        LoadError {
+            path: path.to_owned(),
+            kind: LoadErrorKind::Yaml(err),
+        }
+    }
+
+    /// The file or directory that failed to load.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("LoadError");
+        f.field("path", &self.path);
+        match self.kind {
+            LoadErrorKind::Io(ref err) => f.field("io", err),
+            LoadErrorKind::Yaml(ref err) => f.field("yaml", err),
+        };
+        f.finish()
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LoadErrorKind::Io(ref err) => write!(f, "{}: {}", self.path.display(), err),
+            LoadErrorKind::Yaml(ref err) => {
+                write!(f, "{}: invalid theme: {}", self.path.display(), err)
+            }
+        }
+    }
+}
+
+impl StdError for LoadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.kind {
+            LoadErrorKind::Io(ref err) => Some(err),
+            LoadErrorKind::Yaml(ref err) => Some(err),
+        }
+    }
+}
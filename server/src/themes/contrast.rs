@@ -0,0 +1,222 @@
+//! WCAG 2.x contrast ratios.
+//!
+//! [`check`] measures how well each text color of a theme stands out from its
+//! background: the foreground, and all 16 ANSI colors. A gradient background
+//! is measured at both ends, and the worse end counts.
+//!
+//! Ratios run from 1 (no contrast) to 21 (black on white). WCAG asks for 4.5
+//! for body text, 3 for large text, and 7 for its enhanced level.
+
+use std::fmt;
+
+use super::{Background, Color, Theme};
+
+/// How readable a color is against a background, for one text color of a
+/// theme.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contrast {
+    /// Which color this is: `foreground`, or an ANSI color like
+    /// `normal.red` or `bright.black`.
+    pub name: String,
+    /// The text color.
+    pub color: Color,
+    /// The background color it was measured against: the end of a gradient
+    /// with the lower contrast.
+    pub background: Color,
+    /// The contrast ratio, from 1 to 21.
+    pub ratio: f64,
+}
+
+/// The WCAG conformance level a contrast ratio meets, for normal text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Below 3:1.
+    Fail,
+    /// At least 3:1, enough for large text only.
+    AaLarge,
+    /// At least 4.5:1.
+    Aa,
+    /// At least 7:1.
+    Aaa,
+}
+
+/// The contrast of every text color of a theme.
+#[derive(Clone, Debug)]
+pub struct Report {
+    /// The foreground against the background.
+    pub foreground: Contrast,
+    /// The ANSI colors against the background, normal then bright, each
+    /// from black to white.
+    pub ansi: Vec<Contrast>,
+    /// The ANSI color meant to blend into the background, if any: normal
+    /// black on a dark theme, normal white on a light one.
+    blends: &'static str,
+}
+
+/// Measures the contrast of a theme's text colors.
+///
+/// # Example
+///
+/// ```
+/// use nextshell::themes::contrast::{self, Level};
+/// # let theme: nextshell::themes::Theme = serde_yaml::from_str(r##"
+/// # accent: "#bd93f9"
+/// # background: { top: "#282a36", bottom: "#44475a" }
+/// # details: darker
+/// # foreground: "#f8f8f2"
+/// # terminal_colors:
+/// #   normal: { black: "#000", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+/// #             blue: "#bd93f9", magenta: "#ff79c6", cyan: "#8be9fd", white: "#bbb" }
+/// #   bright: { black: "#555", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+/// #             blue: "#caa9fa", magenta: "#ff79c6", cyan: "#8be9fd", white: "#fff" }
+/// # "##).unwrap();
+///
+/// let report = contrast::check(&theme);
+/// assert_eq!(report.foreground.level(), Level::Aaa);
+/// // Measured against the lighter, bottom end of the gradient.
+/// assert_eq!(report.foreground.background.to_string(), "#44475a");
+/// assert_eq!(report.worst_ansi().unwrap().name, "bright.black");
+/// ```
+pub fn check(theme: &Theme) -> Report {
+    let backgrounds = match theme.background {
+        Background::Solid(color) => vec![color],
+        Background::Gradient { top, bottom } => vec![top, bottom],
+    };
+    let measure = |name: String, color: Color| {
+        backgrounds
+            .iter()
+            .map(|&background| Contrast {
+                ratio: color.contrast(background),
+                name: name.clone(),
+                color,
+                background,
+            })
+            .min_by(|a, b| a.ratio.total_cmp(&b.ratio))
+            .expect("a background")
+    };
+
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    let colors = &theme.terminal_colors;
+    let ansi = [("normal", &colors.normal), ("bright", &colors.bright)]
+        .iter()
+        .flat_map(|&(set, colors)| {
+            NAMES
+                .iter()
+                .zip(colors.to_array())
+                .map(move |(name, color)| (format!("{}.{}", set, name), color))
+        })
+        .map(|(name, color)| measure(name, color))
+        .collect();
+
+    // A theme is dark if its text is lighter than its background.
+    let dark = theme.foreground.luminance() > backgrounds[0].luminance();
+    Report {
+        foreground: measure("foreground".to_owned(), theme.foreground),
+        ansi,
+        blends: if dark { "normal.black" } else { "normal.white" },
+    }
+}
+
+impl Report {
+    /// The ANSI color with the lowest contrast.
+    ///
+    /// This leaves out the one color meant to blend into the background:
+    /// normal black on a dark theme, or normal white on a light one.
+    pub fn worst_ansi(&self) -> Option<&Contrast> {
+        self.ansi
+            .iter()
+            .filter(|contrast| contrast.name != self.blends)
+            .min_by(|a, b| a.ratio.total_cmp(&b.ratio))
+    }
+
+    /// The colors with a contrast below the given minimums: `foreground` for
+    /// the foreground, and `ansi` for the ANSI colors that aren't meant to
+    /// blend in.
+    pub fn below(&self, foreground: f64, ansi: f64) -> Vec<&Contrast> {
+        let mut below = Vec::new();
+        if self.foreground.ratio < foreground {
+            below.push(&self.foreground);
+        }
+        below.extend(
+            self.ansi
+                .iter()
+                .filter(|contrast| contrast.name != self.blends && contrast.ratio < ansi),
+        );
+        below
+    }
+}
+
+impl Contrast {
+    /// The WCAG level this contrast meets.
+    pub fn level(&self) -> Level {
+        Level::of(self.ratio)
+    }
+}
+
+impl Level {
+    /// The level a contrast ratio meets.
+    pub fn of(ratio: f64) -> Level {
+        if ratio >= 7.0 {
+            Level::Aaa
+        } else if ratio >= 4.5 {
+            Level::Aa
+        } else if ratio >= 3.0 {
+            Level::AaLarge
+        } else {
+            Level::Fail
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Level::Fail => "fail",
+            Level::AaLarge => "AA large",
+            Level::Aa => "AA",
+            Level::Aaa => "AAA",
+        })
+    }
+}
+
+impl fmt::Display for Contrast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} on {}: {:.2} ({})",
+            self.name,
+            self.color,
+            self.background,
+            self.ratio,
+            self.level()
+        )
+    }
+}
+
+impl Color {
+    /// The relative luminance of the color, from 0 for black to 1 for white.
+    pub fn luminance(&self) -> f64 {
+        // The sRGB transfer function. WCAG 2.x quotes 0.03928 for the cutoff,
+        // from an old draft of sRGB; no 8-bit channel lands between the two.
+        let linear = |channel: u8| {
+            let c = f64::from(channel) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// The WCAG contrast ratio between two colors, from 1 to 21.
+    ///
+    /// The ratio is the same whichever color is the text.
+    pub fn contrast(&self, other: Color) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        let (light, dark) = if a > b { (a, b) } else { (b, a) };
+        (light + 0.05) / (dark + 0.05)
+    }
+}
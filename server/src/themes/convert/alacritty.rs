@@ -0,0 +1,203 @@
+//! Alacritty configs: the `colors.primary`, `colors.normal` and
+//! `colors.bright` tables.
+//!
+//! Alacritty moved from YAML to TOML in 0.13; both are read. The TOML reader
+//! only understands what color configs use: table headers, and keys set to
+//! strings or to inline tables of strings.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::{parse_color, ConvertError, Format, Imported, Palette, Slot, ANSI_NAMES};
+use crate::themes::Theme;
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let values = match toml_strings(text) {
+        Ok(values) => values,
+        // Alacritty's YAML configs aren't valid TOML, but pass as YAML.
+        Err(err) => yaml_strings(text).ok_or(err)?,
+    };
+
+    let palette = Palette::read(Format::Alacritty, |slot| {
+        let key = match slot {
+            Slot::Background => "colors.primary.background".to_owned(),
+            Slot::Foreground => "colors.primary.foreground".to_owned(),
+            Slot::Ansi(i) if i < 8 => format!("colors.normal.{}", ANSI_NAMES[i]),
+            Slot::Ansi(i) => format!("colors.bright.{}", ANSI_NAMES[i - 8]),
+        };
+        match values.get(&key) {
+            Some(&(line, ref value)) => parse_color(value).map(Some).ok_or_else(|| {
+                let message = format!("invalid color {:?} for {}", value, key);
+                match line {
+                    Some(line) => ConvertError::at(Format::Alacritty, line, message),
+                    None => ConvertError::new(Format::Alacritty, message),
+                }
+            }),
+            None => Ok(None),
+        }
+    })?;
+
+    Ok(Imported {
+        name: None,
+        theme: palette.into_theme(),
+    })
+}
+
+pub(super) fn export(name: &str, theme: &Theme) -> String {
+    let palette = Palette::of(theme);
+    let mut toml = format!("# {}\n\n[colors.primary]\n", name);
+    writeln!(toml, "background = \"{}\"", palette.background).unwrap();
+    writeln!(toml, "foreground = \"{}\"", palette.foreground).unwrap();
+    for (table, colors) in [
+        ("normal", &palette.ansi[..8]),
+        ("bright", &palette.ansi[8..]),
+    ] {
+        writeln!(toml, "\n[colors.{}]", table).unwrap();
+        for (name, color) in ANSI_NAMES.iter().zip(colors) {
+            writeln!(toml, "{} = \"{}\"", name, color).unwrap();
+        }
+    }
+    toml
+}
+
+/// Reads every string in a TOML document, keyed by its dotted path, with
+/// the line it's on.
+fn toml_strings(text: &str) -> Result<HashMap<String, (Option<usize>, String)>, ConvertError> {
+    let mut values = HashMap::new();
+    let mut table = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let error = |message: &str| ConvertError::at(Format::Alacritty, line_no, message);
+        let mut rest = line.trim();
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = rest.strip_prefix('[') {
+            // Arrays of tables, like `[[hints.enabled]]`, hold no colors.
+            let array = header.starts_with('[');
+            let end = header
+                .find(']')
+                .ok_or_else(|| error("unclosed table header"))?;
+            table = if array {
+                vec!["[]".to_owned()]
+            } else {
+                key_path(&header[..end]).ok_or_else(|| error("invalid table name"))?
+            };
+            continue;
+        }
+
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| error("expected `key = value`"))?;
+        let mut path = table.clone();
+        path.extend(key_path(&rest[..eq]).ok_or_else(|| error("invalid key"))?);
+        rest = rest[eq + 1..].trim_start();
+
+        if let Some(inline) = rest.strip_prefix('{') {
+            let mut inner = inline;
+            loop {
+                inner = inner.trim_start();
+                if inner.starts_with('}') {
+                    break;
+                }
+                let eq = inner
+                    .find('=')
+                    .ok_or_else(|| error("unclosed inline table"))?;
+                let mut key = path.clone();
+                key.extend(key_path(&inner[..eq]).ok_or_else(|| error("invalid key"))?);
+                let (value, after) =
+                    value(inner[eq + 1..].trim_start()).ok_or_else(|| error("unclosed string"))?;
+                if let Some(value) = value {
+                    values.insert(key.join("."), (Some(line_no), value));
+                }
+                inner = after.trim_start();
+                inner = inner.strip_prefix(',').unwrap_or(inner);
+                if inner.trim().is_empty() {
+                    return Err(error("unclosed inline table"));
+                }
+            }
+        } else {
+            let (value, _) = value(rest).ok_or_else(|| error("unclosed string"))?;
+            if let Some(value) = value {
+                values.insert(path.join("."), (Some(line_no), value));
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Splits a dotted TOML key, like `colors."primary"`, into its parts.
+fn key_path(key: &str) -> Option<Vec<String>> {
+    key.split('.')
+        .map(|part| {
+            let part = part.trim();
+            let part = part
+                .strip_prefix('"')
+                .and_then(|part| part.strip_suffix('"'))
+                .or_else(|| part.strip_prefix('\'')?.strip_suffix('\''))
+                .unwrap_or(part);
+            if part.is_empty() {
+                None
+            } else {
+                Some(part.to_owned())
+            }
+        })
+        .collect()
+}
+
+/// Reads a value from the start of `s`, returning it if it's a string, and
+/// the text after it.
+///
+/// Returns `None` for an unclosed string.
+fn value(s: &str) -> Option<(Option<String>, &str)> {
+    match s.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => {
+            let end = s[1..].find(quote)? + 1;
+            Some((Some(s[1..end].to_owned()), &s[end + 1..]))
+        }
+        _ => {
+            // Numbers, booleans and the like, which colors never are.
+            let end = s.find([',', '}', '#']).unwrap_or(s.len());
+            Some((None, &s[end..]))
+        }
+    }
+}
+
+/// Reads the strings of a YAML config, the way [`toml_strings`] does.
+fn yaml_strings(text: &str) -> Option<HashMap<String, (Option<usize>, String)>> {
+    fn walk(
+        path: &str,
+        value: &serde_yaml::Value,
+        out: &mut HashMap<String, (Option<usize>, String)>,
+    ) {
+        match value {
+            serde_yaml::Value::Mapping(map) => {
+                for (key, value) in map {
+                    if let Some(key) = key.as_str() {
+                        let path = if path.is_empty() {
+                            key.to_owned()
+                        } else {
+                            format!("{}.{}", path, key)
+                        };
+                        walk(&path, value, out);
+                    }
+                }
+            }
+            serde_yaml::Value::String(s) => {
+                // YAML values don't keep their lines.
+                out.insert(path.to_owned(), (None, s.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    let value = serde_yaml::from_str::<serde_yaml::Value>(text).ok()?;
+    if !value.is_mapping() {
+        return None;
+    }
+    let mut out = HashMap::new();
+    walk("", &value, &mut out);
+    Some(out)
+}
@@ -0,0 +1,108 @@
+//! Base16 schemes: sixteen colors, `base00` to `base0F`, in YAML.
+//!
+//! Both the classic layout, with `scheme` and the colors at the top level,
+//! and tinted-theming's, with `name` and a `palette` map, are read. The
+//! classic layout is written.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use super::{parse_color, ConvertError, Format, Imported, Palette};
+use crate::themes::{Accent, Color, Theme};
+
+/// Which base16 color each ANSI color is, following base16-shell's default
+/// template.
+const ANSI: [usize; 16] = [
+    0x00, 0x08, 0x0b, 0x0a, 0x0d, 0x0e, 0x0c, 0x05, // normal
+    0x03, 0x09, 0x01, 0x02, 0x04, 0x06, 0x0f, 0x07, // bright
+];
+
+#[derive(Deserialize)]
+struct Scheme {
+    #[serde(default)]
+    scheme: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    palette: Option<BTreeMap<String, String>>,
+    #[serde(flatten)]
+    rest: BTreeMap<String, serde_yaml::Value>,
+}
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let scheme = serde_yaml::from_str::<Scheme>(text).map_err(|err| match err.location() {
+        Some(location) => ConvertError::at(Format::Base16, location.line(), err.to_string()),
+        None => ConvertError::new(Format::Base16, err.to_string()),
+    })?;
+
+    let mut base = [Color { r: 0, g: 0, b: 0 }; 16];
+    let mut missing = Vec::new();
+    for (i, slot) in base.iter_mut().enumerate() {
+        let key = format!("base{:02X}", i);
+        let value = match scheme.palette {
+            Some(ref palette) => palette.get(&key).cloned(),
+            None => scheme
+                .rest
+                .get(&key)
+                .and_then(|value| value.as_str())
+                .map(str::to_owned),
+        };
+        match value {
+            Some(value) => {
+                *slot = parse_color(&value).ok_or_else(|| {
+                    ConvertError::new(
+                        Format::Base16,
+                        format!("invalid color {:?} for {}", value, key),
+                    )
+                })?
+            }
+            None => missing.push(key),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(ConvertError::new(
+            Format::Base16,
+            format!("missing {}", missing.join(", ")),
+        ));
+    }
+
+    let mut ansi = [Color { r: 0, g: 0, b: 0 }; 16];
+    for (color, &i) in ansi.iter_mut().zip(ANSI.iter()) {
+        *color = base[i];
+    }
+    let mut theme = Palette {
+        background: base[0x00],
+        foreground: base[0x05],
+        ansi,
+    }
+    .into_theme();
+    theme.accent = Accent::Solid(base[0x0d]);
+
+    Ok(Imported {
+        name: scheme.scheme.or(scheme.name),
+        theme,
+    })
+}
+
+pub(super) fn export(name: &str, theme: &Theme) -> String {
+    let palette = Palette::of(theme);
+    let mut base = [Color { r: 0, g: 0, b: 0 }; 16];
+    for (&i, &color) in ANSI.iter().zip(palette.ansi.iter()) {
+        base[i] = color;
+    }
+    // The ANSI colors repeat base00 and base05 as black and white; the
+    // theme's own background and foreground win.
+    base[0x00] = palette.background;
+    base[0x05] = palette.foreground;
+
+    let mut yaml = format!(
+        "scheme: {}\nauthor: \"\"\n",
+        serde_json::to_string(name).expect("string serializes")
+    );
+    for (i, color) in base.iter().enumerate() {
+        writeln!(yaml, "base{:02X}: \"{}\"", i, &color.to_string()[1..]).unwrap();
+    }
+    yaml
+}
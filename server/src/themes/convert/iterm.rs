@@ -0,0 +1,320 @@
+//! iTerm2 `.itermcolors` files: XML property lists mapping names like
+//! `Ansi 4 Color` to dictionaries of color components from 0 to 1.
+//!
+//! Components are read as sRGB whatever their `Color Space`; iTerm2's other
+//! spaces are close enough for picking a theme.
+
+use std::fmt::Write;
+
+use super::{ConvertError, Format, Imported, Palette, Slot};
+use crate::themes::{Color, Theme};
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let mut parser = Parser {
+        tokens: Tokens {
+            text,
+            pos: 0,
+            line: 1,
+        },
+        peeked: None,
+    };
+    let root = loop {
+        match parser.next()? {
+            Some((_, Token::Text(_))) => {}
+            Some((_, Token::Open(ref name))) if name == "plist" => {}
+            Some((_, Token::Open(ref name))) if name == "dict" => {
+                break parser.dict()?;
+            }
+            Some((line, _)) => {
+                return Err(ConvertError::at(
+                    Format::ITerm,
+                    line,
+                    "expected a property list dictionary",
+                ))
+            }
+            None => return Err(ConvertError::new(Format::ITerm, "empty property list")),
+        }
+    };
+
+    let palette = Palette::read(Format::ITerm, |slot| {
+        let key = match slot {
+            Slot::Background => "Background Color".to_owned(),
+            Slot::Foreground => "Foreground Color".to_owned(),
+            Slot::Ansi(i) => format!("Ansi {} Color", i),
+        };
+        let (line, components) = match lookup(&root, &key) {
+            Some(&(line, Plist::Dict(ref components))) => (line, components),
+            Some(&(line, _)) => {
+                return Err(ConvertError::at(
+                    Format::ITerm,
+                    line,
+                    format!("`{}` isn't a dictionary", key),
+                ))
+            }
+            None => return Ok(None),
+        };
+        let channel = |name: &str| match lookup(components, name) {
+            Some(&(_, Plist::Real(c))) => Ok((c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            _ => Err(ConvertError::at(
+                Format::ITerm,
+                line,
+                format!("`{}` has no `{}`", key, name),
+            )),
+        };
+        Ok(Some(Color {
+            r: channel("Red Component")?,
+            g: channel("Green Component")?,
+            b: channel("Blue Component")?,
+        }))
+    })?;
+
+    Ok(Imported {
+        name: None,
+        theme: palette.into_theme(),
+    })
+}
+
+pub(super) fn export(theme: &Theme) -> String {
+    let palette = Palette::of(theme);
+    let mut plist = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+        "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n",
+        "<dict>\n",
+    ));
+    let mut color = |key: &str, color: Color| {
+        let component = |c: u8| f64::from(c) / 255.0;
+        write!(
+            plist,
+            concat!(
+                "\t<key>{}</key>\n",
+                "\t<dict>\n",
+                "\t\t<key>Alpha Component</key>\n\t\t<real>1</real>\n",
+                "\t\t<key>Blue Component</key>\n\t\t<real>{}</real>\n",
+                "\t\t<key>Color Space</key>\n\t\t<string>sRGB</string>\n",
+                "\t\t<key>Green Component</key>\n\t\t<real>{}</real>\n",
+                "\t\t<key>Red Component</key>\n\t\t<real>{}</real>\n",
+                "\t</dict>\n",
+            ),
+            key,
+            component(color.b),
+            component(color.g),
+            component(color.r),
+        )
+        .unwrap();
+    };
+    for (i, &ansi) in palette.ansi.iter().enumerate() {
+        color(&format!("Ansi {} Color", i), ansi);
+    }
+    color("Background Color", palette.background);
+    color("Foreground Color", palette.foreground);
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+/// The property list values a color file has. Others are skipped.
+enum Plist {
+    Dict(Dict),
+    Real(f64),
+    Other,
+}
+
+/// A dictionary's entries, in order, each with the line its value is on.
+type Dict = Vec<(String, (usize, Plist))>;
+
+fn lookup<'a>(dict: &'a Dict, key: &str) -> Option<&'a (usize, Plist)> {
+    dict.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+}
+
+enum Token {
+    Open(String),
+    Close(String),
+    Empty,
+    Text(String),
+}
+
+struct Parser<'a> {
+    tokens: Tokens<'a>,
+    peeked: Option<(usize, Token)>,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Result<Option<(usize, Token)>, ConvertError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.tokens.next(),
+        }
+    }
+
+    /// Reads the text up to a closing tag, having read the opening one.
+    fn text(&mut self, tag: &str) -> Result<String, ConvertError> {
+        let mut text = String::new();
+        loop {
+            match self.next()? {
+                Some((_, Token::Text(s))) => text.push_str(&s),
+                Some((_, Token::Close(ref name))) if name == tag => return Ok(text),
+                Some((line, _)) => {
+                    return Err(ConvertError::at(
+                        Format::ITerm,
+                        line,
+                        format!("expected text in <{}>", tag),
+                    ))
+                }
+                None => {
+                    return Err(ConvertError::new(
+                        Format::ITerm,
+                        format!("unclosed <{}>", tag),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Reads a dictionary, having read its opening tag.
+    fn dict(&mut self) -> Result<Dict, ConvertError> {
+        let mut entries = Vec::new();
+        loop {
+            match self.next()? {
+                Some((_, Token::Text(_))) => {}
+                Some((_, Token::Close(ref name))) if name == "dict" => return Ok(entries),
+                Some((_, Token::Open(ref name))) if name == "key" => {
+                    let key = self.text("key")?;
+                    let value = self.value()?;
+                    entries.push((key.trim().to_owned(), value));
+                }
+                Some((line, _)) => {
+                    return Err(ConvertError::at(Format::ITerm, line, "expected a <key>"))
+                }
+                None => return Err(ConvertError::new(Format::ITerm, "unclosed <dict>")),
+            }
+        }
+    }
+
+    /// Reads any value, with the line it starts on.
+    fn value(&mut self) -> Result<(usize, Plist), ConvertError> {
+        loop {
+            let (line, token) = match self.next()? {
+                Some(token) => token,
+                None => return Err(ConvertError::new(Format::ITerm, "expected a value")),
+            };
+            let value = match token {
+                Token::Text(_) => continue,
+                Token::Empty => Plist::Other,
+                Token::Open(name) => match &*name {
+                    "dict" => Plist::Dict(self.dict()?),
+                    "real" | "integer" => {
+                        let text = self.text(&name)?;
+                        let n = text.trim().parse().map_err(|_| {
+                            ConvertError::at(
+                                Format::ITerm,
+                                line,
+                                format!("invalid number {:?}", text.trim()),
+                            )
+                        })?;
+                        Plist::Real(n)
+                    }
+                    "array" => {
+                        loop {
+                            match self.next()? {
+                                Some((_, Token::Close(ref close))) if close == "array" => break,
+                                Some(token) => {
+                                    self.peeked = Some(token);
+                                    self.value()?;
+                                }
+                                None => {
+                                    return Err(ConvertError::new(
+                                        Format::ITerm,
+                                        "unclosed <array>",
+                                    ))
+                                }
+                            }
+                        }
+                        Plist::Other
+                    }
+                    _ => {
+                        self.text(&name)?;
+                        Plist::Other
+                    }
+                },
+                Token::Close(name) => {
+                    return Err(ConvertError::at(
+                        Format::ITerm,
+                        line,
+                        format!("unexpected </{}>", name),
+                    ))
+                }
+            };
+            return Ok((line, value));
+        }
+    }
+}
+
+/// Splits XML into tags and text, skipping declarations and comments.
+struct Tokens<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Tokens<'_> {
+    fn advance(&mut self, len: usize) {
+        let end = self.pos + len;
+        self.line += self.text[self.pos..end].matches('\n').count();
+        self.pos = end;
+    }
+
+    fn next(&mut self) -> Result<Option<(usize, Token)>, ConvertError> {
+        loop {
+            let rest = &self.text[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            let line = self.line;
+
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.advance(end);
+                return Ok(Some((line, Token::Text(unescape(&rest[..end])))));
+            }
+
+            let (close, skip) = if rest.starts_with("<!--") {
+                ("-->", true)
+            } else if rest.starts_with("<?") || rest.starts_with("<!") {
+                (">", true)
+            } else {
+                (">", false)
+            };
+            let end = rest
+                .find(close)
+                .ok_or_else(|| ConvertError::at(Format::ITerm, line, "unclosed tag"))?
+                + close.len();
+            self.advance(end);
+            if skip {
+                continue;
+            }
+
+            let tag = &rest[1..end - 1];
+            let token = if let Some(name) = tag.strip_prefix('/') {
+                Token::Close(name.trim().to_owned())
+            } else if tag.ends_with('/') {
+                Token::Empty
+            } else {
+                Token::Open(tag_name(tag))
+            };
+            return Ok(Some((line, token)));
+        }
+    }
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.split_whitespace().next().unwrap_or_default().to_owned()
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
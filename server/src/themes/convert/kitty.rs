@@ -0,0 +1,70 @@
+//! kitty `.conf` themes: `name value` lines, with `colorN` for the ANSI
+//! colors. Themes from kitty-themes start with a `## name: ...` comment.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::{parse_color, ConvertError, Format, Imported, Palette, Slot};
+use crate::themes::Theme;
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let mut name = None;
+    let mut colors = HashMap::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(value) = comment.trim_start_matches('#').trim().strip_prefix("name:") {
+                name = Some(value.trim().to_owned());
+            }
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let (key, value) = match (words.next(), words.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        let wanted = key == "background"
+            || key == "foreground"
+            || matches!(
+                key.strip_prefix("color").map(str::parse::<usize>),
+                Some(Ok(n)) if n < 16
+            );
+        if wanted {
+            let color = parse_color(value).ok_or_else(|| {
+                ConvertError::at(
+                    Format::Kitty,
+                    i + 1,
+                    format!("invalid color {:?} for {}", value, key),
+                )
+            })?;
+            colors.insert(key.to_owned(), color);
+        }
+    }
+
+    let palette = Palette::read(Format::Kitty, |slot| {
+        Ok(match slot {
+            Slot::Background => colors.get("background").copied(),
+            Slot::Foreground => colors.get("foreground").copied(),
+            Slot::Ansi(i) => colors.get(&format!("color{}", i)).copied(),
+        })
+    })?;
+    Ok(Imported {
+        name,
+        theme: palette.into_theme(),
+    })
+}
+
+pub(super) fn export(name: &str, theme: &Theme) -> String {
+    let palette = Palette::of(theme);
+    let mut conf = format!("## name: {}\n\n", name);
+    writeln!(conf, "foreground {}", palette.foreground).unwrap();
+    writeln!(conf, "background {}", palette.background).unwrap();
+    for (i, color) in palette.ansi.iter().enumerate() {
+        if i % 8 == 0 {
+            conf.push('\n');
+        }
+        writeln!(conf, "color{} {}", i, color).unwrap();
+    }
+    conf
+}
@@ -0,0 +1,354 @@
+//! Converting themes to and from other terminals' formats.
+//!
+//! Other formats only know a background, a foreground and the 16 ANSI
+//! colors, so exporting drops a theme's accent, gradient and image, and
+//! importing fills them in: the accent is the ANSI blue, and the details are
+//! darker or lighter to match the background. Base16 is the exception; see
+//! [`Format::Base16`].
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Accent, AnsiColors, Background, Color, Details, TerminalColors, Theme};
+
+mod alacritty;
+mod base16;
+mod iterm;
+mod kitty;
+mod windows_terminal;
+
+/// A theme format of another terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// An iTerm2 `.itermcolors` property list.
+    ITerm,
+    /// The `colors` tables of an Alacritty TOML config.
+    ///
+    /// Importing also reads the older YAML configs.
+    Alacritty,
+    /// A Windows Terminal color scheme, as JSON.
+    ///
+    /// Importing also reads a whole `settings.json`, taking its first scheme.
+    WindowsTerminal,
+    /// A kitty `.conf` theme.
+    Kitty,
+    /// A base16 scheme, as YAML.
+    ///
+    /// Base16's 16 colors map onto the ANSI colors the way base16-shell's
+    /// default template does, with its base0D blue as the accent. Exporting
+    /// a theme imported from base16 gives back the same scheme.
+    Base16,
+}
+
+/// A theme read from another format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Imported {
+    /// The theme's name, if the format has one.
+    pub name: Option<String>,
+    /// The theme.
+    pub theme: Theme,
+}
+
+/// An error reading a theme in another format.
+#[derive(Debug)]
+pub struct ConvertError {
+    format: Format,
+    line: Option<usize>,
+    message: String,
+}
+
+/// A name that isn't a [`Format`].
+#[derive(Debug)]
+pub struct UnknownFormat(String);
+
+/// The formats, in the order of [`Format`]'s variants.
+pub const FORMATS: [Format; 5] = [
+    Format::ITerm,
+    Format::Alacritty,
+    Format::WindowsTerminal,
+    Format::Kitty,
+    Format::Base16,
+];
+
+/// Reads a theme in another format.
+///
+/// # Example
+///
+/// ```
+/// use nextshell::themes::convert::{self, Format};
+///
+/// let kitty = "\
+/// ## name: Plain
+/// foreground #c0c0c0
+/// background #101010
+/// color0  #000000
+/// color1  #aa0000
+/// color2  #00aa00
+/// color3  #aa5500
+/// color4  #0000aa
+/// color5  #aa00aa
+/// color6  #00aaaa
+/// color7  #aaaaaa
+/// color8  #555555
+/// color9  #ff5555
+/// color10 #55ff55
+/// color11 #ffff55
+/// color12 #5555ff
+/// color13 #ff55ff
+/// color14 #55ffff
+/// color15 #ffffff
+/// ";
+/// let imported = convert::import(Format::Kitty, kitty).unwrap();
+/// assert_eq!(imported.name.as_deref(), Some("Plain"));
+/// assert_eq!(imported.theme.terminal_colors.bright.red.to_string(), "#ff5555");
+///
+/// let alacritty = convert::export(Format::Alacritty, "Plain", &imported.theme);
+/// assert!(alacritty.contains("red = \"#ff5555\""));
+/// ```
+pub fn import(format: Format, text: &str) -> Result<Imported, ConvertError> {
+    match format {
+        Format::ITerm => iterm::import(text),
+        Format::Alacritty => alacritty::import(text),
+        Format::WindowsTerminal => windows_terminal::import(text),
+        Format::Kitty => kitty::import(text),
+        Format::Base16 => base16::import(text),
+    }
+}
+
+/// Writes a theme in another format.
+///
+/// A gradient background is written as its top color. Formats that carry a
+/// name get `name`.
+pub fn export(format: Format, name: &str, theme: &Theme) -> String {
+    match format {
+        Format::ITerm => iterm::export(theme),
+        Format::Alacritty => alacritty::export(name, theme),
+        Format::WindowsTerminal => windows_terminal::export(name, theme),
+        Format::Kitty => kitty::export(name, theme),
+        Format::Base16 => base16::export(name, theme),
+    }
+}
+
+impl Format {
+    /// The name of the format in URLs, like `windows-terminal`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Format::ITerm => "iterm",
+            Format::Alacritty => "alacritty",
+            Format::WindowsTerminal => "windows-terminal",
+            Format::Kitty => "kitty",
+            Format::Base16 => "base16",
+        }
+    }
+
+    /// The usual file extension, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::ITerm => "itermcolors",
+            Format::Alacritty => "toml",
+            Format::WindowsTerminal => "json",
+            Format::Kitty => "conf",
+            Format::Base16 => "yaml",
+        }
+    }
+
+    /// The media type of the format.
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            Format::ITerm => "application/x-plist",
+            Format::Alacritty => "application/toml",
+            Format::WindowsTerminal => "application/json",
+            Format::Kitty => "text/plain; charset=utf-8",
+            Format::Base16 => "application/yaml",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Format, UnknownFormat> {
+        FORMATS
+            .iter()
+            .copied()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| UnknownFormat(s.to_owned()))
+    }
+}
+
+impl ConvertError {
+    fn new(format: Format, message: impl Into<String>) -> ConvertError {
+        ConvertError {
+            format,
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    fn at(format: Format, line: usize, message: impl Into<String>) -> ConvertError {
+        ConvertError {
+            format,
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+
+    /// The line of the error, if known, counting from 1.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(
+                f,
+                "invalid {} theme: line {}: {}",
+                self.format, line, self.message
+            ),
+            None => write!(f, "invalid {} theme: {}", self.format, self.message),
+        }
+    }
+}
+
+impl StdError for ConvertError {}
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown theme format {:?}", self.0)
+    }
+}
+
+impl StdError for UnknownFormat {}
+
+/// The colors every format has.
+struct Palette {
+    background: Color,
+    foreground: Color,
+    /// The ANSI colors, 0 to 15.
+    ansi: [Color; 16],
+}
+
+impl Palette {
+    fn of(theme: &Theme) -> Palette {
+        let mut ansi = [Color { r: 0, g: 0, b: 0 }; 16];
+        ansi[..8].copy_from_slice(&theme.terminal_colors.normal.to_array());
+        ansi[8..].copy_from_slice(&theme.terminal_colors.bright.to_array());
+        Palette {
+            background: theme.background.top(),
+            foreground: theme.foreground,
+            ansi,
+        }
+    }
+
+    /// Reads a palette from named colors, as most formats have them.
+    ///
+    /// `get` looks up the background, the foreground, and the ANSI colors
+    /// by number.
+    fn read<F>(format: Format, mut get: F) -> Result<Palette, ConvertError>
+    where
+        F: FnMut(Slot) -> Result<Option<Color>, ConvertError>,
+    {
+        let mut missing = Vec::new();
+        let mut require = |slot: Slot| -> Result<Color, ConvertError> {
+            Ok(get(slot)?.unwrap_or_else(|| {
+                missing.push(slot.to_string());
+                Color { r: 0, g: 0, b: 0 }
+            }))
+        };
+        let background = require(Slot::Background)?;
+        let foreground = require(Slot::Foreground)?;
+        let mut ansi = [Color { r: 0, g: 0, b: 0 }; 16];
+        for (i, color) in ansi.iter_mut().enumerate() {
+            *color = require(Slot::Ansi(i))?;
+        }
+
+        if !missing.is_empty() {
+            return Err(ConvertError::new(
+                format,
+                format!("missing {}", missing.join(", ")),
+            ));
+        }
+        Ok(Palette {
+            background,
+            foreground,
+            ansi,
+        })
+    }
+
+    fn into_theme(self) -> Theme {
+        let ansi = |colors: &[Color]| AnsiColors {
+            black: colors[0],
+            red: colors[1],
+            green: colors[2],
+            yellow: colors[3],
+            blue: colors[4],
+            magenta: colors[5],
+            cyan: colors[6],
+            white: colors[7],
+        };
+        Theme {
+            accent: Accent::Solid(self.ansi[4]),
+            background: Background::Solid(self.background),
+            details: details(self.background, self.foreground),
+            foreground: self.foreground,
+            terminal_colors: TerminalColors {
+                normal: ansi(&self.ansi[..8]),
+                bright: ansi(&self.ansi[8..]),
+            },
+            background_image: None,
+        }
+    }
+}
+
+/// A color every format has.
+#[derive(Clone, Copy, Debug)]
+enum Slot {
+    Background,
+    Foreground,
+    Ansi(usize),
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Slot::Background => f.write_str("background"),
+            Slot::Foreground => f.write_str("foreground"),
+            Slot::Ansi(i) => write!(f, "color {}", i),
+        }
+    }
+}
+
+/// The ANSI color names, in order, as in the theme model.
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Dark themes draw details darker than the background; light themes,
+/// lighter.
+fn details(background: Color, foreground: Color) -> Details {
+    if background.luminance() > foreground.luminance() {
+        Details::Lighter
+    } else {
+        Details::Darker
+    }
+}
+
+/// Parses a color written `#rrggbb`, `0xrrggbb` or `rrggbb`.
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    let hex = s
+        .strip_prefix('#')
+        .or_else(|| s.strip_prefix("0x"))
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    format!("#{}", hex).parse().ok()
+}
@@ -0,0 +1,123 @@
+//! Windows Terminal color schemes: JSON objects with camel-case color names,
+//! found in the `schemes` list of `settings.json`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{parse_color, ConvertError, Format, Imported, Palette, Slot};
+use crate::themes::{Color, Theme};
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Scheme {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    background: String,
+    foreground: String,
+    black: String,
+    red: String,
+    green: String,
+    yellow: String,
+    blue: String,
+    purple: String,
+    cyan: String,
+    white: String,
+    bright_black: String,
+    bright_red: String,
+    bright_green: String,
+    bright_yellow: String,
+    bright_blue: String,
+    bright_purple: String,
+    bright_cyan: String,
+    bright_white: String,
+}
+
+pub(super) fn import(text: &str) -> Result<Imported, ConvertError> {
+    let error = |err: serde_json::Error| {
+        ConvertError::at(Format::WindowsTerminal, err.line(), err.to_string())
+    };
+    let mut value = serde_json::from_str::<Value>(text).map_err(error)?;
+
+    // A whole settings.json, or a list of schemes: take the first.
+    if let Some(schemes) = value.get_mut("schemes") {
+        value = schemes.take();
+    }
+    if let Value::Array(ref mut schemes) = value {
+        if schemes.is_empty() {
+            return Err(ConvertError::new(Format::WindowsTerminal, "no schemes"));
+        }
+        value = schemes.swap_remove(0);
+    }
+    let scheme = serde_json::from_value::<Scheme>(value)
+        .map_err(|err| ConvertError::new(Format::WindowsTerminal, err.to_string()))?;
+
+    let normal = [
+        &scheme.black,
+        &scheme.red,
+        &scheme.green,
+        &scheme.yellow,
+        &scheme.blue,
+        &scheme.purple,
+        &scheme.cyan,
+        &scheme.white,
+    ];
+    let bright = [
+        &scheme.bright_black,
+        &scheme.bright_red,
+        &scheme.bright_green,
+        &scheme.bright_yellow,
+        &scheme.bright_blue,
+        &scheme.bright_purple,
+        &scheme.bright_cyan,
+        &scheme.bright_white,
+    ];
+    let palette = Palette::read(Format::WindowsTerminal, |slot| {
+        let value = match slot {
+            Slot::Background => &scheme.background,
+            Slot::Foreground => &scheme.foreground,
+            Slot::Ansi(i) if i < 8 => normal[i],
+            Slot::Ansi(i) => bright[i - 8],
+        };
+        parse_color(value).map(Some).ok_or_else(|| {
+            ConvertError::new(
+                Format::WindowsTerminal,
+                format!("invalid color {:?} for {}", value, slot),
+            )
+        })
+    })?;
+
+    Ok(Imported {
+        name: scheme.name,
+        theme: palette.into_theme(),
+    })
+}
+
+pub(super) fn export(name: &str, theme: &Theme) -> String {
+    let palette = Palette::of(theme);
+    let hex = |color: Color| color.to_string();
+    let ansi = |i: usize| hex(palette.ansi[i]);
+    let scheme = Scheme {
+        name: Some(name.to_owned()),
+        background: hex(palette.background),
+        foreground: hex(palette.foreground),
+        black: ansi(0),
+        red: ansi(1),
+        green: ansi(2),
+        yellow: ansi(3),
+        blue: ansi(4),
+        purple: ansi(5),
+        cyan: ansi(6),
+        white: ansi(7),
+        bright_black: ansi(8),
+        bright_red: ansi(9),
+        bright_green: ansi(10),
+        bright_yellow: ansi(11),
+        bright_blue: ansi(12),
+        bright_purple: ansi(13),
+        bright_cyan: ansi(14),
+        bright_white: ansi(15),
+    };
+    let mut json = serde_json::to_string_pretty(&scheme).expect("scheme serializes");
+    json.push('\n');
+    json
+}
@@ -0,0 +1,468 @@
+//! Terminal themes
+//!
+//! A typed model of the theme files under `static/themes`, a [`Catalog`] to
+//! load them, and filters serving them as JSON. The [`validate`] module
+//! checks theme files more strictly than loading them does, the
+//! [`contrast`] module checks that their colors are readable, and the
+//! [`preview`] module draws them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use nextshell::Filter;
+//!
+//! let catalog = nextshell::themes::Catalog::load("static/themes").expect("themes");
+//!
+//! // GET /themes                -> every theme, in brief
+//! // GET /themes/search?q=dark  -> matching themes, in brief
+//! // GET /themes/dracula        -> one theme, in full
+//! // GET /themes/dracula/preview.svg -> a preview of one theme
+//! // POST /themes/import/kitty  -> an uploaded kitty theme, as nextshell YAML
+//! // POST /themes/export/kitty  -> an uploaded nextshell theme, for kitty
+//! let routes = nextshell::path("themes").and(nextshell::themes::api(Arc::new(catalog)));
+//! ```
+//!
+//! Theme and preview responses carry an `ETag`, and requests with a matching
+//! `If-None-Match` are answered with `304 Not Modified`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use headers::{ContentType, ETag, HeaderMapExt, IfNoneMatch};
+use http::StatusCode;
+use hyper::Body;
+use serde::{Deserialize, Serialize};
+
+use crate::filter::{Filter, FilterClone, One};
+use crate::reject::{self, Rejection};
+use crate::reply::Response;
+
+#[cfg(feature = "multipart")]
+use self::convert::{Format, UnknownFormat};
+
+mod catalog;
+pub mod contrast;
+pub mod convert;
+mod model;
+pub mod preview;
+pub mod validate;
+
+pub use self::catalog::{theme_files, Catalog, Entry, LoadError};
+pub use self::model::{
+    Accent, AnsiColors, Background, BackgroundImage, Color, Details, InvalidColor, TerminalColors,
+    Theme,
+};
+
+/// The largest theme file the import and export routes accept.
+#[cfg(feature = "multipart")]
+const MAX_UPLOAD: u64 = 256 * 1024;
+
+/// Creates a `Filter` serving every theme API route.
+///
+/// Combines [`list`], [`search`], [`get`] and [`preview`], and with the
+/// `multipart` feature, [`import`] and [`export`].
+pub fn api(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    let routes = list(catalog.clone())
+        .or(search(catalog.clone()))
+        .unify()
+        .or(get(catalog.clone()))
+        .unify()
+        .or(self::preview(catalog))
+        .unify();
+    #[cfg(feature = "multipart")]
+    let routes = routes.or(import()).unify().or(export()).unify();
+    routes
+}
+
+/// Creates a `Filter` that lists themes in brief, at `GET /`.
+///
+/// A `collection` query parameter limits the list to one collection.
+pub fn list(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path::end())
+        .and(crate::query::<ListQuery>())
+        .and(crate::header::optional2())
+        .map(
+            move |query: ListQuery, if_none_match: Option<IfNoneMatch>| {
+                let summaries = catalog
+                    .iter()
+                    .filter(|entry| query.matches(entry))
+                    .map(Summary::from)
+                    .collect::<Vec<_>>();
+                json_response(&summaries, if_none_match)
+            },
+        )
+}
+
+/// Creates a `Filter` that searches themes, at `GET /search?q=<query>`.
+///
+/// Matching themes are listed in brief, best matches first, as ranked by
+/// [`Catalog::search`]. A `collection` query parameter limits the search to
+/// one collection.
+pub fn search(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path("search"))
+        .and(crate::path::end())
+        .and(crate::query::<SearchQuery>())
+        .and(crate::header::optional2())
+        .map(
+            move |query: SearchQuery, if_none_match: Option<IfNoneMatch>| {
+                let summaries = catalog
+                    .search(&query.q)
+                    .into_iter()
+                    .filter(|entry| query.list.matches(entry))
+                    .map(Summary::from)
+                    .collect::<Vec<_>>();
+                json_response(&summaries, if_none_match)
+            },
+        )
+}
+
+/// Creates a `Filter` that fetches one theme in full, at `GET /<slug>`.
+///
+/// Rejects with "not found" if there's no theme with that slug.
+pub fn get(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(entry(catalog))
+        .and(crate::path::end())
+        .and(crate::header::optional2())
+        .map(|entry: Entry, if_none_match: Option<IfNoneMatch>| {
+            json_response(&entry, if_none_match)
+        })
+}
+
+/// Creates a `Filter` that draws a theme's preview, at
+/// `GET /<slug>/preview.svg`.
+///
+/// Previews are drawn by [`preview::render`] on first request, and kept by
+/// the hash of the theme and the [`preview::VERSION`], which is also their
+/// `ETag`.
+///
+/// Rejects with "not found" if there's no theme with that slug.
+pub fn preview(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    let cache = Arc::new(Mutex::new(HashMap::<u64, Bytes>::new()));
+    crate::get()
+        .and(entry(catalog))
+        .and(crate::path("preview.svg"))
+        .and(crate::path::end())
+        .and(crate::header::optional2())
+        .map(move |entry: Entry, if_none_match: Option<IfNoneMatch>| {
+            let mut json = serde_json::to_vec(&entry.theme).expect("themes serialize to json");
+            // A new drawing of the same theme is a new preview.
+            json.extend_from_slice(&preview::VERSION.to_le_bytes());
+            let hash = content_hash(&json);
+            let etag = etag(hash);
+
+            let mut res = match if_none_match {
+                Some(ref tags) if !tags.precondition_passes(&etag) => not_modified(),
+                _ => {
+                    let svg = cache
+                        .lock()
+                        .unwrap()
+                        .entry(hash)
+                        .or_insert_with(|| Bytes::from(preview::render(&entry.theme)))
+                        .clone();
+                    let mut res = Response::new(Body::from(svg));
+                    res.headers_mut().insert(
+                        http::header::CONTENT_TYPE,
+                        http::HeaderValue::from_static("image/svg+xml"),
+                    );
+                    res
+                }
+            };
+            res.headers_mut().typed_insert(etag);
+            res
+        })
+}
+
+/// Creates a `Filter` that converts an uploaded theme from another format,
+/// at `POST /import/<format>`, where `format` is a [`convert::Format`], like
+/// `iterm` or `base16`.
+///
+/// The upload is the `file` field of a multipart form, or its first field.
+/// The reply is the theme as a nextshell theme file, or `400 Bad Request`
+/// saying why it couldn't be read, or that the format is unknown.
+#[cfg(feature = "multipart")]
+pub fn import() -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::path("import")
+        .and(crate::post())
+        .and(format())
+        .and(crate::path::end())
+        .and(upload())
+        .map(
+            |format: Result<Format, UnknownFormat>, upload: Result<Upload, String>| {
+                let (format, upload) = match (format, upload) {
+                    (Ok(format), Ok(upload)) => (format, upload),
+                    (Err(err), _) => return bad_request(err.to_string()),
+                    (_, Err(message)) => return bad_request(message),
+                };
+                match convert::import(format, &upload.text) {
+                    Ok(imported) => {
+                        let name = imported.name.or(upload.stem);
+                        let yaml =
+                            serde_yaml::to_string(&imported.theme).expect("themes serialize");
+                        attachment(yaml, "application/yaml", name.as_deref(), "yaml")
+                    }
+                    Err(err) => bad_request(err.to_string()),
+                }
+            },
+        )
+}
+
+/// Creates a `Filter` that converts an uploaded nextshell theme to another
+/// format, at `POST /export/<format>`.
+///
+/// The upload is as for [`import`]. The theme is checked as by
+/// [`validate::check`], and if it has errors, they are the reply, with
+/// `400 Bad Request`.
+#[cfg(feature = "multipart")]
+pub fn export() -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::path("export")
+        .and(crate::post())
+        .and(format())
+        .and(crate::path::end())
+        .and(upload())
+        .map(
+            |format: Result<Format, UnknownFormat>, upload: Result<Upload, String>| {
+                let (format, upload) = match (format, upload) {
+                    (Ok(format), Ok(upload)) => (format, upload),
+                    (Err(err), _) => return bad_request(err.to_string()),
+                    (_, Err(message)) => return bad_request(message),
+                };
+                let checked = validate::check(&upload.text);
+                let theme = match checked.theme {
+                    Some(theme) => theme,
+                    None => {
+                        let errors = checked
+                            .diagnostics
+                            .iter()
+                            .filter(|diagnostic| diagnostic.severity == validate::Severity::Error)
+                            .map(|diagnostic| {
+                                format!("line {}: {}", diagnostic.line, diagnostic.message)
+                            })
+                            .collect::<Vec<_>>();
+                        return bad_request(errors.join("\n"));
+                    }
+                };
+                let name = upload
+                    .stem
+                    .as_deref()
+                    .map_or_else(|| "Nextshell".to_owned(), catalog::title_case);
+                let body = convert::export(format, &name, &theme);
+                attachment(
+                    body,
+                    format.content_type(),
+                    upload.stem.as_deref(),
+                    format.extension(),
+                )
+            },
+        )
+}
+
+/// Extracts a theme format from the next path segment, or the name that
+/// isn't one.
+#[cfg(feature = "multipart")]
+fn format() -> impl FilterClone<Extract = One<Result<Format, UnknownFormat>>, Error = Rejection> {
+    crate::path::param::<String>().map(|format: String| format.parse::<Format>())
+}
+
+/// Extracts an uploaded file from a multipart form, or why there isn't one.
+#[cfg(feature = "multipart")]
+fn upload() -> impl FilterClone<Extract = One<Result<Upload, String>>, Error = Rejection> {
+    crate::multipart::form()
+        .max_length(MAX_UPLOAD)
+        .then(read_upload)
+}
+
+#[cfg(feature = "multipart")]
+struct Upload {
+    /// The uploaded file's name, without its extension.
+    stem: Option<String>,
+    text: String,
+}
+
+#[cfg(feature = "multipart")]
+async fn read_upload(mut form: crate::multipart::FormData) -> Result<Upload, String> {
+    use bytes::BufMut;
+    use futures_util::TryStreamExt;
+
+    let mut upload = None;
+    while let Some(part) = form
+        .try_next()
+        .await
+        .map_err(|err| format!("invalid form: {}", err))?
+    {
+        let is_file = part.name() == "file";
+        if upload.is_some() && !is_file {
+            continue;
+        }
+        let stem = part
+            .filename()
+            .map(|name| name.rsplit_once('.').map_or(name, |(stem, _)| stem))
+            .filter(|stem| !stem.is_empty())
+            .map(str::to_owned);
+        let bytes = part
+            .stream()
+            .try_fold(Vec::new(), |mut bytes, buf| {
+                bytes.put(buf);
+                async move { Ok(bytes) }
+            })
+            .await
+            .map_err(|err| format!("invalid form: {}", err))?;
+        let text = String::from_utf8(bytes).map_err(|_| "the theme isn't UTF-8 text".to_owned())?;
+        upload = Some(Upload { stem, text });
+        if is_file {
+            break;
+        }
+    }
+    upload.ok_or_else(|| "expected a theme file in a multipart form".to_owned())
+}
+
+#[cfg(feature = "multipart")]
+fn bad_request(message: String) -> Response {
+    let mut res = Response::new(Body::from(message + "\n"));
+    *res.status_mut() = StatusCode::BAD_REQUEST;
+    res.headers_mut().typed_insert(ContentType::text_utf8());
+    res
+}
+
+/// Replies with a file to download, named after `name`.
+#[cfg(feature = "multipart")]
+fn attachment(body: String, content_type: &str, name: Option<&str>, extension: &str) -> Response {
+    let stem = name
+        .map(|name| {
+            name.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        })
+        .filter(|stem| stem.chars().any(|c| c != '_'))
+        .unwrap_or_else(|| "theme".to_owned());
+
+    let mut res = Response::new(Body::from(body));
+    let headers = res.headers_mut();
+    headers.insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_str(content_type).expect("valid content type"),
+    );
+    headers.insert(
+        http::header::CONTENT_DISPOSITION,
+        http::HeaderValue::from_str(&format!("attachment; filename=\"{}.{}\"", stem, extension))
+            .expect("ascii file name"),
+    );
+    res
+}
+
+/// Extracts the theme named by the next path segment.
+fn entry(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Entry>, Error = Rejection> {
+    crate::path::param::<String>().and_then(move |slug: String| {
+        let entry = catalog.get(&slug).cloned();
+        async move {
+            entry.ok_or_else(|| {
+                tracing::debug!("themes: no theme {:?}", slug);
+                reject::not_found()
+            })
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    collection: Option<String>,
+}
+
+impl ListQuery {
+    fn matches(&self, entry: &Entry) -> bool {
+        match self.collection {
+            Some(ref collection) => *collection == entry.collection,
+            None => true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(flatten)]
+    list: ListQuery,
+}
+
+/// A theme in brief: enough to draw a swatch.
+#[derive(Serialize)]
+struct Summary<'a> {
+    slug: &'a str,
+    name: &'a str,
+    collection: &'a str,
+    details: &'static str,
+    background: &'a Background,
+    accent: &'a Accent,
+    foreground: Color,
+}
+
+impl<'a> From<&'a Entry> for Summary<'a> {
+    fn from(entry: &'a Entry) -> Summary<'a> {
+        Summary {
+            slug: &entry.slug,
+            name: &entry.name,
+            collection: &entry.collection,
+            details: match entry.theme.details {
+                Details::Darker => "darker",
+                Details::Lighter => "lighter",
+                Details::Custom(_) => "custom",
+            },
+            background: &entry.theme.background,
+            accent: &entry.theme.accent,
+            foreground: entry.theme.foreground,
+        }
+    }
+}
+
+/// Replies with `val` as JSON, or `304 Not Modified` if the client already
+/// has it.
+fn json_response<T: Serialize>(val: &T, if_none_match: Option<IfNoneMatch>) -> Response {
+    let body = serde_json::to_vec(val).expect("themes serialize to json");
+    let etag = etag(content_hash(&body));
+
+    let mut res = match if_none_match {
+        Some(ref tags) if !tags.precondition_passes(&etag) => not_modified(),
+        _ => {
+            let mut res = Response::new(Body::from(body));
+            res.headers_mut().typed_insert(ContentType::json());
+            res
+        }
+    };
+    res.headers_mut().typed_insert(etag);
+    res
+}
+
+fn not_modified() -> Response {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = StatusCode::NOT_MODIFIED;
+    res
+}
+
+/// A 64-bit FNV-1a hash, which is stable across runs and builds.
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A strong ETag from a [`content_hash`].
+fn etag(hash: u64) -> ETag {
+    format!("\"{:016x}\"", hash)
+        .parse()
+        .expect("hex is a valid etag")
+}
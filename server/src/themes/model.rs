@@ -0,0 +1,275 @@
+//! The theme file format.
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+/// A terminal theme, as stored in a theme YAML file.
+///
+/// # Example
+///
+/// ```
+/// let theme: nextshell::themes::Theme = serde_yaml::from_str(r##"
+/// accent: "#bd93f9"
+/// background:
+///   top: "#282a36"
+///   bottom: "#44475a"
+/// details: darker
+/// foreground: "#f8f8f2"
+/// terminal_colors:
+///   normal: { black: "#000", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+///             blue: "#bd93f9", magenta: "#ff79c6", cyan: "#8be9fd", white: "#bbb" }
+///   bright: { black: "#555", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+///             blue: "#caa9fa", magenta: "#ff79c6", cyan: "#8be9fd", white: "#fff" }
+/// "##).unwrap();
+///
+/// assert_eq!(theme.terminal_colors.bright.white.to_string(), "#ffffff");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Theme {
+    /// The color of highlighted UI elements.
+    pub accent: Accent,
+    /// The terminal background.
+    pub background: Background,
+    /// Whether UI details are drawn darker or lighter than the background.
+    pub details: Details,
+    /// The default text color.
+    pub foreground: Color,
+    /// The 16 ANSI colors.
+    pub terminal_colors: TerminalColors,
+    /// An image drawn over the background.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_image: Option<BackgroundImage>,
+}
+
+/// An RGB color, written `#rrggbb` or `#rgb`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Color {
+    /// Red.
+    pub r: u8,
+    /// Green.
+    pub g: u8,
+    /// Blue.
+    pub b: u8,
+}
+
+/// The background of a theme: a solid color, or a gradient.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Background {
+    /// A solid color.
+    Solid(Color),
+    /// A gradient from the top of the window to the bottom.
+    Gradient {
+        /// The color at the top.
+        top: Color,
+        /// The color at the bottom.
+        bottom: Color,
+    },
+}
+
+/// The accent of a theme: a solid color, or a gradient.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Accent {
+    /// A solid color.
+    Solid(Color),
+    /// A gradient from left to right.
+    Gradient {
+        /// The color on the left.
+        left: Color,
+        /// The color on the right.
+        right: Color,
+    },
+}
+
+/// How UI details are drawn against the background.
+///
+/// Written `darker`, `lighter`, or a `custom:` map of opacities.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Details {
+    /// Darker than the background, for dark themes.
+    Darker,
+    /// Lighter than the background, for light themes.
+    Lighter,
+    /// Opacities, in percent, for individual UI elements.
+    Custom(BTreeMap<String, u8>),
+}
+
+/// The 16 ANSI colors of a theme.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TerminalColors {
+    /// Colors 0 to 7.
+    pub normal: AnsiColors,
+    /// Colors 8 to 15.
+    pub bright: AnsiColors,
+}
+
+/// Eight ANSI colors.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[allow(missing_docs)]
+pub struct AnsiColors {
+    pub black: Color,
+    pub red: Color,
+    pub green: Color,
+    pub yellow: Color,
+    pub blue: Color,
+    pub magenta: Color,
+    pub cyan: Color,
+    pub white: Color,
+}
+
+/// An image drawn over a theme's background.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BackgroundImage {
+    /// The image file, relative to the themes directory.
+    pub path: String,
+    /// How opaque the image is, in percent (defaults to 100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<u8>,
+}
+
+impl Background {
+    /// The color at the top, or the only color.
+    pub fn top(&self) -> Color {
+        match *self {
+            Background::Solid(color) => color,
+            Background::Gradient { top, .. } => top,
+        }
+    }
+}
+
+impl Accent {
+    /// The color on the left, or the only color.
+    pub fn left(&self) -> Color {
+        match *self {
+            Accent::Solid(color) => color,
+            Accent::Gradient { left, .. } => left,
+        }
+    }
+}
+
+impl AnsiColors {
+    /// The colors in ANSI order, from black to white.
+    pub fn to_array(&self) -> [Color; 8] {
+        [
+            self.black,
+            self.red,
+            self.green,
+            self.yellow,
+            self.blue,
+            self.magenta,
+            self.cyan,
+            self.white,
+        ]
+    }
+}
+
+// Serde's externally tagged enums don't read plain YAML maps, so `Details`
+// goes through its own shapes.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DetailsRepr {
+    Named(String),
+    Custom { custom: BTreeMap<String, u8> },
+}
+
+impl Serialize for Details {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Details::Darker => serializer.serialize_str("darker"),
+            Details::Lighter => serializer.serialize_str("lighter"),
+            Details::Custom(ref custom) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("custom", custom)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Details {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Details, D::Error> {
+        match DetailsRepr::deserialize(deserializer)? {
+            DetailsRepr::Named(name) => match name.trim() {
+                "darker" => Ok(Details::Darker),
+                "lighter" => Ok(Details::Lighter),
+                _ => Err(de::Error::invalid_value(
+                    de::Unexpected::Str(&name),
+                    &"`darker`, `lighter` or `custom`",
+                )),
+            },
+            DetailsRepr::Custom { custom } => Ok(Details::Custom(custom)),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = InvalidColor;
+
+    fn from_str(s: &str) -> Result<Color, InvalidColor> {
+        let hex = s.strip_prefix('#').ok_or(InvalidColor)?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(InvalidColor);
+        }
+        let channel = |i: usize, len: usize| {
+            let v = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).expect("hex digits");
+            if len == 1 {
+                v * 0x11
+            } else {
+                v
+            }
+        };
+        match hex.len() {
+            3 => Ok(Color {
+                r: channel(0, 1),
+                g: channel(1, 1),
+                b: channel(2, 1),
+            }),
+            6 => Ok(Color {
+                r: channel(0, 2),
+                g: channel(1, 2),
+                b: channel(2, 2),
+            }),
+            _ => Err(InvalidColor),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.trim().parse().map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Str(&s), &"a color like \"#rrggbb\"")
+        })
+    }
+}
+
+/// A string that isn't a `#rrggbb` or `#rgb` color.
+#[derive(Debug)]
+pub struct InvalidColor;
+
+impl fmt::Display for InvalidColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid color, expected \"#rrggbb\" or \"#rgb\"")
+    }
+}
+
+impl StdError for InvalidColor {}
@@ -0,0 +1,124 @@
+//! Preview images of themes, as SVG.
+//!
+//! A preview is a small mock terminal: a listing, the 16 ANSI colors, and a
+//! prompt with a cursor in the accent color. These are the images under
+//! `previews/` in each theme collection, which `gen-theme-previews` writes.
+
+use std::fmt::Write;
+
+use super::{Accent, Background, Theme};
+
+/// The preview, with `{name}` where each color goes.
+const TEMPLATE: &str = include_str!("preview.svg");
+
+/// The version of [`render`]'s drawing. Bump it whenever the drawing of a
+/// theme changes, so that previews tagged by an older version aren't taken
+/// as still fresh.
+pub const VERSION: u32 = 1;
+
+/// Renders a theme's preview.
+///
+/// A gradient background runs from top to bottom, and a gradient accent
+/// from left to right, as in the app.
+///
+/// # Example
+///
+/// ```
+/// let theme: nextshell::themes::Theme = serde_yaml::from_str(r##"
+/// accent: "#bd93f9"
+/// background: "#282a36"
+/// details: darker
+/// foreground: "#f8f8f2"
+/// terminal_colors:
+///   normal: {black: "#000000", red: "#ff5555", green: "#50fa7b", yellow: "#f1fa8c",
+///            blue: "#bd93f9", magenta: "#ff79c6", cyan: "#8be9fd", white: "#bbbbbb"}
+///   bright: {black: "#555555", red: "#ff5555", green: "#50fa7b", yellow: "#f1fa8c",
+///            blue: "#caa9fa", magenta: "#ff79c6", cyan: "#8be9fd", white: "#ffffff"}
+/// "##).unwrap();
+///
+/// let svg = nextshell::themes::preview::render(&theme);
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains(r##"<rect width="300" height="145" fill="#282a36""##));
+/// ```
+pub fn render(theme: &Theme) -> String {
+    let mut defs = String::new();
+    let background = match theme.background {
+        Background::Solid(color) => color.to_string(),
+        Background::Gradient { top, bottom } => {
+            write!(
+                defs,
+                concat!(
+                    r#"<linearGradient id="background" x2="0" y2="1">"#,
+                    r#"<stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/>"#,
+                    "</linearGradient>",
+                ),
+                top, bottom
+            )
+            .unwrap();
+            "url(#background)".to_owned()
+        }
+    };
+    let accent = match theme.accent {
+        Accent::Solid(color) => color.to_string(),
+        Accent::Gradient { left, right } => {
+            // The cursor is a line, whose bounding box has no width, so the
+            // gradient is laid out across the stroke itself.
+            write!(
+                defs,
+                concat!(
+                    r#"<linearGradient id="accent" gradientUnits="userSpaceOnUse" x1="14" x2="16">"#,
+                    r#"<stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/>"#,
+                    "</linearGradient>",
+                ),
+                left, right
+            )
+            .unwrap();
+            "url(#accent)".to_owned()
+        }
+    };
+
+    let normal = &theme.terminal_colors.normal;
+    let bright = &theme.terminal_colors.bright;
+    let color = |name: &str| -> Option<String> {
+        let color = match name {
+            "background" => return Some(background.clone()),
+            "accent" => return Some(accent.clone()),
+            "foreground" => theme.foreground,
+            "black" => normal.black,
+            "red" => normal.red,
+            "green" => normal.green,
+            "yellow" => normal.yellow,
+            "blue" => normal.blue,
+            "magenta" => normal.magenta,
+            "cyan" => normal.cyan,
+            "white" => normal.white,
+            "brblack" => bright.black,
+            "brred" => bright.red,
+            "brgreen" => bright.green,
+            "bryellow" => bright.yellow,
+            "brblue" => bright.blue,
+            "brmagenta" => bright.magenta,
+            "brcyan" => bright.cyan,
+            "brwhite" => bright.white,
+            _ => return None,
+        };
+        Some(color.to_string())
+    };
+
+    let mut svg = String::with_capacity(TEMPLATE.len() + defs.len());
+    let mut rest = TEMPLATE;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}').expect("template braces are closed");
+        svg.push_str(&rest[..start]);
+        let name = &rest[start + 1..end];
+        svg.push_str(&color(name).unwrap_or_else(|| panic!("unknown template color {}", name)));
+        rest = &rest[end + 1..];
+    }
+    svg.push_str(rest);
+
+    if !defs.is_empty() {
+        let open = svg.find('>').expect("template has an <svg> tag") + 1;
+        svg.insert_str(open, &format!("<defs>{}</defs>", defs));
+    }
+    svg
+}
@@ -0,0 +1,654 @@
+//! Strict checking of theme files.
+//!
+//! [`check`] reads a theme the way the server does, but rather than stopping
+//! at the first problem, it reports every problem it finds with the line it
+//! is on. Besides outright errors, it warns about style the bundled themes
+//! should agree on: double-quoted, lower-case colors, and no comments
+//! trailing a value.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::{Mapping, Value};
+
+use super::{LoadError, Theme};
+
+const KEYS: &[&str] = &[
+    "accent",
+    "background",
+    "details",
+    "foreground",
+    "terminal_colors",
+    "background_image",
+];
+const REQUIRED: &[&str] = &[
+    "accent",
+    "background",
+    "details",
+    "foreground",
+    "terminal_colors",
+];
+const ANSI: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// A problem found in a theme file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The line the problem is on, counting from 1.
+    pub line: usize,
+    /// Whether the theme is unusable, or merely untidy.
+    pub severity: Severity,
+    /// What's wrong.
+    pub message: String,
+}
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The file doesn't follow the house style.
+    Warning,
+    /// The file isn't a valid theme.
+    Error,
+}
+
+/// The result of checking a theme file.
+#[derive(Debug)]
+pub struct Checked {
+    /// The theme, if the file had no errors.
+    pub theme: Option<Theme>,
+    /// Every problem found, in line order.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The diagnostics for one file of a themes directory.
+#[derive(Debug)]
+pub struct Report {
+    /// The theme file.
+    pub file: PathBuf,
+    /// Every problem found, in line order.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Checked {
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        has_errors(&self.diagnostics)
+    }
+}
+
+impl Report {
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        has_errors(&self.diagnostics)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.line, self.severity, self.message)
+    }
+}
+
+impl fmt::Display for Report {
+    /// Formats the diagnostics as `file:line: severity: message` lines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}:{}", self.file.display(), diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the text of a theme file.
+///
+/// The theme's `background_image`, if any, isn't looked for; see
+/// [`check_dir`] for that.
+///
+/// # Example
+///
+/// ```
+/// use nextshell::themes::validate::{self, Severity};
+///
+/// let checked = validate::check("accent: '#FFF'\nbackground:\n  left: \"#000\"\n");
+/// assert!(checked.has_errors());
+/// assert!(checked.theme.is_none());
+///
+/// for diagnostic in &checked.diagnostics {
+///     println!("theme.yaml:{}", diagnostic);
+/// }
+/// assert!(checked.diagnostics.contains(&validate::Diagnostic {
+///     line: 2,
+///     severity: Severity::Error,
+///     message: "`background` gradients run from `top` to `bottom`, not `left` to `right`".into(),
+/// }));
+/// ```
+pub fn check(text: &str) -> Checked {
+    let mut checker = Checker {
+        lines: Lines::index(text),
+        diagnostics: Vec::new(),
+    };
+
+    let value = match serde_yaml::from_str::<Value>(text) {
+        Ok(value) => value,
+        Err(err) => {
+            let line = err.location().map_or(1, |location| location.line());
+            checker.error(line, format!("invalid YAML: {}", err));
+            return checker.finish(None);
+        }
+    };
+
+    checker.theme(&value);
+    if has_errors(&checker.diagnostics) {
+        return checker.finish(None);
+    }
+    match serde_yaml::from_value(value) {
+        Ok(theme) => checker.finish(Some(theme)),
+        Err(err) => {
+            // The checks above should catch anything the model rejects.
+            checker.error(1, format!("invalid theme: {}", err));
+            checker.finish(None)
+        }
+    }
+}
+
+/// Checks every theme file in a themes directory, as listed by
+/// [`theme_files`](super::theme_files).
+///
+/// On top of [`check`], this makes sure each `background_image.path` names a
+/// file, relative to `dir`. Fails only if a file or directory can't be read.
+pub fn check_dir(dir: impl AsRef<Path>) -> Result<Vec<Report>, LoadError> {
+    let dir = dir.as_ref();
+    super::theme_files(dir)?
+        .into_iter()
+        .map(|file| {
+            let diagnostics = check_file(dir, &file)?;
+            Ok(Report { file, diagnostics })
+        })
+        .collect()
+}
+
+/// Checks one theme file of the themes directory `dir`.
+///
+/// Like [`check_dir`], this makes sure the `background_image.path` names a
+/// file relative to `dir`.
+pub fn check_file(dir: &Path, file: &Path) -> Result<Vec<Diagnostic>, LoadError> {
+    let text = fs::read_to_string(file).map_err(|err| LoadError::io(file, err))?;
+    let mut checked = check(&text);
+
+    let image = checked
+        .theme
+        .as_ref()
+        .and_then(|theme| theme.background_image.as_ref());
+    if let Some(image) = image {
+        if !dir.join(&image.path).is_file() {
+            let mut message = format!(
+                "`background_image.path`: no such file `{}` in {}",
+                image.path,
+                dir.display()
+            );
+            let beside = file.parent().map(|parent| parent.join(&image.path));
+            if let Some(beside) = beside.filter(|beside| beside.is_file()) {
+                if let Ok(relative) = beside.strip_prefix(dir) {
+                    message += &format!(
+                        " (paths are relative to the themes directory; did you mean `{}`?)",
+                        relative.display()
+                    );
+                }
+            }
+            let line = Lines::index(&text).line(&["background_image", "path"]);
+            checked.diagnostics.push(Diagnostic {
+                line,
+                severity: Severity::Error,
+                message,
+            });
+            checked
+                .diagnostics
+                .sort_by_key(|diagnostic| diagnostic.line);
+        }
+    }
+    Ok(checked.diagnostics)
+}
+
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
+
+struct Checker {
+    lines: Lines,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn finish(mut self, theme: Option<Theme>) -> Checked {
+        // Sorting is stable, so problems on one line stay in the order found.
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        Checked {
+            theme,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    fn theme(&mut self, value: &Value) {
+        for line in self.lines.trailing_comments.clone() {
+            self.warning(line, "comment after a value".to_owned());
+        }
+
+        let map = match value {
+            Value::Mapping(map) => map,
+            Value::Null => return self.error(1, "empty theme".to_owned()),
+            _ => return self.error(1, "expected a map of theme keys".to_owned()),
+        };
+        self.keys(&[], map, KEYS, Severity::Warning);
+        for &key in REQUIRED {
+            if !map.contains_key(key) {
+                self.error(1, format!("missing `{}`", key));
+            }
+        }
+
+        if let Some(accent) = map.get("accent") {
+            self.gradient(&["accent"], accent, ("left", "right"), ("top", "bottom"));
+        }
+        if let Some(background) = map.get("background") {
+            self.gradient(
+                &["background"],
+                background,
+                ("top", "bottom"),
+                ("left", "right"),
+            );
+        }
+        if let Some(foreground) = map.get("foreground") {
+            self.color(&["foreground"], foreground);
+        }
+        if let Some(details) = map.get("details") {
+            self.details(details);
+        }
+        if let Some(colors) = map.get("terminal_colors") {
+            self.terminal_colors(colors);
+        }
+        if let Some(image) = map.get("background_image") {
+            self.background_image(image);
+        }
+    }
+
+    /// Reports keys of `map` that aren't in `known`.
+    fn keys(&mut self, path: &[&str], map: &Mapping, known: &[&str], severity: Severity) {
+        for key in map.keys() {
+            let name = match key.as_str() {
+                Some(name) if known.contains(&name) => continue,
+                Some(name) => name.to_owned(),
+                None => format!("{:?}", key),
+            };
+            let mut at = path.to_vec();
+            at.push(&name);
+            let line = self.lines.line(&at);
+            let message = format!("unknown key `{}`", at.join("."));
+            match severity {
+                Severity::Warning => self.warning(line, message),
+                Severity::Error => self.error(line, message),
+            }
+        }
+    }
+
+    fn mapping<'v>(&mut self, path: &[&str], value: &'v Value) -> Option<&'v Mapping> {
+        match value {
+            Value::Mapping(map) => Some(map),
+            Value::Null => {
+                self.error(
+                    self.lines.line(path),
+                    format!("`{}` is empty", path.join(".")),
+                );
+                None
+            }
+            _ => {
+                self.error(
+                    self.lines.line(path),
+                    format!("`{}` should be a map", path.join(".")),
+                );
+                None
+            }
+        }
+    }
+
+    fn color(&mut self, path: &[&str], value: &Value) {
+        let line = self.lines.line(path);
+        let name = path.join(".");
+        let s = match value {
+            Value::String(s) => s,
+            Value::Null => {
+                return self.error(
+                    line,
+                    format!(
+                        "`{}` is empty (quote colors, or `#` starts a comment)",
+                        name
+                    ),
+                );
+            }
+            _ => {
+                return self.error(
+                    line,
+                    format!("`{}` should be a color like \"#rrggbb\"", name),
+                );
+            }
+        };
+
+        if s.trim().parse::<super::Color>().is_err() {
+            return self.error(
+                line,
+                format!("`{}`: invalid color {:?}, expected \"#rrggbb\"", name, s),
+            );
+        }
+        if let Some(raw) = self.lines.value(path) {
+            if raw.starts_with('\'') {
+                self.warning(
+                    line,
+                    format!("`{}` is single-quoted; use double quotes", name),
+                );
+            }
+        }
+        if s.bytes().any(|b| b.is_ascii_uppercase()) {
+            self.warning(
+                line,
+                format!("`{}` has upper-case hex; use lower case", name),
+            );
+        }
+    }
+
+    /// Checks a color or a two-stop gradient, like `background`'s `top` to
+    /// `bottom`. `other` is the direction the gradient can't run in.
+    fn gradient(
+        &mut self,
+        path: &[&str],
+        value: &Value,
+        (start, end): (&str, &str),
+        other: (&str, &str),
+    ) {
+        let map = match value {
+            Value::Mapping(map) => map,
+            _ => return self.color(path, value),
+        };
+        let name = path.join(".");
+        let line = self.lines.line(path);
+
+        if !map.contains_key(start)
+            && !map.contains_key(end)
+            && (map.contains_key(other.0) || map.contains_key(other.1))
+        {
+            return self.error(
+                line,
+                format!(
+                    "`{}` gradients run from `{}` to `{}`, not `{}` to `{}`",
+                    name, start, end, other.0, other.1
+                ),
+            );
+        }
+        self.keys(path, map, &[start, end], Severity::Error);
+        for stop in &[start, end] {
+            let mut at = path.to_vec();
+            at.push(stop);
+            match map.get(*stop) {
+                Some(color) => self.color(&at, color),
+                None => self.error(line, format!("`{}` gradient is missing `{}`", name, stop)),
+            }
+        }
+    }
+
+    fn details(&mut self, value: &Value) {
+        let line = self.lines.line(&["details"]);
+        match value {
+            Value::String(s) if s.trim() == "darker" || s.trim() == "lighter" => {}
+            Value::Mapping(map) => {
+                self.keys(&["details"], map, &["custom"], Severity::Error);
+                let custom = match map.get("custom") {
+                    Some(custom) => custom,
+                    None => {
+                        return self.error(line, "`details` map is missing `custom`".to_owned())
+                    }
+                };
+                let custom = match self.mapping(&["details", "custom"], custom) {
+                    Some(custom) => custom,
+                    None => return,
+                };
+                for (key, opacity) in custom {
+                    let key = key.as_str().unwrap_or_default();
+                    self.percent(&["details", "custom", key], opacity);
+                }
+            }
+            _ => self.error(
+                line,
+                format!(
+                    "`details` should be `darker`, `lighter` or `custom`, not {}",
+                    describe(value)
+                ),
+            ),
+        }
+    }
+
+    fn terminal_colors(&mut self, value: &Value) {
+        let map = match self.mapping(&["terminal_colors"], value) {
+            Some(map) => map,
+            None => return,
+        };
+        self.keys(
+            &["terminal_colors"],
+            map,
+            &["normal", "bright"],
+            Severity::Warning,
+        );
+
+        for &set in &["normal", "bright"] {
+            let path = ["terminal_colors", set];
+            let colors = match map.get(set) {
+                Some(colors) => colors,
+                None => {
+                    let line = self.lines.line(&path[..1]);
+                    self.error(line, format!("missing `terminal_colors.{}`", set));
+                    continue;
+                }
+            };
+            let colors = match self.mapping(&path, colors) {
+                Some(colors) => colors,
+                None => continue,
+            };
+            self.keys(&path, colors, ANSI, Severity::Warning);
+            for &color in ANSI {
+                let at = ["terminal_colors", set, color];
+                match colors.get(color) {
+                    Some(value) => self.color(&at, value),
+                    None => {
+                        let line = self.lines.line(&path);
+                        self.error(line, format!("missing `{}`", at.join(".")));
+                    }
+                }
+            }
+        }
+    }
+
+    fn background_image(&mut self, value: &Value) {
+        let path = ["background_image"];
+        let map = match self.mapping(&path, value) {
+            Some(map) => map,
+            None => return,
+        };
+        self.keys(&path, map, &["path", "opacity"], Severity::Warning);
+
+        match map.get("path") {
+            Some(Value::String(s)) if !s.trim().is_empty() => {}
+            Some(_) => self.error(
+                self.lines.line(&["background_image", "path"]),
+                "`background_image.path` should name an image file".to_owned(),
+            ),
+            None => self.error(
+                self.lines.line(&path),
+                "missing `background_image.path`".to_owned(),
+            ),
+        }
+        if let Some(opacity) = map.get("opacity") {
+            self.percent(&["background_image", "opacity"], opacity);
+        }
+    }
+
+    fn percent(&mut self, path: &[&str], value: &Value) {
+        if !matches!(value.as_u64(), Some(0..=100)) {
+            self.error(
+                self.lines.line(path),
+                format!(
+                    "`{}` should be a percentage from 0 to 100, not {}",
+                    path.join("."),
+                    describe(value)
+                ),
+            );
+        }
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "empty".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("{:?}", s),
+        Value::Sequence(_) => "a list".to_owned(),
+        Value::Mapping(_) => "a map".to_owned(),
+        Value::Tagged(tagged) => format!("a `{}` value", tagged.tag),
+    }
+}
+
+/// Where each key of a block-style YAML document is.
+///
+/// This is no YAML parser, but theme files are simple enough that
+/// indentation tells which map a key belongs to. Keys inside flow-style maps,
+/// like `{ black: "#000" }`, aren't indexed; they're placed on the line of the
+/// nearest enclosing key that is.
+struct Lines {
+    keys: HashMap<String, Key>,
+    trailing_comments: Vec<usize>,
+}
+
+struct Key {
+    line: usize,
+    value: String,
+}
+
+impl Lines {
+    fn index(text: &str) -> Lines {
+        let mut keys = HashMap::new();
+        let mut trailing_comments = Vec::new();
+        let mut stack: Vec<(usize, String)> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - content.len();
+            let (key, rest) = match split_key(content) {
+                Some(split) => split,
+                None => continue,
+            };
+
+            while matches!(stack.last(), Some(&(depth, _)) if depth >= indent) {
+                stack.pop();
+            }
+            stack.push((indent, key.to_owned()));
+            let path = stack
+                .iter()
+                .map(|(_, key)| key.as_str())
+                .collect::<Vec<_>>()
+                .join(".");
+
+            let (value, comment) = split_comment(rest);
+            if comment {
+                trailing_comments.push(i + 1);
+            }
+            keys.entry(path).or_insert(Key {
+                line: i + 1,
+                value: value.to_owned(),
+            });
+        }
+
+        Lines {
+            keys,
+            trailing_comments,
+        }
+    }
+
+    /// The line of a key, or of the nearest enclosing key that was indexed.
+    fn line(&self, path: &[&str]) -> usize {
+        (1..=path.len())
+            .rev()
+            .find_map(|len| self.keys.get(&path[..len].join(".")))
+            .map_or(1, |key| key.line)
+    }
+
+    /// The raw text of a key's value, if it is on the key's line.
+    fn value(&self, path: &[&str]) -> Option<&str> {
+        self.keys
+            .get(&path.join("."))
+            .map(|key| key.value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+}
+
+/// Splits `key: value` into the key and the rest of the line.
+fn split_key(content: &str) -> Option<(&str, &str)> {
+    let (key, rest) = if let Some(quoted) = content.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (&quoted[..end], quoted[end + 1..].strip_prefix(':')?)
+    } else {
+        let colon = content.find(':')?;
+        (content[..colon].trim_end(), &content[colon + 1..])
+    };
+    if !(rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t')) {
+        return None;
+    }
+    Some((key, rest.trim()))
+}
+
+/// Splits a value from a trailing comment, returning the value and whether
+/// there was a comment.
+fn split_comment(rest: &str) -> (&str, bool) {
+    if rest.starts_with('#') {
+        return ("", true);
+    }
+    let end = match rest.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => rest[1..].find(quote).map(|end| end + 2),
+        _ => rest.find(" #").or_else(|| rest.find("\t#")),
+    };
+    match end {
+        Some(end) => {
+            let (value, after) = rest.split_at(end);
+            (value.trim_end(), after.trim_start().starts_with('#'))
+        }
+        None => (rest, false),
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+
+use nextshell_workflows::search::{Hit, Query, SearchIndex};
+use nextshell_workflows::usage::{self, Frecency, UsageLog, UsageRecord, UsageStats};
+use nextshell_workflows::{Runbook, Source, Workflow, WorkflowStore, EMBEDDED_SPECS};
+use serde::Serialize;
+
+/// The workflows of a [`WorkflowStore`], indexed for search.
+///
+/// The catalog lists and searches the workflows slugs resolve to, over the
+/// store's layers. Shadowed workflows are left out, but can still be looked
+/// up by their qualified slugs, like `bundled:deploy`.
+///
+/// With a [`UsageLog`], the catalog keeps track of the workflows run, and
+/// ranks those run most, and most lately, first.
+#[derive(Debug)]
+pub struct Catalog {
+    /// The resolved workflows, then the shadowed ones.
+    entries: Vec<Entry>,
+    resolved: usize,
+    by_slug: HashMap<String, usize>,
+    index: SearchIndex,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug)]
+struct Usage {
+    log: UsageLog,
+    frecency: Mutex<Frecency>,
+}
+
+/// A workflow in a [`Catalog`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Entry {
+    /// Identifies the workflow in URLs: its spec's file name, without the
+    /// extension, or for a shadowed workflow, its qualified slug.
+    pub slug: String,
+    /// The spec, relative to the directory it was loaded from.
+    pub path: String,
+    /// The name of the layer the workflow was loaded from, like `bundled`.
+    pub source: String,
+    /// The slug qualified by the workflow's layer, like `bundled:deploy`,
+    /// which names this workflow whichever layers shadow it.
+    pub qualified_slug: String,
+    /// The qualified slugs of the workflows this one shadows.
+    pub shadows: Vec<String>,
+    /// The workflow itself.
+    pub workflow: Workflow,
+    /// The workflow as a runbook, of its steps, or of its command as the
+    /// only one.
+    #[serde(skip)]
+    pub runbook: Runbook,
+}
+
+impl Catalog {
+    /// Indexes the workflows of a store.
+    ///
+    /// Each slug is the workflow it resolves to, as by
+    /// [`WorkflowStore::get`], and each qualified slug its own workflow.
+    pub fn new(store: &WorkflowStore) -> Catalog {
+        let mut entries = Vec::new();
+        let mut by_slug = HashMap::new();
+        for stored in store.resolved() {
+            by_slug.insert(stored.slug.clone(), entries.len());
+            by_slug.insert(stored.qualified_slug(), entries.len());
+            entries.push(Entry {
+                slug: stored.slug.clone(),
+                path: stored.path.clone(),
+                source: stored.source.clone(),
+                qualified_slug: stored.qualified_slug(),
+                shadows: Vec::new(),
+                workflow: stored.workflow.clone(),
+                runbook: stored.runbook.clone(),
+            });
+        }
+        let resolved = entries.len();
+        for stored in store.workflows() {
+            let qualified = stored.qualified_slug();
+            if by_slug.contains_key(&qualified) {
+                continue;
+            }
+            if let Some(&shadowing) = by_slug.get(&stored.slug) {
+                entries[shadowing].shadows.push(qualified.clone());
+            }
+            by_slug.insert(qualified.clone(), entries.len());
+            entries.push(Entry {
+                slug: qualified.clone(),
+                path: stored.path.clone(),
+                source: stored.source.clone(),
+                qualified_slug: qualified,
+                shadows: Vec::new(),
+                workflow: stored.workflow.clone(),
+                runbook: stored.runbook.clone(),
+            });
+        }
+        let index = SearchIndex::new(
+            entries[..resolved]
+                .iter()
+                .map(|entry| entry.workflow.clone())
+                .collect(),
+        );
+        Catalog {
+            entries,
+            resolved,
+            by_slug,
+            index,
+            usage: None,
+        }
+    }
+
+    /// Keeps track of the workflows run in a usage log, ranking search
+    /// results by the runs already in it, and those [recorded](Catalog::record)
+    /// from now on.
+    ///
+    /// Fails if the log exists but can't be read.
+    pub fn with_usage(mut self, log: UsageLog) -> io::Result<Catalog> {
+        let frecency = Frecency::new(&log.records()?);
+        self.usage = Some(Usage {
+            log,
+            frecency: Mutex::new(frecency),
+        });
+        Ok(self)
+    }
+
+    /// Appends a run of a workflow to the usage log, if there's one, and
+    /// counts it in the ranking.
+    pub fn record(&self, record: &UsageRecord) -> io::Result<()> {
+        match self.usage {
+            Some(ref usage) => {
+                usage.frecency.lock().unwrap().add(record);
+                usage.log.append(record)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sums up the runs in the usage log since a time, in seconds since the
+    /// Unix epoch, by workflow, as by [`usage::stats`]. Without a usage log,
+    /// there are none.
+    pub fn usage(&self, since: u64) -> io::Result<Vec<UsageStats>> {
+        match self.usage {
+            Some(ref usage) => Ok(usage::stats(&usage.log.records()?, since)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Indexes the workflows built into `nextshell-workflows`.
+    pub fn bundled() -> Catalog {
+        Catalog::new(&WorkflowStore::new().with_source(Source::Embedded(EMBEDDED_SPECS)))
+    }
+
+    /// Looks a workflow up by its slug, or its qualified slug.
+    pub fn get(&self, slug: &str) -> Option<&Entry> {
+        self.by_slug.get(slug).map(|&i| &self.entries[i])
+    }
+
+    /// Iterates over the workflows that aren't shadowed, in the order of
+    /// [`WorkflowStore::resolved`].
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> + '_ {
+        self.entries[..self.resolved].iter()
+    }
+
+    /// Returns the number of workflows that aren't shadowed.
+    pub fn len(&self) -> usize {
+        self.resolved
+    }
+
+    /// Returns true if there are no workflows.
+    pub fn is_empty(&self) -> bool {
+        self.resolved == 0
+    }
+
+    /// Finds the workflows matching a query, best matches first, with their
+    /// scores, as ranked by [`SearchIndex::search`], plus the
+    /// [boost](Frecency::boost) of how much they were used, with a usage log.
+    pub fn search(&self, query: &Query) -> Vec<(&Entry, u32)> {
+        let hits = match self.usage {
+            Some(ref usage) => {
+                let frecency = usage.frecency.lock().unwrap();
+                let now = UsageRecord::now();
+                self.index.search_with(query, |position| {
+                    frecency.boost(&self.entries[position].slug, now)
+                })
+            }
+            None => self.index.search(query),
+        };
+        hits.into_iter()
+            .map(|hit: Hit<'_>| (&self.entries[hit.position], hit.score))
+            .collect()
+    }
+}
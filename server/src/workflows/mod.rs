@@ -0,0 +1,356 @@
+//! Workflows
+//!
+//! The workflows of `nextshell-workflows`, indexed for search by a
+//! [`Catalog`], and filters serving them as JSON, or as shell functions to
+//! download. With the `terminal` feature, [`run`] also runs them, in
+//! terminal sessions.
+//!
+//! Besides the bundled workflows, a catalog can have a team's own, from a
+//! directory or a git checkout, layered over them with a [`WorkflowStore`].
+//! Every workflow is served with the name of the layer it came from, as its
+//! `source`.
+//!
+//! With a [`UsageLog`], a local file, the catalog records each workflow
+//! [`run`], without the values of secret arguments, ranks the workflows used
+//! most, and most lately, first in search results, and sums up their runs.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use nextshell::Filter;
+//! use nextshell::workflows::{Catalog, Layer, Source, UsageLog, WorkflowStore};
+//!
+//! let store = WorkflowStore::new()
+//!     .with_layer(Layer::bundled())
+//!     .with_layer(Layer::new("team", Source::Git("/srv/team-workflows".into())));
+//! let catalog = Catalog::new(&store).with_usage(UsageLog::new("/srv/usage.jsonl"))?;
+//!
+//! // GET /workflows/search?q=rebsae&tags=git&shell=fish&page=2
+//! //                       -> matching workflows, in brief, a page at a time
+//! // GET /workflows/undo_most_recent_git_commit -> one workflow, in full,
+//! //                                               the team's if it has one
+//! // GET /workflows/bundled:undo_most_recent_git_commit -> the bundled one
+//! // GET /workflows/export/bash?tags=git -> the git workflows, as a file of
+//! //                                        bash functions to source
+//! // GET /workflows/usage?since=1700000000 -> runs of each workflow, with a
+//! //                                          usage log
+//! let routes = nextshell::path("workflows").and(nextshell::workflows::api(Arc::new(catalog)));
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::sync::Arc;
+
+use headers::{ContentType, HeaderMapExt};
+use http::header::{HeaderValue, CONTENT_DISPOSITION};
+use http::StatusCode;
+use hyper::Body;
+use serde::{Deserialize, Serialize};
+
+use crate::filter::{Filter, FilterClone, One};
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+
+mod catalog;
+#[cfg(feature = "terminal")]
+mod run;
+
+pub use self::catalog::{Catalog, Entry};
+#[cfg(feature = "terminal")]
+pub use self::run::{run, AllowList};
+pub use nextshell_workflows::search::Query;
+pub use nextshell_workflows::usage::{UsageLog, UsageRecord, UsageStats};
+pub use nextshell_workflows::{Layer, Shell, Source, Workflow, WorkflowStore};
+
+/// How many workflows a page of search results has, unless asked for
+/// another number.
+const PER_PAGE: usize = 20;
+
+/// The most workflows a page of search results may have.
+const MAX_PER_PAGE: usize = 100;
+
+/// Creates a `Filter` serving every workflow API route.
+///
+/// Combines [`search`], [`export`], [`usage`] and [`get`].
+pub fn api(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    search(catalog.clone())
+        .or(export(catalog.clone()))
+        .unify()
+        .or(usage(catalog.clone()))
+        .unify()
+        .or(get(catalog))
+        .unify()
+}
+
+/// Creates a `Filter` that searches workflows, at `GET /search`.
+///
+/// The query parameters are:
+///
+/// - `q`: the words to look for, in the name, tags, description or command,
+///   forgiving typos. Without it, every workflow is found, ordered by name,
+///   or with a usage log, the most used first.
+/// - `tags`: tags the workflows must all have, separated by commas.
+/// - `shell`: `bash`, `zsh` or `fish`, a shell the workflows must run in.
+/// - `page` and `per_page`: which page of results to reply with, counting
+///   from 1, and how many workflows it has, 20 unless asked for up to 100.
+///
+/// The reply has the `total` number of workflows found, the `page` and
+/// `per_page`, and the page's `results`, best matches first, as ranked by
+/// [`Catalog::search`]. An unknown shell or a page of 0 is a
+/// `400 Bad Request`.
+pub fn search(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path("search"))
+        .and(crate::path::end())
+        .and(crate::query::<SearchQuery>())
+        .map(move |query: SearchQuery| {
+            let page = query.page.unwrap_or(1);
+            let per_page = query.per_page.unwrap_or(PER_PAGE);
+            if page == 0 || per_page == 0 {
+                return bad_request("`page` and `per_page` count from 1".to_owned());
+            }
+            let per_page = per_page.min(MAX_PER_PAGE);
+
+            let found = catalog.search(&Query {
+                text: query.q,
+                tags: list(&query.tags),
+                shell: query.shell,
+            });
+            let results = found
+                .iter()
+                .skip((page - 1).saturating_mul(per_page))
+                .take(per_page)
+                .map(|&(entry, score)| Summary::new(entry, score))
+                .collect::<Vec<_>>();
+            crate::reply::json(&SearchPage {
+                total: found.len(),
+                page,
+                per_page,
+                results,
+            })
+            .into_response()
+        })
+}
+
+/// Creates a `Filter` that exports workflows as shell functions, at
+/// `GET /export/<shell>`, for `bash`, `zsh` or `fish`.
+///
+/// Every workflow that runs in the shell is exported, or only those given by
+/// the query parameters:
+///
+/// - `slugs`: the workflows to export, separated by commas.
+/// - `q` and `tags`: the workflows found by these, as for [`search`].
+///
+/// For bash and zsh, the reply is a file to source, `workflows.bash` or
+/// `workflows.zsh`. For fish, it's a tar archive of a `functions` directory,
+/// with a file per function to copy into `~/.config/fish/functions`. Either
+/// way, it's an attachment, for browsers to save. Each function is named after
+/// its workflow's slug, and takes the workflow's arguments as positional
+/// parameters; see [`nextshell_workflows::export`]. Runbooks of several
+/// steps, or of a step to confirm, aren't exported.
+///
+/// An unknown shell or slug is a `400 Bad Request`, as is, for fish, a slug
+/// too long to be a file name in the tar archive.
+pub fn export(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path("export"))
+        .and(crate::path::param::<String>())
+        .and(crate::path::end())
+        .and(crate::query::<ExportQuery>())
+        .map(move |shell: String, query: ExportQuery| {
+            let shell = match &*shell {
+                "bash" => Shell::Bash,
+                "zsh" => Shell::Zsh,
+                "fish" => Shell::Fish,
+                _ => return bad_request(format!("unknown shell `{}`", shell)),
+            };
+
+            let mut entries = Vec::new();
+            for slug in list(&query.slugs) {
+                match catalog.get(&slug) {
+                    Some(entry) => entries.push(entry),
+                    None => return bad_request(format!("no workflow `{}`", slug)),
+                }
+            }
+            if entries.is_empty() {
+                let found = catalog.search(&Query {
+                    text: query.q,
+                    tags: list(&query.tags),
+                    shell: Some(shell.clone()),
+                });
+                entries.extend(found.into_iter().map(|(entry, _)| entry));
+            }
+
+            let mut exported = nextshell_workflows::export::export(
+                &shell,
+                entries.iter().map(|entry| (&*entry.slug, &entry.runbook)),
+            );
+            let (body, content_type, filename) = match shell {
+                Shell::Bash | Shell::Zsh => {
+                    // Always a single file, even with no functions in it.
+                    let file = exported.files.remove(0);
+                    (
+                        Body::from(file.contents),
+                        ContentType::text_utf8(),
+                        file.path,
+                    )
+                }
+                Shell::Fish => match exported.to_tar() {
+                    Ok(tar) => (
+                        Body::from(tar),
+                        ContentType::from("application/x-tar".parse::<mime::Mime>().unwrap()),
+                        "workflows-fish.tar".to_owned(),
+                    ),
+                    Err(err) => return bad_request(err.to_string()),
+                },
+            };
+            let mut res = Response::new(body);
+            res.headers_mut().typed_insert(content_type);
+            if let Ok(disposition) =
+                HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename))
+            {
+                res.headers_mut().insert(CONTENT_DISPOSITION, disposition);
+            }
+            res
+        })
+}
+
+/// Creates a `Filter` that sums up how much workflows were used, at
+/// `GET /usage`, from the catalog's usage log.
+///
+/// The reply is a list of the workflows run, the most run first, each with
+/// its `slug`, its number of `runs`, of `failures`, the time of its
+/// `last_run`, and its runs in each of the `shells`. Times are in seconds
+/// since the Unix epoch. A `since` query parameter, such a time, counts only
+/// the runs since then. A catalog without a usage log has no runs.
+pub fn usage(
+    catalog: Arc<Catalog>,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path("usage"))
+        .and(crate::path::end())
+        .and(crate::query::<UsageQuery>())
+        .map(move |query: UsageQuery| match catalog.usage(query.since) {
+            Ok(stats) => crate::reply::json(&stats).into_response(),
+            Err(err) => {
+                tracing::warn!("workflows: couldn't read the usage log: {}", err);
+                text_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "couldn't read the usage log".to_owned(),
+                )
+            }
+        })
+}
+
+/// Creates a `Filter` that fetches one workflow in full, at `GET /<slug>`.
+///
+/// The slug may be qualified by the workflow's layer, as `<source>:<slug>`,
+/// to fetch a workflow another layer shadows. The reply has the workflow's
+/// `source`, its `qualified_slug`, and the qualified slugs of any workflows
+/// it `shadows`.
+///
+/// Rejects with "not found" if there's no workflow with that slug.
+pub fn get(catalog: Arc<Catalog>) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::get()
+        .and(crate::path::param::<String>())
+        .and(crate::path::end())
+        .and_then(move |slug: String| {
+            let entry = catalog.get(&slug).cloned();
+            async move {
+                match entry {
+                    Some(entry) => Ok(crate::reply::json(&entry).into_response()),
+                    None => {
+                        tracing::debug!("workflows: no workflow {:?}", slug);
+                        Err(reject::not_found())
+                    }
+                }
+            }
+        })
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    tags: String,
+    shell: Option<Shell>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct UsageQuery {
+    #[serde(default)]
+    since: u64,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    slugs: String,
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    tags: String,
+}
+
+/// A page of search results.
+#[derive(Serialize)]
+struct SearchPage<'a> {
+    total: usize,
+    page: usize,
+    per_page: usize,
+    results: Vec<Summary<'a>>,
+}
+
+/// A workflow in brief.
+#[derive(Serialize)]
+struct Summary<'a> {
+    slug: &'a str,
+    source: &'a str,
+    name: &'a str,
+    description: Option<&'a str>,
+    tags: &'a [String],
+    shells: &'a [Shell],
+    score: u32,
+}
+
+impl<'a> Summary<'a> {
+    fn new(entry: &'a Entry, score: u32) -> Summary<'a> {
+        Summary {
+            slug: &entry.slug,
+            source: &entry.source,
+            name: &entry.workflow.name,
+            description: entry.workflow.description.as_deref(),
+            tags: &entry.workflow.tags,
+            shells: &entry.workflow.shells,
+            score,
+        }
+    }
+}
+
+/// The items of a comma-separated list, without the spaces around them.
+fn list(items: &str) -> Vec<String> {
+    items
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn bad_request(message: String) -> Response {
+    text_response(StatusCode::BAD_REQUEST, message)
+}
+
+fn text_response(status: StatusCode, message: String) -> Response {
+    let mut res = Response::new(Body::from(message + "\n"));
+    *res.status_mut() = status;
+    res.headers_mut().typed_insert(ContentType::text_utf8());
+    res
+}
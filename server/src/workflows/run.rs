@@ -0,0 +1,519 @@
+//! Running workflows in terminal sessions.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use futures_channel::mpsc;
+use futures_util::{future, SinkExt, Stream, StreamExt};
+use http::StatusCode;
+use nextshell_workflows::{OnFailure, Step};
+use serde::{Deserialize, Serialize};
+
+use super::{bad_request, text_response, Catalog, Shell, UsageRecord};
+use crate::filter::{Filter, FilterClone, One};
+use crate::filters::sse;
+use crate::reject::{self, Rejection};
+use crate::reply::{Reply, Response};
+use crate::terminal::{Command, Registry, Run, RunEvent};
+
+/// How much output is held for a client that's slow to read, before the
+/// oldest of it is dropped.
+const HELD_OUTPUT: usize = 64 * 1024;
+
+/// The exit code a run is recorded with when one of its steps couldn't be
+/// started, as a shell exits with when it can't run a command.
+const NOT_STARTED: i32 = 127;
+
+/// The workflows that may be run, by qualified slug, like `bundled:deploy`.
+///
+/// Running a workflow runs a command on the server, so none may be run unless
+/// listed here. A workflow is looked up in the list by the qualified slug of
+/// the workflow its slug resolves to, so `deploy` and `bundled:deploy` are
+/// allowed alike while `deploy` resolves to the bundled one, and a layer that
+/// shadows `deploy` doesn't change what's run until its own `deploy` is
+/// listed too.
+#[derive(Clone, Debug, Default)]
+pub struct AllowList {
+    slugs: HashSet<String>,
+}
+
+impl AllowList {
+    /// Creates a list allowing the workflows with these qualified slugs.
+    pub fn new<I>(slugs: I) -> AllowList
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        AllowList {
+            slugs: slugs.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns true if the workflow with this qualified slug may be run.
+    pub fn allows(&self, slug: &str) -> bool {
+        self.slugs.contains(slug)
+    }
+}
+
+/// Creates a `Filter` that runs a workflow, at `POST /<slug>/run`, and
+/// streams what it does as server-sent events.
+///
+/// The request body is JSON, with the `values` of the workflow's arguments,
+/// by name, and optionally the id of a live `session` of `registry` to type
+/// the command into. Without one, the command is run by `shell` in a new
+/// session; see [`Registry::run`]. Either way, the command is rendered for
+/// the session's shell, and arguments left out take their defaults.
+///
+/// For a single command, the stream starts with a `session` event,
+/// `{"session":"<id>"}`, naming the session, which clients can attach to to
+/// watch. Then come `output` events, whose data is a JSON string of what the
+/// terminal printed, and a final `exit` event, `{"code":0,"signal":null}`. A
+/// client too slow to keep up may miss some of the output, but no other
+/// event. When the run exits, it's
+/// [recorded](Catalog::record) in the catalog's usage log, if it has one,
+/// even if the client stopped listening before then. It's recorded under the
+/// slug the workflow resolves to, so a run by its qualified slug counts as
+/// one by its plain slug, unless it's shadowed.
+///
+/// A runbook of several steps runs them one at a time, in order, each once
+/// the one before has exited, and each in a new session of its own unless
+/// typed into a live one. Each step starts with a `step` event,
+/// `{"step":0,"description":"Check it's stuck"}`, counting from 0, followed
+/// by a `session` event if its session is a new one, and ends with its own
+/// `exit` event. A step that fails, exiting with a non-zero status, is the
+/// last run, unless its [`on_failure`](nextshell_workflows::Step::on_failure)
+/// is to continue. The run is recorded with the exit code of the last step
+/// run.
+///
+/// A step that couldn't be started ends the stream with an `aborted` event,
+/// `{"step":1,"reason":"couldn't start the step"}`, rather than an `exit`,
+/// and the run is recorded as failed, with an exit code of 127. So does a
+/// step whose session is killed before it exits, with the reason
+/// `"the session was killed"`, but the run is recorded without an exit code,
+/// as if killed by a signal.
+///
+/// A workflow whose qualified slug isn't in `allowed` is a `403 Forbidden`,
+/// and one without that slug is rejected as not found. Missing or invalid
+/// values, a workflow that doesn't run in the session's shell, or one with a
+/// step to confirm before it's run, which can't be asked here, are a
+/// `400 Bad Request`. A session that isn't live is a `404 Not Found`, and one
+/// still running another command a `409 Conflict`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use nextshell::Filter;
+/// use nextshell::workflows::{AllowList, Catalog};
+///
+/// let catalog = Arc::new(Catalog::bundled());
+/// let allowed = Arc::new(AllowList::new(["bundled:undo_most_recent_git_commit"]));
+/// let registry = nextshell::terminal::Registry::new().blocks(true);
+///
+/// // POST /workflows/undo_most_recent_git_commit/run {"values":{}}
+/// let route = nextshell::path("workflows").and(nextshell::workflows::run(
+///     catalog,
+///     allowed,
+///     registry,
+///     nextshell::terminal::shell(),
+/// ));
+/// ```
+pub fn run(
+    catalog: Arc<Catalog>,
+    allowed: Arc<AllowList>,
+    registry: Registry,
+    shell: Command,
+) -> impl FilterClone<Extract = One<Response>, Error = Rejection> {
+    crate::post()
+        .and(crate::path::param::<String>())
+        .and(crate::path("run"))
+        .and(crate::path::end())
+        .and(crate::body::json::<RunRequest>())
+        .and_then(move |slug: String, request: RunRequest| {
+            let catalog = catalog.clone();
+            let allowed = allowed.clone();
+            let registry = registry.clone();
+            let shell = shell.clone();
+            async move {
+                let entry = match catalog.get(&slug) {
+                    Some(entry) => entry,
+                    None => {
+                        tracing::debug!("workflows: no workflow {:?} to run", slug);
+                        return Err(reject::not_found());
+                    }
+                };
+                if !allowed.allows(&entry.qualified_slug) {
+                    tracing::debug!("workflows: {:?} isn't allowed to run", slug);
+                    return Ok(text_response(
+                        StatusCode::FORBIDDEN,
+                        format!("workflow `{}` isn't allowed to run", slug),
+                    ));
+                }
+
+                let program = match request.session {
+                    Some(ref id) => match registry.program(id) {
+                        Some(program) => program,
+                        None => return Ok(no_session()),
+                    },
+                    None => shell.program().to_owned(),
+                };
+                let target = shell_of(&program);
+                let runbook = &entry.runbook;
+                if !runbook.shells.is_empty() && !runbook.shells.contains(&target) {
+                    return Ok(bad_request(format!(
+                        "workflow `{}` doesn't run in {:?}",
+                        slug, program
+                    )));
+                }
+                if runbook.steps.iter().any(|step| step.confirm) {
+                    return Ok(bad_request(format!(
+                        "workflow `{}` has a step to confirm before it's run",
+                        slug
+                    )));
+                }
+                // By the slug the workflow resolves to, as search ranks it,
+                // whichever slug it was run by.
+                let record = UsageRecord::new(
+                    entry.slug.clone(),
+                    &entry.workflow,
+                    &request.values,
+                    target.clone(),
+                    None,
+                );
+                let steps = match runbook.render(&target, &request.values) {
+                    Ok(steps) if !steps.is_empty() => steps,
+                    Ok(_) => return Ok(bad_request(format!("workflow `{}` has no steps", slug))),
+                    Err(errors) => {
+                        let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                        return Ok(bad_request(errors.join("; ")));
+                    }
+                };
+
+                match registry
+                    .run(request.session.as_deref(), &shell, &steps[0].command)
+                    .await
+                {
+                    Ok(run) => {
+                        tracing::debug!("workflows: running {:?} in {}", slug, run.session());
+                        let steps = Steps {
+                            steps,
+                            registry,
+                            shell,
+                            session: request.session,
+                        };
+                        Ok(sse::reply(events(run, steps, catalog.clone(), record)).into_response())
+                    }
+                    Err(err) => {
+                        tracing::debug!("workflows: couldn't run {:?}: {}", slug, err);
+                        Ok(match err.kind() {
+                            io::ErrorKind::NotFound => no_session(),
+                            io::ErrorKind::WouldBlock => {
+                                text_response(StatusCode::CONFLICT, err.to_string())
+                            }
+                            _ => text_response(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                "couldn't start the workflow".to_owned(),
+                            ),
+                        })
+                    }
+                }
+            }
+        })
+}
+
+#[derive(Deserialize)]
+struct RunRequest {
+    #[serde(default)]
+    values: HashMap<String, String>,
+    session: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SessionEvent<'a> {
+    session: &'a str,
+}
+
+#[derive(Serialize)]
+struct StepEvent<'a> {
+    step: usize,
+    description: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct AbortedEvent<'a> {
+    step: usize,
+    reason: &'a str,
+}
+
+/// A workflow's steps, rendered, and where to run them.
+struct Steps {
+    steps: Vec<Step>,
+    registry: Registry,
+    shell: Command,
+    /// The live session to type them into, if any.
+    session: Option<String>,
+}
+
+/// Something a run of a workflow's steps did.
+enum Event {
+    Step(usize, Option<String>),
+    Session(String),
+    Run(RunEvent),
+    /// The steps couldn't go on, at this one.
+    Aborted(usize, &'static str),
+}
+
+fn no_session() -> Response {
+    text_response(
+        StatusCode::NOT_FOUND,
+        "no such session, or it has exited".to_owned(),
+    )
+}
+
+/// The shell a program is, going by its name, or bash for any other shell.
+fn shell_of(program: &OsStr) -> Shell {
+    match Path::new(program).file_name().and_then(OsStr::to_str) {
+        Some("zsh") => Shell::Zsh,
+        Some("fish") => Shell::Fish,
+        _ => Shell::Bash,
+    }
+}
+
+fn events(
+    run: Run,
+    steps: Steps,
+    catalog: Arc<Catalog>,
+    record: UsageRecord,
+) -> impl Stream<Item = Result<sse::Event, serde_json::Error>> + Send {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::task::spawn(follow(run, steps, catalog, record, tx));
+    let mut text = Utf8::default();
+    rx.map(move |event| match event {
+        Event::Step(step, description) => {
+            sse::Event::default().event("step").json_data(StepEvent {
+                step,
+                description: description.as_deref(),
+            })
+        }
+        Event::Session(session) => sse::Event::default()
+            .event("session")
+            .json_data(SessionEvent { session: &session }),
+        Event::Run(RunEvent::Output(bytes)) => sse::Event::default()
+            .event("output")
+            .json_data(text.decode(&bytes)),
+        Event::Run(RunEvent::Exit(status)) => sse::Event::default().event("exit").json_data(status),
+        Event::Aborted(step, reason) => sse::Event::default()
+            .event("aborted")
+            .json_data(AbortedEvent { step, reason }),
+    })
+}
+
+/// Follows a run of a workflow's steps until the last to run exits, sending
+/// what they do to `events` for as long as they're listened to, and records
+/// the run once it's over.
+///
+/// The run is followed apart from the response streaming it, so that it's
+/// recorded even if the client goes away first, and so that its steps go on
+/// running. Nor does it wait on a client that's slow to read: what the client
+/// hasn't room for waits in an [`Outbox`].
+async fn follow(
+    mut run: Run,
+    steps: Steps,
+    catalog: Arc<Catalog>,
+    record: UsageRecord,
+    events: mpsc::Sender<Event>,
+) {
+    let mut outbox = Outbox::new(events);
+    let mut announced: Option<String> = None;
+    for (i, step) in steps.steps.iter().enumerate() {
+        if steps.steps.len() > 1 {
+            outbox.send(Event::Step(i, step.description.clone()));
+        }
+        if announced.as_deref() != Some(run.session()) {
+            announced = Some(run.session().to_owned());
+            outbox.send(Event::Session(run.session().to_owned()));
+        }
+
+        let exit = loop {
+            let event = if outbox.is_empty() {
+                run.next().await
+            } else {
+                // Waiting on the run, send what's held as soon as there's
+                // room for it.
+                let next = run.next();
+                futures_util::pin_mut!(next);
+                let polled = match future::select(next, outbox.ready()).await {
+                    future::Either::Left((event, _)) => Some(event),
+                    future::Either::Right(_) => None,
+                };
+                match polled {
+                    Some(event) => event,
+                    None => {
+                        outbox.flush();
+                        continue;
+                    }
+                }
+            };
+            match event {
+                Some(RunEvent::Exit(status)) => break Some(status),
+                Some(output) => outbox.send(Event::Run(output)),
+                None => break None,
+            }
+        };
+        let status = match exit {
+            Some(status) => status,
+            None => {
+                // The session was killed, and the run with it.
+                let aborted = Event::Aborted(i, "the session was killed");
+                return finish(outbox, &catalog, record, None, aborted).await;
+            }
+        };
+
+        let go_on = status.code == Some(0) || step.on_failure == Some(OnFailure::Continue);
+        let next = match steps.steps.get(i + 1) {
+            Some(next) if go_on => next,
+            _ => {
+                let code = status.code;
+                let exit = Event::Run(RunEvent::Exit(status));
+                return finish(outbox, &catalog, record, code, exit).await;
+            }
+        };
+        outbox.send(Event::Run(RunEvent::Exit(status)));
+        let started = steps
+            .registry
+            .run(steps.session.as_deref(), &steps.shell, &next.command)
+            .await;
+        run = match started {
+            Ok(next) => next,
+            Err(err) => {
+                tracing::warn!(
+                    "workflows: couldn't run step {} of {:?}: {}",
+                    i + 1,
+                    record.slug,
+                    err
+                );
+                let aborted = Event::Aborted(i + 1, "couldn't start the step");
+                return finish(outbox, &catalog, record, Some(NOT_STARTED), aborted).await;
+            }
+        };
+    }
+}
+
+/// Ends a run of a workflow's steps: records it, with `exit_code`, and sends
+/// the client its `last` event.
+async fn finish(
+    mut outbox: Outbox,
+    catalog: &Arc<Catalog>,
+    mut record: UsageRecord,
+    exit_code: Option<i32>,
+    last: Event,
+) {
+    record.exit_code = exit_code;
+    record_run(catalog, record).await;
+    outbox.send(last);
+    // Nothing's left to follow, so the client can be waited on.
+    outbox.close().await;
+}
+
+/// Events on their way to the client, held while it hasn't room for them,
+/// so that sending them never waits.
+///
+/// Held output is merged, and only the last [`HELD_OUTPUT`] bytes of it kept.
+/// Once the client has gone, there's no one to send to, and nothing is held.
+struct Outbox {
+    events: mpsc::Sender<Event>,
+    held: VecDeque<Event>,
+}
+
+impl Outbox {
+    fn new(events: mpsc::Sender<Event>) -> Outbox {
+        Outbox {
+            events,
+            held: VecDeque::new(),
+        }
+    }
+
+    /// Returns true if no events are held.
+    fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Sends an event, after those held, or holds it too.
+    fn send(&mut self, event: Event) {
+        if let Event::Run(RunEvent::Output(ref bytes)) = event {
+            if let Some(Event::Run(RunEvent::Output(held))) = self.held.back_mut() {
+                held.extend_from_slice(bytes);
+                let dropped = held.len().saturating_sub(HELD_OUTPUT);
+                held.drain(..dropped);
+                self.flush();
+                return;
+            }
+        }
+        self.held.push_back(event);
+        self.flush();
+    }
+
+    /// Sends as many of the events held as the client has room for.
+    fn flush(&mut self) {
+        while let Some(event) = self.held.pop_front() {
+            if let Err(err) = self.events.try_send(event) {
+                if err.is_full() {
+                    self.held.push_front(err.into_inner());
+                } else {
+                    self.held.clear();
+                }
+                return;
+            }
+        }
+    }
+
+    /// Waits until the client has room for an event, or has gone.
+    fn ready(&mut self) -> impl Future<Output = ()> + Unpin + '_ {
+        future::poll_fn(move |cx| self.events.poll_ready(cx).map(|_| ()))
+    }
+
+    /// Sends every event held, waiting on the client for room.
+    async fn close(mut self) {
+        while let Some(event) = self.held.pop_front() {
+            if self.events.send(event).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Records a run in the catalog's usage log.
+async fn record_run(catalog: &Arc<Catalog>, record: UsageRecord) {
+    let catalog = catalog.clone();
+    let slug = record.slug.clone();
+    let recorded = tokio::task::spawn_blocking(move || catalog.record(&record))
+        .await
+        .unwrap_or_else(|err| Err(io::Error::new(io::ErrorKind::Other, err)));
+    if let Err(err) = recorded {
+        tracing::warn!("workflows: couldn't record a run of {:?}: {}", slug, err);
+    }
+}
+
+/// Decodes output as UTF-8, holding back a character split across reads
+/// until the rest of it comes.
+#[derive(Default)]
+struct Utf8 {
+    partial: Vec<u8>,
+}
+
+impl Utf8 {
+    fn decode(&mut self, bytes: &[u8]) -> String {
+        self.partial.extend_from_slice(bytes);
+        let complete = match std::str::from_utf8(&self.partial) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.partial.len(),
+        };
+        let text = String::from_utf8_lossy(&self.partial[..complete]).into_owned();
+        self.partial.drain(..complete);
+        text
+    }
+}
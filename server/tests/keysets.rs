@@ -0,0 +1,632 @@
+#![deny(warnings)]
+
+use std::sync::Arc;
+
+use nextshell::keysets::convert::{self, Format};
+use nextshell::keysets::validate::{self, Severity};
+use nextshell::keysets::{resolve, Catalog, Key, KeyBinding, Keyset, Modifiers, Platform};
+use nextshell::Filter;
+use serde_json::Value;
+
+fn bundled(file: &str) -> String {
+    let path = format!("{}/static/keysets/{}", env!("CARGO_MANIFEST_DIR"), file);
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn catalog() -> Arc<Catalog> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/static/keysets");
+    Arc::new(Catalog::load(dir).expect("bundled keysets load"))
+}
+
+#[test]
+fn bundled_keysets_validate() {
+    let _ = pretty_env_logger::try_init();
+
+    for file in &["default-nextshell-keybindings.yaml", "emacs.yaml"] {
+        let checked = validate::check(&bundled(file), None);
+        for diagnostic in &checked.diagnostics {
+            println!("{}:{}", file, diagnostic);
+        }
+        assert!(!checked.has_errors(), "{}", file);
+    }
+}
+
+#[test]
+fn parse() {
+    let binding: KeyBinding = "ctrl-shift-up".parse().unwrap();
+    assert_eq!(
+        binding,
+        KeyBinding {
+            modifiers: Modifiers {
+                ctrl: true,
+                shift: true,
+                ..Modifiers::default()
+            },
+            key: Key::Up,
+        }
+    );
+
+    let fold: KeyBinding = "alt-cmd-[".parse().unwrap();
+    assert!(fold.modifiers.alt && fold.modifiers.cmd);
+    assert_eq!(fold.key, Key::Char('['));
+
+    // The same chord, however it's written.
+    let a: KeyBinding = "cmd-shift-A".parse().unwrap();
+    let b: KeyBinding = "shift-cmd-A".parse().unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a.to_string(), "shift-cmd-A");
+
+    for s in &[
+        "a",
+        "-",
+        "cmd--",
+        "ctrl-`",
+        "alt-shift-cmd-C",
+        "f1",
+        "meta-f20",
+        "numpadenter",
+        "ctrl-alt-shift-meta-cmd-pagedown",
+    ] {
+        let binding: KeyBinding = s.parse().unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(binding.to_string(), *s);
+    }
+    assert_eq!("f12".parse::<KeyBinding>().unwrap().key, Key::F(12));
+    assert_eq!("shift-f".parse::<KeyBinding>().unwrap().key, Key::Char('f'));
+
+    let err = |s: &str| s.parse::<KeyBinding>().unwrap_err().to_string();
+    assert_eq!(
+        err("super-a"),
+        "invalid key binding \"super-a\": unknown modifier `super`"
+    );
+    assert_eq!(
+        err("ctrl-esc"),
+        "invalid key binding \"ctrl-esc\": unknown key `esc`"
+    );
+    assert_eq!(
+        err("ctrl-ctrl-a"),
+        "invalid key binding \"ctrl-ctrl-a\": `ctrl` appears twice"
+    );
+    assert_eq!(err("cmd-"), "invalid key binding \"cmd-\": missing a key");
+    assert_eq!(err(""), "invalid key binding \"\": missing a key");
+    assert!("-a".parse::<KeyBinding>().is_err());
+    assert!("ctrl--a".parse::<KeyBinding>().is_err());
+    assert!("f21".parse::<KeyBinding>().is_err());
+    assert!("f01".parse::<KeyBinding>().is_err());
+    assert!("shift-meta-&gt;".parse::<KeyBinding>().is_err());
+    assert!("ctrl- ".parse::<KeyBinding>().is_err());
+}
+
+#[test]
+fn conflicts() {
+    let keyset: Keyset = serde_yaml::from_str(
+        r#"
+"editor_view:select_all": cmd-a
+"terminal:select_all_blocks": cmd-a
+"workspace:show_settings_modal": cmd-,
+"workspace:show_settings_account_page": cmd-,
+"workspace:toggle_command_palette": cmd-p
+"#,
+    )
+    .unwrap();
+
+    // Different contexts may share a chord.
+    let conflicts = keyset.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].binding.to_string(), "cmd-,");
+    assert_eq!(
+        conflicts[0].actions,
+        [
+            "workspace:show_settings_account_page",
+            "workspace:show_settings_modal"
+        ]
+    );
+
+    let yaml = serde_yaml::to_string(&keyset).unwrap();
+    assert_eq!(serde_yaml::from_str::<Keyset>(&yaml).unwrap(), keyset);
+    assert!(serde_yaml::from_str::<Keyset>("\"terminal:copy\": hyper-c").is_err());
+}
+
+#[test]
+fn validate_errors() {
+    let text = "\
+---
+# Bindings
+\"terminal:copy\": cmd-c
+\"terminal:paste\": ctrl-super-v
+terminal:find: [cmd, f]
+\"terminal:focus_input\": ctrl-l
+\"terminal:clear\": ctrl-l
+";
+    let checked = validate::check(text, None);
+    assert!(checked.keyset.is_none());
+    let found = checked
+        .diagnostics
+        .iter()
+        .map(|d| (d.line, d.severity, &*d.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (
+                4,
+                Severity::Error,
+                "`terminal:paste`: invalid key binding \"ctrl-super-v\": unknown modifier `super`"
+            ),
+            (
+                5,
+                Severity::Error,
+                "`terminal:find`: expected a key binding"
+            ),
+            (
+                7,
+                Severity::Warning,
+                "`terminal:clear` is bound to `ctrl-l`, as is `terminal:focus_input` on line 6; \
+                 only one of them can be used"
+            ),
+        ]
+    );
+
+    let checked = validate::check("- cmd-c\n", None);
+    assert_eq!(
+        checked.diagnostics[0].message,
+        "expected a map of action names to key bindings"
+    );
+    let checked = validate::check("\"terminal:copy\": [\n", None);
+    assert!(checked.diagnostics[0].message.starts_with("invalid YAML"));
+
+    let checked = validate::check("# Nothing yet\n", None);
+    assert_eq!(checked.keyset, Some(Keyset::new()));
+}
+
+#[test]
+fn validate_style() {
+    let text = "\
+\"editor:select_to_line_end\": ctrl-shift-e
+\"editor:select_to_line_start\": ctrl-A
+\"editor:select_all\": ctrl-shift-1
+CopyAll: cmd-c
+";
+    let checked = validate::check(text, None);
+    assert!(!checked.has_errors());
+    assert_eq!(checked.keyset.unwrap().len(), 4);
+    let found = checked
+        .diagnostics
+        .iter()
+        .map(|d| (d.line, &*d.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (
+                1,
+                "`editor:select_to_line_end`: keys are written as typed, so `ctrl-shift-e` should be `ctrl-shift-E`"
+            ),
+            (
+                2,
+                "`editor:select_to_line_start`: keys are written as typed, so `ctrl-A` should be `ctrl-shift-A`"
+            ),
+            (4, "`CopyAll`: action names look like `context:action_name`"),
+        ]
+    );
+}
+
+#[test]
+fn validate_platform() {
+    let default = bundled("default-nextshell-keybindings.yaml");
+    assert!(!validate::check(&default, Some(Platform::Mac)).has_errors());
+
+    let linux = validate::check(&default, Some(Platform::Linux));
+    assert!(linux.keyset.is_none());
+    let cmd = linux
+        .diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .collect::<Vec<_>>();
+    assert!(cmd.len() > 50, "{}", cmd.len());
+    assert!(cmd
+        .iter()
+        .all(|d| d.message.ends_with("uses `cmd`, which linux doesn't have")));
+    assert_eq!(
+        cmd[0].to_string(),
+        "13: error: `editor_view:clear_lines`: `shift-cmd-K` uses `cmd`, which linux doesn't have"
+    );
+
+    // Emacs bindings work anywhere.
+    for platform in &["linux", "windows", "mac"] {
+        let platform = platform.parse::<Platform>().unwrap();
+        assert!(!validate::check(&bundled("emacs.yaml"), Some(platform)).has_errors());
+    }
+    assert!("beos".parse::<Platform>().is_err());
+}
+
+#[test]
+fn unbinding() {
+    let keyset: Keyset = serde_yaml::from_str(
+        r#"
+"terminal:copy": cmd-c
+"terminal:find": none
+"terminal:paste":
+"#,
+    )
+    .unwrap();
+    assert_eq!(keyset.len(), 3);
+    assert_eq!(keyset.iter().count(), 1);
+    assert!(keyset.get("terminal:find").is_none());
+    assert!(keyset.unbinds("terminal:find"));
+    assert!(!keyset.unbinds("terminal:copy"));
+    assert!(!keyset.unbinds("terminal:focus_input"));
+    assert_eq!(
+        keyset.unbound().collect::<Vec<_>>(),
+        ["terminal:find", "terminal:paste"]
+    );
+
+    // Unbinding survives a round trip, written as null.
+    let yaml = serde_yaml::to_string(&keyset).unwrap();
+    assert_eq!(serde_yaml::from_str::<Keyset>(&yaml).unwrap(), keyset);
+
+    let checked = validate::check(
+        "\"terminal:copy\": cmd-c\n\"terminal:find\": none\n\"terminal:paste\": ~\n",
+        None,
+    );
+    assert!(checked.diagnostics.is_empty(), "{:?}", checked.diagnostics);
+    assert_eq!(checked.keyset, Some(keyset));
+}
+
+#[test]
+fn resolve_layers() {
+    let _ = pretty_env_logger::try_init();
+
+    let catalog = catalog();
+    assert_eq!(
+        catalog.iter().map(|entry| &*entry.name).collect::<Vec<_>>(),
+        ["default-nextshell-keybindings", "emacs"]
+    );
+    let default = &catalog.get("default-nextshell-keybindings").unwrap().keyset;
+    let emacs = &catalog.get("emacs").unwrap().keyset;
+    let user: Keyset = serde_yaml::from_str(
+        r#"
+"editor_view:cut_word_right": alt-d
+"terminal:find": none
+"terminal:copy": ctrl-shift-C
+"#,
+    )
+    .unwrap();
+
+    let resolved = resolve::resolve(vec![
+        ("default", default),
+        ("emacs", emacs),
+        ("user", &user),
+    ]);
+    assert_eq!(resolved.layers, ["default", "emacs", "user"]);
+    assert_eq!(resolved.bindings.len(), default.len());
+
+    let paste = &resolved.bindings["terminal:paste"];
+    assert_eq!(paste.binding.unwrap().to_string(), "cmd-v");
+    assert_eq!(paste.layer, "default");
+    assert!(paste.overrides.is_empty());
+
+    // Bound by every layer: the topmost wins, and the others are listed
+    // topmost first.
+    let cut = &resolved.bindings["editor_view:cut_word_right"];
+    assert_eq!(cut.binding.unwrap().to_string(), "alt-d");
+    assert_eq!(cut.layer, "user");
+    assert_eq!(
+        cut.overrides
+            .iter()
+            .map(|o| (&*o.layer, o.binding.unwrap().to_string()))
+            .collect::<Vec<_>>(),
+        [
+            ("emacs", "meta-d".to_owned()),
+            ("default", "meta-d".to_owned())
+        ]
+    );
+
+    let find = &resolved.bindings["terminal:find"];
+    assert_eq!(find.binding, None);
+    assert_eq!(find.layer, "user");
+    assert_eq!(find.overrides[0].binding.unwrap().to_string(), "cmd-f");
+
+    let keyset = resolved.keyset();
+    assert_eq!(keyset.len(), default.len() - 1);
+    assert!(keyset.get("terminal:find").is_none());
+    assert_eq!(keyset.conflicts(), resolved.conflicts);
+    assert_eq!(resolved.conflicts, default.conflicts());
+
+    // A lower layer can't bring back what a higher one unbinds, but a
+    // higher one can bind it again.
+    let unbind: Keyset = serde_yaml::from_str("\"terminal:find\": none").unwrap();
+    let rebind: Keyset = serde_yaml::from_str("\"terminal:find\": ctrl-f").unwrap();
+    let resolved = resolve::resolve(vec![("a", &rebind), ("b", &unbind)]);
+    assert_eq!(resolved.bindings["terminal:find"].binding, None);
+    let resolved = resolve::resolve(vec![("a", &unbind), ("b", &rebind)]);
+    let find = &resolved.bindings["terminal:find"];
+    assert_eq!(find.binding.unwrap().to_string(), "ctrl-f");
+    assert_eq!(find.overrides[0].binding, None);
+}
+
+#[tokio::test]
+async fn routes() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = nextshell::path("keysets").and(nextshell::keysets::api(catalog()));
+
+    let res = nextshell::test::request()
+        .path("/keysets")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body[1]["name"], "emacs");
+    assert_eq!(body[1]["file"], "emacs.yaml");
+    assert!(body[1]["actions"].as_u64().unwrap() > 0);
+
+    let res = nextshell::test::request()
+        .path("/keysets/emacs")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body["keyset"]["editor_view:cut_word_right"], "meta-d");
+
+    let res = nextshell::test::request()
+        .path("/keysets/vim")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = nextshell::test::request()
+        .path("/keysets/resolve?layers=default-nextshell-keybindings,emacs")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        body["layers"],
+        serde_json::json!(["default-nextshell-keybindings", "emacs"])
+    );
+    assert_eq!(
+        body["bindings"]["editor_view:cut_word_right"],
+        serde_json::json!({
+            "binding": "meta-d",
+            "layer": "emacs",
+            "overrides": [{ "layer": "default-nextshell-keybindings", "binding": "meta-d" }],
+        })
+    );
+    assert_eq!(
+        body["bindings"]["terminal:paste"],
+        serde_json::json!({ "binding": "cmd-v", "layer": "default-nextshell-keybindings" })
+    );
+    assert_eq!(body["conflicts"].as_array().unwrap().len(), 2);
+    assert_eq!(body["conflicts"][0]["binding"], "cmd-,");
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/keysets/resolve?layers=default-nextshell-keybindings")
+        .body("\"terminal:paste\": none\n\"terminal:copy\": ctrl-shift-C\n")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    let body: Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        body["layers"],
+        serde_json::json!(["default-nextshell-keybindings", "user"])
+    );
+    assert_eq!(body["bindings"]["terminal:paste"]["binding"], Value::Null);
+    assert_eq!(body["bindings"]["terminal:copy"]["layer"], "user");
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/keysets/resolve?layers=default-nextshell-keybindings")
+        .body("\"terminal:copy\": cmd-c\n\"terminal:paste\": hyper-v\n")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(
+        res.body(),
+        "line 2: `terminal:paste`: invalid key binding \"hyper-v\": unknown modifier `hyper`\n"
+    );
+
+    let res = nextshell::test::request()
+        .path("/keysets/resolve?layers=default-nextshell-keybindings,vim")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "unknown keyset `vim`\n");
+}
+
+#[test]
+fn convert_vscode() {
+    let text = r#"// Place your key bindings in this file to override the defaults
+[
+    {
+        "key": "ctrl+shift+k",
+        "command": "editor.action.deleteLines",
+        "when": "textInputFocus && !editorReadonly"
+    },
+    { "key": "alt+left", "command": "cursorWordLeft" },
+    /* two keys for one command */
+    { "key": "ctrl+left", "command": "cursorWordLeft" },
+    { "key": "ctrl+shift+]", "command": "editor.unfold" },
+    { "key": "cmd+=", "command": "workbench.action.zoomIn" },
+    { "key": "ctrl+k ctrl+c", "command": "editor.fold" },
+    { "key": "ctrl+space", "command": "editor.action.triggerSuggest" },
+    { "key": "ctrl+space", "command": "editor.action.selectAll" },
+    { "key": "ctrl+d", "command": "-deleteRight" },
+    { "key": "hyper+x", "command": "cursorUp" },
+    { "command": "cursorDown" },
+]
+"#;
+    let imported = convert::import(Format::VsCode, text).unwrap();
+    let keyset = &imported.keyset;
+    let get = |action: &str| keyset.get(action).map(|binding| binding.to_string());
+    assert_eq!(get("editor_view:clear_lines").unwrap(), "ctrl-shift-K");
+    assert_eq!(
+        get("editor_view:move_backward_one_word").unwrap(),
+        "ctrl-left"
+    );
+    assert_eq!(get("editor_view:unfold").unwrap(), "ctrl-shift-}");
+    assert_eq!(get("workspace:increase_font_size").unwrap(), "cmd-=");
+    assert!(keyset.unbinds("editor_view:delete"));
+    assert_eq!(keyset.len(), 5);
+
+    let unmapped = imported
+        .unmapped
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unmapped,
+        [
+            "line 8: alt+left: cursorWordLeft: `editor_view:move_backward_one_word` is bound again on line 10",
+            "line 13: ctrl+k ctrl+c: editor.fold: sequences of several keys aren't supported",
+            "line 14: ctrl+space: editor.action.triggerSuggest: no nextshell action for `editor.action.triggerSuggest`",
+            "line 15: ctrl+space: editor.action.selectAll: nextshell can't bind `space`",
+            "line 17: hyper+x: cursorUp: unknown modifier `hyper`",
+            "line 18: {\"command\":\"cursorDown\"}: expected a `key` and a `command`",
+        ]
+    );
+
+    let err = convert::import(Format::VsCode, "[\n  { \"key\": }\n]").unwrap_err();
+    assert_eq!(err.line(), Some(2));
+    assert!(convert::import(Format::VsCode, "{}").is_err());
+}
+
+#[test]
+fn convert_inputrc() {
+    let text = r#"# ~/.inputrc
+set editing-mode emacs
+$include /etc/inputrc
+
+"\C-a": beginning-of-line
+"\e[1;5D": backward-word
+"\e[3~": delete-char
+"\ed": kill-word
+"\M-<": beginning-of-history
+Control-w: unix-word-rubout
+Meta-Rubout: backward-kill-word
+"\C-xe": kill-whole-line
+"\C-x\"": "\"\"\C-b"
+"\C-i": menu-complete
+$if mode=vi
+"\C-l": clear-screen
+$else
+"\C-l": clear-screen
+$endif
+"\t": forward-char
+"#;
+    let imported = convert::import(Format::Inputrc, text).unwrap();
+    let keyset = &imported.keyset;
+    let get = |action: &str| keyset.get(action).map(|binding| binding.to_string());
+    assert_eq!(get("editor_view:move_to_line_start").unwrap(), "ctrl-a");
+    assert_eq!(
+        get("editor_view:move_backward_one_word").unwrap(),
+        "ctrl-left"
+    );
+    assert_eq!(get("editor_view:delete").unwrap(), "delete");
+    assert_eq!(get("editor_view:cut_word_right").unwrap(), "meta-d");
+    assert_eq!(
+        get("editor_view:move_to_buffer_start").unwrap(),
+        "shift-meta-<"
+    );
+    assert_eq!(get("editor_view:cut_word_left").unwrap(), "ctrl-w");
+    assert_eq!(get("editor:delete_word_left").unwrap(), "meta-backspace");
+    assert_eq!(get("input:clear_screen").unwrap(), "ctrl-l");
+    assert_eq!(get("editor_view:right").unwrap(), "tab");
+
+    let unmapped = imported
+        .unmapped
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unmapped,
+        [
+            "line 12: \"\\C-xe\": kill-whole-line: sequences of several keys aren't supported",
+            "line 13: \"\\C-x\\\"\": \"\\\"\\\"\\C-b\": macros aren't supported",
+            "line 14: \"\\C-i\": menu-complete: no nextshell action for `menu-complete`",
+            "line 16: \"\\C-l\": clear-screen: vi mode bindings aren't supported",
+        ]
+    );
+
+    let err = convert::import(Format::Inputrc, "\"\\C-a\" beginning-of-line").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid inputrc keybindings: line 1: expected `keyseq: function-name`, not `\"\\C-a\" beginning-of-line`"
+    );
+    assert!(convert::import(Format::Inputrc, "$endif").is_err());
+}
+
+#[test]
+fn convert_to_yaml() {
+    let text = r#"
+"\C-a": beginning-of-line
+"\M-<": beginning-of-history
+"\C-x\C-e": kill-whole-line
+"#;
+    let imported = convert::import(Format::Inputrc, text).unwrap();
+    let yaml = convert::to_yaml(Format::Inputrc, &imported);
+    assert_eq!(
+        yaml,
+        r#"# Imported from readline inputrc.
+#
+# Not imported:
+#   line 4: "\C-x\C-e": kill-whole-line: sequences of several keys aren't supported
+---
+"editor_view:move_to_buffer_start": shift-meta-<
+"editor_view:move_to_line_start": ctrl-a
+"#
+    );
+
+    // What's written reads back the same, without warnings.
+    let imported = convert::import(
+        Format::VsCode,
+        r#"[
+            { "key": "ctrl+[", "command": "editor.fold" },
+            { "key": "shift+;", "command": "editor.unfold" },
+            { "key": "ctrl+d", "command": "-deleteRight" }
+        ]"#,
+    )
+    .unwrap();
+    let yaml = convert::to_yaml(Format::VsCode, &imported);
+    let checked = validate::check(&yaml, None);
+    assert!(checked.diagnostics.is_empty(), "{:?}", checked.diagnostics);
+    assert_eq!(checked.keyset.unwrap(), imported.keyset);
+}
+
+#[tokio::test]
+async fn import_route() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = nextshell::path("keysets").and(nextshell::keysets::api(catalog()));
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/keysets/import/vscode")
+        .body(r#"[{ "key": "ctrl+shift+k", "command": "editor.action.deleteLines" }]"#)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/yaml");
+    assert_eq!(
+        res.body(),
+        "# Imported from VS Code keybindings.json.\n---\n\"editor_view:clear_lines\": ctrl-shift-K\n"
+    );
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/keysets/import/inputrc")
+        .body("\"\\C-a\" beginning-of-line\n")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 400);
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/keysets/import/emacs")
+        .body("")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.body(), "unknown keybindings format \"emacs\"\n");
+}
@@ -0,0 +1,1015 @@
+#![deny(warnings)]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use nextshell::terminal::asciicast::{Event, EventKind, Header, Recorder, Recording};
+use nextshell::terminal::blocks::{self, BlockEvent, Shell};
+use nextshell::terminal::hub::{Hub, Recv};
+use nextshell::terminal::protocol::{ClientFrame, ServerFrame};
+use nextshell::terminal::{Command, ExitStatus, Registry, RunEvent, Size};
+use nextshell::test::WsClient;
+use nextshell::ws::Message;
+use nextshell::Filter;
+
+#[tokio::test]
+async fn echo() {
+    let _ = pretty_env_logger::try_init();
+
+    let mut client = nextshell::test::ws()
+        .handshake(terminal_route(nextshell::terminal::command("/bin/sh")))
+        .await
+        .expect("handshake");
+
+    client
+        .send(ClientFrame::Input(b"printf 'a%sb\\n' 12\n".to_vec()).into_message())
+        .await;
+
+    let output = read_until(&mut client, "a12b").await;
+    assert!(output.contains("a12b"), "output: {:?}", output);
+}
+
+#[tokio::test]
+async fn exit_status() {
+    let _ = pretty_env_logger::try_init();
+
+    let cmd = nextshell::terminal::command("/bin/sh").args(["-c", "exit 3"]);
+    let mut client = nextshell::test::ws()
+        .handshake(terminal_route(cmd))
+        .await
+        .expect("handshake");
+
+    let status = read_exit(&mut client).await;
+    assert_eq!(
+        status,
+        ExitStatus {
+            code: Some(3),
+            signal: None
+        }
+    );
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn resize() {
+    let _ = pretty_env_logger::try_init();
+
+    let cmd = nextshell::terminal::command("/bin/sh").size(90, 30);
+    let mut client = nextshell::test::ws()
+        .handshake(terminal_route(cmd))
+        .await
+        .expect("handshake");
+
+    client
+        .send(ClientFrame::Input(b"stty size\n".to_vec()).into_message())
+        .await;
+    read_until(&mut client, "30 90").await;
+
+    client
+        .send(
+            ClientFrame::Resize(Size {
+                cols: 100,
+                rows: 33,
+            })
+            .into_message(),
+        )
+        .await;
+    client
+        .send(ClientFrame::Input(b"stty size\n".to_vec()).into_message())
+        .await;
+    read_until(&mut client, "33 100").await;
+}
+
+#[tokio::test]
+async fn spawn_and_read() {
+    let mut terminal = nextshell::terminal::command("/bin/echo")
+        .arg("hello")
+        .spawn()
+        .expect("spawn");
+
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    loop {
+        let n = terminal.read(&mut buf).await.expect("read");
+        if n == 0 {
+            break;
+        }
+        output.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(output, b"hello\r\n");
+    assert!(terminal.wait().await.expect("wait").success());
+}
+
+#[tokio::test]
+async fn reattach_replays_scrollback() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new();
+    let route = session_route(registry.clone(), nextshell::terminal::command("/bin/sh"));
+
+    let mut client = nextshell::test::ws()
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+    let id = read_attached(&mut client).await;
+    client
+        .send(ClientFrame::Input(b"printf 'a%sb\\n' 12\n".to_vec()).into_message())
+        .await;
+    read_until(&mut client, "a12b").await;
+    drop(client);
+
+    let mut client = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(route)
+        .await
+        .expect("handshake");
+    assert_eq!(read_attached(&mut client).await, id);
+    read_until(&mut client, "a12b").await;
+
+    // And it's live again after the replay.
+    client
+        .send(ClientFrame::Input(b"printf 'c%sd\\n' 34\n".to_vec()).into_message())
+        .await;
+    read_until(&mut client, "c34d").await;
+    assert_eq!(registry.len(), 1);
+}
+
+#[tokio::test]
+async fn scrollback_is_bounded() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new().scrollback(8);
+    let cmd =
+        nextshell::terminal::command("/bin/sh").args(["-c", "printf 0123456789abcdef; sleep 10"]);
+    let route = session_route(registry.clone(), cmd);
+
+    let mut client = nextshell::test::ws()
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+    let id = read_attached(&mut client).await;
+    read_until(&mut client, "0123456789abcdef").await;
+    drop(client);
+
+    let mut client = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(route)
+        .await
+        .expect("handshake");
+    read_attached(&mut client).await;
+    let msg = client.recv().await.expect("recv");
+    assert_eq!(
+        ServerFrame::from_message(&msg).expect("frame"),
+        ServerFrame::Output(b"89abcdef".to_vec())
+    );
+    assert!(registry.kill(&id));
+    assert!(registry.is_empty());
+}
+
+#[tokio::test]
+async fn exit_while_detached() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new();
+    let cmd = nextshell::terminal::command("/bin/sh").args(["-c", "printf done; exit 4"]);
+    let id = registry.spawn(&cmd).expect("spawn");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut client = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(session_route(registry.clone(), cmd))
+        .await
+        .expect("handshake");
+    assert_eq!(read_attached(&mut client).await, id);
+    read_until(&mut client, "done").await;
+    assert_eq!(read_exit(&mut client).await.code, Some(4));
+
+    // Once the exit status has been delivered, the session is forgotten.
+    client.recv_closed().await.expect("closed");
+    assert!(!registry.contains(&id));
+}
+
+#[tokio::test]
+async fn idle_sessions_are_reaped() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new().idle_timeout(Duration::from_millis(100));
+    let route = session_route(registry.clone(), nextshell::terminal::command("/bin/sh"));
+
+    let mut client = nextshell::test::ws()
+        .handshake(route)
+        .await
+        .expect("handshake");
+    let id = read_attached(&mut client).await;
+
+    // Attached sessions are never idle.
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(registry.contains(&id));
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!registry.contains(&id));
+
+    // Sessions nobody ever attached to are reaped too.
+    let id = registry
+        .spawn(&nextshell::terminal::command("/bin/sh"))
+        .expect("spawn");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!registry.contains(&id));
+}
+
+#[tokio::test]
+async fn shared_session() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new();
+    let route = session_route(registry.clone(), nextshell::terminal::command("/bin/sh"));
+
+    let mut alice = nextshell::test::ws()
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+    let (id, a, controller) = read_greeting(&mut alice).await;
+    assert_eq!(controller, a);
+
+    let mut bob = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(route)
+        .await
+        .expect("handshake");
+    let (bob_id, b, controller) = read_greeting(&mut bob).await;
+    assert_eq!((bob_id.as_str(), controller), (id.as_str(), a));
+    assert_ne!(a, b);
+
+    // Only the controller's input reaches the shell, and everyone sees it.
+    bob.send(ClientFrame::Input(b"printf 'x%sy\\n' 1\n".to_vec()).into_message())
+        .await;
+    alice
+        .send(ClientFrame::Input(b"printf 'a%sb\\n' 12\n".to_vec()).into_message())
+        .await;
+    let seen = read_until(&mut bob, "a12b").await;
+    assert!(!seen.contains("x1y"), "output: {:?}", seen);
+    read_until(&mut alice, "a12b").await;
+
+    alice
+        .send(ClientFrame::HandOff { viewer: b }.into_message())
+        .await;
+    assert_eq!(
+        read_control(&mut alice).await,
+        ServerFrame::Control { controller: b }
+    );
+    assert_eq!(
+        read_control(&mut bob).await,
+        ServerFrame::Control { controller: b }
+    );
+
+    bob.send(ClientFrame::Input(b"printf 'c%sd\\n' 34\n".to_vec()).into_message())
+        .await;
+    read_until(&mut alice, "c34d").await;
+    read_until(&mut bob, "c34d").await;
+    assert!(registry.kill(&id));
+}
+
+#[tokio::test]
+async fn control_requests() {
+    let _ = pretty_env_logger::try_init();
+
+    let registry = Registry::new();
+    let route = session_route(registry.clone(), nextshell::terminal::command("/bin/sh"));
+
+    let mut alice = nextshell::test::ws()
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+    let (id, a, _) = read_greeting(&mut alice).await;
+    let mut bob = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(route.clone())
+        .await
+        .expect("handshake");
+    let (_, b, _) = read_greeting(&mut bob).await;
+
+    bob.send(ClientFrame::RequestControl.into_message()).await;
+    assert_eq!(
+        read_control(&mut alice).await,
+        ServerFrame::ControlRequested { viewer: b }
+    );
+
+    // When the controller leaves, control passes on.
+    drop(alice);
+    assert_eq!(
+        read_control(&mut bob).await,
+        ServerFrame::Control { controller: b }
+    );
+
+    let mut carol = nextshell::test::ws()
+        .path(&format!("/?session={}", id))
+        .handshake(route)
+        .await
+        .expect("handshake");
+    let (_, c, controller) = read_greeting(&mut carol).await;
+    assert_eq!(controller, b);
+    assert!(c != a && c != b);
+    assert!(registry.kill(&id));
+}
+
+#[tokio::test]
+async fn slow_viewers_lag_alone() {
+    let mut hub = Hub::new(8);
+    let slow = hub.join();
+    let fast = hub.join();
+    assert_eq!(hub.controller(), Some(slow.id()));
+
+    hub.broadcast(ServerFrame::Output(b"01234".to_vec()));
+    assert_eq!(
+        fast.recv().await,
+        Some(Recv::Frame(ServerFrame::Output(b"01234".to_vec())))
+    );
+    hub.broadcast(ServerFrame::Output(b"56789".to_vec()));
+    assert_eq!(
+        fast.recv().await,
+        Some(Recv::Frame(ServerFrame::Output(b"56789".to_vec())))
+    );
+
+    // The slow viewer never took its first frame, so it overflowed, and is
+    // sent no more output until it is resynced.
+    assert_eq!(slow.recv().await, Some(Recv::Lagged));
+    hub.broadcast(ServerFrame::Output(b"ab".to_vec()));
+    assert_eq!(slow.recv().await, Some(Recv::Lagged));
+    // It's still told the program exited.
+    let exit = ServerFrame::Exit(ExitStatus {
+        code: Some(0),
+        signal: None,
+    });
+    hub.send(slow.id(), exit.clone());
+    assert_eq!(slow.recv().await, Some(Recv::Frame(exit)));
+    assert_eq!(slow.recv().await, Some(Recv::Lagged));
+    hub.resync(
+        slow.id(),
+        vec![ServerFrame::Output(b"0123456789ab".to_vec())],
+    );
+    assert_eq!(
+        slow.recv().await,
+        Some(Recv::Frame(ServerFrame::Output(b"0123456789ab".to_vec())))
+    );
+
+    // Leaving passes control on, and closes the queue once drained.
+    assert!(hub.leave(slow.id()));
+    assert_eq!(hub.controller(), Some(fast.id()));
+    assert_eq!(slow.recv().await, None);
+    assert_eq!(
+        fast.recv().await,
+        Some(Recv::Frame(ServerFrame::Output(b"ab".to_vec())))
+    );
+    assert_eq!(
+        fast.recv().await,
+        Some(Recv::Frame(ServerFrame::Control {
+            controller: fast.id()
+        }))
+    );
+    assert!(!hub.hand_off(slow.id(), fast.id()));
+}
+
+#[test]
+fn watchers_never_hold_control() {
+    let mut hub = Hub::new(1024);
+    let watcher = hub.watch();
+    assert_eq!(hub.controller(), None);
+    let first = hub.join();
+    let second = hub.join();
+    assert_eq!(hub.controller(), Some(first.id()));
+    assert!(!hub.hand_off(first.id(), watcher.id()));
+
+    // Control skips the watcher, though it joined first.
+    assert!(hub.leave(first.id()));
+    assert_eq!(hub.controller(), Some(second.id()));
+    assert!(hub.leave(second.id()));
+    assert_eq!(hub.controller(), None);
+    assert_eq!(hub.len(), 1);
+}
+
+#[test]
+fn block_marks() {
+    let output: &[u8] = b"\x1b]133;A\x07\x1b[1mdev\x1b[0m $ \x1b]133;B\x1b\\lx\x08s -l\r\n\
+        \x1b]133;C\x07total 0\r\n\x1b]133;D;2\x07\x1b]133;A\x07$ \x1b]133;B\x07\
+        \x1b]133;C;cmdline_url=echo%20%C3%A9\x07\xc3\xa9\r\n\x1b]133;A\x07";
+    let expected = vec![
+        BlockEvent::PromptStart,
+        BlockEvent::Prompt {
+            text: "dev $ ".to_owned(),
+        },
+        BlockEvent::Command {
+            text: "ls -l".to_owned(),
+        },
+        BlockEvent::Finished { exit_code: Some(2) },
+        BlockEvent::PromptStart,
+        BlockEvent::Prompt {
+            text: "$ ".to_owned(),
+        },
+        BlockEvent::Command {
+            text: "echo \u{e9}".to_owned(),
+        },
+        // No D mark, so the next prompt ends the command.
+        BlockEvent::Finished { exit_code: None },
+        BlockEvent::PromptStart,
+    ];
+
+    let mut parser = blocks::Parser::new();
+    let marks = parser.feed(output);
+    assert_eq!(marks[0].0, 8);
+    assert_eq!(marks.len(), expected.len());
+    assert_eq!(
+        marks
+            .into_iter()
+            .map(|(_, event)| event)
+            .collect::<Vec<_>>(),
+        expected
+    );
+
+    // Marks split across reads are still found.
+    for split in 0..output.len() {
+        let mut parser = blocks::Parser::new();
+        let mut events = parser.feed(&output[..split]);
+        events.extend(parser.feed(&output[split..]));
+        assert_eq!(
+            events
+                .into_iter()
+                .map(|(_, event)| event)
+                .collect::<Vec<_>>(),
+            expected,
+            "split at {}",
+            split
+        );
+    }
+
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+        assert!(blocks::init_script(&shell).contains("133;C"));
+    }
+}
+
+#[tokio::test]
+async fn bash_blocks() {
+    let _ = pretty_env_logger::try_init();
+
+    let rcfile = temp_dir("bash_blocks").join("bashrc");
+    std::fs::write(
+        &rcfile,
+        format!("PS1='$ '\n{}", blocks::init_script(&Shell::Bash)),
+    )
+    .expect("write rcfile");
+    let cmd = nextshell::terminal::command("bash").args([
+        "--noprofile".as_ref(),
+        "--rcfile".as_ref(),
+        rcfile.as_os_str(),
+        "-i".as_ref(),
+    ]);
+
+    let registry = Registry::new().blocks(true);
+    let mut client = nextshell::test::ws()
+        .handshake(session_route(registry.clone(), cmd))
+        .await
+        .expect("handshake");
+    let id = read_attached(&mut client).await;
+
+    let mut events = Vec::new();
+    let mut output = String::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        let mut sent = false;
+        while events
+            .iter()
+            .filter(|event| **event == BlockEvent::PromptStart)
+            .count()
+            < 2
+        {
+            // Type only once bash is reading input, or the terminal echoes
+            // the command before the prompt, outside the block.
+            if !sent && events.len() == 2 {
+                client
+                    .send(
+                        ClientFrame::Input(b"printf 'a%sb\\n' 12; false\n".to_vec()).into_message(),
+                    )
+                    .await;
+                sent = true;
+            }
+            let msg = client.recv().await.expect("recv");
+            match ServerFrame::from_message(&msg).expect("frame") {
+                ServerFrame::Block(event) => events.push(event),
+                ServerFrame::Output(bytes) => output.push_str(&String::from_utf8_lossy(&bytes)),
+                frame => panic!("unexpected frame: {:?}", frame),
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out, got {:?}", events));
+
+    assert_eq!(
+        events,
+        [
+            BlockEvent::PromptStart,
+            BlockEvent::Prompt {
+                text: "$ ".to_owned()
+            },
+            BlockEvent::Command {
+                text: "printf 'a%sb\\n' 12; false".to_owned()
+            },
+            BlockEvent::Finished { exit_code: Some(1) },
+            BlockEvent::PromptStart,
+        ]
+    );
+    assert!(output.contains("a12b"), "output: {:?}", output);
+    assert!(registry.kill(&id));
+}
+
+#[tokio::test]
+async fn record_session() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = temp_dir("record_session");
+    let registry = Registry::new().record(&dir).record_input(true);
+    let cmd = nextshell::terminal::command("/bin/sh").size(90, 30);
+    let mut client = nextshell::test::ws()
+        .handshake(session_route(registry.clone(), cmd))
+        .await
+        .expect("handshake");
+    let id = read_attached(&mut client).await;
+
+    client
+        .send(
+            ClientFrame::Resize(Size {
+                cols: 100,
+                rows: 33,
+            })
+            .into_message(),
+        )
+        .await;
+    client
+        .send(ClientFrame::Input(b"printf 'a%sb\\n' 12; exit\n".to_vec()).into_message())
+        .await;
+    read_exit(&mut client).await;
+
+    let recording = Recording::open(dir.join(format!("{}.cast", id)))
+        .await
+        .expect("recording");
+    assert_eq!((recording.header.width, recording.header.height), (90, 30));
+    assert_eq!(recording.header.env["TERM"], "xterm-256color");
+    assert_eq!(recording.header.env["SHELL"], "/bin/sh");
+
+    let of_kind = |kind| {
+        recording
+            .events
+            .iter()
+            .filter(move |event| event.kind == kind)
+            .map(|event| event.data.as_str())
+    };
+    assert_eq!(of_kind(EventKind::Resize).collect::<Vec<_>>(), ["100x33"]);
+    assert_eq!(
+        of_kind(EventKind::Input).collect::<String>(),
+        "printf 'a%sb\\n' 12; exit\n"
+    );
+    assert!(of_kind(EventKind::Output)
+        .collect::<String>()
+        .contains("a12b"));
+    assert!(recording
+        .events
+        .windows(2)
+        .all(|pair| pair[0].time <= pair[1].time));
+}
+
+#[tokio::test]
+async fn run_input_isnt_recorded() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = temp_dir("run_input_isnt_recorded");
+    let registry = Registry::new().record(&dir).record_input(true);
+    let cmd = nextshell::terminal::command("/bin/sh");
+    let id = registry.spawn(&cmd).expect("spawn");
+    let mut run = registry
+        .run(Some(&id), &cmd, "true s3cret; exit")
+        .await
+        .expect("run");
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(event) = run.next().await {
+            if let RunEvent::Exit(_) = event {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("timed out");
+
+    let recording = Recording::open(dir.join(format!("{}.cast", id)))
+        .await
+        .expect("recording");
+    assert!(!recording
+        .events
+        .iter()
+        .any(|event| event.kind == EventKind::Input));
+}
+
+#[tokio::test]
+async fn lagging_runs_still_end() {
+    let _ = pretty_env_logger::try_init();
+
+    let rcfile = temp_dir("lagging_runs_still_end").join("bashrc");
+    std::fs::write(
+        &rcfile,
+        format!("PS1='$ '\n{}", blocks::init_script(&Shell::Bash)),
+    )
+    .expect("write rcfile");
+    let bash = nextshell::terminal::command("bash").args([
+        "--noprofile".as_ref(),
+        "--rcfile".as_ref(),
+        rcfile.as_os_str(),
+        "-i".as_ref(),
+    ]);
+    let registry = Registry::new().blocks(true).viewer_buffer(256);
+    let id = registry.spawn(&bash).expect("spawn");
+
+    // Each run falls far behind the output before it's followed, but still
+    // sees the command finish, or the shell exit.
+    let live = registry.run(Some(&id), &bash, "seq 1 20000; false");
+    let fresh = registry.run(None, &bash, "seq 1 20000");
+    for (mut run, code) in [
+        (live.await.expect("run"), 1),
+        (fresh.await.expect("run"), 0),
+    ] {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let exit = tokio::time::timeout(Duration::from_secs(10), async {
+            while let Some(event) = run.next().await {
+                if let RunEvent::Exit(status) = event {
+                    return Some(status);
+                }
+            }
+            None
+        })
+        .await
+        .expect("the run never ended");
+        assert_eq!(exit.map(|status| status.code), Some(Some(code)));
+    }
+    assert!(registry.kill(&id));
+}
+
+#[test]
+fn recorder() {
+    let path = temp_dir("recorder").join("test.cast");
+    let mut header = Header::new(Size { cols: 80, rows: 24 });
+    header.title = Some("demo".to_owned());
+
+    let mut recorder = Recorder::create(&path, &header).expect("create");
+    // A character split across reads is recorded once it's complete.
+    recorder.output(b"caf\xc3").unwrap();
+    recorder.output(b"\xa9 \xff!").unwrap();
+    // Input isn't recorded unless asked for.
+    recorder.input(b"secret\n").unwrap();
+    recorder.resize(Size { cols: 1, rows: 2 }).unwrap();
+    recorder.marker("done").unwrap();
+    drop(recorder);
+
+    let text = std::fs::read_to_string(&path).expect("read");
+    assert!(
+        text.starts_with(r#"{"version":2,"width":80,"height":24,"timestamp":"#),
+        "header: {:?}",
+        text
+    );
+
+    let recording = Recording::from_reader(text.as_bytes()).expect("parse");
+    assert_eq!(recording.header, header);
+    let events = recording
+        .events
+        .iter()
+        .map(|event| (event.kind, event.data.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            (EventKind::Output, "caf"),
+            (EventKind::Output, "\u{e9} \u{fffd}!"),
+            (EventKind::Resize, "1x2"),
+            (EventKind::Marker, "done"),
+        ]
+    );
+    assert_eq!(recording.events[2].size(), Some(Size { cols: 1, rows: 2 }));
+
+    Recording::from_reader(&br#"{"version":1,"width":80,"height":24}"#[..])
+        .expect_err("asciicast v1");
+}
+
+#[tokio::test]
+async fn playback_speeds() {
+    let event = |time: f64| Event {
+        time,
+        kind: EventKind::Output,
+        data: "x".to_owned(),
+    };
+    let mut recording = Recording {
+        header: Header::new(Size { cols: 80, rows: 24 }),
+        events: vec![event(0.0), event(0.01)],
+    };
+
+    // Too slow to overflow a pause, or not a speed at all.
+    for speed in [1e-300, 0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let events = tokio::time::timeout(
+            Duration::from_secs(5),
+            recording.clone().play(speed).collect::<Vec<_>>(),
+        )
+        .await
+        .expect("timed out");
+        assert_eq!(events.len(), 2, "{}", speed);
+    }
+
+    // A negative limit, or time going backwards, makes for no pause.
+    recording.header.idle_time_limit = Some(-1.0);
+    recording.events.push(event(-5.0));
+    let events = tokio::time::timeout(
+        Duration::from_secs(5),
+        recording.play(1.0).collect::<Vec<_>>(),
+    )
+    .await
+    .expect("timed out");
+    assert_eq!(events.len(), 3);
+}
+
+#[tokio::test]
+async fn playback_sse() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = write_recording("playback_sse");
+    let route = nextshell::terminal::playback::sse(dir);
+
+    let res = nextshell::test::request()
+        .path("/demo?speed=4")
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+    assert_eq!(
+        std::str::from_utf8(res.body()).unwrap(),
+        "event:header\n\
+         data:{\"width\":80,\"height\":24,\"idle_time_limit\":0.5}\n\n\
+         event:output\ndata:\"$ \"\n\n\
+         event:input\ndata:\"ls\\r\"\n\n\
+         event:output\ndata:\"ls\\r\\n\"\n\n\
+         event:resize\ndata:{\"cols\":100,\"rows\":40}\n\n\
+         event:end\ndata:\n\n"
+    );
+
+    for path in [
+        "/missing",
+        "/..%2Fplayback_sse%2Fdemo",
+        "/.demo",
+        "/demo/more",
+    ] {
+        let res = nextshell::test::request().path(path).reply(&route).await;
+        assert_eq!(res.status(), 404, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn playback_ws() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = write_recording("playback_ws");
+    let started = std::time::Instant::now();
+    let mut client = nextshell::test::ws()
+        .path("/demo?speed=2")
+        .handshake(nextshell::terminal::playback::ws(dir))
+        .await
+        .expect("handshake");
+
+    let mut frames = Vec::new();
+    while let Ok(msg) = client.recv().await {
+        frames.push(ServerFrame::from_message(&msg).expect("frame"));
+    }
+    assert_eq!(
+        frames,
+        [
+            ServerFrame::Resize(Size { cols: 80, rows: 24 }),
+            ServerFrame::Output(b"$ ".to_vec()),
+            ServerFrame::Output(b"ls\r\n".to_vec()),
+            ServerFrame::Resize(Size {
+                cols: 100,
+                rows: 40
+            }),
+        ]
+    );
+    // The long pause is cut down to the idle time limit, at double speed.
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+}
+
+#[test]
+fn frames() {
+    let resize = ClientFrame::Resize(Size {
+        cols: 120,
+        rows: 40,
+    })
+    .into_message();
+    assert_eq!(
+        resize.to_str(),
+        Ok(r#"{"type":"resize","cols":120,"rows":40}"#)
+    );
+    assert_eq!(
+        ClientFrame::from_message(&resize).unwrap(),
+        ClientFrame::Resize(Size {
+            cols: 120,
+            rows: 40
+        })
+    );
+
+    let input = Message::binary(&b"ls\n"[..]);
+    assert_eq!(
+        ClientFrame::from_message(&input).unwrap(),
+        ClientFrame::Input(b"ls\n".to_vec())
+    );
+
+    let resize = ServerFrame::Resize(Size { cols: 80, rows: 24 }).into_message();
+    assert_eq!(
+        resize.to_str(),
+        Ok(r#"{"type":"resize","cols":80,"rows":24}"#)
+    );
+    assert_eq!(
+        ServerFrame::from_message(&resize).unwrap(),
+        ServerFrame::Resize(Size { cols: 80, rows: 24 })
+    );
+
+    let hand_off = ClientFrame::HandOff { viewer: 2 }.into_message();
+    assert_eq!(hand_off.to_str(), Ok(r#"{"type":"hand_off","viewer":2}"#));
+    assert_eq!(
+        ClientFrame::from_message(&hand_off).unwrap(),
+        ClientFrame::HandOff { viewer: 2 }
+    );
+
+    let attached = ServerFrame::Attached {
+        session: "abc".to_owned(),
+        viewer: 2,
+        controller: 1,
+        size: Size { cols: 80, rows: 24 },
+    };
+    assert_eq!(
+        attached.clone().into_message().to_str(),
+        Ok(r#"{"type":"attached","session":"abc","viewer":2,"controller":1,"cols":80,"rows":24}"#)
+    );
+    assert_eq!(
+        ServerFrame::from_message(&attached.clone().into_message()).unwrap(),
+        attached
+    );
+
+    let block = ServerFrame::Block(BlockEvent::Finished { exit_code: Some(1) });
+    assert_eq!(
+        block.clone().into_message().to_str(),
+        Ok(r#"{"type":"block","kind":"finished","exit_code":1}"#)
+    );
+    assert_eq!(
+        ServerFrame::from_message(&block.clone().into_message()).unwrap(),
+        block
+    );
+
+    let exit = ServerFrame::Exit(ExitStatus {
+        code: None,
+        signal: Some(9),
+    })
+    .into_message();
+    assert_eq!(
+        exit.to_str(),
+        Ok(r#"{"type":"exit","code":null,"signal":9}"#)
+    );
+
+    ClientFrame::from_message(&Message::text(r#"{"type":"nope"}"#))
+        .expect_err("unknown control type");
+    ClientFrame::from_message(&Message::ping(Vec::new())).expect_err("ping isn't a frame");
+}
+
+fn session_route(
+    registry: Registry,
+    cmd: Command,
+) -> impl Filter<Extract = impl nextshell::Reply, Error = nextshell::Rejection> + Clone {
+    nextshell::ws()
+        .and(nextshell::query::<HashMap<String, String>>())
+        .map(
+            move |ws: nextshell::ws::Ws, query: HashMap<String, String>| {
+                let registry = registry.clone();
+                let cmd = cmd.clone();
+                ws.on_upgrade(move |socket| async move {
+                    let id = query.get("session").map(String::as_str);
+                    registry.serve(socket, id, &cmd).await
+                })
+            },
+        )
+}
+
+fn terminal_route(
+    cmd: Command,
+) -> impl Filter<Extract = impl nextshell::Reply, Error = nextshell::Rejection> + Clone {
+    nextshell::ws().map(move |ws: nextshell::ws::Ws| {
+        let terminal = cmd.spawn().expect("spawn");
+        ws.on_upgrade(move |socket| nextshell::terminal::serve(socket, terminal))
+    })
+}
+
+async fn read_until(client: &mut WsClient, needle: &str) -> String {
+    let mut output = String::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !output.contains(needle) {
+            let msg = client.recv().await.expect("recv");
+            match ServerFrame::from_message(&msg).expect("frame") {
+                ServerFrame::Output(bytes) => output.push_str(&String::from_utf8_lossy(&bytes)),
+                frame => panic!("unexpected frame: {:?}", frame),
+            }
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {:?}, got {:?}", needle, output));
+    output
+}
+
+async fn read_attached(client: &mut WsClient) -> String {
+    let msg = client.recv().await.expect("recv");
+    match ServerFrame::from_message(&msg).expect("frame") {
+        ServerFrame::Attached { session, .. } => session,
+        frame => panic!("expected attached frame, got {:?}", frame),
+    }
+}
+
+async fn read_greeting(client: &mut WsClient) -> (String, u64, u64) {
+    let msg = client.recv().await.expect("recv");
+    match ServerFrame::from_message(&msg).expect("frame") {
+        ServerFrame::Attached {
+            session,
+            viewer,
+            controller,
+            ..
+        } => (session, viewer, controller),
+        frame => panic!("expected attached frame, got {:?}", frame),
+    }
+}
+
+/// Reads the next frame that isn't output.
+async fn read_control(client: &mut WsClient) -> ServerFrame {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let msg = client.recv().await.expect("recv");
+            match ServerFrame::from_message(&msg).expect("frame") {
+                ServerFrame::Output(_) => continue,
+                frame => return frame,
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for a control frame")
+}
+
+async fn read_exit(client: &mut WsClient) -> ExitStatus {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let msg = client.recv().await.expect("recv");
+            if let ServerFrame::Exit(status) = ServerFrame::from_message(&msg).expect("frame") {
+                return status;
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for exit")
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "nextshell-terminal-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
+
+/// Writes `demo.cast`, with a 100 second pause that playback should cut down
+/// to its idle time limit.
+fn write_recording(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    let cast = concat!(
+        r#"{"version":2,"width":80,"height":24,"idle_time_limit":0.5}"#,
+        "\n",
+        r#"[0.1,"o","$ "]"#,
+        "\n",
+        r#"[0.2,"i","ls\r"]"#,
+        "\n",
+        r#"[0.3,"o","ls\r\n"]"#,
+        "\n",
+        r#"[0.4,"x","unknown event kinds are skipped"]"#,
+        "\n",
+        r#"[100.3,"r","100x40"]"#,
+        "\n",
+    );
+    std::fs::write(dir.join("demo.cast"), cast).expect("write recording");
+    assert_eq!(
+        Recording::from_reader(cast.as_bytes()).unwrap().events[3],
+        Event {
+            time: 100.3,
+            kind: EventKind::Resize,
+            data: "100x40".to_owned(),
+        }
+    );
+    dir
+}
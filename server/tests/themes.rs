@@ -0,0 +1,790 @@
+#![deny(warnings)]
+
+use std::sync::Arc;
+
+use nextshell::http::StatusCode;
+use nextshell::themes::contrast::{self, Level};
+use nextshell::themes::convert::{self, Format, FORMATS};
+use nextshell::themes::preview;
+use nextshell::themes::validate::{self, Severity};
+use nextshell::themes::{Accent, Background, Catalog, Color, Details, Theme};
+use nextshell::Filter;
+use serde_json::Value;
+
+fn catalog() -> Arc<Catalog> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/static/themes");
+    Arc::new(Catalog::load(dir).expect("bundled themes load"))
+}
+
+#[test]
+fn bundled_themes_load() {
+    let _ = pretty_env_logger::try_init();
+
+    let catalog = catalog();
+    assert!(catalog.len() > 300, "only {} themes", catalog.len());
+
+    let dracula = catalog.get("standard-dracula").expect("standard dracula");
+    assert_eq!(dracula.name, "Dracula");
+    assert_eq!(dracula.file, "standard/dracula.yaml");
+    assert!(catalog.get("dracula").is_none());
+
+    let willow = catalog.get("willow-dream").expect("willow dream");
+    assert_eq!(
+        willow.theme.accent,
+        Accent::Gradient {
+            left: "#f9aea8".parse().unwrap(),
+            right: "#dd6258".parse().unwrap(),
+        }
+    );
+    assert!(matches!(
+        willow.theme.background,
+        Background::Gradient { .. }
+    ));
+
+    let pink_city = catalog.get("pink-city").expect("pink city");
+    match pink_city.theme.details {
+        Details::Custom(ref opacities) => assert_eq!(opacities["outline_opacity"], 12),
+        ref details => panic!("details: {:?}", details),
+    }
+    let image = pink_city.theme.background_image.as_ref().expect("image");
+    assert_eq!(image.path, "nextshell_bundled/pink_city_bg.jpg");
+    assert_eq!(image.opacity, Some(40));
+
+    let vitesse = catalog.get("vitesse-black").expect("vitesse black");
+    assert_eq!(
+        vitesse.theme.background,
+        Background::Solid(Color { r: 0, g: 0, b: 0 })
+    );
+}
+
+#[test]
+fn colors() {
+    let color: Color = "#1A2b3C".parse().unwrap();
+    assert_eq!(
+        color,
+        Color {
+            r: 0x1a,
+            g: 0x2b,
+            b: 0x3c
+        }
+    );
+    assert_eq!(color.to_string(), "#1a2b3c");
+    assert_eq!("#abc".parse::<Color>().unwrap().to_string(), "#aabbcc");
+
+    assert!("1a2b3c".parse::<Color>().is_err());
+    assert!("#1a2b3".parse::<Color>().is_err());
+    assert!("#gggggg".parse::<Color>().is_err());
+}
+
+#[test]
+fn round_trip() {
+    let catalog = catalog();
+    for entry in catalog.iter() {
+        let yaml = serde_yaml::to_string(&entry.theme).unwrap();
+        let theme: Theme = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(theme, entry.theme, "{}", entry.file);
+    }
+}
+
+#[test]
+fn search() {
+    let catalog = catalog();
+
+    let found = catalog.search("solarized dark");
+    assert!(!found.is_empty());
+    assert!(found
+        .iter()
+        .all(|entry| entry.name.to_lowercase().contains("solarized")));
+
+    let found = catalog.search("Vitesse Black");
+    assert_eq!(found[0].slug, "vitesse-black");
+
+    assert!(catalog.search("no such theme anywhere").is_empty());
+}
+
+#[tokio::test]
+async fn list() {
+    let _ = pretty_env_logger::try_init();
+
+    let catalog = catalog();
+    let api = nextshell::path("themes").and(nextshell::themes::api(catalog.clone()));
+
+    let res = nextshell::test::request().path("/themes").reply(&api).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    let body: Vec<Value> = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(body.len(), catalog.len());
+
+    let res = nextshell::test::request()
+        .path("/themes?collection=special_edition")
+        .reply(&api)
+        .await;
+    let body: Vec<Value> = serde_json::from_slice(res.body()).unwrap();
+    assert!(!body.is_empty());
+    assert!(body
+        .iter()
+        .all(|theme| theme["collection"] == "special_edition"));
+
+    let pink_city = body_of(&api, "/themes?collection=nextshell_bundled")
+        .await
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|theme| theme["slug"] == "pink-city")
+        .cloned()
+        .expect("pink city listed");
+    assert_eq!(pink_city["details"], "custom");
+    assert_eq!(pink_city["background"], "#fbeff6");
+    assert!(pink_city.get("terminal_colors").is_none());
+}
+
+#[tokio::test]
+async fn get() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = nextshell::path("themes").and(nextshell::themes::api(catalog()));
+
+    let theme = body_of(&api, "/themes/willow-dream").await;
+    assert_eq!(theme["name"], "Willow Dream");
+    assert_eq!(theme["theme"]["accent"]["left"], "#f9aea8");
+    assert_eq!(theme["theme"]["background"]["top"], "#206169");
+    assert_eq!(theme["theme"]["details"], "darker");
+    assert_eq!(
+        theme["theme"]["terminal_colors"]["normal"]["black"],
+        "#616161"
+    );
+
+    let res = nextshell::test::request()
+        .path("/themes/no-such-theme")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn search_route() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = nextshell::path("themes").and(nextshell::themes::api(catalog()));
+
+    let found = body_of(&api, "/themes/search?q=vitesse%20black").await;
+    assert_eq!(found[0]["slug"], "vitesse-black");
+
+    let found = body_of(&api, "/themes/search?q=dracula&collection=standard").await;
+    let found = found.as_array().unwrap();
+    assert!(!found.is_empty());
+    assert!(found.iter().all(|theme| theme["collection"] == "standard"));
+}
+
+#[tokio::test]
+async fn etags() {
+    let _ = pretty_env_logger::try_init();
+
+    let api = nextshell::path("themes").and(nextshell::themes::api(catalog()));
+
+    let res = nextshell::test::request()
+        .path("/themes/pink-city")
+        .reply(&api)
+        .await;
+    let etag = res.headers()["etag"].clone();
+    assert!(etag.to_str().unwrap().starts_with('"'), "etag: {:?}", etag);
+
+    // The same content always has the same tag.
+    let again = nextshell::themes::api(catalog());
+    let res = nextshell::test::request()
+        .path("/pink-city")
+        .reply(&again)
+        .await;
+    assert_eq!(res.headers()["etag"], etag);
+
+    let res = nextshell::test::request()
+        .path("/themes/pink-city")
+        .header("if-none-match", etag.clone())
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()["etag"], etag);
+    assert!(res.body().is_empty());
+
+    let res = nextshell::test::request()
+        .path("/themes/pink-city")
+        .header("if-none-match", "\"stale\"")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+
+    // Other themes have other tags.
+    let res = nextshell::test::request()
+        .path("/themes/willow-dream")
+        .header("if-none-match", etag)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+}
+
+async fn body_of<F>(api: &F, path: &str) -> Value
+where
+    F: Filter + 'static,
+    F::Extract: nextshell::Reply + Send,
+{
+    let res = nextshell::test::request().path(path).reply(api).await;
+    assert_eq!(res.status(), 200, "{}", path);
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[test]
+fn bundled_themes_validate() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/static/themes");
+    let reports = validate::check_dir(dir).expect("check_dir");
+    assert_eq!(reports.len(), catalog().len());
+
+    let errors = reports
+        .iter()
+        .filter(|report| report.has_errors())
+        .map(ToString::to_string)
+        .collect::<String>();
+    assert!(errors.is_empty(), "{}", errors);
+}
+
+#[test]
+fn validate_errors() {
+    let checked = validate::check(
+        r##"accent:
+
+  left: "#f9aea8"
+background:
+  left: "#000000"
+  right: "#111111"
+details: darkest
+foreground: "#12345"
+terminal_colors:
+  normal:
+    black: #000000
+    red: 12
+  bright: { black: "#555", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+            blue: "#caa9fa", magenta: "#ff79c6", cyan: "#8be9fd", white: "#fff" }
+background_image:
+  opacity: 120
+"##,
+    );
+    assert!(checked.theme.is_none());
+
+    let errors = checked
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            "1: error: `accent` gradient is missing `right`",
+            "4: error: `background` gradients run from `top` to `bottom`, not `left` to `right`",
+            "7: error: `details` should be `darker`, `lighter` or `custom`, not \"darkest\"",
+            "8: error: `foreground`: invalid color \"#12345\", expected \"#rrggbb\"",
+            "10: error: missing `terminal_colors.normal.green`",
+            "10: error: missing `terminal_colors.normal.yellow`",
+            "10: error: missing `terminal_colors.normal.blue`",
+            "10: error: missing `terminal_colors.normal.magenta`",
+            "10: error: missing `terminal_colors.normal.cyan`",
+            "10: error: missing `terminal_colors.normal.white`",
+            "11: error: `terminal_colors.normal.black` is empty (quote colors, or `#` starts a comment)",
+            "12: error: `terminal_colors.normal.red` should be a color like \"#rrggbb\"",
+            "15: error: missing `background_image.path`",
+            "16: error: `background_image.opacity` should be a percentage from 0 to 100, not 120",
+        ]
+    );
+}
+
+#[test]
+fn validate_style() {
+    let checked = validate::check(
+        r##"accent: '#BD93F9'
+background: "#282a36" # dracula
+details:
+  custom:
+    outline_opacity: 12
+foreground: "#f8f8f2"
+terminal_colors:
+  normal: { black: "#000", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+            blue: "#bd93f9", magenta: "#ff79c6", cyan: "#8be9fd", white: "#bbb" }
+  bright: { black: "#555", red: "#f55", green: "#50fa7b", yellow: "#f1fa8c",
+            blue: "#caa9fa", magenta: "#ff79c6", cyan: "#8be9fd", white: "#fff" }
+cursor: "#ffffff"
+"##,
+    );
+
+    let theme = checked.theme.as_ref().expect("warnings aren't fatal");
+    assert_eq!(theme.accent, Accent::Solid("#bd93f9".parse().unwrap()));
+    let warnings = checked
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "1: warning: `accent` is single-quoted; use double quotes",
+            "1: warning: `accent` has upper-case hex; use lower case",
+            "2: warning: comment after a value",
+            "12: warning: unknown key `cursor`",
+        ]
+    );
+
+    let checked = validate::check("accent: [\n");
+    assert_eq!(checked.diagnostics.len(), 1);
+    assert_eq!(checked.diagnostics[0].line, 2);
+    assert!(checked.diagnostics[0].message.starts_with("invalid YAML"));
+}
+
+#[test]
+fn validate_background_images() {
+    let dir = std::env::temp_dir().join(format!("nextshell-themes-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("special")).unwrap();
+    std::fs::write(dir.join("special/bg.jpg"), b"").unwrap();
+
+    let theme = |path: &str| {
+        format!(
+            "{}background_image:\n  path: {}\n",
+            std::fs::read_to_string(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/static/themes/standard/dracula.yaml"
+            ))
+            .unwrap(),
+            path
+        )
+    };
+    std::fs::write(dir.join("special/good.yaml"), theme("special/bg.jpg")).unwrap();
+    std::fs::write(dir.join("special/bad.yaml"), theme("bg.jpg")).unwrap();
+
+    let reports = validate::check_dir(&dir).expect("check_dir");
+    assert_eq!(reports.len(), 2);
+    assert!(reports[0].file.ends_with("special/bad.yaml"));
+    let error = reports[0].to_string();
+    assert!(
+        error.contains("bad.yaml:") && error.contains("did you mean `special/bg.jpg`?"),
+        "{}",
+        error
+    );
+    assert!(!reports[1].has_errors(), "{}", reports[1]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn contrast_ratios() {
+    let color = |s: &str| s.parse::<Color>().unwrap();
+    let ratio = |a: &str, b: &str| (color(a).contrast(color(b)) * 100.0).round() / 100.0;
+
+    assert_eq!(ratio("#000000", "#ffffff"), 21.0);
+    assert_eq!(ratio("#ffffff", "#000000"), 21.0);
+    assert_eq!(ratio("#123456", "#123456"), 1.0);
+    assert_eq!(ratio("#777777", "#ffffff"), 4.48);
+    assert_eq!(ratio("#f8f8f2", "#282a36"), 13.36);
+
+    assert_eq!(Level::of(4.48), Level::AaLarge);
+    assert_eq!(Level::of(4.5), Level::Aa);
+    assert_eq!(Level::of(7.0), Level::Aaa);
+    assert_eq!(Level::of(2.99), Level::Fail);
+}
+
+#[test]
+fn contrast_report() {
+    let catalog = catalog();
+
+    // A gradient counts at its worse end.
+    let willow = contrast::check(&catalog.get("willow-dream").unwrap().theme);
+    let (top, bottom) = ("#206169".parse().unwrap(), "#022f27".parse().unwrap());
+    let fg: Color = "#ffffff".parse().unwrap();
+    assert_eq!(willow.foreground.background, top);
+    assert_eq!(willow.foreground.ratio, fg.contrast(top));
+    assert!(fg.contrast(bottom) > fg.contrast(top));
+    assert_eq!(willow.ansi.len(), 16);
+    assert_eq!(willow.ansi[9].name, "bright.red");
+
+    // Black on a dark background is meant to blend in.
+    let dracula = contrast::check(&catalog.get("standard-dracula").unwrap().theme);
+    assert_eq!(dracula.foreground.level(), Level::Aaa);
+    assert_eq!(dracula.ansi[0].name, "normal.black");
+    assert_eq!(dracula.ansi[0].level(), Level::Fail);
+    let worst = dracula.worst_ansi().unwrap();
+    assert_ne!(worst.name, "normal.black");
+    assert!(dracula.below(4.5, 1.0).is_empty());
+    assert_eq!(
+        dracula
+            .below(4.5, worst.ratio + 0.01)
+            .iter()
+            .map(|contrast| &contrast.name)
+            .collect::<Vec<_>>(),
+        [&worst.name]
+    );
+    assert_eq!(dracula.below(21.0, 1.0)[0].name, "foreground");
+}
+
+#[test]
+fn base16_round_trip() {
+    let catalog = catalog();
+
+    let (mut checked, mut guessed_wrong) = (0, 0);
+    // Asam's colors were picked by hand, not generated from its scheme.
+    let generated = catalog
+        .iter()
+        .filter(|entry| entry.collection == "base16" && entry.slug != "base16-asam");
+    for entry in generated {
+        let scheme = convert::export(Format::Base16, &entry.name, &entry.theme);
+        let imported = convert::import(Format::Base16, &scheme).expect(&entry.file);
+        assert_eq!(imported.name.as_deref(), Some(&*entry.name));
+        // Schemes don't say how to draw details, so a few themes differ.
+        let theme = Theme {
+            details: entry.theme.details.clone(),
+            ..imported.theme.clone()
+        };
+        assert_eq!(theme, entry.theme, "{}", entry.file);
+        if imported.theme.details != entry.theme.details {
+            guessed_wrong += 1;
+        }
+        assert_eq!(
+            convert::export(Format::Base16, &entry.name, &imported.theme),
+            scheme
+        );
+        checked += 1;
+    }
+    assert!(checked > 170, "only {} base16 themes", checked);
+    assert!(
+        guessed_wrong <= 10,
+        "{} themes' details differ",
+        guessed_wrong
+    );
+}
+
+#[test]
+fn convert_round_trip() {
+    let catalog = catalog();
+
+    for slug in &[
+        "standard-dracula",
+        "willow-dream",
+        "standard-solarized-light",
+    ] {
+        let theme = &catalog.get(slug).expect(slug).theme;
+        for &format in FORMATS.iter() {
+            let exported = convert::export(format, "Round Trip", theme);
+            let imported = convert::import(format, &exported)
+                .unwrap_or_else(|err| panic!("{} {}: {}", slug, format, err));
+            let back = imported.theme;
+            assert_eq!(back.foreground, theme.foreground, "{} {}", slug, format);
+            assert_eq!(back.background, Background::Solid(theme.background.top()));
+            assert_eq!(back.accent, Accent::Solid(back.terminal_colors.normal.blue));
+
+            // Base16 has no black or white of its own.
+            let mut colors = theme.terminal_colors.clone();
+            if format == Format::Base16 {
+                colors.normal.black = theme.background.top();
+                colors.normal.white = theme.foreground;
+            }
+            assert_eq!(back.terminal_colors, colors, "{} {}", slug, format);
+            assert_eq!(back.details, theme.details, "{} {}", slug, format);
+            assert_eq!(back.background_image, None);
+
+            let name = imported.name.as_deref();
+            match format {
+                Format::ITerm | Format::Alacritty => assert_eq!(name, None),
+                _ => assert_eq!(name, Some("Round Trip"), "{}", format),
+            }
+            assert_eq!(format.to_string().parse::<Format>().unwrap(), format);
+        }
+    }
+    assert!("xterm".parse::<Format>().is_err());
+}
+
+#[test]
+fn convert_formats() {
+    let catalog = catalog();
+    let dracula = &catalog.get("standard-dracula").unwrap().theme;
+
+    // Alacritty's older YAML configs.
+    let yaml = "\
+colors:
+  primary:
+    background: '0x282a36'
+    foreground: '0xf8f8f2'
+  normal: {black: '0x000000', red: '0xff5555', green: '0x50fa7b', yellow: '0xf1fa8c',
+           blue: '0xbd93f9', magenta: '0xff79c6', cyan: '0x8be9fd', white: '0xbbbbbb'}
+  bright: {black: '0x555555', red: '0xff5555', green: '0x50fa7b', yellow: '0xf1fa8c',
+           blue: '0xcaa9fa', magenta: '0xff79c6', cyan: '0x8be9fd', white: '0xffffff'}
+";
+    let imported = convert::import(Format::Alacritty, yaml).unwrap();
+    assert_eq!(imported.theme.foreground, dracula.foreground);
+    assert_eq!(imported.theme.terminal_colors, dracula.terminal_colors);
+    assert_eq!(
+        imported.theme.accent,
+        Accent::Solid(dracula.terminal_colors.normal.blue)
+    );
+
+    // TOML with inline tables and quoted keys.
+    let toml = convert::export(Format::Alacritty, "Dracula", dracula)
+        .replace("[colors.primary]", "[colors.\"primary\"]  # quoted");
+    let toml = format!("[window]\nopacity = 0.9\n\n{}", toml);
+    let imported = convert::import(Format::Alacritty, &toml).unwrap();
+    assert_eq!(
+        imported.theme.background,
+        Background::Solid(dracula.background.top())
+    );
+
+    // A whole Windows Terminal settings.json.
+    let scheme = convert::export(Format::WindowsTerminal, "Dracula", dracula);
+    let settings = format!(r#"{{"profiles": {{}}, "schemes": [{}]}}"#, scheme);
+    let imported = convert::import(Format::WindowsTerminal, &settings).unwrap();
+    assert_eq!(imported.name.as_deref(), Some("Dracula"));
+    assert_eq!(imported.theme.terminal_colors, dracula.terminal_colors);
+
+    // Tinted-theming's base16 layout.
+    let classic = convert::export(Format::Base16, "Dracula", dracula);
+    let mut tinted = String::from("system: base16\nname: Tinted\npalette:\n");
+    for line in classic.lines().filter(|line| line.starts_with("base")) {
+        tinted.push_str("  ");
+        tinted.push_str(line);
+        tinted.push('\n');
+    }
+    let imported = convert::import(Format::Base16, &tinted).unwrap();
+    assert_eq!(imported.name.as_deref(), Some("Tinted"));
+    assert_eq!(
+        imported.theme,
+        convert::import(Format::Base16, &classic).unwrap().theme
+    );
+}
+
+#[test]
+fn convert_errors() {
+    let catalog = catalog();
+    let dracula = &catalog.get("standard-dracula").unwrap().theme;
+
+    let kitty =
+        convert::export(Format::Kitty, "Dracula", dracula).replace("color3 ", "color3 nope");
+    let err = convert::import(Format::Kitty, &kitty).unwrap_err();
+    let line = kitty
+        .lines()
+        .position(|line| line.starts_with("color3"))
+        .unwrap()
+        + 1;
+    assert_eq!(err.line(), Some(line));
+    assert!(
+        err.to_string().starts_with("invalid kitty theme: line "),
+        "{}",
+        err
+    );
+
+    let toml = convert::export(Format::Alacritty, "Dracula", dracula);
+    let missing = toml.replace("magenta = ", "# magenta = ");
+    let err = convert::import(Format::Alacritty, &missing).unwrap_err();
+    assert_eq!(err.line(), None);
+    assert!(
+        err.to_string().ends_with("missing color 5, color 13"),
+        "{}",
+        err
+    );
+    let unclosed = toml.replace("[colors.bright]", "[colors.bright");
+    let err = convert::import(Format::Alacritty, &unclosed).unwrap_err();
+    let line = unclosed
+        .lines()
+        .position(|line| line == "[colors.bright")
+        .unwrap()
+        + 1;
+    assert_eq!(err.line(), Some(line));
+
+    let plist = convert::export(Format::ITerm, "Dracula", dracula);
+    let broken = plist.replacen("<real>1</real>", "<real>one</real>", 1);
+    let err = convert::import(Format::ITerm, &broken).unwrap_err();
+    let line = broken
+        .lines()
+        .position(|line| line.contains("<real>one"))
+        .unwrap()
+        + 1;
+    assert_eq!(err.line(), Some(line));
+    assert!(convert::import(Format::ITerm, "").is_err());
+
+    let err = convert::import(Format::WindowsTerminal, "{\n  \"name\": \n}").unwrap_err();
+    assert_eq!(err.line(), Some(3));
+    let err = convert::import(Format::Base16, "scheme: Half\nbase00: \"000000\"\n").unwrap_err();
+    assert!(err.to_string().contains("missing base01"), "{}", err);
+}
+
+#[test]
+fn previews() {
+    let catalog = catalog();
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/static/themes");
+
+    // Every theme's bundled preview is exactly what's rendered, as
+    // `gen-theme-previews` writes it.
+    for entry in catalog.iter() {
+        let (collection, file) = entry.file.split_once('/').unwrap();
+        let path = format!("{}/{}/previews/{}.svg", dir, collection, file);
+        let bundled = std::fs::read_to_string(&path).expect(&path);
+        assert!(
+            preview::render(&entry.theme) == bundled,
+            "{} is out of date",
+            path
+        );
+    }
+
+    // Colors are lowercase hex, and a solid background fills the frame.
+    let dracula = preview::render(&catalog.get("standard-dracula").unwrap().theme);
+    assert!(dracula.starts_with(concat!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 300 145">"##,
+        r##"<rect width="300" height="145" fill="#282a36" class="Dynamic" rx="5"/>"##,
+    )));
+    assert!(!dracula.contains("<defs>"));
+
+    let willow = preview::render(&catalog.get("willow-dream").unwrap().theme);
+    assert!(willow.contains(concat!(
+        r##"<linearGradient id="background" x2="0" y2="1">"##,
+        r##"<stop offset="0" stop-color="#206169"/><stop offset="1" stop-color="#022f27"/>"##,
+    )));
+    assert!(willow.contains(r##"fill="url(#background)""##));
+    assert!(willow.contains(r##"stroke="url(#accent)""##));
+    assert!(!willow.contains('{'));
+}
+
+#[tokio::test]
+async fn preview_route() {
+    let catalog = catalog();
+    let api = nextshell::themes::api(catalog.clone());
+
+    let res = nextshell::test::request()
+        .path("/standard-dracula/preview.svg")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "image/svg+xml");
+    let dracula = &catalog.get("standard-dracula").unwrap().theme;
+    assert_eq!(res.body(), &preview::render(dracula));
+
+    // Cached, and tagged by the theme, not the image.
+    let etag = res.headers()["etag"].clone();
+    let again = nextshell::test::request()
+        .path("/standard-dracula/preview.svg")
+        .reply(&api)
+        .await;
+    assert_eq!(again.body(), res.body());
+    assert_eq!(again.headers()["etag"], etag);
+    let theme = nextshell::test::request()
+        .path("/standard-dracula")
+        .reply(&api)
+        .await;
+    assert_ne!(theme.headers()["etag"], etag);
+
+    let res = nextshell::test::request()
+        .path("/standard-dracula/preview.svg")
+        .header("if-none-match", etag.clone())
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert!(res.body().is_empty());
+    assert_eq!(res.headers()["etag"], etag);
+
+    let res = nextshell::test::request()
+        .path("/no-such-theme/preview.svg")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[cfg(feature = "multipart")]
+fn upload(path: &str, filename: &str, file: &str) -> nextshell::test::RequestBuilder {
+    let boundary = "--theme-upload--";
+    let body = format!(
+        "\
+         --{0}\r\n\
+         content-disposition: form-data; name=\"file\"; filename=\"{1}\"\r\n\r\n\
+         {2}\r\n\
+         --{0}--\r\n\
+         ",
+        boundary, filename, file
+    );
+    nextshell::test::request()
+        .method("POST")
+        .path(path)
+        .header("content-length", body.len())
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+}
+
+#[cfg(feature = "multipart")]
+#[tokio::test]
+async fn import_route() {
+    let catalog = catalog();
+    let api = nextshell::themes::api(catalog.clone());
+    let dracula = &catalog.get("standard-dracula").unwrap().theme;
+
+    let kitty = convert::export(Format::Kitty, "Dracula Pro", dracula);
+    let res = upload("/import/kitty", "ignored.conf", &kitty)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/yaml");
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"dracula_pro.yaml\""
+    );
+    let theme: Theme = serde_yaml::from_slice(res.body()).unwrap();
+    assert_eq!(theme.terminal_colors, dracula.terminal_colors);
+
+    // iTerm2 files have no name; the upload's is used.
+    let plist = convert::export(Format::ITerm, "Dracula", dracula);
+    let res = upload("/import/iterm", "Night Owl.itermcolors", &plist)
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"night_owl.yaml\""
+    );
+
+    let res = upload("/import/kitty", "bad.conf", "color1 #12345")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        res.body(),
+        "invalid kitty theme: line 1: invalid color \"#12345\" for color1\n"
+    );
+
+    let res = upload("/import/xterm", "a.conf", &kitty).reply(&api).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.body(), "unknown theme format \"xterm\"\n");
+}
+
+#[cfg(feature = "multipart")]
+#[tokio::test]
+async fn export_route() {
+    let catalog = catalog();
+    let api = nextshell::themes::api(catalog.clone());
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/static/themes");
+    let yaml = std::fs::read_to_string(format!("{}/standard/dracula.yaml", dir)).unwrap();
+
+    for &format in FORMATS.iter() {
+        let res = upload(&format!("/export/{}", format), "dracula.yaml", &yaml)
+            .reply(&api)
+            .await;
+        assert_eq!(res.status(), 200, "{}", format);
+        assert_eq!(res.headers()["content-type"], format.content_type());
+        assert_eq!(
+            res.headers()["content-disposition"],
+            &*format!("attachment; filename=\"dracula.{}\"", format.extension())
+        );
+        let body = std::str::from_utf8(res.body()).unwrap();
+        let dracula = &catalog.get("standard-dracula").unwrap().theme;
+        assert_eq!(body, convert::export(format, "Dracula", dracula));
+    }
+
+    let res = upload("/export/kitty", "broken.yaml", "foreground: \"#fff\"\n")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(body.starts_with("line 1: missing `accent`"), "{}", body);
+}
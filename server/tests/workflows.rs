@@ -0,0 +1,249 @@
+#![deny(warnings)]
+
+use std::sync::Arc;
+
+use nextshell::workflows::{
+    Catalog, Layer, Query, Shell, Source, UsageLog, UsageRecord, WorkflowStore,
+};
+use nextshell::Filter;
+use serde_json::Value;
+
+fn catalog() -> Arc<Catalog> {
+    Arc::new(Catalog::bundled())
+}
+
+async fn body_of<F>(api: &F, path: &str) -> Value
+where
+    F: Filter + 'static,
+    F::Extract: nextshell::Reply + Send,
+{
+    let res = nextshell::test::request().path(path).reply(api).await;
+    assert_eq!(res.status(), 200, "{}", path);
+    serde_json::from_slice(res.body()).unwrap()
+}
+
+#[test]
+fn bundled() {
+    let catalog = catalog();
+    assert_eq!(catalog.len(), nextshell_workflows::EMBEDDED_SPECS.len());
+
+    let entry = catalog.get("undo_most_recent_git_commit").unwrap();
+    assert_eq!(entry.path, "git/undo_most_recent_git_commit.yaml");
+
+    let found = catalog.search(&Query::new("undo comit"));
+    assert!(found
+        .iter()
+        .any(|(entry, _)| entry.slug == "undo_most_recent_git_commit"));
+
+    let fish = catalog.search(&Query::new("").with_shell(Shell::Fish));
+    assert!(fish
+        .iter()
+        .all(|(entry, _)| entry.workflow.shells.is_empty()
+            || entry.workflow.shells.contains(&Shell::Fish)));
+}
+
+#[tokio::test]
+async fn search_route() {
+    let _ = pretty_env_logger::try_init();
+
+    let catalog = catalog();
+    let api = nextshell::path("workflows").and(nextshell::workflows::api(catalog.clone()));
+
+    let page = body_of(&api, "/workflows/search?q=chnage%20remote%20url").await;
+    assert_eq!(
+        page["results"][0]["slug"],
+        "change_url_of_remote_git_repository"
+    );
+    assert_eq!(page["page"], 1);
+    assert_eq!(page["per_page"], 20);
+
+    let page = body_of(&api, "/workflows/search?tags=git,%20GitHub&shell=zsh").await;
+    let results = page["results"].as_array().unwrap();
+    assert!(!results.is_empty());
+    for workflow in results {
+        let tags = workflow["tags"].as_array().unwrap();
+        assert!(tags.iter().any(|tag| tag == "git"), "{}", workflow);
+        assert!(tags.iter().any(|tag| tag == "github"), "{}", workflow);
+    }
+}
+
+#[tokio::test]
+async fn pagination() {
+    let _ = pretty_env_logger::try_init();
+
+    let catalog = catalog();
+    let api = nextshell::workflows::api(catalog.clone());
+
+    let first = body_of(&api, "/search?per_page=7").await;
+    assert_eq!(first["total"], catalog.len());
+    assert_eq!(first["results"].as_array().unwrap().len(), 7);
+
+    let second = body_of(&api, "/search?per_page=7&page=2").await;
+    assert_eq!(second["results"].as_array().unwrap().len(), 7);
+    assert_ne!(second["results"][0], first["results"][0]);
+    assert_eq!(
+        second["results"][0],
+        body_of(&api, "/search?per_page=14").await["results"][7]
+    );
+
+    let past = body_of(&api, "/search?page=1000").await;
+    assert_eq!(past["total"], catalog.len());
+    assert!(past["results"].as_array().unwrap().is_empty());
+
+    let most = body_of(&api, "/search?per_page=100000").await;
+    assert_eq!(most["per_page"], 100);
+
+    for path in &[
+        "/search?page=0",
+        "/search?shell=powershell",
+        "/search?page=x",
+    ] {
+        let res = nextshell::test::request().path(path).reply(&api).await;
+        assert_eq!(res.status(), 400, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn get() {
+    let api = nextshell::workflows::api(catalog());
+
+    let entry = body_of(&api, "/undo_most_recent_git_commit").await;
+    assert_eq!(entry["slug"], "undo_most_recent_git_commit");
+    assert!(entry["workflow"]["command"]
+        .as_str()
+        .unwrap()
+        .contains("git reset"));
+
+    let res = nextshell::test::request()
+        .path("/no_such_workflow")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn layered_sources() {
+    let dir = std::env::temp_dir().join(format!("nextshell-workflows-team-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    std::fs::write(
+        dir.join("undo_most_recent_git_commit.yaml"),
+        "name: Undo, our way\ncommand: git undo\n",
+    )
+    .expect("write spec");
+    let store = WorkflowStore::new()
+        .with_layer(Layer::bundled())
+        .with_layer(Layer::new("team", Source::Directory(dir)));
+    let catalog = Arc::new(Catalog::new(&store));
+    assert_eq!(catalog.len(), nextshell_workflows::EMBEDDED_SPECS.len());
+    let api = nextshell::workflows::api(catalog);
+
+    let entry = body_of(&api, "/undo_most_recent_git_commit").await;
+    assert_eq!(entry["source"], "team");
+    assert_eq!(entry["qualified_slug"], "team:undo_most_recent_git_commit");
+    assert_eq!(entry["workflow"]["command"], "git undo");
+    assert_eq!(
+        entry["shadows"],
+        serde_json::json!(["bundled:undo_most_recent_git_commit"])
+    );
+
+    let shadowed = body_of(&api, "/bundled:undo_most_recent_git_commit").await;
+    assert_eq!(shadowed["slug"], "bundled:undo_most_recent_git_commit");
+    assert_eq!(shadowed["source"], "bundled");
+    assert_eq!(shadowed["qualified_slug"], shadowed["slug"]);
+    assert!(shadowed["workflow"]["command"]
+        .as_str()
+        .unwrap()
+        .contains("git reset"));
+    assert_eq!(
+        body_of(&api, "/team:undo_most_recent_git_commit").await,
+        entry
+    );
+
+    let page = body_of(&api, "/search?q=undo%20our%20way").await;
+    assert_eq!(page["results"][0]["slug"], "undo_most_recent_git_commit");
+    assert_eq!(page["results"][0]["source"], "team");
+    assert_eq!(page["total"], 1);
+}
+
+#[tokio::test]
+async fn usage() {
+    let path = std::env::temp_dir().join(format!(
+        "nextshell-workflows-usage-{}.jsonl",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let log = UsageLog::new(&path);
+    let bundled = Catalog::bundled();
+    let run = |slug: &str, exit_code: i32| {
+        let workflow = &bundled.get(slug).unwrap().workflow;
+        UsageRecord::new(
+            slug,
+            workflow,
+            &Default::default(),
+            Shell::Bash,
+            Some(exit_code),
+        )
+    };
+    log.append(&run("undo_most_recent_git_commit", 0)).unwrap();
+
+    let used = Arc::new(Catalog::bundled().with_usage(log).unwrap());
+    used.record(&run("undo_most_recent_git_commit", 1)).unwrap();
+    used.record(&run("clone_all_repos_in_org", 0)).unwrap();
+    let api = nextshell::workflows::api(used);
+
+    // The most used first, then by name.
+    let page = body_of(&api, "/search").await;
+    assert_eq!(page["results"][0]["slug"], "undo_most_recent_git_commit");
+    assert_eq!(page["results"][1]["slug"], "clone_all_repos_in_org");
+    assert_eq!(page["results"][2]["score"], 0);
+
+    let stats = body_of(&api, "/usage").await;
+    assert_eq!(stats[0]["slug"], "undo_most_recent_git_commit");
+    assert_eq!(stats[0]["runs"], 2);
+    assert_eq!(stats[0]["failures"], 1);
+    assert_eq!(stats[0]["shells"]["bash"], 2);
+    assert_eq!(stats[1]["slug"], "clone_all_repos_in_org");
+    assert_eq!(
+        body_of(&api, "/usage?since=99999999999").await,
+        Value::Array(vec![])
+    );
+
+    let stats = body_of(&nextshell::workflows::api(catalog()), "/usage").await;
+    assert_eq!(stats, Value::Array(vec![]));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn export() {
+    let api = nextshell::workflows::api(catalog());
+
+    let res = nextshell::test::request()
+        .path("/export/bash?tags=git&q=undo%20commit")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(
+        res.headers()["content-disposition"],
+        "attachment; filename=\"workflows.bash\""
+    );
+    let file = std::str::from_utf8(res.body()).unwrap();
+    assert!(file.starts_with("# Workflows from nextshell, as bash functions.\n"));
+    assert!(file.contains("\nundo_most_recent_git_commit() {\n"));
+    assert!(!file.contains("docker"));
+
+    let res = nextshell::test::request()
+        .path("/export/fish?slugs=undo_most_recent_git_commit")
+        .reply(&api)
+        .await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "application/x-tar");
+    let tar = res.body();
+    // A ustar header: the file name, then later its directory.
+    assert!(tar.starts_with(b"undo_most_recent_git_commit.fish\0"));
+    assert_eq!(&tar[345..355], b"functions\0");
+
+    for path in &["/export/powershell", "/export/zsh?slugs=no_such_workflow"] {
+        let res = nextshell::test::request().path(path).reply(&api).await;
+        assert_eq!(res.status(), 400, "{}", path);
+    }
+}
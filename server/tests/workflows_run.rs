@@ -0,0 +1,572 @@
+#![deny(warnings)]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::body::HttpBody;
+use nextshell::terminal::blocks::{self, Shell};
+use nextshell::terminal::{Command, Registry};
+use nextshell::workflows::{AllowList, Catalog, UsageLog};
+use nextshell::Filter;
+use nextshell_workflows::{Layer, Source, WorkflowStore};
+use serde_json::json;
+
+const GREET: &str = "\
+name: Greet
+command: echo {{greeting}}; test {{greeting}} = hello
+arguments:
+  - name: greeting
+";
+
+const CHECK: &str = "\
+name: Check
+arguments:
+  - name: word
+steps:
+  - description: Say it
+    command: echo said {{word}}
+  - command: echo then; test {{word}} = hello
+  - command: echo done
+";
+
+// With recordings in `dir`, the second step can't be started.
+const UNSTARTABLE: &str = "\
+name: Unstartable
+arguments:
+  - name: dir
+steps:
+  - command: rm -r {{dir}}
+  - command: echo never
+";
+
+const CONFIRM: &str = "\
+name: Confirm
+steps:
+  - command: echo sure
+    confirm: true
+";
+
+fn store() -> WorkflowStore {
+    let dir = temp_dir("specs");
+    std::fs::write(dir.join("greet.yaml"), GREET).expect("write spec");
+    std::fs::write(dir.join("check.yaml"), CHECK).expect("write spec");
+    std::fs::write(dir.join("confirm.yaml"), CONFIRM).expect("write spec");
+    std::fs::write(dir.join("unstartable.yaml"), UNSTARTABLE).expect("write spec");
+    std::fs::write(dir.join("wait.yaml"), "name: Wait\ncommand: sleep 30\n").expect("write spec");
+    std::fs::write(
+        dir.join("count.yaml"),
+        "name: Count\ncommand: seq 1 20000\n",
+    )
+    .expect("write spec");
+    WorkflowStore::new().with_layer(Layer::new("local", Source::Directory(dir)))
+}
+
+fn catalog() -> Catalog {
+    Catalog::new(&store())
+}
+
+fn route(
+    catalog: Catalog,
+    allowed: &[&str],
+    registry: Registry,
+    shell: Command,
+) -> impl Filter<Extract = (nextshell::reply::Response,), Error = nextshell::Rejection> + Clone + 'static
+{
+    nextshell::workflows::run(
+        Arc::new(catalog),
+        Arc::new(AllowList::new(allowed.iter().copied())),
+        registry,
+        shell,
+    )
+}
+
+#[tokio::test]
+async fn run_in_new_session() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("new").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let registry = Registry::new();
+    let route = route(
+        catalog,
+        &["local:greet"],
+        registry.clone(),
+        nextshell::terminal::command("bash"),
+    );
+
+    let res = tokio::time::timeout(
+        Duration::from_secs(10),
+        nextshell::test::request()
+            .method("POST")
+            .path("/greet/run")
+            .json(&json!({"values": {"greeting": "hi there; ls"}}))
+            .reply(&route),
+    )
+    .await
+    .expect("timed out");
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(
+        body.starts_with("event:session\ndata:{\"session\":\""),
+        "{}",
+        body
+    );
+    // The value is one argument, not a second command.
+    assert!(body.contains("data:\"hi there; ls\\r\\n\""), "{}", body);
+    assert!(
+        body.ends_with("event:exit\ndata:{\"code\":1,\"signal\":null}\n\n"),
+        "{}",
+        body
+    );
+    // The session was the run's own, and is gone with it.
+    assert_eq!(registry.len(), 0);
+
+    let records = UsageLog::new(&log).records().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].slug, "greet");
+    assert_eq!(records[0].arguments["greeting"], "hi there; ls");
+    assert_eq!(records[0].exit_code, Some(1));
+}
+
+#[tokio::test]
+async fn recorded_without_listening() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("unheard").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let route = route(
+        catalog,
+        &["local:greet"],
+        Registry::new(),
+        nextshell::terminal::command("bash"),
+    );
+
+    // The client goes away without reading a single event.
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/greet/run")
+        .json(&json!({"values": {"greeting": "hello"}}))
+        .filter(&route)
+        .await
+        .expect("filter");
+    assert_eq!(res.status(), 200);
+    drop(res);
+
+    let records = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let records = UsageLog::new(&log).records().unwrap();
+            if !records.is_empty() {
+                return records;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("the run wasn't recorded");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].exit_code, Some(0));
+}
+
+#[tokio::test]
+async fn slow_clients_dont_hold_up_runs() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("slow").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let route = route(
+        catalog,
+        &["local:count"],
+        Registry::new(),
+        nextshell::terminal::command("bash"),
+    );
+
+    // The client stays, but reads nothing until the run is over.
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/count/run")
+        .json(&json!({}))
+        .filter(&route)
+        .await
+        .expect("filter");
+    assert_eq!(res.status(), 200);
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while UsageLog::new(&log).records().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("the run was held up");
+
+    let body = hyper::body::to_bytes(res.into_body()).await.expect("body");
+    let body = std::str::from_utf8(&body).unwrap();
+    assert!(
+        body.ends_with("event:exit\ndata:{\"code\":0,\"signal\":null}\n\n"),
+        "{}",
+        body
+    );
+}
+
+#[tokio::test]
+async fn recorded_as_resolved() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("resolved").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let route = route(
+        catalog,
+        &["local:greet"],
+        Registry::new(),
+        nextshell::terminal::command("bash"),
+    );
+
+    for slug in &["greet", "local:greet"] {
+        let res = nextshell::test::request()
+            .method("POST")
+            .path(&format!("/{}/run", slug))
+            .json(&json!({"values": {"greeting": "hello"}}))
+            .reply(&route)
+            .await;
+        assert_eq!(res.status(), 200, "{}", slug);
+    }
+
+    let records = UsageLog::new(&log).records().unwrap();
+    let slugs = records
+        .iter()
+        .map(|record| &*record.slug)
+        .collect::<Vec<_>>();
+    assert_eq!(slugs, ["greet", "greet"]);
+}
+
+#[tokio::test]
+async fn run_in_live_session() {
+    let _ = pretty_env_logger::try_init();
+
+    let rcfile = temp_dir("live").join("bashrc");
+    std::fs::write(
+        &rcfile,
+        format!("PS1='$ '\n{}", blocks::init_script(&Shell::Bash)),
+    )
+    .expect("write rcfile");
+    let cmd = nextshell::terminal::command("bash").args([
+        "--noprofile".as_ref(),
+        "--rcfile".as_ref(),
+        rcfile.as_os_str(),
+        "-i".as_ref(),
+    ]);
+    let registry = Registry::new().blocks(true);
+    let id = registry.spawn(&cmd).expect("spawn");
+    let route = route(catalog(), &["local:greet"], registry.clone(), cmd);
+
+    let res = tokio::time::timeout(
+        Duration::from_secs(10),
+        nextshell::test::request()
+            .method("POST")
+            .path("/greet/run")
+            .json(&json!({"values": {"greeting": "hello"}, "session": id}))
+            .reply(&route),
+    )
+    .await
+    .expect("timed out");
+    assert_eq!(res.status(), 200);
+
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(
+        body.starts_with(&format!(
+            "event:session\ndata:{{\"session\":\"{}\"}}\n\n",
+            id
+        )),
+        "{}",
+        body
+    );
+    assert!(body.contains("event:output\n"), "{}", body);
+    assert!(
+        body.ends_with("event:exit\ndata:{\"code\":0,\"signal\":null}\n\n"),
+        "{}",
+        body
+    );
+    // The session outlives the run.
+    assert!(registry.kill(&id));
+}
+
+#[tokio::test]
+async fn runbook_steps() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("steps").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let route = route(
+        catalog,
+        &["local:check", "local:confirm"],
+        Registry::new(),
+        nextshell::terminal::command("bash"),
+    );
+    let run = |word: &str| {
+        let req = nextshell::test::request()
+            .method("POST")
+            .path("/check/run")
+            .json(&json!({"values": {"word": word}}));
+        let route = route.clone();
+        async move {
+            let res = tokio::time::timeout(Duration::from_secs(10), req.reply(&route))
+                .await
+                .expect("timed out");
+            assert_eq!(res.status(), 200);
+            String::from_utf8(res.body().to_vec()).unwrap()
+        }
+    };
+
+    let body = run("hello").await;
+    assert!(
+        body.starts_with(
+            "event:step\ndata:{\"step\":0,\"description\":\"Say it\"}\n\n\
+             event:session\n"
+        ),
+        "{}",
+        body
+    );
+    assert!(body.contains("data:\"said hello\\r\\n\""), "{}", body);
+    assert!(
+        body.contains("event:step\ndata:{\"step\":2,\"description\":null}\n\n"),
+        "{}",
+        body
+    );
+    assert!(body.contains("data:\"done\\r\\n\""), "{}", body);
+    assert_eq!(body.matches("event:session\n").count(), 3, "{}", body);
+    assert_eq!(body.matches("event:exit\n").count(), 3, "{}", body);
+
+    // A failing step is the last run.
+    let body = run("bye").await;
+    assert!(body.contains("data:\"then\\r\\n\""), "{}", body);
+    assert!(!body.contains("\"step\":2"), "{}", body);
+    assert!(!body.contains("done"), "{}", body);
+    assert!(
+        body.ends_with("event:exit\ndata:{\"code\":1,\"signal\":null}\n\n"),
+        "{}",
+        body
+    );
+
+    let records = UsageLog::new(&log).records().unwrap();
+    let exit_codes = records
+        .iter()
+        .map(|record| record.exit_code)
+        .collect::<Vec<_>>();
+    assert_eq!(exit_codes, [Some(0), Some(1)]);
+
+    // There's no one to ask before a step to confirm.
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/confirm/run")
+        .json(&json!({}))
+        .reply(&route)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(
+        res.body(),
+        "workflow `confirm` has a step to confirm before it's run\n"
+    );
+}
+
+#[tokio::test]
+async fn steps_that_cant_start() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("unstartable").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let recordings = temp_dir("unstartable-recordings");
+    let route = route(
+        catalog,
+        &["local:unstartable"],
+        Registry::new().record(&recordings),
+        nextshell::terminal::command("bash"),
+    );
+
+    let res = tokio::time::timeout(
+        Duration::from_secs(10),
+        nextshell::test::request()
+            .method("POST")
+            .path("/unstartable/run")
+            .json(&json!({"values": {"dir": recordings}}))
+            .reply(&route),
+    )
+    .await
+    .expect("timed out");
+    assert_eq!(res.status(), 200);
+
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(!body.contains("never"), "{}", body);
+    assert!(
+        body.ends_with(
+            "event:exit\ndata:{\"code\":0,\"signal\":null}\n\n\
+             event:aborted\ndata:{\"step\":1,\"reason\":\"couldn't start the step\"}\n\n"
+        ),
+        "{}",
+        body
+    );
+
+    let records = UsageLog::new(&log).records().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].exit_code, Some(127));
+}
+
+#[tokio::test]
+async fn killed_sessions() {
+    let _ = pretty_env_logger::try_init();
+
+    let log = temp_dir("killed").join("usage.jsonl");
+    let _ = std::fs::remove_file(&log);
+    let catalog = catalog().with_usage(UsageLog::new(&log)).unwrap();
+    let registry = Registry::new();
+    let route = route(
+        catalog,
+        &["local:wait"],
+        registry.clone(),
+        nextshell::terminal::command("bash"),
+    );
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/wait/run")
+        .json(&json!({}))
+        .filter(&route)
+        .await
+        .expect("filter");
+    assert_eq!(res.status(), 200);
+    let mut body = res.into_body();
+    let first = body.data().await.expect("an event").expect("body");
+    let first = std::str::from_utf8(&first).unwrap();
+    let id = first
+        .split('"')
+        .nth(3)
+        .unwrap_or_else(|| panic!("no session in {:?}", first));
+    assert!(registry.kill(id));
+
+    let rest = tokio::time::timeout(Duration::from_secs(10), hyper::body::to_bytes(body))
+        .await
+        .expect("timed out")
+        .expect("body");
+    let rest = std::str::from_utf8(&rest).unwrap();
+    assert!(
+        rest.ends_with(
+            "event:aborted\ndata:{\"step\":0,\"reason\":\"the session was killed\"}\n\n"
+        ),
+        "{}",
+        rest
+    );
+
+    let records = UsageLog::new(&log).records().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].exit_code, None);
+}
+
+#[tokio::test]
+async fn allowed_as_resolved() {
+    let _ = pretty_env_logger::try_init();
+
+    let dir = temp_dir("team");
+    std::fs::write(
+        dir.join("greet.yaml"),
+        "name: Greet, our way\ncommand: echo team\n",
+    )
+    .expect("write spec");
+    let shadowed = Catalog::new(
+        &store().with_layer(Layer::new("team", Source::Directory(dir)).with_precedence(1)),
+    );
+    let shell = nextshell::terminal::command("bash");
+    let post = |slug: &str| {
+        nextshell::test::request()
+            .method("POST")
+            .path(&format!("/{}/run", slug))
+            .json(&json!({"values": {"greeting": "hello"}}))
+    };
+
+    // Without a shadowing layer, the slug and the qualified slug are allowed
+    // alike.
+    let unshadowed = route(catalog(), &["local:greet"], Registry::new(), shell.clone());
+    for slug in &["greet", "local:greet"] {
+        let res = post(slug).reply(&unshadowed).await;
+        assert_eq!(res.status(), 200, "{}", slug);
+    }
+
+    // A layer shadowing an allowed workflow doesn't run in its place.
+    let allowed = route(shadowed, &["local:greet"], Registry::new(), shell);
+    let res = post("greet").reply(&allowed).await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), "workflow `greet` isn't allowed to run\n");
+    let res = post("team:greet").reply(&allowed).await;
+    assert_eq!(res.status(), 403);
+    let res = post("local:greet").reply(&allowed).await;
+    assert_eq!(res.status(), 200);
+    let body = std::str::from_utf8(res.body()).unwrap();
+    assert!(body.contains("event:exit\ndata:{\"code\":0"), "{}", body);
+}
+
+#[tokio::test]
+async fn rejected() {
+    let _ = pretty_env_logger::try_init();
+
+    let shell = nextshell::terminal::command("bash");
+    let registry = Registry::new();
+    let allowed = route(catalog(), &["local:greet"], registry.clone(), shell.clone());
+    let post = |body: serde_json::Value| {
+        nextshell::test::request()
+            .method("POST")
+            .path("/greet/run")
+            .json(&body)
+    };
+
+    let res = post(json!({"values": {"greeting": "hello"}}))
+        .reply(&route(catalog(), &[], registry.clone(), shell))
+        .await;
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.body(), "workflow `greet` isn't allowed to run\n");
+
+    let res = nextshell::test::request()
+        .method("POST")
+        .path("/nope/run")
+        .json(&json!({}))
+        .reply(&allowed)
+        .await;
+    assert_eq!(res.status(), 404);
+
+    let res = post(json!({})).reply(&allowed).await;
+    assert_eq!(res.status(), 400);
+    assert!(std::str::from_utf8(res.body())
+        .unwrap()
+        .contains("greeting"));
+
+    // Quoted, a value can't be a second command line, but typed into a
+    // terminal, control characters could start one anyway.
+    let res = post(json!({"values": {"greeting": "x\u{15}touch pwned\r"}}))
+        .reply(&allowed)
+        .await;
+    assert_eq!(res.status(), 400);
+    assert_eq!(
+        res.body(),
+        "invalid value for argument `greeting`: control characters aren't allowed\n"
+    );
+
+    let res = post(json!({"values": {"greeting": "hello"}, "session": "nope"}))
+        .reply(&allowed)
+        .await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(registry.len(), 0);
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "nextshell-workflows-run-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
@@ -1,27 +1,12 @@
-export interface Workflow {
-  slug: WorkflowSlug;
-  name: string;
-  command: string;
-  tags?: [string];
-  description?: string;
-  arguments?: [Argument];
-  source_url?: string;
-  author?: string;
-  author_url?: string;
-  shells?: [Shell];
-  relative_git_url: string;
-}
+import { Workflow as Spec } from "./types";
 
-export interface Argument {
-  name: string;
-  description?: string;
-  default_value?: string;
-}
+export type { Argument } from "./types";
+export { ArgumentType, Shell } from "./types";
 
-export const enum Shell {
-  Bash = "bash",
-  Fish = "fish",
-  Zsh = "zsh",
+// A workflow as loaded from its spec, with where the spec is.
+export interface Workflow extends Spec {
+  slug: WorkflowSlug;
+  relative_git_url: string;
 }
 
 export type WorkflowSlug = string;
@@ -0,0 +1,99 @@
+// Generated from the Rust types of `workflow-types`. Don't edit this file; change the types and
+// run `cargo run --bin generate-types` instead.
+
+/** A command, with `{{argument}}` placeholders, and what it's for. */
+export interface Workflow {
+  name: string;
+  /** The command, with a `{{name}}` placeholder for each argument. */
+  command: string;
+  tags?: string[];
+  description?: string;
+  /** The arguments of the command, one for each placeholder. */
+  arguments?: Argument[];
+  /** Where the command comes from, like a Stack Overflow answer. */
+  source_url?: string;
+  /** Who wrote the workflow, or several authors, separated by commas. */
+  author?: string;
+  /** A URL for the author, or several, separated by commas, one for each author. */
+  author_url?: string;
+  /** The shells the command runs in, or empty for any. */
+  shells?: Shell[];
+}
+
+/** A workflow spec, of a single `command` or of `steps` run in order. */
+export interface Runbook {
+  name: string;
+  /** The command, with a `{{name}}` placeholder for each argument, for a spec without `steps`. */
+  command?: string;
+  /** The commands, run in order, for a spec without a single `command`. */
+  steps?: Step[];
+  tags?: string[];
+  description?: string;
+  /** The arguments of the command, or of the steps, one for each placeholder. */
+  arguments?: Argument[];
+  /** What to do when a step exits with a non-zero status, unless the step says otherwise. */
+  on_failure?: OnFailure;
+  /** Where the command comes from, like a Stack Overflow answer. */
+  source_url?: string;
+  /** Who wrote the workflow, or several authors, separated by commas. */
+  author?: string;
+  /** A URL for the author, or several, separated by commas, one for each author. */
+  author_url?: string;
+  /** The shells the command runs in, or empty for any. */
+  shells?: Shell[];
+}
+
+/** A value filled in for a placeholder of a `Workflow`'s command. */
+export interface Argument {
+  name: string;
+  description?: string;
+  default_value?: string;
+  type?: ArgumentType;
+  /** The values an `ArgumentType::Enum` can take. */
+  allowed_values?: string[];
+  /** A regular expression the whole value must match. */
+  pattern?: string;
+  /** Whether the value is a password, token or the like, not to be shown or kept. */
+  secret?: boolean;
+  /** What an `ArgumentType::Boolean` is replaced with when true, like `--force`. Without one, it's replaced with `true` or `false`. */
+  flag?: string;
+  /** A command whose output, a value per line, is suggested for the argument, like `git branch --format='%(refname:short)'`. */
+  suggestions_command?: string;
+}
+
+/** What kind of value an `Argument` takes. */
+export type ArgumentType = "string" | "integer" | "path" | "enum" | "boolean";
+export const ArgumentType = {
+  String: "string",
+  Integer: "integer",
+  Path: "path",
+  Enum: "enum",
+  Boolean: "boolean",
+} as const;
+
+/** A shell a workflow runs in. */
+export type Shell = "Fish" | "fish" | "Bash" | "bash" | "Zsh" | "zsh";
+export const Shell = {
+  Fish: "fish",
+  Bash: "bash",
+  Zsh: "zsh",
+} as const;
+
+/** A command of a `Runbook`. */
+export interface Step {
+  /** The command, with `{{argument}}` placeholders for the runbook's arguments. */
+  command: string;
+  /** What the step is for, shown before it's run. */
+  description?: string;
+  /** Whether to ask before running the step, for one that changes things. */
+  confirm?: boolean;
+  /** What to do when the step exits with a non-zero status, instead of the runbook's `on_failure`. */
+  on_failure?: OnFailure;
+}
+
+/** What to do when a step of a `Runbook` exits with a non-zero status. */
+export type OnFailure = "stop" | "continue";
+export const OnFailure = {
+  Stop: "stop",
+  Continue: "continue",
+} as const;
@@ -1,5 +1,15 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+mod runbook;
+mod template;
+
+pub use runbook::{InvalidRunbook, OnFailure, Runbook, Step};
+pub use template::{Template, TemplateError, Variable};
+
+/// A shell a workflow runs in.
 #[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
 pub enum Shell {
     #[serde(alias = "fish")]
@@ -10,22 +20,50 @@ pub enum Shell {
     Zsh,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+/// A command, with `{{argument}}` placeholders, and what it's for.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, Eq, PartialEq, Hash, PartialOrd)]
 pub struct Workflow {
     pub name: String,
+    /// The command, with a `{{name}}` placeholder for each argument.
     pub command: String,
     #[serde(default)]
     pub tags: Vec<String>,
     pub description: Option<String>,
+    /// The arguments of the command, one for each placeholder.
     #[serde(default)]
     pub arguments: Vec<Argument>,
+    /// Where the command comes from, like a Stack Overflow answer.
     pub source_url: Option<String>,
+    /// Who wrote the workflow, or several authors, separated by commas.
     pub author: Option<String>,
+    /// A URL for the author, or several, separated by commas, one for each author.
     pub author_url: Option<String>,
+    /// The shells the command runs in, or empty for any.
     #[serde(default)]
     pub shells: Vec<Shell>,
 }
 
+impl JsonSchema for Shell {
+    fn schema_name() -> String {
+        "Shell".to_owned()
+    }
+
+    /// The names serde writes, like `Bash`, and the lowercase ones it also reads, like `bash`.
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let names = ["Fish", "fish", "Bash", "bash", "Zsh", "zsh"];
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some("A shell a workflow runs in.".to_owned()),
+                ..Metadata::default()
+            })),
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(names.iter().map(|&name| name.into()).collect()),
+            ..SchemaObject::default()
+        }
+        .into()
+    }
+}
+
 impl Workflow {
     pub fn name(&self) -> &str {
         &self.name
@@ -84,11 +122,57 @@ impl Workflow {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+/// A value filled in for a placeholder of a [`Workflow`]'s command.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, Eq, PartialEq, Hash, PartialOrd)]
 pub struct Argument {
     pub name: String,
     pub description: Option<String>,
     pub default_value: Option<String>,
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "ArgumentType::is_string"
+    )]
+    pub arg_type: ArgumentType,
+    /// The values an [`ArgumentType::Enum`] can take.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_values: Vec<String>,
+    /// A regular expression the whole value must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Whether the value is a password, token or the like, not to be shown or kept.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub secret: bool,
+    /// What an [`ArgumentType::Boolean`] is replaced with when true, like `--force`. Without one,
+    /// it's replaced with `true` or `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
+    /// A command whose output, a value per line, is suggested for the argument, like
+    /// `git branch --format='%(refname:short)'`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestions_command: Option<String>,
+}
+
+/// What kind of value an [`Argument`] takes.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, Eq, PartialEq, Hash, PartialOrd,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentType {
+    #[default]
+    String,
+    Integer,
+    Path,
+    /// One of the argument's [`allowed_values`](Argument::allowed_values).
+    Enum,
+    /// `true` or `false`, absent meaning `false`.
+    Boolean,
+}
+
+impl ArgumentType {
+    fn is_string(&self) -> bool {
+        *self == ArgumentType::String
+    }
 }
 
 impl Argument {
@@ -97,6 +181,12 @@ impl Argument {
             description: None,
             name: name.into(),
             default_value: None,
+            arg_type: ArgumentType::String,
+            allowed_values: vec![],
+            pattern: None,
+            secret: false,
+            flag: None,
+            suggestions_command: None,
         }
     }
 
@@ -105,6 +195,49 @@ impl Argument {
         self
     }
 
+    pub fn with_default_value(mut self, default_value: impl Into<String>) -> Self {
+        self.default_value = Some(default_value.into());
+        self
+    }
+
+    pub fn with_type(mut self, arg_type: ArgumentType) -> Self {
+        self.arg_type = arg_type;
+        self
+    }
+
+    /// Makes the argument an [`ArgumentType::Enum`] of these values.
+    pub fn with_allowed_values<I>(mut self, values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.arg_type = ArgumentType::Enum;
+        self.allowed_values = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    pub fn with_secret(mut self, secret: bool) -> Self {
+        self.secret = secret;
+        self
+    }
+
+    /// Makes the argument an [`ArgumentType::Boolean`] replaced with `flag` when true.
+    pub fn with_flag(mut self, flag: impl Into<String>) -> Self {
+        self.arg_type = ArgumentType::Boolean;
+        self.flag = Some(flag.into());
+        self
+    }
+
+    pub fn with_suggestions_command(mut self, command: impl Into<String>) -> Self {
+        self.suggestions_command = Some(command.into());
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -116,4 +249,28 @@ impl Argument {
     pub fn default_value(&self) -> &Option<String> {
         &self.default_value
     }
+
+    pub fn arg_type(&self) -> ArgumentType {
+        self.arg_type
+    }
+
+    pub fn allowed_values(&self) -> &[String] {
+        &self.allowed_values
+    }
+
+    pub fn pattern(&self) -> &Option<String> {
+        &self.pattern
+    }
+
+    pub fn is_secret(&self) -> bool {
+        self.secret
+    }
+
+    pub fn flag(&self) -> &Option<String> {
+        &self.flag
+    }
+
+    pub fn suggestions_command(&self) -> &Option<String> {
+        &self.suggestions_command
+    }
 }
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ObjectValidation, Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::template::check_placeholders;
+use crate::{Argument, Shell, Template, TemplateError, Workflow};
+
+/// A sequence of commands, run in order, like checking something, fixing it, then checking again.
+///
+/// The steps share the runbook's arguments: each argument is a placeholder in one step or more.
+/// A spec with a single `command` rather than `steps`, like a [`Workflow`]'s, reads as a runbook
+/// of that one step.
+///
+/// ```
+/// use nextshell_workflows_types::{OnFailure, Runbook};
+///
+/// let runbook: Runbook = serde_yaml::from_str(
+///     "
+/// name: Restart a stuck service
+/// arguments:
+///   - name: service
+/// steps:
+///   - description: Check it's stuck
+///     command: systemctl is-active {{service}}
+///     on_failure: continue
+///   - command: sudo systemctl restart {{service}}
+///     confirm: true
+/// ",
+/// )
+/// .unwrap();
+/// assert_eq!(runbook.steps.len(), 2);
+/// assert_eq!(runbook.on_failure_of(0), OnFailure::Continue);
+/// assert_eq!(runbook.on_failure_of(1), OnFailure::Stop);
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, Hash, PartialOrd)]
+#[serde(try_from = "RunbookSpec")]
+pub struct Runbook {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    pub arguments: Vec<Argument>,
+    pub steps: Vec<Step>,
+    /// What to do when a step exits with a non-zero status, unless the step says otherwise.
+    pub on_failure: OnFailure,
+    pub source_url: Option<String>,
+    pub author: Option<String>,
+    pub author_url: Option<String>,
+    pub shells: Vec<Shell>,
+}
+
+/// A command of a [`Runbook`].
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, Eq, PartialEq, Hash, PartialOrd)]
+pub struct Step {
+    /// The command, with `{{argument}}` placeholders for the runbook's arguments.
+    pub command: String,
+    /// What the step is for, shown before it's run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether to ask before running the step, for one that changes things.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub confirm: bool,
+    /// What to do when the step exits with a non-zero status, instead of the runbook's
+    /// [`on_failure`](Runbook::on_failure).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<OnFailure>,
+}
+
+/// What to do when a step of a [`Runbook`] exits with a non-zero status.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, Eq, PartialEq, Hash, PartialOrd,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// Run no more steps.
+    #[default]
+    Stop,
+    /// Go on to the next step anyway, as for a check whose failure is worth knowing about but
+    /// doesn't matter to what follows.
+    Continue,
+}
+
+/// A workflow spec, of a single `command` or of `steps` run in order.
+#[derive(Deserialize, JsonSchema)]
+#[schemars(rename = "Runbook")]
+struct RunbookSpec {
+    name: String,
+    /// The command, with a `{{name}}` placeholder for each argument, for a spec without `steps`.
+    command: Option<String>,
+    /// The commands, run in order, for a spec without a single `command`.
+    #[serde(default)]
+    steps: Vec<Step>,
+    #[serde(default)]
+    tags: Vec<String>,
+    description: Option<String>,
+    /// The arguments of the command, or of the steps, one for each placeholder.
+    #[serde(default)]
+    arguments: Vec<Argument>,
+    /// What to do when a step exits with a non-zero status, unless the step says otherwise.
+    #[serde(default)]
+    on_failure: OnFailure,
+    /// Where the command comes from, like a Stack Overflow answer.
+    source_url: Option<String>,
+    /// Who wrote the workflow, or several authors, separated by commas.
+    author: Option<String>,
+    /// A URL for the author, or several, separated by commas, one for each author.
+    author_url: Option<String>,
+    /// The shells the command runs in, or empty for any.
+    #[serde(default)]
+    shells: Vec<Shell>,
+}
+
+/// Why a runbook spec isn't one.
+#[derive(Debug)]
+pub struct InvalidRunbook(&'static str);
+
+impl TryFrom<RunbookSpec> for Runbook {
+    type Error = InvalidRunbook;
+
+    fn try_from(spec: RunbookSpec) -> Result<Runbook, InvalidRunbook> {
+        let steps = match (spec.command, spec.steps.is_empty()) {
+            (Some(command), true) => vec![Step::new(command)],
+            (None, false) => spec.steps,
+            (Some(_), false) => {
+                return Err(InvalidRunbook(
+                    "a runbook has `command` or `steps`, not both",
+                ))
+            }
+            (None, true) => return Err(InvalidRunbook("a runbook needs `command` or `steps`")),
+        };
+        Ok(Runbook {
+            name: spec.name,
+            tags: spec.tags,
+            description: spec.description,
+            arguments: spec.arguments,
+            steps,
+            on_failure: spec.on_failure,
+            source_url: spec.source_url,
+            author: spec.author,
+            author_url: spec.author_url,
+            shells: spec.shells,
+        })
+    }
+}
+
+impl JsonSchema for Runbook {
+    fn schema_name() -> String {
+        "Runbook".to_owned()
+    }
+
+    /// The schema of a spec as written, which has `command` or `steps`.
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema = SchemaObject::from(RunbookSpec::json_schema(gen));
+        let required = |field: &str| -> Schema {
+            SchemaObject {
+                object: Some(Box::new(ObjectValidation {
+                    required: [field.to_owned()].into(),
+                    ..ObjectValidation::default()
+                })),
+                ..SchemaObject::default()
+            }
+            .into()
+        };
+        schema.subschemas = Some(Box::new(SubschemaValidation {
+            one_of: Some(vec![required("command"), required("steps")]),
+            ..SubschemaValidation::default()
+        }));
+        schema.into()
+    }
+}
+
+impl fmt::Display for InvalidRunbook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for InvalidRunbook {}
+
+impl From<Workflow> for Runbook {
+    /// A runbook of the workflow's command, as its only step.
+    fn from(workflow: Workflow) -> Runbook {
+        Runbook {
+            name: workflow.name,
+            tags: workflow.tags,
+            description: workflow.description,
+            arguments: workflow.arguments,
+            steps: vec![Step::new(workflow.command)],
+            on_failure: OnFailure::Stop,
+            source_url: workflow.source_url,
+            author: workflow.author,
+            author_url: workflow.author_url,
+            shells: workflow.shells,
+        }
+    }
+}
+
+impl Runbook {
+    pub fn new(name: impl Into<String>, steps: Vec<Step>) -> Self {
+        Runbook {
+            name: name.into(),
+            tags: vec![],
+            description: None,
+            arguments: vec![],
+            steps,
+            on_failure: OnFailure::Stop,
+            source_url: None,
+            author: None,
+            author_url: None,
+            shells: vec![],
+        }
+    }
+
+    pub fn with_arguments(mut self, arguments: Vec<Argument>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_on_failure(mut self, on_failure: OnFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+
+    /// What to do when step `index` fails: the step's policy, or else the runbook's.
+    pub fn on_failure_of(&self, index: usize) -> OnFailure {
+        self.steps
+            .get(index)
+            .and_then(|step| step.on_failure)
+            .unwrap_or(self.on_failure)
+    }
+
+    /// Checks that every placeholder, in any step, has an argument, that every argument is a
+    /// placeholder in some step, and that the arguments are [well defined](Argument::check).
+    pub fn check_arguments(&self) -> Result<(), Vec<TemplateError>> {
+        check_placeholders(&self.arguments, &self.placeholders())
+    }
+
+    /// The runbook as a single workflow, to list and search it with the others: the steps'
+    /// commands, one a line, in order, and the runbook's arguments.
+    ///
+    /// Run as a command, every line runs, whatever the steps' [`on_failure`](Step::on_failure),
+    /// and none is confirmed first. For a runbook of a single step, it's the workflow the runbook
+    /// was made [from](Runbook::from).
+    pub fn to_workflow(&self) -> Workflow {
+        let command = self
+            .steps
+            .iter()
+            .map(|step| &*step.command)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Workflow {
+            name: self.name.clone(),
+            command,
+            tags: self.tags.clone(),
+            description: self.description.clone(),
+            arguments: self.arguments.clone(),
+            source_url: self.source_url.clone(),
+            author: self.author.clone(),
+            author_url: self.author_url.clone(),
+            shells: self.shells.clone(),
+        }
+    }
+
+    /// Renders every step's command for `shell`, as [`Workflow::render`] does, with the same
+    /// values for the same arguments in each step.
+    ///
+    /// The steps are returned in order, with their commands rendered and their
+    /// [`on_failure`](Step::on_failure) the policy that applies to them, the runbook's if they
+    /// have none of their own.
+    pub fn render(
+        &self,
+        shell: &Shell,
+        values: &HashMap<String, String>,
+    ) -> Result<Vec<Step>, Vec<TemplateError>> {
+        self.check_arguments()?;
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| {
+                let command = self.step_workflow(step).render(shell, values)?;
+                Ok(Step {
+                    command,
+                    description: step.description.clone(),
+                    confirm: step.confirm,
+                    on_failure: Some(self.on_failure_of(i)),
+                })
+            })
+            .collect()
+    }
+
+    /// The names of the placeholders of all the steps, in order, each once.
+    fn placeholders(&self) -> Vec<&str> {
+        let mut placeholders = Vec::new();
+        for step in &self.steps {
+            for name in Template::parse(&step.command).placeholders() {
+                if !placeholders.contains(&name) {
+                    placeholders.push(name);
+                }
+            }
+        }
+        placeholders
+    }
+
+    /// A step as a workflow of its own, with the arguments it uses.
+    fn step_workflow(&self, step: &Step) -> Workflow {
+        let placeholders = Template::parse(&step.command).placeholders();
+        let mut workflow = Workflow::new(self.name.clone(), step.command.clone());
+        workflow.arguments = self
+            .arguments
+            .iter()
+            .filter(|arg| placeholders.contains(&arg.name.as_str()))
+            .cloned()
+            .collect();
+        workflow.shells = self.shells.clone();
+        workflow
+    }
+}
+
+impl Step {
+    pub fn new(command: impl Into<String>) -> Self {
+        Step {
+            command: command.into(),
+            description: None,
+            confirm: false,
+            on_failure: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Makes the step one to ask about before running it.
+    pub fn with_confirm(mut self, confirm: bool) -> Self {
+        self.confirm = confirm;
+        self
+    }
+
+    pub fn with_on_failure(mut self, on_failure: OnFailure) -> Self {
+        self.on_failure = Some(on_failure);
+        self
+    }
+}
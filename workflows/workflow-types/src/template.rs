@@ -0,0 +1,562 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use regex::Regex;
+
+use crate::{Argument, ArgumentType, Shell, Workflow};
+
+/// A workflow's command, split into literal text and `{{argument}}` placeholders.
+///
+/// A placeholder's name is letters, digits, `_` and `-`. Anything else between double braces, like
+/// the `{{.State}}` of a Go template, is literal text, as is a `{` before a placeholder, as in
+/// `${{{array_name}}[@]}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// A shell variable a placeholder is replaced with by [`Template::expand`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    /// Whether an empty value means no word at all, rather than an empty one, like a boolean
+    /// argument's flag. Only makes a difference outside quotes.
+    pub optional: bool,
+}
+
+/// A problem rendering a workflow's command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A placeholder without a matching argument.
+    UnknownArgument(String),
+    /// An argument without a matching placeholder.
+    UnusedArgument(String),
+    /// An argument given no value, and without a default.
+    MissingValue(String),
+    /// An argument whose definition is wrong, like an enum without values.
+    InvalidArgument { argument: String, message: String },
+    /// A value not of its argument's type, not matching its pattern, or with control characters.
+    InvalidValue { argument: String, message: String },
+    /// A value that can't be quoted where its placeholder is, like a variable name containing
+    /// spaces in `${{{name}}}`.
+    Unquotable { argument: String, value: String },
+}
+
+impl<'a> Template<'a> {
+    pub fn parse(command: &'a str) -> Self {
+        let mut parts = Vec::new();
+        let mut rest = command;
+        let mut text_start = 0;
+        let mut offset = 0;
+        while let Some(open) = rest.find("{{") {
+            // In a run of braces, the placeholder opens at the last two.
+            let braces = rest[open..].bytes().take_while(|&b| b == b'{').count();
+            let name_start = open + braces;
+            let after = &rest[name_start..];
+            match after.find("}}") {
+                Some(len) if is_name(&after[..len]) => {
+                    let start = offset + name_start - 2;
+                    if start > text_start {
+                        parts.push(Part::Text(&command[text_start..start]));
+                    }
+                    parts.push(Part::Placeholder(&after[..len]));
+                    text_start = offset + name_start + len + 2;
+                    offset = text_start;
+                    rest = &command[offset..];
+                }
+                _ => {
+                    offset += name_start;
+                    rest = &command[offset..];
+                }
+            }
+        }
+        if text_start < command.len() {
+            parts.push(Part::Text(&command[text_start..]));
+        }
+        Template { parts }
+    }
+
+    /// The names of the placeholders, in order, each once.
+    pub fn placeholders(&self) -> Vec<&'a str> {
+        let mut seen = BTreeSet::new();
+        self.parts
+            .iter()
+            .filter_map(|part| match *part {
+                Part::Placeholder(name) if seen.insert(name) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Fills in the placeholders with `value`, quoting each value for `shell` so that it stays a
+    /// single word, whether the placeholder is bare or within quotes, and can't end the command or
+    /// start another. A placeholder whose value is `None` is left out.
+    pub fn render<F>(&self, shell: &Shell, mut value: F) -> Result<String, TemplateError>
+    where
+        F: FnMut(&str) -> Result<Option<String>, TemplateError>,
+    {
+        let mut rendered = String::new();
+        let mut contexts = vec![Context::Bare];
+        for part in &self.parts {
+            match *part {
+                Part::Text(text) => {
+                    scan(shell, text, &mut contexts);
+                    rendered.push_str(text);
+                }
+                Part::Placeholder(name) => {
+                    let value = match value(name)? {
+                        Some(value) => value,
+                        None => continue,
+                    };
+                    let context = *contexts.last().unwrap_or(&Context::Bare);
+                    rendered.push_str(&quote_in(shell, context, &value).ok_or_else(|| {
+                        TemplateError::Unquotable {
+                            argument: name.to_owned(),
+                            value: value.clone(),
+                        }
+                    })?);
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Replaces the placeholders with references to shell variables, for a script or function
+    /// that sets them. Each reference expands to a single word, whether the placeholder is bare
+    /// or within quotes, or to none for an empty [optional](Variable::optional) variable.
+    ///
+    /// `variable` names the variable of each placeholder, or fails for placeholders it doesn't
+    /// know. Fails with [`TemplateError::Unquotable`] for a placeholder in a `${...}` parameter
+    /// expansion, or naming the variable of an assignment, where a variable can't be referred to.
+    ///
+    /// ```
+    /// use nextshell_workflows_types::{Shell, Template, Variable};
+    ///
+    /// let template = Template::parse(r#"git commit -m "{{message}}" {{amend}}"#);
+    /// let variable = |name: &str| {
+    ///     Ok(Variable {
+    ///         name: name.to_owned(),
+    ///         optional: name == "amend",
+    ///     })
+    /// };
+    /// assert_eq!(
+    ///     template.expand(&Shell::Bash, variable).unwrap(),
+    ///     r#"git commit -m "${message}" ${amend:+"$amend"}"#
+    /// );
+    /// assert_eq!(
+    ///     template.expand(&Shell::Fish, variable).unwrap(),
+    ///     r#"git commit -m "{$message}" $amend"#
+    /// );
+    /// ```
+    pub fn expand<F>(&self, shell: &Shell, mut variable: F) -> Result<String, TemplateError>
+    where
+        F: FnMut(&str) -> Result<Variable, TemplateError>,
+    {
+        let mut expanded = String::new();
+        let mut contexts = vec![Context::Bare];
+        for (i, part) in self.parts.iter().enumerate() {
+            match *part {
+                Part::Text(text) => {
+                    scan(shell, text, &mut contexts);
+                    expanded.push_str(text);
+                }
+                Part::Placeholder(name) => {
+                    let variable = variable(name)?;
+                    let context = *contexts.last().unwrap_or(&Context::Bare);
+                    let next = match self.parts.get(i + 1) {
+                        Some(Part::Text(next)) => next,
+                        _ => "",
+                    };
+                    let reference = if context == Context::Bare && is_name_of(&expanded, next) {
+                        None
+                    } else {
+                        reference_in(shell, context, &variable)
+                    };
+                    expanded.push_str(&reference.ok_or_else(|| TemplateError::Unquotable {
+                        argument: name.to_owned(),
+                        value: format!("${}", variable.name),
+                    })?);
+                }
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+impl Shell {
+    /// Quotes a value so the shell reads it as a single word, as it is.
+    pub fn quote(&self, value: &str) -> String {
+        quote_in(self, Context::Bare, value).unwrap_or_default()
+    }
+}
+
+impl Workflow {
+    /// The workflow's command as a [`Template`].
+    pub fn template(&self) -> Template<'_> {
+        Template::parse(&self.command)
+    }
+
+    /// Checks that every placeholder in the command has an argument, every argument a
+    /// placeholder, and that the arguments are [well defined](Argument::check).
+    pub fn check_arguments(&self) -> Result<(), Vec<TemplateError>> {
+        check_placeholders(&self.arguments, &self.template().placeholders())
+    }
+
+    /// Renders the command for `shell`, with the given values for its arguments, or their
+    /// defaults.
+    ///
+    /// A boolean argument is replaced with its [`flag`](Argument::flag) when true and left out when
+    /// false, or absent.
+    ///
+    /// Fails if the arguments don't match the placeholders, as checked by
+    /// [`check_arguments`](Workflow::check_arguments), or if a value is missing or
+    /// [invalid](Argument::check_value). Errors don't include the values of secret arguments.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use nextshell_workflows_types::{Argument, Shell, Workflow};
+    ///
+    /// let workflow = Workflow::new("Commit", "git commit -m {{message}}")
+    ///     .with_arguments(vec![Argument::new("message")]);
+    /// let values = HashMap::from([("message".to_owned(), "it's done; rm -rf ~".to_owned())]);
+    /// assert_eq!(
+    ///     workflow.render(&Shell::Bash, &values).unwrap(),
+    ///     r#"git commit -m 'it'\''s done; rm -rf ~'"#
+    /// );
+    /// assert_eq!(
+    ///     workflow.render(&Shell::Fish, &values).unwrap(),
+    ///     r#"git commit -m 'it\'s done; rm -rf ~'"#
+    /// );
+    /// ```
+    pub fn render(
+        &self,
+        shell: &Shell,
+        values: &HashMap<String, String>,
+    ) -> Result<String, Vec<TemplateError>> {
+        self.check_arguments()?;
+        self.template()
+            .render(shell, |name| {
+                let argument = self.argument(name);
+                let value = values
+                    .get(name)
+                    .or_else(|| argument.and_then(|arg| arg.default_value.as_ref()));
+                if let Some(value) = value {
+                    argument.map_or(Ok(()), |arg| arg.check_value(value))?;
+                }
+                match (argument, value) {
+                    (Some(arg), value) if arg.arg_type == ArgumentType::Boolean => {
+                        let on = value.is_some_and(|value| value == "true");
+                        Ok(match &arg.flag {
+                            Some(flag) => on.then(|| flag.clone()),
+                            None => Some(on.to_string()),
+                        })
+                    }
+                    (_, Some(value)) => Ok(Some(value.clone())),
+                    (_, None) => Err(TemplateError::MissingValue(name.to_owned())),
+                }
+            })
+            .map_err(|err| match err {
+                TemplateError::Unquotable { argument, .. }
+                    if self.argument(&argument).is_some_and(|arg| arg.secret) =>
+                {
+                    TemplateError::Unquotable {
+                        argument,
+                        value: "<secret>".to_owned(),
+                    }
+                }
+                err => err,
+            })
+            .map_err(|err| vec![err])
+    }
+
+    fn argument(&self, name: &str) -> Option<&Argument> {
+        self.arguments.iter().find(|arg| arg.name == name)
+    }
+}
+
+impl Argument {
+    /// Checks that the argument makes sense: that only an enum has allowed values, and has some,
+    /// that only a boolean has a flag, that the pattern is a regular expression and that the
+    /// default is a valid value.
+    pub fn check(&self) -> Result<(), TemplateError> {
+        let invalid = |message: String| TemplateError::InvalidArgument {
+            argument: self.name.clone(),
+            message,
+        };
+        let is_enum = self.arg_type == ArgumentType::Enum;
+        if is_enum && self.allowed_values.is_empty() {
+            return Err(invalid("an enum needs `allowed_values`".to_owned()));
+        }
+        if !is_enum && !self.allowed_values.is_empty() {
+            return Err(invalid("only an enum has `allowed_values`".to_owned()));
+        }
+        if self.arg_type != ArgumentType::Boolean && self.flag.is_some() {
+            return Err(invalid("only a boolean has a `flag`".to_owned()));
+        }
+        self.regex()?;
+        if let Some(default) = &self.default_value {
+            self.check_value(default).map_err(|err| match err {
+                TemplateError::InvalidValue { message, .. } => {
+                    invalid(format!("invalid `default_value`: {message}"))
+                }
+                err => err,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that a value is of the argument's type, matches its pattern, and has no control
+    /// characters.
+    ///
+    /// Quoting doesn't keep control characters from a terminal: typed into one, its line
+    /// discipline and the shell's line editor act on them before the command line is parsed, so a
+    /// Ctrl-U and a carriage return in a value would run whatever follows them.
+    pub fn check_value(&self, value: &str) -> Result<(), TemplateError> {
+        let invalid = |message: String| TemplateError::InvalidValue {
+            argument: self.name.clone(),
+            message,
+        };
+        if value.chars().any(char::is_control) {
+            return Err(invalid("control characters aren't allowed".to_owned()));
+        }
+        match self.arg_type {
+            ArgumentType::String => {}
+            ArgumentType::Integer => {
+                if value.parse::<i64>().is_err() {
+                    return Err(invalid("expected an integer".to_owned()));
+                }
+            }
+            ArgumentType::Path => {
+                if value.is_empty() {
+                    return Err(invalid("expected a path".to_owned()));
+                }
+            }
+            ArgumentType::Enum => {
+                if !self.allowed_values.iter().any(|allowed| allowed == value) {
+                    let allowed = self
+                        .allowed_values
+                        .iter()
+                        .map(|allowed| format!("`{allowed}`"))
+                        .collect::<Vec<_>>();
+                    return Err(invalid(format!("expected one of {}", allowed.join(", "))));
+                }
+            }
+            ArgumentType::Boolean => {
+                if value != "true" && value != "false" {
+                    return Err(invalid("expected `true` or `false`".to_owned()));
+                }
+            }
+        }
+        if let Some(regex) = self.regex()? {
+            if !regex.is_match(value) {
+                return Err(invalid(format!(
+                    "doesn't match `{}`",
+                    self.pattern.as_deref().unwrap_or_default()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The pattern, anchored to match whole values.
+    fn regex(&self) -> Result<Option<Regex>, TemplateError> {
+        self.pattern
+            .as_ref()
+            .map(|pattern| {
+                Regex::new(&format!("^(?:{pattern})$")).map_err(|err| {
+                    TemplateError::InvalidArgument {
+                        argument: self.name.clone(),
+                        message: format!("invalid `pattern`: {err}"),
+                    }
+                })
+            })
+            .transpose()
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownArgument(name) => {
+                write!(f, "`{{{{{name}}}}}` has no matching argument")
+            }
+            TemplateError::UnusedArgument(name) => {
+                write!(f, "argument `{name}` isn't used in the command")
+            }
+            TemplateError::MissingValue(name) => {
+                write!(f, "argument `{name}` has no value, and no default")
+            }
+            TemplateError::InvalidArgument { argument, message } => {
+                write!(f, "argument `{argument}` is invalid: {message}")
+            }
+            TemplateError::InvalidValue { argument, message } => {
+                write!(f, "invalid value for argument `{argument}`: {message}")
+            }
+            TemplateError::Unquotable { argument, value } => write!(
+                f,
+                "argument `{argument}` can't be {value:?} where it is in the command"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Where a placeholder is, as far as quoting goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Context {
+    /// Outside quotes, or in a `$(...)` command substitution.
+    Bare,
+    SingleQuoted,
+    DoubleQuoted,
+    /// In a `${...}` parameter expansion.
+    Parameter,
+}
+
+/// Follows the quoting of the command's literal text.
+fn scan(shell: &Shell, text: &str, contexts: &mut Vec<Context>) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let context = *contexts.last().unwrap_or(&Context::Bare);
+        match (context, c) {
+            (Context::SingleQuoted, '\\') if *shell == Shell::Fish => {
+                chars.next();
+            }
+            (Context::SingleQuoted, '\'') => {
+                contexts.pop();
+            }
+            (Context::SingleQuoted, _) => {}
+            (Context::DoubleQuoted, '"') | (Context::Parameter, '}') => {
+                contexts.pop();
+            }
+            (Context::Bare, ')') if contexts.len() > 1 => {
+                contexts.pop();
+            }
+            (_, '\\') => {
+                chars.next();
+            }
+            (_, '$') if chars.peek() == Some(&'(') => {
+                chars.next();
+                contexts.push(Context::Bare);
+            }
+            (_, '$') if chars.peek() == Some(&'{') => {
+                chars.next();
+                contexts.push(Context::Parameter);
+            }
+            (Context::Bare, '\'') => contexts.push(Context::SingleQuoted),
+            (Context::Bare, '"') => contexts.push(Context::DoubleQuoted),
+            _ => {}
+        }
+    }
+}
+
+/// Quotes a value for where it's going, or `None` if it can't be.
+///
+/// Within quotes, the quotes are closed, the value is quoted as it would be outside them, and the
+/// quotes reopened.
+fn quote_in(shell: &Shell, context: Context, value: &str) -> Option<String> {
+    let is_safe = !value.is_empty() && value.chars().all(is_safe);
+    match context {
+        _ if is_safe => Some(value.to_owned()),
+        Context::Bare => Some(quote(shell, value)),
+        Context::SingleQuoted => Some(format!("'{}'", quote(shell, value))),
+        Context::DoubleQuoted => Some(format!("\"{}\"", quote(shell, value))),
+        Context::Parameter => None,
+    }
+}
+
+/// Refers to a variable where it's going, or `None` if it can't be.
+fn reference_in(shell: &Shell, context: Context, variable: &Variable) -> Option<String> {
+    let name = &variable.name;
+    let bare = match (shell, variable.optional) {
+        (Shell::Bash | Shell::Zsh, false) => format!("\"${name}\""),
+        (Shell::Bash | Shell::Zsh, true) => format!("${{{name}:+\"${name}\"}}"),
+        // Fish doesn't split words, and an empty list is no word at all.
+        (Shell::Fish, false) => format!("\"${name}\""),
+        (Shell::Fish, true) => format!("${name}"),
+    };
+    match context {
+        Context::Bare => Some(bare),
+        Context::SingleQuoted => Some(format!("'{bare}'")),
+        Context::DoubleQuoted => Some(match shell {
+            Shell::Bash | Shell::Zsh => format!("${{{name}}}"),
+            Shell::Fish => format!("{{${name}}}"),
+        }),
+        Context::Parameter => None,
+    }
+}
+
+/// Single-quotes a value.
+fn quote(shell: &Shell, value: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("'{}'", value.replace('\'', r"'\''")),
+        Shell::Fish => format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'")),
+    }
+}
+
+/// Characters that mean nothing special to any shell.
+///
+/// Not `=`: zsh expands a word starting with it, like `=ls`, to the path of the command.
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_@+:,./-".contains(c)
+}
+
+/// Whether a bare placeholder between `before` and `after` names a variable being set, as in
+/// `{{name}}=value` or `for {{name}} in ...`.
+fn is_name_of(before: &str, after: &str) -> bool {
+    if after.starts_with('=') || after.starts_with("+=") {
+        return is_command_start(before);
+    }
+    before.ends_with([' ', '\t'])
+        && before
+            .trim_end_matches([' ', '\t'])
+            .strip_suffix("for")
+            .is_some_and(is_command_start)
+}
+
+/// Whether the text so far ends where a command, or an assignment, can start.
+fn is_command_start(text: &str) -> bool {
+    let text = text.trim_end_matches([' ', '\t']);
+    text.is_empty() || text.ends_with(['\n', ';', '&', '|', '(', '{'])
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Checks that every placeholder has an argument, every argument a placeholder, and that the
+/// arguments are [well defined](Argument::check), for a command or the steps of a runbook.
+pub(crate) fn check_placeholders(
+    arguments: &[Argument],
+    placeholders: &[&str],
+) -> Result<(), Vec<TemplateError>> {
+    let mut errors = arguments
+        .iter()
+        .filter_map(|arg| arg.check().err())
+        .collect::<Vec<_>>();
+    errors.extend(
+        placeholders
+            .iter()
+            .filter(|name| !arguments.iter().any(|arg| arg.name == **name))
+            .map(|name| TemplateError::UnknownArgument((*name).to_owned())),
+    );
+    errors.extend(
+        arguments
+            .iter()
+            .filter(|arg| !placeholders.contains(&arg.name.as_str()))
+            .map(|arg| TemplateError::UnusedArgument(arg.name.clone())),
+    );
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
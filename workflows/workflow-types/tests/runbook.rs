@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use nextshell_workflows_types::{
+    Argument, OnFailure, Runbook, Shell, Step, TemplateError, Workflow,
+};
+
+#[test]
+fn single_command_specs() {
+    let spec = "
+name: Undo the last commit
+command: git reset HEAD~{{count}}
+tags: [git]
+arguments:
+  - name: count
+    default_value: '1'
+shells: [Bash]
+";
+    let runbook: Runbook = serde_yaml::from_str(spec).unwrap();
+    let workflow: Workflow = serde_yaml::from_str(spec).unwrap();
+    assert_eq!(runbook, Runbook::from(workflow));
+    assert_eq!(runbook.steps, [Step::new("git reset HEAD~{{count}}")]);
+    assert_eq!(runbook.on_failure, OnFailure::Stop);
+    assert_eq!(runbook.tags, ["git"]);
+
+    // Written out, it's a runbook of steps, which reads back the same.
+    let yaml = serde_yaml::to_string(&runbook).unwrap();
+    assert!(yaml.contains("steps:\n- command: git reset HEAD~{{count}}\n"));
+    assert_eq!(serde_yaml::from_str::<Runbook>(&yaml).unwrap(), runbook);
+}
+
+#[test]
+fn command_or_steps() {
+    let both = "name: Both\ncommand: ls\nsteps:\n  - command: pwd\n";
+    let err = serde_yaml::from_str::<Runbook>(both).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("a runbook has `command` or `steps`, not both"));
+
+    let neither = serde_yaml::from_str::<Runbook>("name: Neither\n").unwrap_err();
+    assert!(neither
+        .to_string()
+        .contains("a runbook needs `command` or `steps`"));
+}
+
+fn restart() -> Runbook {
+    Runbook::new(
+        "Restart a service",
+        vec![
+            Step::new("systemctl status {{service}}")
+                .with_description("Check it")
+                .with_on_failure(OnFailure::Continue),
+            Step::new("sudo systemctl restart {{force}} {{service}}").with_confirm(true),
+            Step::new("curl -fsS {{url}}"),
+        ],
+    )
+    .with_arguments(vec![
+        Argument::new("service"),
+        Argument::new("force").with_flag("--force"),
+        Argument::new("url").with_default_value("http://localhost/health"),
+    ])
+}
+
+#[test]
+fn render() {
+    let values = HashMap::from([
+        ("service".to_owned(), "my app".to_owned()),
+        ("force".to_owned(), "true".to_owned()),
+    ]);
+    let steps = restart().render(&Shell::Bash, &values).unwrap();
+    assert_eq!(
+        steps,
+        [
+            Step::new("systemctl status 'my app'")
+                .with_description("Check it")
+                .with_on_failure(OnFailure::Continue),
+            Step::new("sudo systemctl restart --force 'my app'")
+                .with_confirm(true)
+                .with_on_failure(OnFailure::Stop),
+            Step::new("curl -fsS http://localhost/health").with_on_failure(OnFailure::Stop),
+        ]
+    );
+
+    let runbook = restart().with_on_failure(OnFailure::Continue);
+    assert_eq!(runbook.on_failure_of(1), OnFailure::Continue);
+    assert_eq!(runbook.on_failure_of(0), OnFailure::Continue);
+}
+
+#[test]
+fn check_arguments() {
+    let mut runbook = restart();
+    runbook.arguments.remove(0);
+    runbook.arguments.push(Argument::new("unused"));
+    assert_eq!(
+        runbook.check_arguments(),
+        Err(vec![
+            TemplateError::UnknownArgument("service".to_owned()),
+            TemplateError::UnusedArgument("unused".to_owned()),
+        ])
+    );
+    assert_eq!(
+        restart().render(&Shell::Fish, &HashMap::new()),
+        Err(vec![TemplateError::MissingValue("service".to_owned())])
+    );
+}
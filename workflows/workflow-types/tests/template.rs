@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use nextshell_workflows_types::{
+    Argument, ArgumentType, Shell, Template, TemplateError, Variable, Workflow,
+};
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn placeholders() {
+    let template = Template::parse("cp {{from}} {{to}} && ls {{to}}");
+    assert_eq!(template.placeholders(), ["from", "to"]);
+
+    // Braces around a placeholder are the shell's.
+    let template = Template::parse("for i in ${{{array_name}}[@]}; do {{command}}; done");
+    assert_eq!(template.placeholders(), ["array_name", "command"]);
+
+    // Go templates and unclosed braces aren't placeholders.
+    let template = Template::parse("docker inspect -f '{{.State.Pid}}' {{container}} {{oops");
+    assert_eq!(template.placeholders(), ["container"]);
+}
+
+#[test]
+fn check_arguments() {
+    let workflow = Workflow::new("Copy", "cp {{from}} {{to}}")
+        .with_arguments(vec![Argument::new("from"), Argument::new("mode")]);
+    assert_eq!(
+        workflow.check_arguments(),
+        Err(vec![
+            TemplateError::UnknownArgument("to".to_owned()),
+            TemplateError::UnusedArgument("mode".to_owned()),
+        ])
+    );
+    assert_eq!(
+        workflow.render(&Shell::Bash, &values(&[])).unwrap_err()[0].to_string(),
+        "`{{to}}` has no matching argument"
+    );
+}
+
+#[test]
+fn defaults() {
+    let workflow = Workflow::new("Tail", "tail -n {{lines}} {{file}}").with_arguments(vec![
+        Argument::new("lines").with_default_value("10"),
+        Argument::new("file"),
+    ]);
+    assert_eq!(
+        workflow
+            .render(&Shell::Zsh, &values(&[("file", "log.txt")]))
+            .unwrap(),
+        "tail -n 10 log.txt"
+    );
+    assert_eq!(
+        workflow
+            .render(&Shell::Zsh, &values(&[("file", "log.txt"), ("lines", "2")]))
+            .unwrap(),
+        "tail -n 2 log.txt"
+    );
+    assert_eq!(
+        workflow.render(&Shell::Zsh, &values(&[])),
+        Err(vec![TemplateError::MissingValue("file".to_owned())])
+    );
+}
+
+#[test]
+fn quoting() {
+    let render = |command: &str, shell: Shell, value: &str| {
+        Workflow::new("Test", command)
+            .with_arguments(vec![Argument::new("x")])
+            .render(&shell, &values(&[("x", value)]))
+    };
+    let hostile = r#"a b'c"d\$(rm -rf ~);`e`"#;
+
+    assert_eq!(render("echo {{x}}", Shell::Bash, "").unwrap(), "echo ''");
+    assert_eq!(
+        render("echo {{x}}", Shell::Bash, hostile).unwrap(),
+        r#"echo 'a b'\''c"d\$(rm -rf ~);`e`'"#
+    );
+    assert_eq!(
+        render("echo {{x}}", Shell::Fish, hostile).unwrap(),
+        r#"echo 'a b\'c"d\\$(rm -rf ~);`e`'"#
+    );
+    // Zsh would expand `=ls` to the path of `ls`.
+    assert_eq!(
+        render("echo {{x}}", Shell::Zsh, "=ls").unwrap(),
+        "echo '=ls'"
+    );
+
+    // Within quotes, the quotes are closed around the value.
+    assert_eq!(
+        render("ssh -o 'ProxyCommand {{x}}'", Shell::Bash, "it's").unwrap(),
+        r#"ssh -o 'ProxyCommand ''it'\''s'''"#
+    );
+    assert_eq!(
+        render(r#"echo "hi {{x}}""#, Shell::Zsh, "$HOME").unwrap(),
+        r#"echo "hi "'$HOME'"""#
+    );
+    assert_eq!(
+        render(r#"echo "$(cat {{x}})""#, Shell::Bash, "a b").unwrap(),
+        r#"echo "$(cat 'a b')""#
+    );
+
+    // Parameter names can't be quoted at all.
+    assert_eq!(
+        render("echo ${{{x}}[@]}", Shell::Bash, "list").unwrap(),
+        "echo ${list[@]}"
+    );
+    assert_eq!(
+        render("echo ${{{x}}[@]}", Shell::Bash, "a;b"),
+        Err(vec![TemplateError::Unquotable {
+            argument: "x".to_owned(),
+            value: "a;b".to_owned(),
+        }])
+    );
+}
+
+#[test]
+fn expand() {
+    let expand = |command: &str, shell: Shell| {
+        Template::parse(command).expand(&shell, |name| {
+            Ok(Variable {
+                name: name.replace('-', "_"),
+                optional: name == "flag",
+            })
+        })
+    };
+
+    assert_eq!(
+        expand("ls {{dir}} {{flag}}", Shell::Zsh).unwrap(),
+        r#"ls "$dir" ${flag:+"$flag"}"#
+    );
+    assert_eq!(
+        expand("ls {{dir}} {{flag}}", Shell::Fish).unwrap(),
+        r#"ls "$dir" $flag"#
+    );
+    assert_eq!(
+        expand("ssh -o 'ProxyCommand nc {{host-name}}'", Shell::Bash).unwrap(),
+        r#"ssh -o 'ProxyCommand nc '"$host_name"''"#
+    );
+    assert_eq!(
+        expand(r#"echo "{{dir}}s""#, Shell::Bash).unwrap(),
+        r#"echo "${dir}s""#
+    );
+    assert_eq!(
+        expand(r#"echo "{{dir}}s""#, Shell::Fish).unwrap(),
+        r#"echo "{$dir}s""#
+    );
+    assert_eq!(
+        expand("echo ${{{dir}}[@]}", Shell::Bash),
+        Err(TemplateError::Unquotable {
+            argument: "dir".to_owned(),
+            value: "$dir".to_owned(),
+        })
+    );
+    assert!(expand("cd /; {{dir}}+=(x)", Shell::Bash).is_err());
+    assert!(expand("for {{dir}} in *; do ls; done", Shell::Bash).is_err());
+    assert_eq!(
+        expand("make {{dir}}=x", Shell::Bash).unwrap(),
+        r#"make "$dir"=x"#
+    );
+
+    assert_eq!(Shell::Bash.quote("it's"), r"'it'\''s'");
+    assert_eq!(Shell::Fish.quote("it's"), r"'it\'s'");
+    assert_eq!(Shell::Zsh.quote("main"), "main");
+}
+
+#[test]
+fn typed_arguments() {
+    let workflow = Workflow::new(
+        "Log",
+        "git log {{oneline}} -n {{count}} --format={{format}} -- {{path}}",
+    )
+    .with_arguments(vec![
+        Argument::new("oneline").with_flag("--oneline"),
+        Argument::new("count")
+            .with_type(ArgumentType::Integer)
+            .with_default_value("10"),
+        Argument::new("format").with_allowed_values(["short", "full"]),
+        Argument::new("path").with_type(ArgumentType::Path),
+    ]);
+    assert_eq!(workflow.check_arguments(), Ok(()));
+
+    let render = |pairs: &[(&str, &str)]| workflow.render(&Shell::Bash, &values(pairs));
+    assert_eq!(
+        render(&[("format", "short"), ("path", "src dir")]).unwrap(),
+        "git log  -n 10 --format=short -- 'src dir'"
+    );
+    assert_eq!(
+        render(&[("oneline", "true"), ("format", "full"), ("path", ".")]).unwrap(),
+        "git log --oneline -n 10 --format=full -- ."
+    );
+
+    let error = |pairs: &[(&str, &str)]| render(pairs).unwrap_err()[0].to_string();
+    assert_eq!(
+        error(&[("count", "ten"), ("format", "short"), ("path", ".")]),
+        "invalid value for argument `count`: expected an integer"
+    );
+    assert_eq!(
+        error(&[("format", "medium"), ("path", ".")]),
+        "invalid value for argument `format`: expected one of `short`, `full`"
+    );
+    assert_eq!(
+        error(&[("oneline", "yes"), ("format", "short"), ("path", ".")]),
+        "invalid value for argument `oneline`: expected `true` or `false`"
+    );
+    assert_eq!(
+        error(&[("format", "short"), ("path", "")]),
+        "invalid value for argument `path`: expected a path"
+    );
+    // Not even quoted, since a terminal acts on them before the shell sees them.
+    for path in ["x\u{15}touch pwned\r", "a\tb", "a\nb", "\u{7f}", "\0"] {
+        assert_eq!(
+            error(&[("format", "short"), ("path", path)]),
+            "invalid value for argument `path`: control characters aren't allowed",
+            "{:?}",
+            path
+        );
+    }
+}
+
+#[test]
+fn patterns_and_secrets() {
+    let workflow = Workflow::new("Login", "login {{user}} ${{{token}}}").with_arguments(vec![
+        Argument::new("user").with_pattern("[a-z]+"),
+        Argument::new("token").with_secret(true),
+    ]);
+    let render = |user: &str, token: &str| {
+        workflow.render(&Shell::Bash, &values(&[("user", user), ("token", token)]))
+    };
+    assert_eq!(render("ann", "TOKEN").unwrap(), "login ann ${TOKEN}");
+    // The pattern matches whole values.
+    assert_eq!(
+        render("ann1", "TOKEN").unwrap_err()[0].to_string(),
+        "invalid value for argument `user`: doesn't match `[a-z]+`"
+    );
+    assert_eq!(
+        render("ann", "hunter 2").unwrap_err(),
+        [TemplateError::Unquotable {
+            argument: "token".to_owned(),
+            value: "<secret>".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn invalid_arguments() {
+    let check = |argument: Argument| {
+        Workflow::new("Test", "test {{x}}")
+            .with_arguments(vec![argument])
+            .check_arguments()
+            .unwrap_err()[0]
+            .to_string()
+    };
+    assert_eq!(
+        check(Argument::new("x").with_type(ArgumentType::Enum)),
+        "argument `x` is invalid: an enum needs `allowed_values`"
+    );
+    assert_eq!(
+        check(
+            Argument::new("x")
+                .with_flag("-f")
+                .with_type(ArgumentType::String)
+        ),
+        "argument `x` is invalid: only a boolean has a `flag`"
+    );
+    assert!(check(Argument::new("x").with_pattern("("))
+        .starts_with("argument `x` is invalid: invalid `pattern`: regex parse error"));
+    assert_eq!(
+        check(
+            Argument::new("x")
+                .with_type(ArgumentType::Integer)
+                .with_default_value("many")
+        ),
+        "argument `x` is invalid: invalid `default_value`: expected an integer"
+    );
+}
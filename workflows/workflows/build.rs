@@ -1,114 +1,53 @@
-use anyhow::Result;
-use convert_case::{Case, Casing};
-use nextshell_workflows_types::Workflow;
-use std::ffi::OsStr;
+use std::env;
 use std::fs::File;
-use std::io::Write;
-use std::process::Command;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
 use walkdir::WalkDir;
 
-/// Generates Workflows as rust files from the yaml stored in /specs. Each Workflow is stored within
-/// its own mod within the `generated_workflows` module. Additionally, a function called `workflows`
-/// is generated that returns a vector of all the `Workflow`s that were created.
-fn main() -> Result<()> {
+/// Embeds the specs in `/specs` into the crate, as a list of their paths, relative to `/specs`,
+/// and their contents. The list is written to `$OUT_DIR/embedded_specs.rs`, and looks like:
+/// ```ignore
+/// &[
+///     ("git/clone_with_ssh.yaml", include_str!("/path/to/specs/git/clone_with_ssh.yaml")),
+///     ...
+/// ]
+/// ```
+/// The specs are parsed at runtime, by the `WorkflowStore`, so a spec that doesn't parse is
+/// reported rather than failing the build.
+fn main() -> std::io::Result<()> {
     println!("cargo:rerun-if-changed=../specs");
 
-    std::fs::create_dir_all("src/generated_workflows")?;
-    let parent_module = std::fs::File::create("src/generated_workflows/mod.rs")?;
-
-    let mut workflows_added = Vec::new();
+    let specs_dir = Path::new(&env::var("CARGO_MANIFEST_DIR").expect("set by cargo"))
+        .join("../specs")
+        .canonicalize()?;
+    let out = PathBuf::from(env::var("OUT_DIR").expect("set by cargo")).join("embedded_specs.rs");
+    let mut out = BufWriter::new(File::create(out)?);
 
-    for entry in WalkDir::new("../specs") {
+    writeln!(out, "&[")?;
+    for entry in WalkDir::new(&specs_dir).sort_by_file_name() {
         let entry = entry?;
-        let valid_yaml_file = entry
+        let is_yaml = entry
             .path()
             .extension()
-            .and_then(OsStr::to_str)
+            .and_then(|extension| extension.to_str())
             .is_some_and(|extension| extension == "yaml" || extension == "yml");
-
-        if valid_yaml_file {
-            let file = File::open(entry.path())?;
-
-            let mmap = unsafe { memmap::Mmap::map(&file) }?;
-            let yaml_content = std::str::from_utf8(&mmap)?;
-
-            println!("attempting to generate workflow at {:?}", entry.path());
-            let workflow: Workflow = serde_yaml::from_str(yaml_content)?;
-            println!("generated workflow is {workflow:?}");
-
-            let file_name = entry
-                .file_name()
-                .to_str()
-                .expect("OsStr should convert to str")
-                .replace(".yaml", "")
-                .replace(".yml", "")
-                .to_case(Case::Snake);
-            println!("file name is {file_name:?}");
-
-            // Create a module for each Workflow within the parent module.
-            workflows_added.push(file_name.clone());
-
-            write_workflow(workflow, file_name.as_str())?;
-
-            writeln!(&parent_module, "pub mod {file_name};")?;
+        if !is_yaml {
+            continue;
         }
+        let relative = entry
+            .path()
+            .strip_prefix(&specs_dir)
+            .expect("walked from the specs directory")
+            .to_str()
+            .expect("spec paths are UTF-8")
+            .replace('\\', "/");
+        writeln!(
+            out,
+            "    ({relative:?}, include_str!({:?})),",
+            entry.path().display().to_string()
+        )?;
     }
-
-    write_workflows_function(parent_module, &mut workflows_added)?;
-
-    println!("running cargo fmt...");
-    Command::new("cargo")
-        .args(["fmt", "-p", "nextshell-workflows"])
-        .output()?;
-
-    Ok(())
-}
-
-/// Writes a `workflows` function into the module at path `parent_modules`. The generated function
-/// will look approximately like:
-/// ```ignore
-/// use nextshell_workflows_metadata::*;
-/// pub fn workflows() -> Vec<Workflow> {
-///     vec![
-///         foo::workflow(),
-///         bar::workflow(),
-///         bazz:workflow(),
-///     ]
-/// }
-/// ```
-fn write_workflows_function(
-    mut parent_module: File,
-    workflows_added: &mut Vec<String>,
-) -> Result<()> {
-    writeln!(parent_module, "\nuse nextshell_workflows_types::*;")?;
-    writeln!(parent_module, "pub fn workflows() -> Vec<Workflow> {{")?;
-    writeln!(parent_module, "vec![")?;
-    for workflows in workflows_added {
-        writeln!(parent_module, "{workflows}::workflow(),")?;
-    }
-    writeln!(parent_module, "]")?;
-    writeln!(parent_module, "}}")?;
-
-    Ok(())
-}
-
-/// Writes a Workflow into the path at `src/generated_workflows/<file_name>.rs`. The generated Rust
-/// will look like:
-/// ```ignore
-/// use nextshell_workflows_metadata::*;
-/// pub fn workflow -> Workflow {
-///    Workflow {
-///      ...
-///    }
-/// }
-/// ```
-fn write_workflow(workflow: Workflow, file_name: &str) -> Result<()> {
-    let module = std::fs::File::create(format!("src/generated_workflows/{file_name}.rs"))?;
-
-    writeln!(&module, "use nextshell_workflows_types::*;")?;
-    writeln!(&module, r#"pub fn workflow() -> Workflow {{"#)?;
-    writeln!(&module, "{}", uneval::to_string(workflow)?)?;
-    writeln!(&module, "}}")?;
-
-    Ok(())
+    writeln!(out, "]")?;
+    out.flush()
 }
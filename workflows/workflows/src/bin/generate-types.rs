@@ -0,0 +1,58 @@
+//! Generates the JSON Schema of workflow specs and the TypeScript definitions of the workflow
+//! types.
+//!
+//! ```text
+//! generate-types [--check] [DIR]
+//! ```
+//!
+//! Writes them under `DIR`, the root of the workflows workspace, `.` by default. With `--check`,
+//! writes nothing, and exits with 1 if the files there aren't up to date.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use nextshell_workflows::schema::generated;
+
+const USAGE: &str = "usage: generate-types [--check] [DIR]";
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut dir = PathBuf::from(".");
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("{USAGE}");
+                return ExitCode::from(2);
+            }
+            _ => dir = PathBuf::from(arg),
+        }
+    }
+
+    let mut stale = 0;
+    for file in generated() {
+        let path = dir.join(file.path);
+        if check {
+            if fs::read_to_string(&path).ok().as_deref() != Some(&*file.contents) {
+                eprintln!(
+                    "{}: out of date; run `cargo run --bin generate-types`",
+                    path.display()
+                );
+                stale += 1;
+            }
+        } else if let Err(err) = fs::write(&path, &file.contents) {
+            eprintln!("{}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    if stale > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,150 @@
+//! Imports cheatsheets as workflow specs.
+//!
+//! ```text
+//! import-workflows --format tldr|navi|cheat [--out DIR] [--force] FILE...
+//! ```
+//!
+//! Converts each cheatsheet `FILE`, writing a spec per workflow under `DIR`, `specs/imported` by
+//! default, and printing a line per entry that couldn't be converted, and per workflow whose
+//! placeholders and arguments don't match, which isn't written. The command a sheet is for is its
+//! file name, without the extension. Specs already in `DIR`, from an earlier import or edited by
+//! hand, are left alone unless `--force` is given. Exits with 1 if a file couldn't be read or
+//! written, or a spec was left alone.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use nextshell_workflows::import::{self, Format};
+
+const USAGE: &str =
+    "usage: import-workflows --format tldr|navi|cheat [--out DIR] [--force] FILE...";
+
+fn main() -> ExitCode {
+    let mut format = None;
+    let mut out = PathBuf::from("specs/imported");
+    let mut force = false;
+    let mut files = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(|value| value.parse::<Format>()) {
+                Some(Ok(value)) => format = Some(value),
+                Some(Err(err)) => {
+                    eprintln!("import-workflows: {err}");
+                    return usage();
+                }
+                None => return usage(),
+            },
+            "--out" => match args.next() {
+                Some(value) => out = PathBuf::from(value),
+                None => return usage(),
+            },
+            "--force" => force = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => return usage(),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    let format = match format {
+        Some(format) if !files.is_empty() => format,
+        _ => return usage(),
+    };
+
+    if let Err(err) = fs::create_dir_all(&out) {
+        eprintln!("import-workflows: {}: {err}", out.display());
+        return ExitCode::FAILURE;
+    }
+    let mut status = ExitCode::SUCCESS;
+    let mut stems = HashSet::new();
+    let (mut written, mut skipped) = (0, 0);
+    for file in &files {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("import-workflows: {}: {err}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        let name = command_name(file);
+        let imported = import::import(format, &name, &text);
+        for entry in &imported.skipped {
+            println!(
+                "{}:{}: skipped {}: {}",
+                file.display(),
+                entry.line,
+                entry.entry,
+                entry.reason
+            );
+        }
+        skipped += imported.skipped.len();
+
+        for workflow in &imported.workflows {
+            let stem = import::file_stem(&name, workflow);
+            let mut unique = stem.clone();
+            let mut n = 2;
+            while !stems.insert(unique.clone()) {
+                unique = format!("{stem}_{n}");
+                n += 1;
+            }
+            let path = out.join(format!("{unique}.yaml"));
+            if let Err(errors) = workflow.check_arguments() {
+                for error in errors {
+                    println!("{}: skipped {}: {error}", file.display(), workflow.name);
+                }
+                skipped += 1;
+                continue;
+            }
+            match write(&path, &import::to_yaml(workflow), force) {
+                Ok(()) => written += 1,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    eprintln!(
+                        "import-workflows: {}: already exists; use --force to overwrite it",
+                        path.display()
+                    );
+                    status = ExitCode::FAILURE;
+                }
+                Err(err) => {
+                    eprintln!("import-workflows: {}: {err}", path.display());
+                    status = ExitCode::FAILURE;
+                }
+            }
+        }
+    }
+    eprintln!(
+        "import-workflows: wrote {written} specs to {}, skipped {skipped} entries",
+        out.display()
+    );
+    status
+}
+
+/// Writes a spec, failing with [`io::ErrorKind::AlreadyExists`] if there's one already, unless
+/// `force`.
+fn write(path: &Path, contents: &str, force: bool) -> io::Result<()> {
+    if force {
+        return fs::write(path, contents);
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::from(2)
+}
+
+/// The command a sheet is for: its file name, without the extension.
+fn command_name(file: &Path) -> String {
+    file.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
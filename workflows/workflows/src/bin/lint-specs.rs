@@ -0,0 +1,93 @@
+//! Lints workflow specs.
+//!
+//! ```text
+//! lint-specs [--format text|json|github] [DIR]
+//! ```
+//!
+//! Checks the specs under `DIR`, `specs` by default, and prints a line per problem, a JSON list of
+//! them, or GitHub Actions commands annotating the specs. Exits with 1 if there are any errors.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use nextshell_workflows::lint::{lint, Diagnostic, Severity};
+
+const USAGE: &str = "usage: lint-specs [--format text|json|github] [DIR]";
+
+fn main() -> ExitCode {
+    let mut format = "text".to_owned();
+    let mut dir = PathBuf::from("specs");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next() {
+                Some(value) if ["text", "json", "github"].contains(&value.as_str()) => {
+                    format = value
+                }
+                _ => return usage(),
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => return usage(),
+            _ => dir = PathBuf::from(arg),
+        }
+    }
+
+    let diagnostics = lint(&dir);
+    match format.as_str() {
+        "json" => match serde_json::to_string_pretty(&diagnostics) {
+            Ok(json) => println!("{json}"),
+            Err(err) => {
+                eprintln!("lint-specs: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        "github" => diagnostics.iter().for_each(|d| println!("{}", github(d))),
+        _ => diagnostics.iter().for_each(|d| println!("{d}")),
+    }
+
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitCode::from(2)
+}
+
+/// A GitHub Actions workflow command annotating the spec.
+fn github(diagnostic: &Diagnostic) -> String {
+    let command = match diagnostic.severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+    let mut properties = format!(
+        "file={}",
+        escape_property(&diagnostic.path.to_string_lossy())
+    );
+    if let Some(line) = diagnostic.line {
+        properties.push_str(&format!(",line={line}"));
+    }
+    properties.push_str(&format!(",title={}", escape_property(diagnostic.rule)));
+    format!(
+        "::{command} {properties}::{}",
+        escape_data(&diagnostic.message)
+    )
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
@@ -0,0 +1,371 @@
+use std::io;
+
+use nextshell_workflows_types::{
+    Argument, ArgumentType, Runbook, Shell, TemplateError, Variable, Workflow,
+};
+
+/// Workflows as shell functions, from [`export`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Exported {
+    /// For bash and zsh, a single file to source. For fish, a file per function, under
+    /// `functions/`, to copy to `~/.config/fish/functions`.
+    pub files: Vec<File>,
+    /// The workflows that couldn't be exported, in the order given.
+    pub skipped: Vec<Skipped>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct File {
+    /// The path of the file, relative to wherever it's unpacked.
+    pub path: String,
+    pub contents: String,
+}
+
+/// A workflow that couldn't be exported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skipped {
+    /// The name it would have had.
+    pub name: String,
+    pub reason: String,
+}
+
+/// Exports workflows as functions of `shell`, each given its name, like the workflow's slug.
+///
+/// Workflows that list their shells, and not this one, are skipped, as are workflows whose
+/// commands can't be made functions; see [`function`]. So are runbooks of several steps, or of a
+/// step to confirm: as a function, every step would run, whatever its
+/// [`on_failure`](nextshell_workflows_types::Step::on_failure), and without asking first.
+///
+/// ```
+/// use nextshell_workflows::export::export;
+/// use nextshell_workflows::{Argument, Runbook, Shell, Workflow};
+///
+/// let workflow = Workflow::new("Check out a branch", "git checkout {{branch}}")
+///     .with_arguments(vec![Argument::new("branch").with_default_value("main")]);
+/// let exported = export(&Shell::Fish, [("checkout", &Runbook::from(workflow))]);
+/// assert_eq!(exported.files[0].path, "functions/checkout.fish");
+/// assert!(exported.files[0].contents.contains("function checkout"));
+/// ```
+pub fn export<'a, I>(shell: &Shell, workflows: I) -> Exported
+where
+    I: IntoIterator<Item = (&'a str, &'a Runbook)>,
+{
+    let mut functions = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, runbook) in workflows {
+        let name = function_name(name);
+        let reason = if !runbook.shells.is_empty() && !runbook.shells.contains(shell) {
+            Some(format!("it doesn't run in {}", shell_name(shell)))
+        } else if runbook.steps.len() > 1 {
+            Some(format!(
+                "it has {} steps, to run one at a time",
+                runbook.steps.len()
+            ))
+        } else if runbook.steps.iter().any(|step| step.confirm) {
+            Some("it's to be confirmed before it's run".to_owned())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            skipped.push(Skipped { name, reason });
+            continue;
+        }
+        match function(shell, &name, &runbook.to_workflow()) {
+            Ok(source) => functions.push((name, source)),
+            Err(err) => skipped.push(Skipped {
+                name,
+                reason: err.to_string(),
+            }),
+        }
+    }
+
+    let files = match shell {
+        Shell::Bash | Shell::Zsh => {
+            let mut contents = format!(
+                "# Workflows from nextshell, as {shell} functions.\n\
+                 #\n\
+                 # Source this file, from ~/.{shell}rc for instance, to define them:\n\
+                 #\n\
+                 #     source workflows.{shell}\n",
+                shell = shell_name(shell)
+            );
+            if !skipped.is_empty() {
+                contents.push_str("#\n# Not exported:\n");
+                for skipped in &skipped {
+                    contents.push_str(&format!("#   {}: {}\n", skipped.name, skipped.reason));
+                }
+            }
+            for (_, source) in &functions {
+                contents.push('\n');
+                contents.push_str(source);
+            }
+            vec![File {
+                path: format!("workflows.{}", shell_name(shell)),
+                contents,
+            }]
+        }
+        Shell::Fish => functions
+            .into_iter()
+            .map(|(name, source)| File {
+                path: format!("functions/{name}.fish"),
+                contents: source,
+            })
+            .collect(),
+    };
+    Exported { files, skipped }
+}
+
+/// Writes a workflow as a function of `shell` named `name`.
+///
+/// The function's arguments are the workflow's, in order, as positional parameters. Arguments
+/// left out or empty take their default, and without one the function prints its usage and
+/// returns 2. A boolean argument is `true` or `false`, and passes its flag, if it has one, when
+/// true. The function starts with a comment giving the workflow's name and description and its
+/// usage.
+///
+/// Fails if the workflow's arguments don't match its placeholders, or if a placeholder is where a
+/// variable can't be, in a `${...}` parameter expansion.
+pub fn function(shell: &Shell, name: &str, workflow: &Workflow) -> Result<String, TemplateError> {
+    if let Err(mut errors) = workflow.check_arguments() {
+        return Err(errors.remove(0));
+    }
+    let command = workflow.template().expand(shell, |placeholder| {
+        let argument = workflow
+            .arguments
+            .iter()
+            .find(|argument| argument.name == placeholder)
+            .ok_or_else(|| TemplateError::UnknownArgument(placeholder.to_owned()))?;
+        Ok(Variable {
+            name: variable(argument),
+            optional: argument.flag.is_some(),
+        })
+    })?;
+    for (i, argument) in workflow.arguments.iter().enumerate() {
+        if workflow.arguments[..i]
+            .iter()
+            .any(|earlier| variable(earlier) == variable(argument))
+        {
+            return Err(TemplateError::InvalidArgument {
+                argument: argument.name.clone(),
+                message: format!(
+                    "its variable, `{}`, is another argument's",
+                    variable(argument)
+                ),
+            });
+        }
+    }
+
+    let usage = std::iter::once(name.to_owned())
+        .chain(workflow.arguments.iter().map(|argument| {
+            if is_required(argument) {
+                format!("<{}>", argument.name)
+            } else {
+                format!("[{}]", argument.name)
+            }
+        }))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut source = help(workflow, &usage);
+    let indent = "    ";
+    match shell {
+        Shell::Bash | Shell::Zsh => source.push_str(&format!("{name}() {{\n")),
+        Shell::Fish => source.push_str(&format!(
+            "function {name} --description {}\n",
+            shell.quote(&workflow.name)
+        )),
+    }
+    for (i, argument) in workflow.arguments.iter().enumerate() {
+        for line in parameter(shell, i + 1, argument, &usage) {
+            source.push_str(indent);
+            source.push_str(&line);
+            source.push('\n');
+        }
+    }
+    // Indenting the lines of a longer command could change what's in its strings.
+    if command.lines().count() == 1 {
+        source.push_str(indent);
+    }
+    source.push_str(&command);
+    source.push('\n');
+    source.push_str(match shell {
+        Shell::Bash | Shell::Zsh => "}\n",
+        Shell::Fish => "end\n",
+    });
+    Ok(source)
+}
+
+/// The comment before a function: the workflow's name, description and usage.
+///
+/// Every line is commented out, even of a name or default with a newline in it, which would
+/// otherwise be run when the file is sourced.
+fn help(workflow: &Workflow, usage: &str) -> String {
+    let mut lines = vec![workflow.name.clone()];
+    if let Some(description) = &workflow.description {
+        lines.push(String::new());
+        lines.extend(description.lines().map(str::to_owned));
+    }
+    lines.push(String::new());
+    lines.push(format!("Usage: {usage}"));
+    let width = workflow
+        .arguments
+        .iter()
+        .map(|argument| argument.name.len())
+        .max()
+        .unwrap_or_default();
+    for argument in &workflow.arguments {
+        let mut line = format!("  {:width$} ", argument.name);
+        if let Some(description) = &argument.description {
+            line.push(' ');
+            line.push_str(description.lines().next().unwrap_or_default());
+        }
+        match (&argument.default_value, argument.arg_type) {
+            (Some(default), _) => line.push_str(&format!(" (default: {default})")),
+            (None, ArgumentType::Boolean) => line.push_str(" (default: false)"),
+            _ => {}
+        }
+        lines.push(line.trim_end().to_owned());
+    }
+    lines
+        .iter()
+        .flat_map(|line| line.split('\n'))
+        .map(|line| format!("# {line}").trim_end().to_owned() + "\n")
+        .collect()
+}
+
+/// The lines setting an argument's variable from positional parameter `n`.
+fn parameter(shell: &Shell, n: usize, argument: &Argument, usage: &str) -> Vec<String> {
+    let var = variable(argument);
+    let default = match (&argument.default_value, argument.arg_type) {
+        (Some(default), _) => Some(shell.quote(default)),
+        (None, ArgumentType::Boolean) => Some("false".to_owned()),
+        (None, _) => None,
+    };
+    let usage = shell.quote(&format!("usage: {usage}"));
+    let mut lines = Vec::new();
+    match shell {
+        Shell::Bash | Shell::Zsh => {
+            lines.push(format!("local {var}=\"${n}\""));
+            lines.push(match default {
+                Some(default) => format!("[ -n \"${var}\" ] || {var}={default}"),
+                None => format!("[ -n \"${var}\" ] || {{ echo {usage} >&2; return 2; }}"),
+            });
+            if let Some(flag) = &argument.flag {
+                lines.push(format!(
+                    "if [ \"${var}\" = true ]; then {var}={}; else {var}=; fi",
+                    shell.quote(flag)
+                ));
+            }
+        }
+        Shell::Fish => {
+            lines.push(format!("set -l {var} $argv[{n}]"));
+            lines.push(match default {
+                Some(default) => format!("test -n \"${var}\"; or set {var} {default}"),
+                None => format!("test -n \"${var}\"; or begin; echo {usage} >&2; return 2; end"),
+            });
+            if let Some(flag) = &argument.flag {
+                lines.push(format!(
+                    "if test \"${var}\" = true; set {var} {}; else; set {var}; end",
+                    shell.quote(flag)
+                ));
+            }
+        }
+    }
+    lines
+}
+
+fn is_required(argument: &Argument) -> bool {
+    argument.default_value.is_none() && argument.arg_type != ArgumentType::Boolean
+}
+
+/// The variable of an argument: its name, prefixed so it can't be one the shell gives meaning to,
+/// like zsh's `path`.
+fn variable(argument: &Argument) -> String {
+    format!("arg_{}", argument.name.replace('-', "_"))
+}
+
+/// A name made a function name, with anything but letters, digits, `_` and `-` replaced with `_`.
+fn function_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with('-') {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+pub(crate) fn shell_name(shell: &Shell) -> &'static str {
+    match shell {
+        Shell::Bash => "bash",
+        Shell::Zsh => "zsh",
+        Shell::Fish => "fish",
+    }
+}
+
+impl Exported {
+    /// The files as a tar archive.
+    ///
+    /// Each path is split at a `/` into the header's name, of up to 100 bytes, and its ustar
+    /// prefix, of up to 155. It's an [`io::ErrorKind::InvalidInput`] error if a path can't be
+    /// split so, like one whose file name alone is over 100 bytes.
+    pub fn to_tar(&self) -> io::Result<Vec<u8>> {
+        let mut tar = Vec::new();
+        for file in &self.files {
+            let (prefix, name) = split_path(&file.path).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("`{}` is too long for a tar archive", file.path),
+                )
+            })?;
+            let mut header = [0u8; 512];
+            put(&mut header[0..100], name.as_bytes());
+            put(&mut header[100..108], b"0000644");
+            put(&mut header[108..116], b"0000000");
+            put(&mut header[116..124], b"0000000");
+            put(
+                &mut header[124..136],
+                format!("{:011o}", file.contents.len()).as_bytes(),
+            );
+            put(&mut header[136..148], b"00000000000");
+            header[156] = b'0';
+            put(&mut header[257..265], b"ustar\x0000");
+            put(&mut header[345..500], prefix.as_bytes());
+            // The checksum is of the header with the checksum as spaces.
+            header[148..156].fill(b' ');
+            let checksum = header.iter().map(|&b| u32::from(b)).sum::<u32>();
+            put(
+                &mut header[148..156],
+                format!("{checksum:06o}\0 ").as_bytes(),
+            );
+
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(file.contents.as_bytes());
+            tar.resize(tar.len().div_ceil(512) * 512, 0);
+        }
+        tar.resize(tar.len() + 1024, 0);
+        Ok(tar)
+    }
+}
+
+/// Splits a path into a tar header's prefix and name, if it fits: at the last `/` it can be.
+fn split_path(path: &str) -> Option<(&str, &str)> {
+    path.rmatch_indices('/')
+        .map(|(at, _)| (&path[..at], &path[at + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+        .or((path.len() <= 100).then_some(("", path)))
+}
+
+/// Copies as much of `value` as fits into a header field.
+fn put(field: &mut [u8], value: &[u8]) {
+    let len = value.len().min(field.len());
+    field[..len].copy_from_slice(&value[..len]);
+}
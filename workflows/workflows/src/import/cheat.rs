@@ -0,0 +1,89 @@
+use nextshell_workflows_types::Workflow;
+use serde::Deserialize;
+
+use super::{angle_placeholders, workflow_name, Arguments, Imported, Importer};
+
+/// What a sheet's front matter says about it.
+#[derive(Default, Deserialize)]
+struct FrontMatter {
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Comments and the command lines after them.
+#[derive(Default)]
+struct Entry<'a> {
+    line: usize,
+    comments: Vec<&'a str>,
+    command: Vec<&'a str>,
+}
+
+pub(super) fn import(name: &str, text: &str) -> Imported {
+    let mut importer = Importer::new();
+    let lines = text.lines().collect::<Vec<_>>();
+
+    let mut body = 0;
+    let mut front_matter = FrontMatter::default();
+    if lines.first().map(|line| line.trim_end()) == Some("---") {
+        if let Some(end) = lines[1..].iter().position(|line| line.trim_end() == "---") {
+            body = end + 2;
+            let yaml = lines[1..=end].join("\n");
+            match serde_yaml::from_str::<Option<FrontMatter>>(&yaml) {
+                Ok(parsed) => front_matter = parsed.unwrap_or_default(),
+                Err(err) => importer.skip(1, "---", format!("the front matter isn't valid: {err}")),
+            }
+        }
+    }
+    let mut tags = vec![name.to_owned()];
+    for tag in front_matter.tags {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let mut entry = Entry::default();
+    for (i, line) in lines.iter().enumerate().skip(body) {
+        let number = i + 1;
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('#') {
+            if !entry.command.is_empty() {
+                add(&mut importer, &tags, std::mem::take(&mut entry));
+            }
+            if entry.comments.is_empty() {
+                entry.line = number;
+            }
+            entry.comments.push(comment.trim());
+        } else if line.trim().is_empty() {
+            add(&mut importer, &tags, std::mem::take(&mut entry));
+        } else {
+            if entry.comments.is_empty() && entry.command.is_empty() {
+                entry.line = number;
+            }
+            entry.command.push(line);
+        }
+    }
+    add(&mut importer, &tags, entry);
+    importer.finish()
+}
+
+/// Adds the workflow of an entry. Comments without a command, like headings, are left out.
+fn add(importer: &mut Importer, tags: &[String], entry: Entry<'_>) {
+    if entry.command.is_empty() {
+        return;
+    }
+    let command = entry.command.join("\n");
+    let comment = match entry.comments.iter().find(|comment| !comment.is_empty()) {
+        Some(comment) => comment,
+        None => return importer.skip(entry.line, &command, "the command has no comment"),
+    };
+
+    let mut arguments = Arguments::default();
+    let command = angle_placeholders(&command, &mut arguments);
+    let mut workflow =
+        Workflow::new(workflow_name(comment), command).with_arguments(arguments.into_vec());
+    if entry.comments.len() > 1 {
+        workflow.description = Some(entry.comments.join(" ").trim().to_owned());
+    }
+    workflow.tags = tags.to_vec();
+    importer.imported.workflows.push(workflow);
+}
@@ -0,0 +1,299 @@
+//! Converting other command-line cheatsheets to workflows.
+//!
+//! Each format has commands with descriptions, and marks the parts to fill in, `{{path/to/file}}`
+//! in tldr-pages and `<branch>` in navi and cheat. Those become the workflow's [`Argument`]s.
+//! Entries that can't be converted are reported as [`Skipped`], with the reason, rather than
+//! dropped.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use nextshell_workflows_types::{Argument, Workflow};
+use serde_yaml::Value;
+
+mod cheat;
+mod navi;
+mod tldr;
+
+/// A cheatsheet format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// A tldr-pages page: a `# command` heading, `> ` description lines, and examples, each a
+    /// `- description:` line and a `` `command` `` line, with `{{placeholders}}`.
+    Tldr,
+    /// A navi `.cheat` file: `% tags`, then `# description` lines each followed by a command with
+    /// `<variables>`, and `$ variable: command` lines suggesting their values.
+    Navi,
+    /// A cheat/cheat sheet: optional YAML front matter with `tags`, then `# comments` each followed
+    /// by commands.
+    Cheat,
+}
+
+/// Workflows read from a cheatsheet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Imported {
+    /// The entries that were understood, in the order found.
+    pub workflows: Vec<Workflow>,
+    /// The entries that weren't, in the order found.
+    pub skipped: Vec<Skipped>,
+}
+
+/// An entry that couldn't be imported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Skipped {
+    /// The line of the entry, counting from 1.
+    pub line: usize,
+    /// The entry, in its own format's words.
+    pub entry: String,
+    /// Why it wasn't imported.
+    pub reason: String,
+}
+
+/// A name that isn't a [`Format`].
+#[derive(Debug)]
+pub struct UnknownFormat(String);
+
+/// The formats, in the order of [`Format`]'s variants.
+pub const FORMATS: [Format; 3] = [Format::Tldr, Format::Navi, Format::Cheat];
+
+/// Reads a cheatsheet for the command `name`, usually the sheet's file name without its
+/// extension, which tags the workflows.
+///
+/// ```
+/// use nextshell_workflows::import::{self, Format};
+///
+/// let navi = "\
+/// % git, code
+///
+/// ## Change branch
+/// git checkout <branch>
+///
+/// $ branch: git branch --format='%(refname:short)'
+/// ";
+/// let imported = import::import(Format::Navi, "git", navi);
+/// let workflow = &imported.workflows[0];
+/// assert_eq!(workflow.name, "Change branch");
+/// assert_eq!(workflow.command, "git checkout {{branch}}");
+/// assert_eq!(workflow.tags, ["git", "code"]);
+/// assert_eq!(
+///     workflow.arguments[0].suggestions_command.as_deref(),
+///     Some("git branch --format='%(refname:short)'")
+/// );
+/// ```
+pub fn import(format: Format, name: &str, text: &str) -> Imported {
+    match format {
+        Format::Tldr => tldr::import(name, text),
+        Format::Navi => navi::import(name, text),
+        Format::Cheat => cheat::import(name, text),
+    }
+}
+
+/// Writes a workflow as a spec, leaving out what it doesn't have.
+pub fn to_yaml(workflow: &Workflow) -> String {
+    let mut value = serde_yaml::to_value(workflow).expect("workflows serialize to yaml");
+    remove_nulls(&mut value);
+    let yaml = serde_yaml::to_string(&value).expect("workflows serialize to yaml");
+    format!("---\n{yaml}")
+}
+
+/// A file name for a workflow's spec, without the extension, like `git_change_branch` for the
+/// `Change branch` workflow of `git`.
+pub fn file_stem(name: &str, workflow: &Workflow) -> String {
+    let stem = identifier(&format!("{name} {}", workflow.name));
+    if stem.is_empty() {
+        "workflow".to_owned()
+    } else {
+        stem
+    }
+}
+
+impl Format {
+    /// The name of the format, like `tldr`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Format::Tldr => "tldr",
+            Format::Navi => "navi",
+            Format::Cheat => "cheat",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Format, UnknownFormat> {
+        FORMATS
+            .iter()
+            .copied()
+            .find(|format| format.as_str() == s)
+            .ok_or_else(|| UnknownFormat(s.to_owned()))
+    }
+}
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let formats = FORMATS.iter().map(Format::as_str).collect::<Vec<_>>();
+        write!(
+            f,
+            "unknown format `{}`, expected one of: {}",
+            self.0,
+            formats.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownFormat {}
+
+impl fmt::Display for Skipped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line, self.entry, self.reason)
+    }
+}
+
+/// Collects the workflows of an import.
+struct Importer {
+    imported: Imported,
+}
+
+impl Importer {
+    fn new() -> Self {
+        Importer {
+            imported: Imported {
+                workflows: Vec::new(),
+                skipped: Vec::new(),
+            },
+        }
+    }
+
+    fn skip(&mut self, line: usize, entry: &str, reason: impl Into<String>) {
+        self.imported.skipped.push(Skipped {
+            line,
+            entry: entry.trim().to_owned(),
+            reason: reason.into(),
+        });
+    }
+
+    fn finish(self) -> Imported {
+        self.imported
+    }
+}
+
+/// Names the arguments of a command from what its placeholders say, each name only once.
+#[derive(Default)]
+struct Arguments {
+    arguments: Vec<Argument>,
+    by_text: HashMap<String, String>,
+}
+
+impl Arguments {
+    /// The name of the argument for a placeholder, adding it if it's new.
+    ///
+    /// Placeholders with the same text are the same argument. The name is the text, made an
+    /// identifier if it isn't a valid argument name, with a number added if another placeholder
+    /// already has it.
+    fn name(&mut self, text: &str, description: Option<String>) -> String {
+        if let Some(name) = self.by_text.get(text) {
+            return name.clone();
+        }
+        let mut base = if is_name(text) {
+            text.to_owned()
+        } else {
+            identifier(text)
+        };
+        if base.is_empty() {
+            base = "argument".to_owned();
+        } else if base.starts_with(|c: char| c.is_ascii_digit()) {
+            base = format!("value_{base}");
+        }
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.arguments.iter().any(|argument| argument.name == name) {
+            name = format!("{base}_{n}");
+            n += 1;
+        }
+        let mut argument = Argument::new(&name);
+        argument.description = description;
+        self.arguments.push(argument);
+        self.by_text.insert(text.to_owned(), name.clone());
+        name
+    }
+
+    fn into_vec(self) -> Vec<Argument> {
+        self.arguments
+    }
+}
+
+/// Replaces navi and cheat's `<variable>` placeholders with `{{variable}}`, naming arguments for
+/// them. A `<` followed by anything but a name and `>`, like a redirection, is left alone.
+fn angle_placeholders(command: &str, arguments: &mut Arguments) -> String {
+    let mut converted = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(open) = rest.find('<') {
+        converted.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after.len());
+        if len > 0 && after[len..].starts_with('>') {
+            let name = arguments.name(&after[..len], None);
+            converted.push_str(&format!("{{{{{name}}}}}"));
+            rest = &after[len + 1..];
+        } else {
+            converted.push('<');
+            rest = after;
+        }
+    }
+    converted.push_str(rest);
+    converted
+}
+
+/// Whether text can be an argument name as it is.
+fn is_name(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Lowercase letters, digits and underscores, runs of anything else made a single underscore.
+fn identifier(text: &str) -> String {
+    let mut identifier = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            identifier.push(c.to_ascii_lowercase());
+        } else if !identifier.is_empty() && !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    identifier.trim_end_matches('_').to_owned()
+}
+
+/// A description made a workflow name: without a trailing colon or period, `To` at the start, or
+/// surrounding space, and capitalized.
+fn workflow_name(description: &str) -> String {
+    let name = description.trim().trim_end_matches([':', '.']).trim_end();
+    let name = name.strip_prefix("To ").unwrap_or(name);
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            mapping.retain(|_, value| !value.is_null());
+            mapping.values_mut().for_each(remove_nulls);
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
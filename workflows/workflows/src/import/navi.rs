@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use nextshell_workflows_types::Workflow;
+
+use super::{angle_placeholders, workflow_name, Arguments, Imported, Importer};
+
+/// A description and the command lines after it.
+struct Entry<'a> {
+    line: usize,
+    description: Option<&'a str>,
+    command: Vec<&'a str>,
+}
+
+pub(super) fn import(name: &str, text: &str) -> Imported {
+    let mut importer = Importer::new();
+    let mut tags = vec![name.to_owned()];
+    // The workflows of the current `%` section, which its `$` lines suggest values for.
+    let mut section_start = 0;
+    let mut suggestions = HashMap::new();
+    let mut entry: Option<Entry<'_>> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim_end();
+        if line.starts_with(';') {
+            continue;
+        }
+        if !line.is_empty() && !line.starts_with(['%', '#', '$', '@']) {
+            entry
+                .get_or_insert(Entry {
+                    line: number,
+                    description: None,
+                    command: Vec::new(),
+                })
+                .command
+                .push(line);
+            continue;
+        }
+
+        if let Some(entry) = entry.take() {
+            add(&mut importer, &tags, entry);
+        }
+        if let Some(section) = line.strip_prefix('%') {
+            suggest(
+                &mut importer.imported.workflows[section_start..],
+                &suggestions,
+            );
+            section_start = importer.imported.workflows.len();
+            suggestions.clear();
+            tags = section
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_owned)
+                .collect();
+        } else if let Some(description) = line.strip_prefix('#') {
+            entry = Some(Entry {
+                line: number,
+                description: Some(description.trim()),
+                command: Vec::new(),
+            });
+        } else if let Some(variable) = line.strip_prefix('$') {
+            match variable.split_once(':') {
+                Some((variable, command)) => {
+                    let command = command
+                        .split_once("---")
+                        .map_or(command, |(command, _)| command);
+                    suggestions.insert(variable.trim().to_owned(), command.trim().to_owned());
+                }
+                None => importer.skip(number, line, "the variable has no command"),
+            }
+        } else if line.starts_with('@') {
+            importer.skip(
+                number,
+                line,
+                "using the variables of another cheat isn't supported",
+            );
+        }
+    }
+    if let Some(entry) = entry {
+        add(&mut importer, &tags, entry);
+    }
+    suggest(
+        &mut importer.imported.workflows[section_start..],
+        &suggestions,
+    );
+    importer.finish()
+}
+
+fn add(importer: &mut Importer, tags: &[String], entry: Entry<'_>) {
+    let command = entry.command.join("\n");
+    let description = match entry.description {
+        Some(description) if !description.is_empty() => description,
+        _ if command.is_empty() => return,
+        _ => return importer.skip(entry.line, &command, "the command has no description"),
+    };
+    if command.is_empty() {
+        return importer.skip(entry.line, description, "the description has no command");
+    }
+
+    let mut arguments = Arguments::default();
+    let command = angle_placeholders(&command, &mut arguments);
+    let mut workflow =
+        Workflow::new(workflow_name(description), command).with_arguments(arguments.into_vec());
+    workflow.tags = tags.to_vec();
+    importer.imported.workflows.push(workflow);
+}
+
+/// Sets the suggestions command of every argument a `$` line is for.
+fn suggest(workflows: &mut [Workflow], suggestions: &HashMap<String, String>) {
+    for argument in workflows
+        .iter_mut()
+        .flat_map(|workflow| workflow.arguments.iter_mut())
+    {
+        if let Some(command) = suggestions.get(&argument.name) {
+            argument.suggestions_command = Some(command.clone());
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use nextshell_workflows_types::Workflow;
+
+use super::{workflow_name, Arguments, Imported, Importer};
+
+pub(super) fn import(name: &str, text: &str) -> Imported {
+    let mut importer = Importer::new();
+    let mut command_name = name.to_owned();
+    let mut summary = Vec::new();
+    let mut source_url = None;
+    let mut example: Option<(usize, &str)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim_end();
+        if let Some(heading) = line.strip_prefix("# ") {
+            command_name = heading.trim().to_owned();
+        } else if let Some(about) = line.strip_prefix('>') {
+            let about = about.trim();
+            match about.strip_prefix("More information:") {
+                Some(link) => {
+                    source_url = link
+                        .split_once('<')
+                        .and_then(|(_, url)| url.split_once('>'))
+                        .map(|(url, _)| url.to_owned());
+                }
+                None => summary.push(about),
+            }
+        } else if let Some(description) = line.strip_prefix("- ") {
+            if let Some((line, description)) = example.replace((number, description)) {
+                importer.skip(line, description, "the example has no command");
+            }
+        } else if line.len() >= 2 && line.starts_with('`') && line.ends_with('`') {
+            let code = &line[1..line.len() - 1];
+            let (_, description) = match example.take() {
+                Some(example) => example,
+                None => {
+                    importer.skip(number, line, "the command has no description");
+                    continue;
+                }
+            };
+            match convert(code) {
+                Ok((command, arguments)) => {
+                    let mut workflow =
+                        Workflow::new(workflow_name(&description.replace(['[', ']'], "")), command)
+                            .with_arguments(arguments.into_vec());
+                    workflow.tags = vec![command_name.clone()];
+                    importer.imported.workflows.push(workflow);
+                }
+                Err(reason) => importer.skip(number, line, reason),
+            }
+        }
+    }
+    if let Some((line, description)) = example {
+        importer.skip(line, description, "the example has no command");
+    }
+
+    let description = (!summary.is_empty()).then(|| summary.join(" "));
+    let mut imported = importer.finish();
+    for workflow in &mut imported.workflows {
+        workflow.description = description.clone();
+        workflow.source_url = source_url.clone();
+    }
+    imported
+}
+
+/// Replaces `{{placeholders}}` with arguments named after what they say, without the
+/// `path/to/` that tldr-pages puts before paths. Option placeholders, like `{{[-a|--all]}}`, are
+/// replaced with their last, long form.
+fn convert(code: &str) -> Result<(String, Arguments), String> {
+    let mut arguments = Arguments::default();
+    let mut command = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(open) = rest.find("{{") {
+        command.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let close = after
+            .find("}}")
+            .ok_or_else(|| "a `{{` placeholder isn't closed".to_owned())?;
+        let text = &after[..close];
+        match text
+            .strip_prefix('[')
+            .and_then(|options| options.strip_suffix(']'))
+            .filter(|options| options.contains('|'))
+        {
+            Some(options) => command.push_str(options.rsplit('|').next().unwrap_or_default()),
+            None => {
+                let name = arguments.name(&text.replace("path/to/", ""), Some(text.to_owned()));
+                command.push_str(&format!("{{{{{name}}}}}"));
+            }
+        }
+        rest = &after[close + 2..];
+    }
+    command.push_str(rest);
+    Ok((command, arguments))
+}
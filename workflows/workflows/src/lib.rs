@@ -1,4 +1,27 @@
-mod generated_workflows;
+pub mod export;
+pub mod import;
+pub mod lint;
+pub mod schema;
+pub mod search;
+mod store;
+pub mod usage;
 
-pub use generated_workflows::workflows;
 pub use nextshell_workflows_types::*;
+pub use store::{Layer, LoadError, Source, StoredWorkflow, WorkflowStore, QUALIFIER};
+
+/// The specs in `/specs`, built into the crate, as pairs of their path relative to `/specs` and
+/// their contents.
+pub static EMBEDDED_SPECS: &[(&str, &str)] =
+    include!(concat!(env!("OUT_DIR"), "/embedded_specs.rs"));
+
+/// Every workflow built into the crate from `/specs`.
+///
+/// Specs that don't parse are left out; load them with a [`WorkflowStore`] to find out why.
+pub fn workflows() -> Vec<Workflow> {
+    WorkflowStore::new()
+        .with_source(Source::Embedded(EMBEDDED_SPECS))
+        .workflows()
+        .iter()
+        .map(|stored| stored.workflow.clone())
+        .collect()
+}
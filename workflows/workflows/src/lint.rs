@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nextshell_workflows_types::{Shell, TemplateError};
+use serde::Serialize;
+
+use crate::{Source, StoredWorkflow, WorkflowStore};
+
+/// A problem with a spec, found by [`lint`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// The line of the problem, if known, counting from 1.
+    pub line: Option<usize>,
+    pub severity: Severity,
+    /// What's checked, like `unused-argument`.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Bash and zsh syntax fish doesn't have, found by what it starts with.
+const BASH_SYNTAX: &[(&str, &str)] = &[
+    ("<(", "process substitution `<(...)`"),
+    (">(", "process substitution `>(...)`"),
+    ("$((", "arithmetic expansion `$((...))`"),
+    ("${", "parameter expansion `${...}`"),
+    ("`", "command substitution with backquotes"),
+    ("<<", "here-documents"),
+    ("[[", "`[[ ... ]]` tests"),
+    ("$?", "`$?`, which is `$status` in fish"),
+];
+
+/// Bash and zsh keywords fish doesn't have, at the start of a command.
+const BASH_KEYWORDS: &[(&str, &str)] = &[
+    ("then", "`if ...; then ... fi`"),
+    ("fi", "`if ...; then ... fi`"),
+    ("do", "`do ... done` loops"),
+    ("done", "`do ... done` loops"),
+    ("esac", "`case ... esac`"),
+];
+
+/// Checks every spec under `dir`: that it loads, that its placeholders and arguments match, and
+/// none follows a `$`, that its name and slug are its own, that its URLs are URLs, and that it
+/// doesn't use bash syntax while claiming to run in fish.
+///
+/// Diagnostics are sorted by path and line.
+pub fn lint(dir: &Path) -> Vec<Diagnostic> {
+    let store = WorkflowStore::new().with_source(Source::Directory(dir.to_owned()));
+    let mut diagnostics = store
+        .errors()
+        .iter()
+        .map(|err| Diagnostic {
+            path: err.path.clone(),
+            line: err.line,
+            severity: Severity::Error,
+            rule: "load",
+            message: err.message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut names = HashMap::new();
+    let mut slugs = HashMap::new();
+    for stored in store.workflows() {
+        let path = dir.join(&stored.path);
+        let text = fs::read_to_string(&path).unwrap_or_default();
+        let mut spec = Spec {
+            path,
+            text: &text,
+            diagnostics: &mut diagnostics,
+        };
+        check_arguments(&mut spec, stored);
+        check_urls(&mut spec, stored);
+        check_shells(&mut spec, stored);
+
+        if let Some(first) = names.insert(&stored.workflow.name, &stored.path) {
+            let line = spec.line_of_key("name");
+            spec.report(
+                line,
+                Severity::Warning,
+                "duplicate-name",
+                format!("`{}` is also the name of {first}", stored.workflow.name),
+            );
+        }
+        if let Some(first) = slugs.insert(&stored.slug, &stored.path) {
+            spec.report(
+                None,
+                Severity::Error,
+                "duplicate-slug",
+                format!("`{}` is also the slug of {first}", stored.slug),
+            );
+        }
+    }
+
+    diagnostics.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    diagnostics
+}
+
+/// A spec being linted.
+struct Spec<'a> {
+    path: PathBuf,
+    text: &'a str,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Spec<'_> {
+    fn report(
+        &mut self,
+        line: Option<usize>,
+        severity: Severity,
+        rule: &'static str,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            path: self.path.clone(),
+            line,
+            severity,
+            rule,
+            message,
+        });
+    }
+
+    /// The line of a top-level key.
+    fn line_of_key(&self, key: &str) -> Option<usize> {
+        let prefix = format!("{key}:");
+        self.line_of(|line| line.starts_with(&prefix))
+    }
+
+    /// The line of the command, or of the steps of a runbook.
+    fn line_of_command(&self) -> Option<usize> {
+        self.line_of_key("command")
+            .or_else(|| self.line_of_key("steps"))
+    }
+
+    /// The line an argument is named on.
+    fn line_of_argument(&self, name: &str) -> Option<usize> {
+        let arguments = self.line_of_key("arguments")?;
+        self.text
+            .lines()
+            .enumerate()
+            .skip(arguments)
+            .find(|(_, line)| {
+                let line = line.trim_start();
+                let line = line.strip_prefix("- ").unwrap_or(line).trim_start();
+                line.strip_prefix("name:")
+                    .is_some_and(|value| value.trim().trim_matches(['"', '\'']) == name)
+            })
+            .map(|(i, _)| i + 1)
+    }
+
+    fn line_of(&self, matches: impl FnMut(&str) -> bool) -> Option<usize> {
+        self.text.lines().position(matches).map(|i| i + 1)
+    }
+}
+
+fn check_arguments(spec: &mut Spec<'_>, stored: &StoredWorkflow) {
+    // A `$` right before a placeholder is left in front of the value, as in `$'my file'`, which
+    // is never what's meant.
+    for step in &stored.runbook.steps {
+        let template = nextshell_workflows_types::Template::parse(&step.command);
+        for name in template.placeholders() {
+            let placeholder = format!("{{{{{name}}}}}");
+            if step.command.contains(&format!("${placeholder}")) {
+                let line = spec.line_of_command();
+                spec.report(
+                    line,
+                    Severity::Error,
+                    "dollar-placeholder",
+                    format!("`${placeholder}` puts a `$` before the value; write `{placeholder}`"),
+                );
+            }
+        }
+    }
+
+    let errors = match stored.runbook.check_arguments() {
+        Ok(()) => return,
+        Err(errors) => errors,
+    };
+    for err in errors {
+        let (line, rule) = match &err {
+            TemplateError::UnknownArgument(_) => (spec.line_of_command(), "undeclared-placeholder"),
+            TemplateError::UnusedArgument(name) => (spec.line_of_argument(name), "unused-argument"),
+            TemplateError::InvalidArgument { argument, .. } => {
+                (spec.line_of_argument(argument), "invalid-argument")
+            }
+            _ => (None, "invalid-argument"),
+        };
+        spec.report(line, Severity::Error, rule, err.to_string());
+    }
+}
+
+fn check_urls(spec: &mut Spec<'_>, stored: &StoredWorkflow) {
+    let mut urls = Vec::new();
+    if let Some(url) = &stored.workflow.source_url {
+        urls.push(("source_url", url.as_str()));
+    }
+    // One URL for each author, like the authors, separated by commas.
+    if let Some(author_urls) = &stored.workflow.author_url {
+        urls.extend(author_urls.split(',').map(|url| ("author_url", url.trim())));
+    }
+    for (key, url) in urls {
+        if !is_url(url) {
+            let line = spec.line_of_key(key);
+            spec.report(
+                line,
+                Severity::Error,
+                "invalid-url",
+                format!("`{key}` isn't an http or https URL: {url:?}"),
+            );
+        }
+    }
+}
+
+fn check_shells(spec: &mut Spec<'_>, stored: &StoredWorkflow) {
+    let shells = &stored.workflow.shells;
+    if !shells.is_empty() && !shells.contains(&Shell::Fish) {
+        return;
+    }
+    let syntax = match bash_syntax(&stored.workflow.command) {
+        Some(syntax) => syntax,
+        None => return,
+    };
+    let line = spec.line_of_command();
+    if shells.is_empty() {
+        spec.report(
+            line,
+            Severity::Warning,
+            "fish-syntax",
+            format!("uses {syntax}, which fish doesn't have; list the `shells` it runs in"),
+        );
+    } else {
+        spec.report(
+            line,
+            Severity::Error,
+            "fish-syntax",
+            format!("uses {syntax}, which fish doesn't have, but lists `Fish` in `shells`"),
+        );
+    }
+}
+
+/// Finds syntax in a command that bash and zsh have and fish doesn't, outside single quotes.
+fn bash_syntax(command: &str) -> Option<&'static str> {
+    let template = nextshell_workflows_types::Template::parse(command);
+    let mut command = command.to_owned();
+    for name in template.placeholders() {
+        command = command.replace(&format!("{{{{{name}}}}}"), "x");
+    }
+    let unquoted = strip_single_quotes(&command);
+
+    for (i, _) in unquoted.char_indices() {
+        if let Some((_, syntax)) = BASH_SYNTAX
+            .iter()
+            .find(|(start, _)| unquoted[i..].starts_with(start))
+        {
+            return Some(syntax);
+        }
+    }
+    unquoted
+        .split(['\n', ';', '&', '|'])
+        .filter_map(|command| command.split_whitespace().next())
+        .find_map(|word| {
+            BASH_KEYWORDS
+                .iter()
+                .find(|(keyword, _)| *keyword == word)
+                .map(|(_, syntax)| *syntax)
+        })
+}
+
+/// Leaves out what's in single quotes, where nothing is special.
+fn strip_single_quotes(command: &str) -> String {
+    let mut stripped = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    let mut in_double = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                stripped.push(c);
+                stripped.extend(chars.next());
+            }
+            '\'' if !in_double => {
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                }
+                stripped.push_str("''");
+            }
+            '"' => {
+                in_double = !in_double;
+                stripped.push(c);
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+fn is_url(url: &str) -> bool {
+    let rest = match url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    !url.contains(char::is_whitespace)
+        && (host.contains('.') || host.starts_with("localhost"))
+        && !host.starts_with('.')
+        && !host.ends_with('.')
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(f, "{}:{}: ", self.path.display(), line)?,
+            None => write!(f, "{}: ", self.path.display())?,
+        }
+        write!(f, "{}: {} [{}]", severity, self.message, self.rule)
+    }
+}
@@ -0,0 +1,255 @@
+//! A JSON Schema of workflow specs, and TypeScript definitions of the workflow types, generated
+//! from the Rust types so that they can't drift from them.
+//!
+//! Both are checked in: the schema for spec authors' editors, which can be pointed at it, as the
+//! YAML language server is by a `# yaml-language-server: $schema=../../workflow.schema.json`
+//! comment, and the definitions for the frontend, in `build_ts`. Regenerate them with
+//! `cargo run --bin generate-types` after changing the types.
+
+use nextshell_workflows_types::{Runbook, Workflow};
+use serde_json::{Map, Value};
+
+/// A generated file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generated {
+    /// The path of the file, relative to the root of the workflows workspace.
+    pub path: &'static str,
+    pub contents: String,
+}
+
+const HEADER: &str = "\
+// Generated from the Rust types of `workflow-types`. Don't edit this file; change the types and
+// run `cargo run --bin generate-types` instead.
+";
+
+/// Every generated file.
+pub fn generated() -> Vec<Generated> {
+    vec![
+        Generated {
+            path: "workflow.schema.json",
+            contents: json_schema(),
+        },
+        Generated {
+            path: "build_ts/types.ts",
+            contents: typescript(),
+        },
+    ]
+}
+
+/// The JSON Schema of a workflow spec: a [`Runbook`], of a single `command` or of `steps`.
+pub fn json_schema() -> String {
+    let json = serde_json::to_string_pretty(&spec_schema()).expect("schemas serialize to JSON");
+    format!("{json}\n")
+}
+
+/// TypeScript definitions of [`Workflow`], [`Runbook`] and the types they're made of.
+///
+/// Fields that can be left out are optional. A field can also be `null` in a spec, as YAML writes
+/// an empty value, but the definitions leave that out, as if the field were absent.
+pub fn typescript() -> String {
+    let mut ts = HEADER.to_owned();
+    let mut definitions = Map::new();
+    for (name, schema) in [("Workflow", workflow_schema()), ("Runbook", spec_schema())] {
+        ts.push('\n');
+        declaration(&mut ts, name, &schema);
+        if let Some(Value::Object(more)) = schema.get("definitions") {
+            definitions.extend(more.clone());
+        }
+    }
+    for (name, definition) in &definitions {
+        ts.push('\n');
+        declaration(&mut ts, name, definition);
+    }
+    ts
+}
+
+/// The schema of a [`Runbook`], as a spec is written, with the rustdoc links of the descriptions
+/// made plain code.
+fn spec_schema() -> Value {
+    plain(schemars::schema_for!(Runbook))
+}
+
+/// The schema of a [`Workflow`], as the server serves it.
+fn workflow_schema() -> Value {
+    plain(schemars::schema_for!(Workflow))
+}
+
+fn plain(schema: schemars::schema::RootSchema) -> Value {
+    let mut schema = serde_json::to_value(schema).expect("schemas serialize to JSON");
+    plain_descriptions(&mut schema);
+    schema
+}
+
+fn plain_descriptions(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(description) if key == "description" => {
+                        *description = without_links(description)
+                    }
+                    _ => plain_descriptions(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(plain_descriptions),
+        _ => {}
+    }
+}
+
+/// Replaces rustdoc links to code, like ``[`Argument`]`` or ``[`flag`](Argument::flag)``, with
+/// their text, `` `Argument` `` and `` `flag` ``.
+fn without_links(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find("[`") {
+        plain.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find("`]") else {
+            rest = &rest[open..];
+            break;
+        };
+        plain.push_str(&after[..close + 1]);
+        rest = &after[close + 2..];
+        if rest.starts_with('(') {
+            if let Some(end) = rest.find(')') {
+                rest = &rest[end + 1..];
+            }
+        }
+    }
+    plain.push_str(rest);
+    plain
+}
+
+/// Declares a type: an interface for an object, or else a type alias.
+///
+/// An enum of strings also gets a const object of the same name, so that the frontend can write
+/// `Shell.Bash`, as it could when these were hand-written `const enum`s.
+fn declaration(ts: &mut String, name: &str, schema: &Value) {
+    doc_comment(ts, "", schema);
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        ts.push_str(&format!("export type {name} = {};\n", type_of(schema)));
+        if let Some(members) = enum_members(schema) {
+            ts.push_str(&format!("export const {name} = {{\n"));
+            for (member, value) in members {
+                ts.push_str(&format!("  {member}: {value:?},\n"));
+            }
+            ts.push_str("} as const;\n");
+        }
+        return;
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    ts.push_str(&format!("export interface {name} {{\n"));
+    for (property, schema) in properties {
+        doc_comment(ts, "  ", schema);
+        let optional = if required.contains(&property.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        ts.push_str(&format!("  {property}{optional}: {};\n", type_of(schema)));
+    }
+    ts.push_str("}\n");
+}
+
+/// The members of an enum of lowercase strings, named by capitalizing them, like `Bash` for
+/// `"bash"`. Aliases, like `"Bash"` too, are left out.
+fn enum_members(schema: &Value) -> Option<Vec<(String, &str)>> {
+    let mut members = Vec::new();
+    for value in enum_values(schema)? {
+        let value = value.as_str()?;
+        if value.is_empty() || value.chars().any(|c| !c.is_ascii_lowercase()) {
+            continue;
+        }
+        members.push((value[..1].to_ascii_uppercase() + &value[1..], value));
+    }
+    (!members.is_empty()).then_some(members)
+}
+
+/// The values of an enum, given at once or, for variants with descriptions, as `oneOf` its parts.
+fn enum_values(schema: &Value) -> Option<Vec<&Value>> {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Some(values.iter().collect());
+    }
+    let mut values = Vec::new();
+    for schema in schema.get("oneOf")?.as_array()? {
+        values.extend(enum_values(schema)?);
+    }
+    Some(values)
+}
+
+fn doc_comment(ts: &mut String, indent: &str, schema: &Value) {
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        ts.push_str(&format!("{indent}/** {description} */\n"));
+    }
+}
+
+/// The TypeScript type of values matching a schema.
+fn type_of(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_owned();
+    }
+    // schemars wraps a reference in `allOf` to give it a description.
+    for (key, separator) in [("allOf", " & "), ("anyOf", " | "), ("oneOf", " | ")] {
+        if let Some(schemas) = schema.get(key).and_then(Value::as_array) {
+            let mut types = Vec::new();
+            for schema in schemas {
+                // An optional field's `null`, which the definitions leave out.
+                if schema.get("type").and_then(Value::as_str) == Some("null") {
+                    continue;
+                }
+                let ty = type_of(schema);
+                if !types.contains(&ty) {
+                    types.push(ty);
+                }
+            }
+            return types.join(separator);
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        // JSON literals are TypeScript literals too.
+        return values
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+
+    let types = match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let types = types
+        .into_iter()
+        .filter(|&ty| ty != "null")
+        .map(|ty| match ty {
+            "string" => "string".to_owned(),
+            "boolean" => "boolean".to_owned(),
+            "integer" | "number" => "number".to_owned(),
+            "array" => {
+                let items = schema.get("items").map_or("unknown".to_owned(), type_of);
+                if items.contains(' ') {
+                    format!("({items})[]")
+                } else {
+                    format!("{items}[]")
+                }
+            }
+            _ => "unknown".to_owned(),
+        })
+        .collect::<Vec<_>>();
+    if types.is_empty() {
+        "unknown".to_owned()
+    } else {
+        types.join(" | ")
+    }
+}
@@ -0,0 +1,273 @@
+use nextshell_workflows_types::{Shell, Workflow};
+
+/// How much a match in each field counts towards a workflow's score.
+const NAME_WEIGHT: u32 = 8;
+const TAGS_WEIGHT: u32 = 5;
+const DESCRIPTION_WEIGHT: u32 = 3;
+const COMMAND_WEIGHT: u32 = 2;
+
+/// How well a query term matches a word, out of 4.
+const EXACT: u32 = 4;
+const PREFIX: u32 = 3;
+const TYPO: u32 = 2;
+const PREFIX_TYPO: u32 = 1;
+
+/// An in-memory index of workflows, searched by name, tags, description and command.
+///
+/// Matching is fuzzy: a query term matches words it's the start of, so results come as the query
+/// is typed, and words a typo or two away, so `rebsae` still finds `rebase`.
+///
+/// ```
+/// use nextshell_workflows::search::{Query, SearchIndex};
+/// use nextshell_workflows::Workflow;
+///
+/// let index = SearchIndex::new(vec![
+///     Workflow::new("Undo the last commit", "git reset --soft HEAD~1"),
+///     Workflow::new("List listening ports", "lsof -i -P | grep LISTEN"),
+/// ]);
+/// let hits = index.search(&Query::new("undo comit"));
+/// assert_eq!(hits.len(), 1);
+/// assert_eq!(hits[0].workflow.name, "Undo the last commit");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    documents: Vec<Document>,
+}
+
+/// What to search for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    /// The words to look for. Each must match somewhere in a workflow for it to be found. With no
+    /// words, every workflow passing the filters is found.
+    pub text: String,
+    /// Tags a workflow must all have, ignoring case.
+    pub tags: Vec<String>,
+    /// A shell the workflow must run in. Workflows that don't list their shells run in any.
+    pub shell: Option<Shell>,
+}
+
+/// A workflow found by [`SearchIndex::search`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit<'a> {
+    /// The position of the workflow in the list the index was built from.
+    pub position: usize,
+    pub workflow: &'a Workflow,
+    /// How well the workflow matches; higher is better.
+    pub score: u32,
+}
+
+/// A workflow and its words, lowercased.
+#[derive(Clone, Debug)]
+struct Document {
+    workflow: Workflow,
+    name: Vec<String>,
+    tags: Vec<String>,
+    description: Vec<String>,
+    command: Vec<String>,
+}
+
+impl Query {
+    pub fn new(text: impl Into<String>) -> Self {
+        Query {
+            text: text.into(),
+            ..Query::default()
+        }
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_shell(mut self, shell: Shell) -> Self {
+        self.shell = Some(shell);
+        self
+    }
+}
+
+impl SearchIndex {
+    pub fn new(workflows: Vec<Workflow>) -> Self {
+        let documents = workflows
+            .into_iter()
+            .map(|workflow| Document {
+                name: words(&workflow.name),
+                tags: workflow.tags.iter().flat_map(|tag| words(tag)).collect(),
+                description: workflow.description.as_deref().map_or_else(Vec::new, words),
+                command: words(&workflow.command),
+                workflow,
+            })
+            .collect();
+        SearchIndex { documents }
+    }
+
+    /// Every workflow indexed, in the order the index was built from.
+    pub fn workflows(&self) -> impl Iterator<Item = &Workflow> {
+        self.documents.iter().map(|document| &document.workflow)
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Finds the workflows matching a query, best matches first.
+    ///
+    /// A term's score is how well it matches, an exact word counting more than the start of one,
+    /// and that more than a word with a typo, times the weight of the field it's in, a workflow's
+    /// name counting most, then its tags, its description and its command. A workflow's score is
+    /// the sum of its terms'. Workflows with the same score are ordered by name.
+    pub fn search(&self, query: &Query) -> Vec<Hit<'_>> {
+        self.search_with(query, |_| 0)
+    }
+
+    /// Finds the workflows matching a query, as [`search`](SearchIndex::search) does, with `boost`
+    /// of each workflow's position added to its score, like its
+    /// [`Frecency`](crate::usage::Frecency) boost, to rank the workflows used most first.
+    pub fn search_with(&self, query: &Query, boost: impl Fn(usize) -> u32) -> Vec<Hit<'_>> {
+        let terms = words(&query.text);
+        let tags = query
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut hits = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter(|(_, document)| document.passes(&tags, query.shell.as_ref()))
+            .filter_map(|(position, document)| {
+                let score = terms
+                    .iter()
+                    .map(|term| document.score(term))
+                    .try_fold(boost(position), |total, score| Some(total + score?))?;
+                Some(Hit {
+                    position,
+                    workflow: &document.workflow,
+                    score,
+                })
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.workflow.name.cmp(&b.workflow.name))
+        });
+        hits
+    }
+}
+
+impl From<Vec<Workflow>> for SearchIndex {
+    fn from(workflows: Vec<Workflow>) -> Self {
+        SearchIndex::new(workflows)
+    }
+}
+
+impl Document {
+    fn passes(&self, tags: &[String], shell: Option<&Shell>) -> bool {
+        let has_tags = tags.iter().all(|tag| {
+            self.workflow
+                .tags
+                .iter()
+                .any(|own| own.to_lowercase() == *tag)
+        });
+        let runs_in = match shell {
+            Some(shell) => self.workflow.shells.is_empty() || self.workflow.shells.contains(shell),
+            None => true,
+        };
+        has_tags && runs_in
+    }
+
+    /// The score of the field a term matches best, or `None` if it matches none.
+    fn score(&self, term: &str) -> Option<u32> {
+        [
+            (&self.name, NAME_WEIGHT),
+            (&self.tags, TAGS_WEIGHT),
+            (&self.description, DESCRIPTION_WEIGHT),
+            (&self.command, COMMAND_WEIGHT),
+        ]
+        .into_iter()
+        .filter_map(|(words, weight)| {
+            words
+                .iter()
+                .filter_map(|word| matches(term, word))
+                .max()
+                .map(|quality| quality * weight)
+        })
+        .max()
+    }
+}
+
+/// How well a query term matches a word, if at all.
+fn matches(term: &str, word: &str) -> Option<u32> {
+    if term == word {
+        return Some(EXACT);
+    }
+    if word.starts_with(term) {
+        return Some(PREFIX);
+    }
+    let typos = allowed_typos(term);
+    if typos == 0 {
+        return None;
+    }
+    let term = term.chars().collect::<Vec<_>>();
+    let word = word.chars().collect::<Vec<_>>();
+    if distance(&term, &word, typos).is_some() {
+        return Some(TYPO);
+    }
+    // A word still being typed: compare with the start of the word, a letter longer or shorter
+    // allowing for a missed or extra letter.
+    (term.len().saturating_sub(1)..=term.len() + 1)
+        .filter(|&len| len < word.len())
+        .any(|len| distance(&term, &word[..len], typos).is_some())
+        .then_some(PREFIX_TYPO)
+}
+
+/// How many typos a term may have and still match: none for short terms, where a typo leaves
+/// little of the word.
+fn allowed_typos(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// The number of letters inserted, deleted, substituted or swapped with their neighbor to get
+/// from `a` to `b`, if it's no more than `max`.
+fn distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before = Vec::<usize>::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        if current.iter().min().is_some_and(|&least| least > max) {
+            return None;
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    Some(previous[b.len()]).filter(|&distance| distance <= max)
+}
+
+/// Splits text into lowercase words of letters and digits.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,419 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use nextshell_workflows_types::{Runbook, Workflow};
+use walkdir::{DirEntry, WalkDir};
+
+/// Separates the name of a layer from a slug, in a qualified slug like `team:deploy`.
+pub const QUALIFIER: char = ':';
+
+/// Where a [`WorkflowStore`] loads specs from.
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// Every `.yaml` and `.yml` file under a directory.
+    Directory(PathBuf),
+    /// A git checkout on local disk, like a clone of a team's private workflow repository: the
+    /// specs under its `specs` directory if it has one, as this repository keeps them, or else
+    /// under the checkout. Hidden directories, like `.git` and `.github`, are skipped, so that CI
+    /// workflows aren't taken for specs.
+    Git(PathBuf),
+    /// Specs built into the binary, as pairs of a path and the spec's contents, like
+    /// [`EMBEDDED_SPECS`](crate::EMBEDDED_SPECS).
+    Embedded(&'static [(&'static str, &'static str)]),
+}
+
+/// A named [`Source`], with a precedence over the other layers of a [`WorkflowStore`].
+///
+/// ```
+/// use nextshell_workflows::{Layer, Source, WorkflowStore};
+///
+/// let store = WorkflowStore::new()
+///     .with_layer(Layer::bundled())
+///     .with_layer(Layer::new("team", Source::Git("/nonexistent/team-workflows".into())));
+/// assert_eq!(store.layers()[1].name, "team");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Layer {
+    /// Names the layer, and namespaces its workflows: the workflow `deploy` of the layer `team`
+    /// is `team:deploy`, whichever layer `deploy` resolves to. A `:` in it is replaced, and it's
+    /// made unique, when the layer is [added](WorkflowStore::add_layer) to a store.
+    pub name: String,
+    pub source: Source,
+    /// Where a slug is in several layers, the layer of the highest precedence has it, and the
+    /// others' workflows are shadowed. Of layers of the same precedence, the first added wins.
+    pub precedence: i32,
+}
+
+/// A workflow loaded by a [`WorkflowStore`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredWorkflow {
+    /// Identifies the workflow: its spec's file name, without the extension.
+    pub slug: String,
+    /// The spec's path, relative to its source.
+    pub path: String,
+    /// The name of the [`Layer`] the workflow was loaded from.
+    pub source: String,
+    /// The spec as a single workflow: the runbook's, as by [`Runbook::to_workflow`].
+    pub workflow: Workflow,
+    /// The spec as a runbook, of its `steps`, or of its `command` as the only step.
+    pub runbook: Runbook,
+}
+
+impl Layer {
+    /// A layer of the default precedence, 0.
+    pub fn new(name: impl Into<String>, source: Source) -> Self {
+        Layer {
+            name: name.into(),
+            source,
+            precedence: 0,
+        }
+    }
+
+    /// A layer of a source, named for it: `bundled` for embedded specs, or else the name of the
+    /// directory or checkout.
+    pub fn of(source: Source) -> Self {
+        let name = match &source {
+            Source::Embedded(_) => "bundled".to_owned(),
+            Source::Directory(dir) | Source::Git(dir) => dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| dir.to_string_lossy().into_owned()),
+        };
+        Layer::new(name, source)
+    }
+
+    /// The specs built into the crate, as the layer `bundled`, of precedence -1: any layer of the
+    /// default precedence shadows their slugs.
+    pub fn bundled() -> Self {
+        Layer::new("bundled", Source::Embedded(crate::EMBEDDED_SPECS)).with_precedence(-1)
+    }
+
+    pub fn with_precedence(mut self, precedence: i32) -> Self {
+        self.precedence = precedence;
+        self
+    }
+}
+
+impl StoredWorkflow {
+    /// The slug, qualified by the name of the workflow's layer, like `team:deploy`, which
+    /// identifies it even where it's shadowed.
+    pub fn qualified_slug(&self) -> String {
+        format!("{}{QUALIFIER}{}", self.source, self.slug)
+    }
+}
+
+/// A spec, or a directory of them, that couldn't be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadError {
+    pub path: PathBuf,
+    /// The line of the error, if known, counting from 1.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Workflows loaded at runtime, from any number of [`Source`]s, layered over each other.
+///
+/// Each source is a [`Layer`], whose name qualifies the slugs of its workflows. A slug in several
+/// layers resolves to the workflow of the layer with the highest precedence, so that a team's own
+/// `deploy` shadows a bundled one, say; the shadowed workflow stays reachable by its
+/// [qualified slug](StoredWorkflow::qualified_slug).
+///
+/// Loading never fails as a whole: a spec that can't be read or parsed is left out, and listed in
+/// [`errors`](WorkflowStore::errors). Directories can be reloaded when their specs change, with
+/// [`reload_if_changed`](WorkflowStore::reload_if_changed).
+///
+/// ```
+/// use nextshell_workflows::{Source, WorkflowStore, EMBEDDED_SPECS};
+///
+/// let store = WorkflowStore::new()
+///     .with_source(Source::Embedded(EMBEDDED_SPECS))
+///     .with_source(Source::Directory("/nonexistent/workflows".into()));
+/// assert!(store.get("clone_all_repos_in_org").is_some());
+/// assert!(store.get("bundled:clone_all_repos_in_org").is_some());
+/// assert_eq!(store.errors().len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct WorkflowStore {
+    layers: Vec<Layer>,
+    workflows: Vec<StoredWorkflow>,
+    errors: Vec<LoadError>,
+    fingerprint: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+impl WorkflowStore {
+    pub fn new() -> Self {
+        WorkflowStore::default()
+    }
+
+    /// Adds a source, loading its specs after those of the sources before it.
+    ///
+    /// The source is a layer of the default precedence, named as by [`Layer::of`].
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.add_source(source);
+        self
+    }
+
+    /// Adds a source, loading its specs after those of the sources before it.
+    ///
+    /// The source is a layer of the default precedence, named as by [`Layer::of`].
+    pub fn add_source(&mut self, source: Source) {
+        self.add_layer(Layer::of(source));
+    }
+
+    /// Adds a layer, loading its specs after those of the layers before it.
+    ///
+    /// A layer named like one added before is renamed, as by
+    /// [`add_layer`](WorkflowStore::add_layer).
+    pub fn with_layer(mut self, layer: Layer) -> Self {
+        self.add_layer(layer);
+        self
+    }
+
+    /// Adds a layer, loading its specs after those of the layers before it.
+    ///
+    /// A layer named like one added before, like a second directory called `specs`, is renamed
+    /// `specs-2`, or `specs-3` and so on, so that the qualified slugs of their workflows don't
+    /// collide. A [`QUALIFIER`] in the name is replaced with a `-`, so that a directory called
+    /// `ops:team` is the layer `ops-team`, and its qualified slugs can be looked up. See
+    /// [`layers`](WorkflowStore::layers) for the names they were given.
+    pub fn add_layer(&mut self, mut layer: Layer) {
+        layer.name = layer.name.replace(QUALIFIER, "-");
+        if self.is_layer(&layer.name) {
+            let name = (2..)
+                .map(|n| format!("{}-{n}", layer.name))
+                .find(|name| !self.is_layer(name))
+                .expect("a free name");
+            layer.name = name;
+        }
+        self.load(&layer);
+        self.layers.push(layer);
+        self.fingerprint = self.fingerprint();
+    }
+
+    /// The layers, in the order added, with the names they were given.
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Every workflow loaded, shadowed or not, in the order of their layers, and by path within
+    /// each.
+    pub fn workflows(&self) -> &[StoredWorkflow] {
+        &self.workflows
+    }
+
+    /// The workflow each slug resolves to, those of the highest precedence first, and in the
+    /// order of [`workflows`](WorkflowStore::workflows) otherwise.
+    pub fn resolved(&self) -> Vec<&StoredWorkflow> {
+        let mut resolved = Vec::new();
+        let mut seen = HashSet::new();
+        for stored in self.by_precedence() {
+            if seen.insert(&*stored.slug) {
+                resolved.push(stored);
+            }
+        }
+        resolved
+    }
+
+    /// Looks a workflow up by its slug, as resolved over the layers, or by its
+    /// [qualified slug](StoredWorkflow::qualified_slug), shadowed or not.
+    pub fn get(&self, slug: &str) -> Option<&StoredWorkflow> {
+        match slug.split_once(QUALIFIER) {
+            Some((layer, slug)) => self
+                .workflows
+                .iter()
+                .find(|stored| stored.source == layer && stored.slug == slug),
+            None => self.by_precedence().find(|stored| stored.slug == slug),
+        }
+    }
+
+    /// The workflows a slug is shadowed in, by the one it resolves to, in the order they would
+    /// have been resolved to.
+    pub fn shadowed(&self, slug: &str) -> Vec<&StoredWorkflow> {
+        self.by_precedence()
+            .filter(|stored| stored.slug == slug)
+            .skip(1)
+            .collect()
+    }
+
+    /// The specs that couldn't be loaded, in the order found.
+    pub fn errors(&self) -> &[LoadError] {
+        &self.errors
+    }
+
+    /// Loads every source again.
+    pub fn reload(&mut self) {
+        self.workflows.clear();
+        self.errors.clear();
+        for layer in &self.layers.clone() {
+            self.load(layer);
+        }
+        self.fingerprint = self.fingerprint();
+    }
+
+    /// Loads every source again if a spec in a directory source was added, removed or modified
+    /// since the last load, and returns whether it did.
+    ///
+    /// This is cheap enough to call every few seconds.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if self.fingerprint() == self.fingerprint {
+            return false;
+        }
+        self.reload();
+        true
+    }
+
+    /// Every workflow, those of the highest precedence first, and in the order loaded otherwise.
+    fn by_precedence(&self) -> impl Iterator<Item = &StoredWorkflow> {
+        let mut workflows = self.workflows.iter().collect::<Vec<_>>();
+        // Stable, so that the first layer added wins a tie.
+        workflows.sort_by_key(|stored| std::cmp::Reverse(self.precedence_of(&stored.source)));
+        workflows.into_iter()
+    }
+
+    fn is_layer(&self, name: &str) -> bool {
+        self.layers.iter().any(|layer| layer.name == name)
+    }
+
+    fn precedence_of(&self, layer: &str) -> i32 {
+        self.layers
+            .iter()
+            .find(|candidate| candidate.name == layer)
+            .map_or(0, |layer| layer.precedence)
+    }
+
+    fn load(&mut self, layer: &Layer) {
+        match &layer.source {
+            Source::Directory(dir) => self.load_dir(layer, dir, false),
+            Source::Git(checkout) => self.load_dir(layer, &specs_of(checkout), true),
+            Source::Embedded(specs) => {
+                for (path, text) in specs.iter() {
+                    self.parse(layer, Path::new(path), Path::new(path), text);
+                }
+            }
+        }
+    }
+
+    /// Loads the specs under a directory, skipping hidden directories if `skip_hidden`.
+    fn load_dir(&mut self, layer: &Layer, dir: &Path, skip_hidden: bool) {
+        for entry in walk(dir, skip_hidden) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.errors.push(LoadError {
+                        path: err.path().unwrap_or(dir).to_owned(),
+                        line: None,
+                        message: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() || !is_spec(entry.path()) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            match fs::read_to_string(entry.path()) {
+                Ok(text) => self.parse(layer, entry.path(), relative, &text),
+                Err(err) => self.errors.push(LoadError {
+                    path: entry.path().to_owned(),
+                    line: None,
+                    message: err.to_string(),
+                }),
+            }
+        }
+    }
+
+    fn parse(&mut self, layer: &Layer, path: &Path, relative: &Path, text: &str) {
+        match serde_yaml::from_str::<Runbook>(text) {
+            Ok(runbook) => self.workflows.push(StoredWorkflow {
+                slug: slug(relative),
+                path: relative.to_string_lossy().replace('\\', "/"),
+                source: layer.name.clone(),
+                workflow: runbook.to_workflow(),
+                runbook,
+            }),
+            Err(err) => self.errors.push(LoadError {
+                path: path.to_owned(),
+                line: err.location().map(|location| location.line()),
+                message: err.to_string(),
+            }),
+        }
+    }
+
+    /// The path, modification time and size of every spec in the directory and git sources.
+    fn fingerprint(&self) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+        let mut fingerprint = Vec::new();
+        for layer in &self.layers {
+            let (dir, skip_hidden) = match &layer.source {
+                Source::Directory(dir) => (dir.clone(), false),
+                Source::Git(checkout) => (specs_of(checkout), true),
+                Source::Embedded(_) => continue,
+            };
+            for entry in walk(&dir, skip_hidden).flatten() {
+                if !is_spec(entry.path()) {
+                    continue;
+                }
+                let metadata = entry.metadata().ok();
+                fingerprint.push((
+                    entry.path().to_owned(),
+                    metadata
+                        .as_ref()
+                        .and_then(|metadata| metadata.modified().ok()),
+                    metadata.map_or(0, |metadata| metadata.len()),
+                ));
+            }
+        }
+        fingerprint
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Walks a directory by file name, skipping hidden directories if `skip_hidden`.
+fn walk(dir: &Path, skip_hidden: bool) -> impl Iterator<Item = walkdir::Result<DirEntry>> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(move |entry| {
+            !(skip_hidden
+                && entry.depth() > 0
+                && entry.file_type().is_dir()
+                && entry.file_name().to_string_lossy().starts_with('.'))
+        })
+}
+
+/// Where the specs of a git checkout are: its `specs` directory, if it has one.
+fn specs_of(checkout: &Path) -> PathBuf {
+    let specs = checkout.join("specs");
+    if specs.is_dir() {
+        specs
+    } else {
+        checkout.to_owned()
+    }
+}
+
+fn is_spec(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension == "yaml" || extension == "yml")
+}
+
+/// The file name up to its first `.`, as the TypeScript bindings name workflows.
+fn slug(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    name.split('.').next().unwrap_or_default().to_owned()
+}
@@ -0,0 +1,238 @@
+//! A local log of the workflows run, and what it says about which are used.
+//!
+//! Each run is a [`UsageRecord`], appended to a [`UsageLog`]: a file of JSON lines, one record a
+//! line, that's never rewritten. The values of secret arguments are never written. The records can
+//! be summed up by workflow with [`stats`], and scored by how often and how lately each workflow
+//! was run with a [`Frecency`], to rank the workflows used most first.
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//! use nextshell_workflows::usage::{Frecency, UsageLog, UsageRecord};
+//! use nextshell_workflows::{Shell, Workflow};
+//!
+//! let log = UsageLog::new("/var/lib/nextshell/usage.jsonl");
+//! let workflow = Workflow::new("List files", "ls");
+//! log.append(&UsageRecord::new("list_files", &workflow, &HashMap::new(), Shell::Bash, Some(0)))?;
+//!
+//! let records = log.records()?;
+//! let frecency = Frecency::new(&records);
+//! assert!(frecency.boost("list_files", UsageRecord::now()) > 0);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nextshell_workflows_types::{Shell, Workflow};
+use serde::{Deserialize, Serialize};
+
+use crate::export::shell_name;
+
+/// How long it takes a run to count half as much towards a workflow's [`Frecency`]: a week.
+pub const HALF_LIFE: u64 = 7 * 24 * 60 * 60;
+
+/// The most a workflow's [`Frecency`] adds to its search score. A word of a query matching a word
+/// of a workflow's name exactly scores 32, so usage breaks ties and nudges, but doesn't outrank a
+/// better match.
+pub const MAX_BOOST: u32 = 12;
+
+/// A run of a workflow.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UsageRecord {
+    pub slug: String,
+    /// The values the workflow was run with, by argument, but for secret arguments'.
+    pub arguments: BTreeMap<String, String>,
+    pub shell: Shell,
+    /// The exit code of the command, if it exited rather than being killed by a signal.
+    pub exit_code: Option<i32>,
+    /// When the workflow was run, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// How much each workflow was run.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct UsageStats {
+    pub slug: String,
+    pub runs: u64,
+    /// The runs that didn't exit with 0.
+    pub failures: u64,
+    /// When the workflow was last run, in seconds since the Unix epoch.
+    pub last_run: u64,
+    /// The runs in each shell.
+    pub shells: BTreeMap<String, u64>,
+}
+
+/// A file of [`UsageRecord`]s, one JSON object a line, appended to and never rewritten.
+#[derive(Clone, Debug)]
+pub struct UsageLog {
+    path: PathBuf,
+}
+
+/// Scores workflows by how often and how lately they were run.
+///
+/// Each run counts 1 when it happens, and half as much every [`HALF_LIFE`] after, so that a
+/// workflow run daily this week outscores one run as often months ago.
+#[derive(Clone, Debug, Default)]
+pub struct Frecency {
+    /// Each workflow's score, and the time it's the score at.
+    scores: HashMap<String, (f64, u64)>,
+}
+
+impl UsageRecord {
+    /// A record of a run of a workflow, now, with the values of its arguments that aren't secret.
+    ///
+    /// Values for anything that isn't an argument of the workflow are left out too.
+    pub fn new(
+        slug: impl Into<String>,
+        workflow: &Workflow,
+        values: &HashMap<String, String>,
+        shell: Shell,
+        exit_code: Option<i32>,
+    ) -> Self {
+        let arguments = workflow
+            .arguments
+            .iter()
+            .filter(|argument| !argument.is_secret())
+            .filter_map(|argument| {
+                let value = values.get(&argument.name)?;
+                Some((argument.name.clone(), value.clone()))
+            })
+            .collect();
+        UsageRecord {
+            slug: slug.into(),
+            arguments,
+            shell,
+            exit_code,
+            timestamp: UsageRecord::now(),
+        }
+    }
+
+    /// The time now, in seconds since the Unix epoch, as records keep it.
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs())
+    }
+}
+
+impl UsageLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        UsageLog { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a record, creating the log if there's none yet.
+    ///
+    /// The record is written as a single line at once, so that records appended by several
+    /// processes at the same time don't interleave.
+    pub fn append(&self, record: &UsageRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+
+    /// Every record, oldest first, or none if there's no log yet.
+    ///
+    /// Lines that aren't records, like one cut short by a crash, are skipped.
+    pub fn records(&self) -> io::Result<Vec<UsageRecord>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        Ok(text
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Sums up the records since a time, in seconds since the Unix epoch, by workflow: the most run
+/// first, and by slug otherwise.
+pub fn stats<'a>(
+    records: impl IntoIterator<Item = &'a UsageRecord>,
+    since: u64,
+) -> Vec<UsageStats> {
+    let mut by_slug = BTreeMap::<&str, UsageStats>::new();
+    for record in records {
+        if record.timestamp < since {
+            continue;
+        }
+        let stats = by_slug.entry(&record.slug).or_insert_with(|| UsageStats {
+            slug: record.slug.clone(),
+            runs: 0,
+            failures: 0,
+            last_run: 0,
+            shells: BTreeMap::new(),
+        });
+        stats.runs += 1;
+        if record.exit_code != Some(0) {
+            stats.failures += 1;
+        }
+        stats.last_run = stats.last_run.max(record.timestamp);
+        *stats
+            .shells
+            .entry(shell_name(&record.shell).to_owned())
+            .or_default() += 1;
+    }
+    let mut stats = by_slug.into_values().collect::<Vec<_>>();
+    // Stable, so that ties stay in order of slug.
+    stats.sort_by(|a, b| b.runs.cmp(&a.runs));
+    stats
+}
+
+impl Frecency {
+    /// Scores the workflows of some records.
+    pub fn new<'a>(records: impl IntoIterator<Item = &'a UsageRecord>) -> Self {
+        let mut frecency = Frecency::default();
+        for record in records {
+            frecency.add(record);
+        }
+        frecency
+    }
+
+    /// Counts another run.
+    pub fn add(&mut self, record: &UsageRecord) {
+        let (score, at) = self
+            .scores
+            .entry(record.slug.clone())
+            .or_insert((0.0, record.timestamp));
+        if record.timestamp >= *at {
+            *score = decay(*score, record.timestamp - *at) + 1.0;
+            *at = record.timestamp;
+        } else {
+            *score += decay(1.0, *at - record.timestamp);
+        }
+    }
+
+    /// A workflow's score at a time, in seconds since the Unix epoch: 0 if it was never run.
+    pub fn score(&self, slug: &str, now: u64) -> f64 {
+        self.scores
+            .get(slug)
+            .map_or(0.0, |&(score, at)| decay(score, now.saturating_sub(at)))
+    }
+
+    /// What a workflow's score at a time adds to its search score, from 0 to [`MAX_BOOST`].
+    ///
+    /// It grows with the logarithm of the score, so that a workflow run once lately gets a few
+    /// points, but it takes many runs to get many more.
+    pub fn boost(&self, slug: &str, now: u64) -> u32 {
+        let boost = (self.score(slug, now).ln_1p() * 4.0).round();
+        (boost as u32).min(MAX_BOOST)
+    }
+}
+
+/// A score, `age` seconds on.
+fn decay(score: f64, age: u64) -> f64 {
+    score * 0.5f64.powf(age as f64 / HALF_LIFE as f64)
+}
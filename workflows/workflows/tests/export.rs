@@ -0,0 +1,239 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use nextshell_workflows::export::{export, function, Exported, File};
+use nextshell_workflows::{Argument, OnFailure, Runbook, Shell, Step, Workflow, WorkflowStore};
+
+fn push() -> Workflow {
+    Workflow::new(
+        "Push a branch",
+        "git push {{force}} {{remote}} '{{branch}}:refs/heads/x'",
+    )
+    .with_description("Pushes a branch.\nForce it if you must.".to_owned())
+    .with_arguments(vec![
+        Argument::new("branch").with_description("The branch to push"),
+        Argument::new("remote").with_default_value("my origin"),
+        Argument::new("force").with_flag("--force-with-lease"),
+    ])
+}
+
+/// Runs a bash script, returning what it prints and its exit status.
+fn bash(script: &str) -> (String, Option<i32>) {
+    let mut child = Command::new("bash")
+        .arg("-s")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("bash runs");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn bash_function() {
+    let source = function(&Shell::Bash, "push", &push()).unwrap();
+    assert_eq!(
+        source,
+        r#"# Push a branch
+#
+# Pushes a branch.
+# Force it if you must.
+#
+# Usage: push <branch> [remote] [force]
+#   branch  The branch to push
+#   remote  (default: my origin)
+#   force   (default: false)
+push() {
+    local arg_branch="$1"
+    [ -n "$arg_branch" ] || { echo 'usage: push <branch> [remote] [force]' >&2; return 2; }
+    local arg_remote="$2"
+    [ -n "$arg_remote" ] || arg_remote='my origin'
+    local arg_force="$3"
+    [ -n "$arg_force" ] || arg_force=false
+    if [ "$arg_force" = true ]; then arg_force=--force-with-lease; else arg_force=; fi
+    git push ${arg_force:+"$arg_force"} "$arg_remote" ''"$arg_branch"':refs/heads/x'
+}
+"#
+    );
+
+    // Shadow git, to see what it's given.
+    let script = format!("git() {{ printf '<%s>' \"$@\"; echo; }}\n{source}");
+    let (out, status) = bash(&format!("{script}push 'it'\\''s'\n"));
+    assert_eq!(status, Some(0));
+    assert_eq!(out, "<push><my origin><it's:refs/heads/x>\n");
+    let (out, _) = bash(&format!("{script}push main '' true\n"));
+    assert_eq!(
+        out,
+        "<push><--force-with-lease><my origin><main:refs/heads/x>\n"
+    );
+    let (out, status) = bash(&format!("{script}push\n"));
+    assert_eq!((&*out, status), ("", Some(2)));
+}
+
+#[test]
+fn multi_line_names() {
+    let workflow = Workflow::new("Foo\necho pwned", "echo foo");
+    let source = function(&Shell::Bash, "foo", &workflow).unwrap();
+    assert!(source.starts_with("# Foo\n# echo pwned\n#\n# Usage: foo\nfoo() {\n"));
+
+    let (output, status) = bash(&format!("{source}echo sourced\n"));
+    assert_eq!((&*output, status), ("sourced\n", Some(0)));
+}
+
+#[test]
+fn fish_function() {
+    let exported = export(&Shell::Fish, [("push", &Runbook::from(push()))]);
+    assert_eq!(exported.files.len(), 1);
+    assert_eq!(exported.files[0].path, "functions/push.fish");
+    let source = &exported.files[0].contents;
+    assert!(source.starts_with("# Push a branch\n"));
+    assert!(source
+        .contains("function push --description 'Push a branch'\n    set -l arg_branch $argv[1]\n"));
+    assert!(source.contains(
+        "    test -n \"$arg_branch\"; or begin; echo 'usage: push <branch> [remote] [force]' >&2; return 2; end\n"
+    ));
+    assert!(source.contains("    test -n \"$arg_remote\"; or set arg_remote 'my origin'\n"));
+    assert!(source.contains(
+        "    if test \"$arg_force\" = true; set arg_force --force-with-lease; else; set arg_force; end\n"
+    ));
+    assert!(source.ends_with(
+        "    git push $arg_force \"$arg_remote\" ''\"$arg_branch\"':refs/heads/x'\nend\n"
+    ));
+}
+
+#[test]
+fn skipped() {
+    let mut bash_only = Workflow::new("Bash only", "echo ${{{name}}[@]}")
+        .with_arguments(vec![Argument::new("name")]);
+    let parameter = Runbook::from(bash_only.clone());
+    bash_only.shells = vec![Shell::Bash];
+    let bash_only = Runbook::from(bash_only);
+    let unused =
+        Runbook::from(Workflow::new("Unused", "ls").with_arguments(vec![Argument::new("dir")]));
+    let multiline = Runbook::from(Workflow::new("Two lines", "cd /\nls"));
+    // A function would run both steps, even if the first failed.
+    let steps = Runbook::new("Steps", vec![Step::new("false"), Step::new("rm -rf build")])
+        .with_on_failure(OnFailure::Stop);
+    let confirm = Runbook::new(
+        "Confirm",
+        vec![Step::new("rm -rf build").with_confirm(true)],
+    );
+
+    let exported = export(
+        &Shell::Zsh,
+        [
+            ("bash only", &bash_only),
+            ("parameter", &parameter),
+            ("unused", &unused),
+            ("two-lines", &multiline),
+            ("steps", &steps),
+            ("confirm", &confirm),
+        ],
+    );
+    let skipped = exported
+        .skipped
+        .iter()
+        .map(|skipped| format!("{}: {}", skipped.name, skipped.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        [
+            "bash_only: it doesn't run in zsh",
+            "parameter: argument `name` can't be \"$arg_name\" where it is in the command",
+            "unused: argument `dir` isn't used in the command",
+            "steps: it has 2 steps, to run one at a time",
+            "confirm: it's to be confirmed before it's run",
+        ]
+    );
+
+    let file = &exported.files[0];
+    assert_eq!(file.path, "workflows.zsh");
+    assert!(file
+        .contents
+        .contains("# Not exported:\n#   bash_only: it doesn't run in zsh\n"));
+    assert!(file.contents.ends_with("two-lines() {\ncd /\nls\n}\n"));
+}
+
+#[test]
+fn bundled_workflows_are_valid_bash() {
+    let store = WorkflowStore::new().with_source(nextshell_workflows::Source::Embedded(
+        nextshell_workflows::EMBEDDED_SPECS,
+    ));
+    let exported = export(
+        &Shell::Bash,
+        store
+            .workflows()
+            .iter()
+            .map(|stored| (&*stored.slug, &stored.runbook)),
+    );
+    for skipped in &exported.skipped {
+        println!("{}: {}", skipped.name, skipped.reason);
+    }
+    assert!(exported.skipped.len() < store.workflows().len() / 10);
+
+    let (_, status) = bash(&format!(
+        "set -e\n{}\ndeclare -F | wc -l\n",
+        exported.files[0].contents
+    ));
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn tar() {
+    let one = Runbook::from(Workflow::new("One", "echo 1"));
+    let two = Runbook::from(Workflow::new("Two", "echo 2"));
+    let exported = export(&Shell::Fish, [("one", &one), ("two", &two)]);
+    let tar = exported.to_tar().unwrap();
+    assert_eq!(tar.len() % 512, 0);
+    assert_eq!(list_tar(&tar), ["functions/one.fish", "functions/two.fish"]);
+}
+
+#[test]
+fn tar_long_paths() {
+    // Over 100 bytes, but split at a `/` into a prefix and a name.
+    let dir = "d".repeat(60);
+    let path = format!("{dir}/{dir}/{}.fish", "f".repeat(60));
+    let exported = Exported {
+        files: vec![File {
+            path: path.clone(),
+            contents: "echo\n".to_owned(),
+        }],
+        skipped: Vec::new(),
+    };
+    assert_eq!(list_tar(&exported.to_tar().unwrap()), [path]);
+
+    // A file name that's over 100 bytes alone doesn't fit, rather than being cut short.
+    let slug = "a_long_workflow_slug_".repeat(5);
+    let runbook = Runbook::from(Workflow::new("Long", "echo long"));
+    let exported = export(&Shell::Fish, [(&*slug, &runbook)]);
+    let err = exported.to_tar().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains(&slug));
+}
+
+fn list_tar(tar: &[u8]) -> Vec<String> {
+    let mut child = Command::new("tar")
+        .args(["-tvf", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("tar runs");
+    child.stdin.take().unwrap().write_all(tar).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let listing = String::from_utf8(output.stdout).unwrap();
+    listing
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap().to_owned())
+        .collect()
+}
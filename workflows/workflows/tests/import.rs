@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::PathBuf;
+
+use nextshell_workflows::import::{self, Format, Imported};
+use nextshell_workflows::lint::lint;
+use nextshell_workflows::{Source, WorkflowStore};
+
+const TLDR: &str = "\
+# tar
+
+> Archiving utility.
+> Often combined with a compression method, such as gzip or bzip2.
+> More information: <https://www.gnu.org/software/tar>.
+
+- [c]reate an archive and write it to a [f]ile:
+
+`tar cf {{path/to/target.tar}} {{path/to/file1 path/to/file2 ...}}`
+
+- E[x]tract a (compressed) archive [f]ile into the current directory:
+
+`tar {{[-x|--extract]}} -f {{path/to/source.tar[.gz|.bz2|.xz]}}`
+
+- Copy a file to itself:
+
+`cp {{path/to/file}} {{path/to/file}}.bak && ls {{file}}`
+
+- List the contents of a tar file:
+";
+
+const NAVI: &str = "\
+% git, code
+
+# Change branch
+git checkout <branch>
+
+; a comment
+# Delete a branch, after checking
+git branch -d <branch> \\
+  || git branch -D <branch> < /dev/null
+
+git status
+
+$ branch: git branch | awk '{print $NF}' --- --column 1
+
+% docker
+
+# Remove a container
+docker rm <container-id>
+
+# Pull an image
+
+@ git
+";
+
+const CHEAT: &str = "\
+---
+tags: [ compression, archive ]
+syntax: bash
+---
+# Basic usage
+
+# To extract an uncompressed archive:
+tar -xvf /path/to/foo.tar
+
+# To create a gzip archive
+# of a directory:
+tar -czvf <archive>.tar.gz <dir>
+ls <dir>
+
+tar -tvf foo.tar
+";
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "nextshell-workflows-import-{name}-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes the imported workflows as specs, and checks they load and lint cleanly.
+fn check_specs(name: &str, imported: &Imported) {
+    let dir = scratch(name);
+    for workflow in &imported.workflows {
+        let path = dir.join(format!("{}.yaml", import::file_stem(name, workflow)));
+        fs::write(path, import::to_yaml(workflow)).unwrap();
+    }
+    let store = WorkflowStore::new().with_source(Source::Directory(dir.clone()));
+    assert!(store.errors().is_empty(), "{:?}", store.errors());
+    let mut loaded = store
+        .workflows()
+        .iter()
+        .map(|stored| stored.workflow.clone())
+        .collect::<Vec<_>>();
+    let mut expected = imported.workflows.clone();
+    loaded.sort_by(|a, b| a.name.cmp(&b.name));
+    expected.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(loaded, expected);
+    let diagnostics = lint(&dir);
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn tldr() {
+    let imported = import::import(Format::Tldr, "tar", TLDR);
+    let names = imported
+        .workflows
+        .iter()
+        .map(|workflow| &*workflow.name)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "Create an archive and write it to a file",
+            "Extract a (compressed) archive file into the current directory",
+            "Copy a file to itself",
+        ]
+    );
+
+    let create = &imported.workflows[0];
+    assert_eq!(create.command, "tar cf {{target_tar}} {{file1_file2}}");
+    assert_eq!(create.arguments[0].name, "target_tar");
+    assert_eq!(
+        create.arguments[0].description.as_deref(),
+        Some("path/to/target.tar")
+    );
+    assert_eq!(create.tags, ["tar"]);
+    assert_eq!(
+        create.description.as_deref(),
+        Some("Archiving utility. Often combined with a compression method, such as gzip or bzip2.")
+    );
+    assert_eq!(
+        create.source_url.as_deref(),
+        Some("https://www.gnu.org/software/tar")
+    );
+
+    assert_eq!(
+        imported.workflows[1].command,
+        "tar --extract -f {{source_tar_gz_bz2_xz}}"
+    );
+    // Placeholders for the same path are the same argument.
+    assert_eq!(
+        imported.workflows[2].command,
+        "cp {{file}} {{file}}.bak && ls {{file}}"
+    );
+
+    assert_eq!(imported.skipped.len(), 1);
+    assert_eq!(imported.skipped[0].line, 19);
+    assert_eq!(
+        imported.skipped[0].to_string(),
+        "line 19: List the contents of a tar file:: the example has no command"
+    );
+
+    check_specs("tar", &imported);
+}
+
+#[test]
+fn navi() {
+    let imported = import::import(Format::Navi, "git", NAVI);
+    assert_eq!(imported.workflows.len(), 3);
+
+    let delete = &imported.workflows[1];
+    assert_eq!(delete.name, "Delete a branch, after checking");
+    assert_eq!(
+        delete.command,
+        "git branch -d {{branch}} \\\n  || git branch -D {{branch}} < /dev/null"
+    );
+    assert_eq!(delete.arguments.len(), 1);
+    assert_eq!(
+        delete.arguments[0].suggestions_command.as_deref(),
+        Some("git branch | awk '{print $NF}'")
+    );
+    assert_eq!(delete.tags, ["git", "code"]);
+
+    let remove = &imported.workflows[2];
+    assert_eq!(remove.command, "docker rm {{container-id}}");
+    assert_eq!(remove.tags, ["docker"]);
+    assert_eq!(remove.arguments[0].suggestions_command, None);
+
+    let skipped = imported
+        .skipped
+        .iter()
+        .map(|skipped| (skipped.line, &*skipped.reason))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        [
+            (11, "the command has no description"),
+            (20, "the description has no command"),
+            (22, "using the variables of another cheat isn't supported"),
+        ]
+    );
+
+    check_specs("git", &imported);
+}
+
+#[test]
+fn cheat() {
+    let imported = import::import(Format::Cheat, "tar", CHEAT);
+    assert_eq!(imported.workflows.len(), 2);
+
+    let extract = &imported.workflows[0];
+    assert_eq!(extract.name, "Extract an uncompressed archive");
+    assert_eq!(extract.command, "tar -xvf /path/to/foo.tar");
+    assert!(extract.arguments.is_empty());
+    assert_eq!(extract.description, None);
+    assert_eq!(extract.tags, ["tar", "compression", "archive"]);
+
+    let create = &imported.workflows[1];
+    assert_eq!(create.name, "Create a gzip archive");
+    assert_eq!(
+        create.description.as_deref(),
+        Some("To create a gzip archive of a directory:")
+    );
+    assert_eq!(
+        create.command,
+        "tar -czvf {{archive}}.tar.gz {{dir}}\nls {{dir}}"
+    );
+
+    assert_eq!(imported.skipped.len(), 1);
+    assert_eq!(imported.skipped[0].line, 15);
+    assert_eq!(imported.skipped[0].entry, "tar -tvf foo.tar");
+
+    check_specs("tar", &imported);
+}
+
+#[test]
+fn formats() {
+    assert_eq!("navi".parse::<Format>().unwrap(), Format::Navi);
+    let err = "man".parse::<Format>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown format `man`, expected one of: tldr, navi, cheat"
+    );
+}
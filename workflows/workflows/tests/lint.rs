@@ -0,0 +1,198 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nextshell_workflows::lint::{lint, Severity};
+
+/// A scratch directory of specs, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "nextshell-workflows-lint-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+
+    fn write(&self, path: &str, contents: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn bundled_specs_are_clean() {
+    let specs = Path::new(env!("CARGO_MANIFEST_DIR")).join("../specs");
+    let diagnostics = lint(&specs);
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+}
+
+#[test]
+fn placeholders_and_arguments() {
+    let dir = ScratchDir::new("arguments");
+    dir.write(
+        "copy.yaml",
+        "name: Copy\ncommand: cp {{from}} {{to}}\narguments:\n  - name: from\n  - name: unused\n",
+    );
+
+    let diagnostics = lint(&dir.0);
+    let rules = diagnostics
+        .iter()
+        .map(|d| (d.rule, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            ("undeclared-placeholder", Some(2)),
+            ("unused-argument", Some(5))
+        ]
+    );
+    assert!(diagnostics[0].message.contains("to"));
+    assert_eq!(
+        diagnostics[1].to_string(),
+        format!(
+            "{}:5: error: {} [unused-argument]",
+            dir.0.join("copy.yaml").display(),
+            diagnostics[1].message
+        )
+    );
+}
+
+#[test]
+fn dollar_placeholders() {
+    let dir = ScratchDir::new("dollar");
+    dir.write(
+        "tail.yaml",
+        "name: Tail\n\
+         command: tail -n +2 \"${{file}}\" ${{{list}}[@]}\n\
+         arguments:\n  - name: file\n  - name: list\n\
+         shells: [bash]\n",
+    );
+
+    let diagnostics = lint(&dir.0);
+    let rules = diagnostics
+        .iter()
+        .map(|d| (d.rule, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(rules, [("dollar-placeholder", Some(2))]);
+    assert!(diagnostics[0].message.contains("`${{file}}`"));
+}
+
+#[test]
+fn duplicates_and_urls() {
+    let dir = ScratchDir::new("duplicates");
+    dir.write(
+        "git/status.yaml",
+        "name: Status\ncommand: git status\nauthor_url: https://a.example, https://b.example\n",
+    );
+    dir.write(
+        "hg/status.yaml",
+        "name: Status\ncommand: hg status\nsource_url: \"\"\nauthor_url: example.com\n",
+    );
+
+    let diagnostics = lint(&dir.0);
+    let rules = diagnostics
+        .iter()
+        .map(|d| (d.rule, d.severity, d.line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            ("duplicate-slug", Severity::Error, None),
+            ("duplicate-name", Severity::Warning, Some(1)),
+            ("invalid-url", Severity::Error, Some(3)),
+            ("invalid-url", Severity::Error, Some(4)),
+        ]
+    );
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.path == dir.0.join("hg/status.yaml")));
+}
+
+#[test]
+fn bash_syntax_in_fish() {
+    let dir = ScratchDir::new("fish");
+    dir.write(
+        "diff.yaml",
+        "name: Diff\ncommand: diff <(ls {{a}}) <(ls {{b}})\narguments:\n  - name: a\n  - name: b\nshells: [Bash, Fish]\n",
+    );
+    dir.write(
+        "unknown.yaml",
+        "name: Unknown\ncommand: echo $((1 + 2))\nshells: []\n",
+    );
+    dir.write(
+        "bash.yaml",
+        "name: Bash\ncommand: cat <<EOF\nshells: [Bash]\n",
+    );
+    dir.write(
+        "quoted.yaml",
+        "name: Quoted\ncommand: awk '{print ${1}}' <(true\nshells: [Fish]\n",
+    );
+
+    let diagnostics = lint(&dir.0);
+    let rules = diagnostics
+        .iter()
+        .map(|d| (d.path.file_name().unwrap().to_str().unwrap(), d.severity))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            ("diff.yaml", Severity::Error),
+            ("quoted.yaml", Severity::Error),
+            ("unknown.yaml", Severity::Warning),
+        ]
+    );
+    assert!(diagnostics[0].message.contains("process substitution"));
+    assert!(diagnostics[2].message.contains("arithmetic expansion"));
+}
+
+#[test]
+fn runbooks() {
+    let dir = ScratchDir::new("runbooks");
+    dir.write(
+        "restart.yaml",
+        "name: Restart\narguments:\n  - name: service\nsteps:\n  - command: systemctl is-active {{service}}\n  - command: systemctl restart {{unit}}\n",
+    );
+    dir.write(
+        "clean.yaml",
+        "name: Clean\nsteps:\n  - command: git status\n  - command: git clean -n\n",
+    );
+
+    let diagnostics = lint(&dir.0);
+    let rules = diagnostics
+        .iter()
+        .map(|d| {
+            (
+                d.path.file_name().unwrap().to_str().unwrap(),
+                d.rule,
+                d.line,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(rules, [("restart.yaml", "undeclared-placeholder", Some(4))]);
+    assert!(diagnostics[0].message.contains("unit"));
+}
+
+#[test]
+fn json_output() {
+    let dir = ScratchDir::new("json");
+    dir.write("broken.yaml", "name: Broken\ncommand: [\n");
+
+    let json = serde_json::to_value(lint(&dir.0)).unwrap();
+    assert_eq!(json[0]["rule"], "load");
+    assert_eq!(json[0]["severity"], "error");
+    assert_eq!(json[0]["line"], 2);
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::Path;
+
+use nextshell_workflows::schema::{self, generated};
+use nextshell_workflows::{Argument, OnFailure, Shell, Step, Workflow, EMBEDDED_SPECS};
+use serde_json::Value;
+
+#[test]
+fn generated_files_are_up_to_date() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    for file in generated() {
+        let checked_in = fs::read_to_string(root.join(file.path)).unwrap_or_default();
+        assert!(
+            checked_in == file.contents,
+            "{} is out of date; run `cargo run --bin generate-types`",
+            file.path
+        );
+    }
+}
+
+#[test]
+fn typescript_enums_have_members() {
+    // The frontend writes `Shell.Bash`, as it did when these were `const enum`s.
+    let ts = schema::typescript();
+    assert!(ts.contains(
+        "export const Shell = {\n  Fish: \"fish\",\n  Bash: \"bash\",\n  Zsh: \"zsh\",\n} as const;\n"
+    ));
+    assert!(ts.contains("export const ArgumentType = {\n  String: \"string\",\n"));
+}
+
+#[test]
+fn schema_has_every_field() {
+    let schema: Value = serde_json::from_str(&schema::json_schema()).unwrap();
+    let workflow =
+        Workflow::new("Push", "git push {{force}}").with_arguments(vec![Argument::new("force")
+            .with_flag("--force")
+            .with_description("Whether to force it")
+            .with_default_value("false")
+            .with_pattern("true|false")
+            .with_secret(true)
+            .with_suggestions_command("echo true")]);
+    let mut workflow = serde_json::to_value(workflow).unwrap();
+    workflow["shells"] = serde_json::to_value([Shell::Bash]).unwrap();
+
+    let properties = |schema: &Value| {
+        schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let keys = |value: &Value| {
+        value
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    // A spec is a runbook, of the workflow's fields, and of steps.
+    let mut workflow_keys = keys(&workflow);
+    workflow_keys.extend(["steps".to_owned(), "on_failure".to_owned()]);
+    workflow_keys.sort();
+    let mut spec_properties = properties(&schema);
+    spec_properties.sort();
+    assert_eq!(spec_properties, workflow_keys);
+    let mut argument_keys = keys(&workflow["arguments"][0]);
+    argument_keys.push("allowed_values".to_owned());
+    argument_keys.sort();
+    let mut argument_properties = properties(&schema["definitions"]["Argument"]);
+    argument_properties.sort();
+    assert_eq!(argument_properties, argument_keys);
+
+    let step = Step::new("ls")
+        .with_description("List")
+        .with_confirm(true)
+        .with_on_failure(OnFailure::Continue);
+    let step = serde_json::to_value(step).unwrap();
+    assert_eq!(properties(&schema["definitions"]["Step"]), keys(&step));
+}
+
+#[test]
+fn schema_describes_bundled_specs() {
+    let schema: Value = serde_json::from_str(&schema::json_schema()).unwrap();
+    let known = |schema: &Value, key: &str| schema["properties"].get(key).is_some();
+    for (path, contents) in EMBEDDED_SPECS {
+        let spec: Value = serde_yaml::from_str(contents).unwrap();
+        for key in spec.as_object().unwrap().keys() {
+            assert!(known(&schema, key), "{path}: `{key}` isn't in the schema");
+        }
+        for argument in spec["arguments"].as_array().into_iter().flatten() {
+            for key in argument.as_object().unwrap().keys() {
+                assert!(
+                    known(&schema["definitions"]["Argument"], key),
+                    "{path}: argument `{key}` isn't in the schema"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use nextshell_workflows::search::{Query, SearchIndex};
+use nextshell_workflows::{Shell, Workflow};
+
+fn workflow(name: &str, command: &str, tags: &[&str], description: &str) -> Workflow {
+    let mut workflow = Workflow::new(name, command).with_description(description.to_owned());
+    workflow.tags = tags.iter().map(|tag| tag.to_string()).collect();
+    workflow
+}
+
+fn index() -> SearchIndex {
+    let mut fish = workflow(
+        "Set an environment variable",
+        "set -x NAME value",
+        &["shell"],
+        "Exports a variable in fish",
+    );
+    fish.shells = vec![Shell::Fish];
+    SearchIndex::new(vec![
+        workflow(
+            "Rebase onto main",
+            "git rebase main",
+            &["git"],
+            "Replays your commits on top of main",
+        ),
+        workflow(
+            "Undo the last commit",
+            "git reset --soft HEAD~1",
+            &["git"],
+            "Keeps the changes, staged, and forgets the commit, to rebase by hand",
+        ),
+        workflow(
+            "List listening ports",
+            "lsof -i -P | grep LISTEN",
+            &["network", "macOS"],
+            "Shows the processes accepting connections",
+        ),
+        fish,
+    ])
+}
+
+fn names(hits: &[nextshell_workflows::search::Hit<'_>]) -> Vec<String> {
+    hits.iter().map(|hit| hit.workflow.name.clone()).collect()
+}
+
+#[test]
+fn name_outranks_description() {
+    let index = index();
+    let hits = index.search(&Query::new("rebase"));
+    assert_eq!(names(&hits), ["Rebase onto main", "Undo the last commit"]);
+    assert!(hits[0].score > hits[1].score);
+    assert_eq!(hits[0].position, 0);
+}
+
+#[test]
+fn every_term_must_match() {
+    let index = index();
+    assert_eq!(
+        names(&index.search(&Query::new("commit undo"))),
+        ["Undo the last commit"]
+    );
+    assert!(index.search(&Query::new("undo ports")).is_empty());
+}
+
+#[test]
+fn typos_and_prefixes() {
+    let index = index();
+    // As it's typed.
+    assert_eq!(
+        names(&index.search(&Query::new("listen"))),
+        ["List listening ports"]
+    );
+    assert_eq!(
+        names(&index.search(&Query::new("rebsae"))),
+        ["Rebase onto main", "Undo the last commit"]
+    );
+    assert_eq!(
+        names(&index.search(&Query::new("enviorn"))),
+        ["Set an environment variable"]
+    );
+    // Too short for typos.
+    assert!(index.search(&Query::new("gti")).is_empty());
+
+    let exact = index.search(&Query::new("ports"))[0].score;
+    let prefix = index.search(&Query::new("port"))[0].score;
+    let typo = index.search(&Query::new("prots"))[0].score;
+    assert!(exact > prefix && prefix > typo);
+}
+
+#[test]
+fn filters() {
+    let index = index();
+    assert_eq!(
+        names(&index.search(&Query::new("").with_tag("GIT"))),
+        ["Rebase onto main", "Undo the last commit"]
+    );
+    assert_eq!(
+        names(&index.search(&Query::new("commit").with_tag("git").with_tag("network"))),
+        Vec::<String>::new()
+    );
+
+    // Workflows that don't list their shells run in any.
+    assert_eq!(
+        index.search(&Query::new("").with_shell(Shell::Fish)).len(),
+        4
+    );
+    assert_eq!(
+        names(&index.search(&Query::new("variable").with_shell(Shell::Bash))),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn bundled_workflows() {
+    let index = SearchIndex::new(nextshell_workflows::workflows());
+    assert_eq!(index.len(), nextshell_workflows::EMBEDDED_SPECS.len());
+
+    let hits = index.search(&Query::new("chnage remote url"));
+    assert_eq!(hits[0].workflow.name, "Change URL of remote git repository");
+}
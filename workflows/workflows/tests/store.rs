@@ -0,0 +1,321 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nextshell_workflows::{
+    Argument, ArgumentType, Layer, OnFailure, Source, Step, Workflow, WorkflowStore, EMBEDDED_SPECS,
+};
+
+fn specs_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../specs")
+}
+
+/// A scratch directory of specs, removed when dropped.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("nextshell-workflows-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+
+    fn write(&self, path: &str, contents: &str) {
+        let path = self.0.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn bundled_specs_load() {
+    let store = WorkflowStore::new().with_source(Source::Embedded(EMBEDDED_SPECS));
+    for error in store.errors() {
+        println!("{error}");
+    }
+    assert!(store.errors().is_empty());
+    assert_eq!(store.workflows().len(), EMBEDDED_SPECS.len());
+    assert_eq!(nextshell_workflows::workflows().len(), EMBEDDED_SPECS.len());
+
+    let stored = store.get("change_url_of_remote_git_repository").unwrap();
+    assert_eq!(stored.path, "git/change_url_of_remote_git_repository.yaml");
+    assert_eq!(stored.workflow.name, "Change URL of remote git repository");
+
+    assert_eq!(stored.source, "bundled");
+
+    // The specs on disk are the ones built in.
+    let from_disk = WorkflowStore::new().with_source(Source::Directory(specs_dir()));
+    assert!(from_disk.errors().is_empty());
+    assert_eq!(from_disk.workflows().len(), store.workflows().len());
+    for (on_disk, built_in) in from_disk.workflows().iter().zip(store.workflows()) {
+        assert_eq!(on_disk.source, "specs");
+        assert_eq!(
+            (&on_disk.slug, &on_disk.path, &on_disk.workflow),
+            (&built_in.slug, &built_in.path, &built_in.workflow)
+        );
+    }
+}
+
+#[test]
+fn bundled_specs_match_their_arguments() {
+    let store = WorkflowStore::new().with_source(Source::Embedded(EMBEDDED_SPECS));
+    let mut mismatched = 0;
+    for stored in store.workflows() {
+        if let Err(errors) = stored.workflow.check_arguments() {
+            mismatched += 1;
+            for error in errors {
+                println!("{}: {error}", stored.path);
+            }
+        }
+    }
+    assert_eq!(mismatched, 0);
+}
+
+#[test]
+fn typed_arguments() {
+    let dir = ScratchDir::new("typed");
+    dir.write(
+        "git/checkout.yaml",
+        r#"
+name: Check out a branch
+command: git checkout {{force}} {{branch}}
+arguments:
+  - name: force
+    type: boolean
+    flag: --force
+  - name: branch
+    default_value: main
+    pattern: "[^ ]+"
+    suggestions_command: git branch --format='%(refname:short)'
+"#,
+    );
+    let store = WorkflowStore::new().with_source(Source::Directory(dir.0.clone()));
+    assert!(store.errors().is_empty(), "{:?}", store.errors());
+
+    let arguments = store.get("checkout").unwrap().workflow.arguments();
+    assert_eq!(arguments[0], Argument::new("force").with_flag("--force"));
+    assert_eq!(arguments[1].arg_type(), ArgumentType::String);
+    assert_eq!(arguments[1].pattern().as_deref(), Some("[^ ]+"));
+    assert_eq!(
+        arguments[1].suggestions_command().as_deref(),
+        Some("git branch --format='%(refname:short)'")
+    );
+    assert!(!arguments[1].is_secret());
+}
+
+#[test]
+fn errors_are_per_file() {
+    let dir = ScratchDir::new("errors");
+    dir.write("git/status.yaml", "name: Status\ncommand: git status\n");
+    dir.write("git/broken.yaml", "name: Broken\ncommand: [\n");
+    dir.write("nameless.yml", "command: ls\n");
+    dir.write("README.md", "not a spec");
+
+    let store = WorkflowStore::new().with_source(Source::Directory(dir.0.clone()));
+    assert_eq!(store.workflows().len(), 1);
+    assert_eq!(store.workflows()[0].slug, "status");
+    assert_eq!(store.workflows()[0].path, "git/status.yaml");
+
+    let errors = store.errors();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].path, dir.0.join("git/broken.yaml"));
+    assert_eq!(errors[0].line, Some(2));
+    assert_eq!(errors[1].path, dir.0.join("nameless.yml"));
+    assert!(errors[1].message.contains("missing field `name`"));
+    assert!(errors[1]
+        .to_string()
+        .starts_with(&format!("{}:1: ", dir.0.join("nameless.yml").display())));
+}
+
+#[test]
+fn runbooks() {
+    let dir = ScratchDir::new("runbooks");
+    dir.write("status.yaml", "name: Status\ncommand: git status\n");
+    dir.write(
+        "restart.yaml",
+        "name: Restart\narguments:\n  - name: service\nsteps:\n  - command: systemctl is-active {{service}}\n    on_failure: continue\n  - command: systemctl restart {{service}}\n    confirm: true\n",
+    );
+    dir.write(
+        "both.yaml",
+        "name: Both\ncommand: ls\nsteps:\n  - command: ls\n",
+    );
+
+    let store = WorkflowStore::new().with_source(Source::Directory(dir.0.clone()));
+    let restart = store.get("restart").unwrap();
+    assert_eq!(restart.runbook.steps.len(), 2);
+    assert_eq!(restart.runbook.on_failure_of(0), OnFailure::Continue);
+    assert_eq!(
+        restart.workflow.command,
+        "systemctl is-active {{service}}\nsystemctl restart {{service}}"
+    );
+    assert_eq!(restart.workflow.arguments, restart.runbook.arguments);
+
+    // A single command is a runbook of one step, and the workflow it's written as.
+    let status = store.get("status").unwrap();
+    assert_eq!(status.runbook.steps, [Step::new("git status")]);
+    assert_eq!(status.workflow, Workflow::new("Status", "git status"));
+
+    assert_eq!(store.errors().len(), 1);
+    assert!(store.errors()[0].message.contains("not both"));
+}
+
+#[test]
+fn sources_stack() {
+    let dir = ScratchDir::new("sources");
+    dir.write("mine.yaml", "name: Mine\ncommand: echo mine\n");
+
+    let store = WorkflowStore::new()
+        .with_source(Source::Embedded(EMBEDDED_SPECS))
+        .with_source(Source::Directory(dir.0.clone()))
+        .with_source(Source::Directory(dir.0.join("missing")));
+    assert_eq!(store.workflows().len(), EMBEDDED_SPECS.len() + 1);
+    assert_eq!(store.workflows().last().unwrap().slug, "mine");
+    assert_eq!(store.errors().len(), 1);
+    assert_eq!(store.errors()[0].path, dir.0.join("missing"));
+}
+
+#[test]
+fn layers_shadow_by_precedence() {
+    let team = ScratchDir::new("team");
+    team.write(
+        "undo_most_recent_git_commit.yaml",
+        "name: Undo, our way\ncommand: git undo\n",
+    );
+    team.write("deploy.yaml", "name: Deploy\ncommand: make deploy\n");
+    let mine = ScratchDir::new("mine");
+    mine.write(
+        "deploy.yaml",
+        "name: My deploy\ncommand: make deploy-mine\n",
+    );
+
+    let store = WorkflowStore::new()
+        .with_layer(Layer::bundled())
+        .with_layer(Layer::new("team", Source::Directory(team.0.clone())))
+        .with_layer(Layer::new("mine", Source::Directory(mine.0.clone())));
+    assert_eq!(store.workflows().len(), EMBEDDED_SPECS.len() + 3);
+    assert_eq!(store.resolved().len(), EMBEDDED_SPECS.len() + 1);
+
+    // The team's workflow shadows the bundled one, and the team's layer, added first, wins the
+    // tie with mine.
+    let undo = store.get("undo_most_recent_git_commit").unwrap();
+    assert_eq!(undo.source, "team");
+    assert_eq!(undo.qualified_slug(), "team:undo_most_recent_git_commit");
+    assert_eq!(store.get("deploy").unwrap().workflow.name, "Deploy");
+    assert_eq!(store.resolved()[0].qualified_slug(), "team:deploy");
+
+    // Shadowed workflows are still there, by their qualified slugs.
+    let shadowed = store.shadowed("undo_most_recent_git_commit");
+    assert_eq!(shadowed.len(), 1);
+    assert_eq!(shadowed[0].source, "bundled");
+    assert_eq!(
+        store.get("bundled:undo_most_recent_git_commit"),
+        Some(shadowed[0])
+    );
+    assert_eq!(store.get("mine:deploy").unwrap().workflow.name, "My deploy");
+    assert!(store.get("mine:undo_most_recent_git_commit").is_none());
+
+    // Precedence beats the order of the layers.
+    let store = WorkflowStore::new()
+        .with_layer(Layer::new("team", Source::Directory(team.0.clone())))
+        .with_layer(Layer::new("mine", Source::Directory(mine.0.clone())).with_precedence(1));
+    assert_eq!(store.get("deploy").unwrap().source, "mine");
+    assert_eq!(store.shadowed("deploy")[0].source, "team");
+}
+
+#[test]
+fn layers_named_alike() {
+    let dir = ScratchDir::new("alike");
+    dir.write("a/specs/deploy.yaml", "name: Deploy A\ncommand: make a\n");
+    dir.write("b/specs/deploy.yaml", "name: Deploy B\ncommand: make b\n");
+
+    let store = WorkflowStore::new()
+        .with_source(Source::Directory(dir.0.join("a/specs")))
+        .with_source(Source::Directory(dir.0.join("b/specs")))
+        .with_layer(Layer::new(
+            "specs",
+            Source::Directory(dir.0.join("b/specs")),
+        ));
+    let names = store
+        .layers()
+        .iter()
+        .map(|layer| &*layer.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["specs", "specs-2", "specs-3"]);
+    assert_eq!(store.get("specs:deploy").unwrap().workflow.name, "Deploy A");
+    assert_eq!(
+        store.get("specs-2:deploy").unwrap().workflow.name,
+        "Deploy B"
+    );
+    assert_eq!(store.shadowed("deploy").len(), 2);
+}
+
+#[test]
+fn layers_named_with_qualifiers() {
+    let dir = ScratchDir::new("qualifier");
+    dir.write("ops:team/deploy.yaml", "name: Deploy\ncommand: make\n");
+
+    let store = WorkflowStore::new().with_layer(Layer::of(Source::Git(dir.0.join("ops:team"))));
+    assert_eq!(store.layers()[0].name, "ops-team");
+    let deploy = store.get("ops-team:deploy").unwrap();
+    assert_eq!(deploy.qualified_slug(), "ops-team:deploy");
+}
+
+#[test]
+fn git_checkouts() {
+    let checkout = ScratchDir::new("checkout");
+    checkout.write(".git/HEAD", "ref: refs/heads/main\n");
+    checkout.write(".github/workflows/ci.yml", "on: push\n");
+    checkout.write("README.md", "Our workflows");
+    checkout.write("ops/restart.yaml", "name: Restart\ncommand: make restart\n");
+
+    let store = WorkflowStore::new().with_source(Source::Git(checkout.0.clone()));
+    assert!(store.errors().is_empty(), "{:?}", store.errors());
+    assert_eq!(store.workflows().len(), 1);
+    assert_eq!(store.workflows()[0].path, "ops/restart.yaml");
+    assert_eq!(
+        store.workflows()[0].source,
+        checkout.0.file_name().unwrap().to_str().unwrap()
+    );
+
+    // With a `specs` directory, as this repository has, only its specs are loaded.
+    checkout.write("specs/git/sync.yaml", "name: Sync\ncommand: git pull\n");
+    let mut store = WorkflowStore::new().with_source(Source::Git(checkout.0.clone()));
+    assert_eq!(store.workflows().len(), 1);
+    assert_eq!(store.workflows()[0].path, "git/sync.yaml");
+    assert!(!store.reload_if_changed());
+    checkout.write("specs/git/push.yaml", "name: Push\ncommand: git push\n");
+    assert!(store.reload_if_changed());
+    assert_eq!(store.workflows().len(), 2);
+}
+
+#[test]
+fn reload_on_change() {
+    let dir = ScratchDir::new("reload");
+    dir.write("one.yaml", "name: One\ncommand: echo 1\n");
+
+    let mut store = WorkflowStore::new().with_source(Source::Directory(dir.0.clone()));
+    assert_eq!(store.workflows().len(), 1);
+    assert!(!store.reload_if_changed());
+
+    dir.write("two.yaml", "name: Two\ncommand: echo 2\n");
+    assert!(store.reload_if_changed());
+    assert_eq!(store.workflows().len(), 2);
+    assert!(!store.reload_if_changed());
+
+    // A change in size is a change, even within the clock's resolution.
+    dir.write("two.yaml", "name: Two\ncommand: [\n");
+    assert!(store.reload_if_changed());
+    assert_eq!(store.workflows().len(), 1);
+    assert_eq!(store.errors().len(), 1);
+
+    fs::remove_file(dir.0.join("two.yaml")).unwrap();
+    assert!(store.reload_if_changed());
+    assert!(store.errors().is_empty());
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fs;
+
+use nextshell_workflows::search::{Query, SearchIndex};
+use nextshell_workflows::usage::{stats, Frecency, UsageLog, UsageRecord, HALF_LIFE, MAX_BOOST};
+use nextshell_workflows::{Argument, Shell, Workflow};
+
+const NOW: u64 = 1_700_000_000;
+const DAY: u64 = 24 * 60 * 60;
+
+fn record(slug: &str, exit_code: i32, timestamp: u64) -> UsageRecord {
+    let mut record = UsageRecord::new(
+        slug,
+        &Workflow::new(slug, "true"),
+        &HashMap::new(),
+        Shell::Bash,
+        Some(exit_code),
+    );
+    record.timestamp = timestamp;
+    record
+}
+
+#[test]
+fn log_round_trip() {
+    let path = std::env::temp_dir().join(format!(
+        "nextshell-workflows-usage-{}.jsonl",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    let log = UsageLog::new(&path);
+    assert_eq!(log.records().unwrap(), []);
+
+    let login = Workflow::new("Log in", "login {{user}} {{password}}").with_arguments(vec![
+        Argument::new("user"),
+        Argument::new("password").with_secret(true),
+    ]);
+    let values = HashMap::from([
+        ("user".to_owned(), "ada".to_owned()),
+        ("password".to_owned(), "hunter2".to_owned()),
+        ("unknown".to_owned(), "x".to_owned()),
+    ]);
+    let first = UsageRecord::new("log_in", &login, &values, Shell::Zsh, Some(0));
+    assert_eq!(first.arguments.len(), 1);
+    assert_eq!(first.arguments["user"], "ada");
+    log.append(&first).unwrap();
+    log.append(&record("list_files", 1, NOW)).unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(!text.contains("hunter2"));
+    assert_eq!(text.lines().count(), 2);
+    assert!(text
+        .lines()
+        .nth(1)
+        .unwrap()
+        .starts_with(r#"{"slug":"list_files","arguments":{}"#));
+
+    // A line cut short is skipped, and what's appended after it is still read.
+    fs::write(&path, format!("{text}{{\"slug\":\"cut")).unwrap();
+    assert_eq!(log.records().unwrap().len(), 2);
+    fs::write(&path, format!("{text}{{\"slug\":\"cut\n")).unwrap();
+    log.append(&record("list_files", 0, NOW)).unwrap();
+    let records = log.records().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0], first);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn aggregation() {
+    let records = [
+        record("a", 0, NOW - 10 * DAY),
+        record("b", 0, NOW - 2 * DAY),
+        record("b", 2, NOW - DAY),
+        record("c", 0, NOW - DAY),
+        record("c", 0, NOW),
+    ];
+    let all = stats(&records, 0);
+    let summary = all
+        .iter()
+        .map(|stats| (&*stats.slug, stats.runs, stats.failures, stats.last_run))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("b", 2, 1, NOW - DAY),
+            ("c", 2, 0, NOW),
+            ("a", 1, 0, NOW - 10 * DAY),
+        ]
+    );
+    assert_eq!(all[0].shells["bash"], 2);
+
+    let lately = stats(&records, NOW - DAY);
+    assert_eq!(lately.len(), 2);
+    assert_eq!((&*lately[0].slug, lately[0].runs), ("c", 2));
+}
+
+#[test]
+fn frecency() {
+    let mut frecency = Frecency::new(&[
+        record("weekly", 0, NOW - HALF_LIFE),
+        // Out of order, as two servers' logs might be.
+        record("weekly", 0, NOW - 2 * HALF_LIFE),
+        record("today", 0, NOW),
+    ]);
+    assert!((frecency.score("weekly", NOW) - 0.75).abs() < 1e-9);
+    assert!((frecency.score("today", NOW) - 1.0).abs() < 1e-9);
+    assert_eq!(frecency.score("never", NOW), 0.0);
+    assert_eq!(frecency.boost("never", NOW), 0);
+    assert!(frecency.boost("today", NOW) > frecency.boost("weekly", NOW));
+
+    // Run a lot, long ago, is worth less than run a little, lately.
+    for _ in 0..10 {
+        frecency.add(&record("old", 0, NOW - 10 * HALF_LIFE));
+    }
+    assert!(frecency.score("old", NOW) < frecency.score("weekly", NOW));
+
+    for _ in 0..1000 {
+        frecency.add(&record("today", 0, NOW));
+    }
+    assert_eq!(frecency.boost("today", NOW), MAX_BOOST);
+}
+
+#[test]
+fn boosted_search() {
+    let index = SearchIndex::new(vec![
+        Workflow::new("Undo the last commit", "git reset --soft HEAD~1"),
+        Workflow::new("Undo a commit by reverting it", "git revert HEAD"),
+    ]);
+    // Tied, by name.
+    let query = Query::new("undo");
+    assert_eq!(index.search(&query)[0].position, 1);
+
+    let frecency = Frecency::new(&[record("reset", 0, NOW)]);
+    let slugs = ["reset", "revert"];
+    let hits = index.search_with(&query, |position| frecency.boost(slugs[position], NOW));
+    assert_eq!(hits[0].position, 0);
+    assert_eq!(hits[0].score, hits[1].score + frecency.boost("reset", NOW));
+
+    // Usage doesn't find what doesn't match.
+    assert!(index
+        .search_with(&Query::new("push"), |_| MAX_BOOST)
+        .is_empty());
+}